@@ -1,8 +1,12 @@
 use crate::error::BackendError;
 use crate::framing::{LspFrameReader, LspFrameWriter};
 use crate::message::{RpcId, RpcMessage};
-use std::path::Path;
+use std::ffi::OsString;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::process::Stdio;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 
@@ -63,12 +67,260 @@ impl BackendKind {
     }
 }
 
+/// Clear the inherited environment, keeping only the `allow`ed keys plus
+/// `PATH` (which `apply_env` extends). Values are read through `lookup`.
+fn restrict_env(cmd: &mut Command, allow: &[String], lookup: impl Fn(&str) -> Option<OsString>) {
+    cmd.env_clear();
+    for key in allow.iter().map(String::as_str).chain(["PATH"]) {
+        if let Some(value) = lookup(key) {
+            cmd.env(key, value);
+        }
+    }
+}
+
 impl std::fmt::Display for BackendKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.display_name())
     }
 }
 
+/// One `--backend-map GLOB=KIND` entry: venvs whose parent directory matches
+/// `pattern` get `kind` instead of the global `--backend`.
+///
+/// Relative patterns are matched against the directory relative to the git
+/// toplevel, absolute ones against the full path. `*` and `?` match within a
+/// path segment, `**` matches any number of segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendMapping {
+    pub pattern: String,
+    pub kind: BackendKind,
+}
+
+impl std::str::FromStr for BackendMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, kind) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("expected GLOB=KIND, got `{s}`"))?;
+        let pattern = pattern.trim().trim_end_matches('/');
+        if pattern.is_empty() {
+            return Err(format!("empty glob in `{s}`"));
+        }
+        let kind = <BackendKind as clap::ValueEnum>::from_str(kind.trim(), true)
+            .map_err(|_| format!("unknown backend `{}` in `{s}`", kind.trim()))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            kind,
+        })
+    }
+}
+
+impl BackendMapping {
+    /// Whether this entry applies to a venv at `venv`
+    pub fn matches(&self, venv: &Path, toplevel: Option<&Path>) -> bool {
+        let Some(dir) = venv.parent() else {
+            return false;
+        };
+        let target = if Path::new(&self.pattern).is_absolute() {
+            dir
+        } else {
+            match toplevel.and_then(|top| dir.strip_prefix(top).ok()) {
+                Some(relative) => relative,
+                None => return false,
+            }
+        };
+        let segments: Vec<String> = target
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .filter(|c| c != "/")
+            .collect();
+        let pattern: Vec<&str> = self.pattern.split('/').filter(|p| !p.is_empty()).collect();
+        glob_segments(&pattern, &segments)
+    }
+}
+
+/// Pick the backend for `venv`: the first matching `--backend-map` entry, else `default`
+pub fn backend_kind_for(
+    map: &[BackendMapping],
+    venv: &Path,
+    toplevel: Option<&Path>,
+    default: BackendKind,
+) -> BackendKind {
+    map.iter()
+        .find(|m| m.matches(venv, toplevel))
+        .map_or(default, |m| m.kind)
+}
+
+fn glob_segments(pattern: &[&str], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_segments(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, tail)| {
+            glob_segment(first.as_bytes(), segment.as_bytes()) && glob_segments(rest, tail)
+        }),
+    }
+}
+
+fn glob_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && glob_segment(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_segment(rest, &text[1..]),
+    }
+}
+
+/// Pyright's `python.analysis.diagnosticMode` (`--pyright-diagnostic-mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PyrightDiagnosticMode {
+    /// Only report diagnostics for open files
+    #[value(name = "openFilesOnly")]
+    OpenFilesOnly,
+    /// Analyze every file in the workspace (much heavier)
+    #[value(name = "workspace")]
+    Workspace,
+}
+
+impl PyrightDiagnosticMode {
+    /// Setting value understood by pyright
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OpenFilesOnly => "openFilesOnly",
+            Self::Workspace => "workspace",
+        }
+    }
+
+    /// `python.analysis.diagnosticMode` as a settings object
+    pub fn settings(&self) -> serde_json::Value {
+        serde_json::json!({ "python": { "analysis": { "diagnosticMode": self.as_str() } } })
+    }
+}
+
+/// Signal used to terminate a backend that did not exit after shutdown/exit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum KillSignal {
+    /// SIGTERM, then SIGKILL if the backend is still alive
+    Term,
+    /// SIGINT (lets some backends flush caches), then SIGKILL if still alive
+    Int,
+    /// SIGKILL immediately
+    #[default]
+    Kill,
+}
+
+/// How long a backend gets to exit after SIGTERM/SIGINT before SIGKILL
+#[cfg(unix)]
+const SIGNAL_GRACE: Duration = Duration::from_secs(1);
+
+/// Terminate `child` with `signal`, falling back to `kill()` if it survives
+/// the grace period. Non-Unix platforms always use `kill()`.
+async fn terminate(child: &mut Child, signal: KillSignal, venv_display: &str) {
+    #[cfg(unix)]
+    {
+        let signum = match signal {
+            KillSignal::Term => Some(libc::SIGTERM),
+            KillSignal::Int => Some(libc::SIGINT),
+            KillSignal::Kill => None,
+        };
+        if let (Some(signum), Some(pid)) = (signum, child.id()) {
+            tracing::info!(venv = %venv_display, signal = ?signal, "Signalling backend");
+            // SAFETY: kill(2) has no memory-safety preconditions; pid is our own child
+            unsafe { libc::kill(pid as libc::pid_t, signum) };
+            if let Ok(Ok(status)) = tokio::time::timeout(SIGNAL_GRACE, child.wait()).await {
+                tracing::info!(venv = %venv_display, status = ?status, "Backend exited after signal");
+                return;
+            }
+            tracing::warn!(venv = %venv_display, signal = ?signal, "Backend ignored signal, killing");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (signal, venv_display);
+
+    let _ = child.kill().await;
+}
+
+/// Upper bound for `--backend-startup-script` before backend creation is aborted
+const STARTUP_SCRIPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Run the `--backend-startup-script` hook for `venv` and wait for it.
+///
+/// The script gets the venv path as its only argument and the same
+/// `VIRTUAL_ENV`/`PATH` the backend will get. A non-zero exit status, a
+/// launch failure, or exceeding the timeout aborts backend creation.
+pub async fn run_startup_script(
+    script: &Path,
+    kind: BackendKind,
+    venv: &Path,
+) -> Result<(), BackendError> {
+    let mut cmd = Command::new(script);
+    cmd.arg(venv)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true);
+    kind.apply_env(&mut cmd, venv);
+
+    tracing::info!(script = %script.display(), venv = %venv.display(), "Running backend startup script");
+    let failed = |reason: String| {
+        BackendError::StartupScriptFailed(format!("{}: {}", script.display(), reason))
+    };
+    let mut child = cmd.spawn().map_err(|e| failed(e.to_string()))?;
+    match tokio::time::timeout(STARTUP_SCRIPT_TIMEOUT, child.wait()).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(failed(format!("exited with {}", status))),
+        Ok(Err(e)) => Err(failed(e.to_string())),
+        Err(_) => Err(failed(format!(
+            "timed out after {}s",
+            STARTUP_SCRIPT_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// Waits between the attempts of one backend launch whose spawn failed (e.g.
+/// the language server isn't on PATH yet right after a venv was created)
+pub const SPAWN_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_millis(100),
+    Duration::from_millis(400),
+    Duration::from_millis(1600),
+];
+
+/// Wait after a launch used up `SPAWN_RETRY_DELAYS`, so a venv that can't
+/// spawn isn't retried on every request
+pub const SPAWN_FAILURE_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// What a [`BackendSpawner`] needs to start a backend process
+#[derive(Debug, Clone)]
+pub struct SpawnRequest {
+    pub kind: BackendKind,
+    pub venv: PathBuf,
+    pub read_buffer_size: usize,
+    pub env_passthrough: Option<Vec<String>>,
+}
+
+/// Starts backend processes. [`LspBackend::spawn`] by default; replaceable
+/// so tests can simulate spawn failures.
+pub type BackendSpawner = Arc<
+    dyn Fn(SpawnRequest) -> Pin<Box<dyn Future<Output = Result<LspBackend, BackendError>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The spawner that starts the backend's real language server
+pub fn default_spawner() -> BackendSpawner {
+    Arc::new(|request: SpawnRequest| {
+        Box::pin(async move {
+            LspBackend::spawn(
+                request.kind,
+                Some(&request.venv),
+                request.read_buffer_size,
+                request.env_passthrough.as_deref(),
+            )
+            .await
+        })
+    })
+}
+
 /// Components returned by `LspBackend::into_split()`
 pub struct BackendParts {
     pub reader: LspFrameReader<ChildStdout>,
@@ -88,15 +340,25 @@ impl LspBackend {
     /// Spawn an LSP backend process.
     ///
     /// When venv_path is Some, apply backend-specific environment variables.
-    pub async fn spawn(kind: BackendKind, venv_path: Option<&Path>) -> Result<Self, BackendError> {
+    /// `read_buffer_size` is the capacity of the buffer over the backend's stdout.
+    /// With `env_passthrough`, only those variables (plus VIRTUAL_ENV/PATH) are
+    /// inherited from the proxy.
+    pub async fn spawn(
+        kind: BackendKind,
+        venv_path: Option<&Path>,
+        read_buffer_size: usize,
+        env_passthrough: Option<&[String]>,
+    ) -> Result<Self, BackendError> {
         let mut cmd = Command::new(kind.command());
         for arg in kind.args() {
             cmd.arg(arg);
         }
-        cmd.stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .kill_on_drop(true);
+        cmd.stderr(Stdio::inherit());
+
+        if let Some(allow) = env_passthrough {
+            tracing::debug!(allow = ?allow, "Restricting backend environment");
+            restrict_env(&mut cmd, allow, |key| std::env::var_os(key));
+        }
 
         if let Some(venv) = venv_path {
             kind.apply_env(&mut cmd, venv);
@@ -105,6 +367,7 @@ impl LspBackend {
                 backend = kind.display_name(),
                 venv = %venv.display(),
                 path_prefix = %format!("{}/bin", venv.display()),
+                read_buffer_size = read_buffer_size,
                 "Spawning backend with venv"
             );
         } else {
@@ -114,12 +377,20 @@ impl LspBackend {
             );
         }
 
+        Self::from_command(cmd, read_buffer_size)
+    }
+
+    /// Spawn `cmd` as a backend speaking LSP over its stdin/stdout
+    pub fn from_command(mut cmd: Command, read_buffer_size: usize) -> Result<Self, BackendError> {
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
         let mut child = cmd.spawn()?;
 
         let stdin = child.stdin.take().unwrap();
         let stdout = child.stdout.take().unwrap();
 
-        let reader = LspFrameReader::new(stdout);
+        let reader = LspFrameReader::with_capacity(read_buffer_size, stdout);
         let writer = LspFrameWriter::new(stdin);
 
         Ok(Self {
@@ -130,6 +401,11 @@ impl LspBackend {
         })
     }
 
+    /// Enable strict JSON parsing for messages read from this backend
+    pub fn set_strict_json(&mut self, strict_json: bool) {
+        self.reader.set_strict_json(strict_json);
+    }
+
     /// Send message
     pub async fn send_message(&mut self, message: &RpcMessage) -> Result<(), BackendError> {
         self.writer
@@ -164,13 +440,14 @@ impl LspBackend {
 /// 1. Sends shutdown request → waits 100ms
 /// 2. Sends exit notification
 /// 3. Waits up to 2s for process exit
-/// 4. Kills if still alive
+/// 4. Terminates with `kill_signal` if still alive
 pub fn shutdown_fire_and_forget(
     mut writer: LspFrameWriter<ChildStdin>,
     mut child: Child,
     next_id: u64,
     venv_display: String,
-) {
+    kill_signal: KillSignal,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         tracing::info!(venv = %venv_display, "Starting fire-and-forget shutdown");
 
@@ -179,7 +456,7 @@ pub fn shutdown_fire_and_forget(
 
         if let Err(e) = writer.write_message(&shutdown_msg).await {
             tracing::warn!(venv = %venv_display, error = ?e, "Failed to send shutdown, killing directly");
-            let _ = child.kill().await;
+            terminate(&mut child, kill_signal, &venv_display).await;
             return;
         }
 
@@ -200,20 +477,63 @@ pub fn shutdown_fire_and_forget(
             }
             Ok(Err(e)) => {
                 tracing::warn!(venv = %venv_display, error = ?e, "Error waiting for backend exit, killing");
-                let _ = child.kill().await;
+                terminate(&mut child, kill_signal, &venv_display).await;
             }
             Err(_) => {
                 tracing::warn!(venv = %venv_display, "Backend exit timeout, killing");
-                let _ = child.kill().await;
+                terminate(&mut child, kill_signal, &venv_display).await;
             }
         }
-    });
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn backend_mapping_parse_and_match() {
+        let top = Path::new("/repo");
+        let api: BackendMapping = "services/*=pyright".parse().unwrap();
+        let tools: BackendMapping = "tools=TY".parse().unwrap();
+        let deep: BackendMapping = "**/legacy=pyrefly".parse().unwrap();
+        let abs: BackendMapping = "/opt/proj/=ty".parse().unwrap();
+        assert_eq!(tools.kind, BackendKind::Ty);
+        assert_eq!(abs.pattern, "/opt/proj");
+
+        assert!(api.matches(Path::new("/repo/services/api/.venv"), Some(top)));
+        assert!(!api.matches(Path::new("/repo/services/api/sub/.venv"), Some(top)));
+        assert!(tools.matches(Path::new("/repo/tools/.venv"), Some(top)));
+        assert!(!tools.matches(Path::new("/repo/tools/.venv"), None));
+        assert!(deep.matches(Path::new("/repo/legacy/.venv"), Some(top)));
+        assert!(deep.matches(Path::new("/repo/a/b/legacy/.venv"), Some(top)));
+        assert!(abs.matches(Path::new("/opt/proj/.venv"), None));
+
+        let map = [api, tools];
+        assert_eq!(
+            backend_kind_for(
+                &map,
+                Path::new("/repo/tools/.venv"),
+                Some(top),
+                BackendKind::Pyrefly
+            ),
+            BackendKind::Ty
+        );
+        assert_eq!(
+            backend_kind_for(
+                &map,
+                Path::new("/repo/other/.venv"),
+                Some(top),
+                BackendKind::Pyrefly
+            ),
+            BackendKind::Pyrefly
+        );
+
+        assert!("tools".parse::<BackendMapping>().is_err());
+        assert!("tools=mypy".parse::<BackendMapping>().is_err());
+        assert!("=ty".parse::<BackendMapping>().is_err());
+    }
+
     #[test]
     fn backend_kind_command_and_args() {
         assert_eq!(BackendKind::Pyright.command(), "pyright-langserver");
@@ -237,4 +557,60 @@ mod tests {
         assert_eq!(format!("{}", BackendKind::Ty), "ty");
         assert_eq!(format!("{}", BackendKind::Pyrefly), "pyrefly");
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn terminate_sends_chosen_signal() {
+        let dir = tempfile::tempdir().unwrap();
+        for (signal, name) in [(KillSignal::Term, "TERM"), (KillSignal::Int, "INT")] {
+            let marker = dir.path().join(name);
+            // Wrapper that reports which signal it received
+            let script = format!(
+                "trap 'echo TERM > {m}; exit 0' TERM; trap 'echo INT > {m}; exit 0' INT; \
+                 while :; do sleep 0.05; done",
+                m = marker.display()
+            );
+            let mut child = Command::new("sh").arg("-c").arg(&script).spawn().unwrap();
+            // Let the shell install its traps
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            terminate(&mut child, signal, "test").await;
+
+            let received = std::fs::read_to_string(&marker).unwrap();
+            assert_eq!(received.trim(), name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn restrict_env_keeps_only_allowlisted_keys() {
+        let parent_env = |key: &str| -> Option<OsString> {
+            match key {
+                "PATH" => Some("/usr/bin:/bin".into()),
+                "KEEP_ME" => Some("1".into()),
+                "SECRET_TOKEN" => Some("hunter2".into()),
+                _ => None,
+            }
+        };
+        let mut cmd = Command::new("/bin/sh");
+        cmd.arg("-c").arg("env");
+        restrict_env(
+            &mut cmd,
+            &["KEEP_ME".to_string(), "UNSET".to_string()],
+            parent_env,
+        );
+        BackendKind::Pyright.apply_env(&mut cmd, Path::new("/venvs/proj"));
+
+        let output = cmd.output().await.unwrap();
+        let env = String::from_utf8(output.stdout).unwrap();
+        let keys: Vec<&str> = env
+            .lines()
+            .filter_map(|line| line.split_once('=').map(|(k, _)| k))
+            .collect();
+        assert!(keys.contains(&"KEEP_ME"), "{env}");
+        assert!(keys.contains(&"VIRTUAL_ENV"), "{env}");
+        assert!(keys.contains(&"PATH"), "{env}");
+        assert!(!keys.contains(&"SECRET_TOKEN"), "{env}");
+        assert!(!keys.contains(&"HOME"), "{env}");
+    }
 }
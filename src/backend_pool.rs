@@ -1,12 +1,13 @@
-use crate::backend::{shutdown_fire_and_forget, BackendParts};
+use crate::backend::{shutdown_fire_and_forget, BackendParts, KillSignal};
 use crate::error::BackendError;
 use crate::framing::{LspFrameReader, LspFrameWriter};
 use crate::message::{RpcId, RpcMessage};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::process::{Child, ChildStdin, ChildStdout};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 use tokio::task::JoinHandle;
 use tokio::time::Instant;
 
@@ -20,18 +21,8 @@ pub enum WarmupState {
     Ready,
 }
 
-/// Default warmup timeout; overridable via `TYPEMUX_CC_WARMUP_TIMEOUT` env var.
-const DEFAULT_WARMUP_TIMEOUT: Duration = Duration::from_secs(2);
-
-/// Returns the warmup timeout duration.
-/// `TYPEMUX_CC_WARMUP_TIMEOUT=0` means warmup is disabled (immediate Ready).
-pub fn warmup_timeout() -> Duration {
-    std::env::var("TYPEMUX_CC_WARMUP_TIMEOUT")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .map(Duration::from_secs)
-        .unwrap_or(DEFAULT_WARMUP_TIMEOUT)
-}
+/// Default warmup timeout; overridable via `--warmup-timeout`.
+pub const DEFAULT_WARMUP_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Default fan-out timeout; overridable via `TYPEMUX_CC_FANOUT_TIMEOUT` env var.
 const DEFAULT_FANOUT_TIMEOUT: Duration = Duration::from_secs(5);
@@ -46,11 +37,99 @@ pub fn fanout_timeout() -> Duration {
         .unwrap_or(DEFAULT_FANOUT_TIMEOUT)
 }
 
+/// Window in which re-creating an LRU-evicted venv counts as eviction thrash.
+const THRASH_WINDOW: Duration = Duration::from_secs(300);
+
+/// Re-creations of the same venv within `THRASH_WINDOW` that grow the cap.
+const THRASH_THRESHOLD: usize = 2;
+
+/// Eviction-thrash driven pool capacity (`--auto-scale-backends`).
+///
+/// The effective cap starts at the configured `max_backends` and grows by one
+/// (up to `ceiling`) whenever a venv has to be re-created `THRASH_THRESHOLD`
+/// times within `THRASH_WINDOW` after LRU eviction. Once no thrash has been
+/// seen for a full window and the pool has room to spare, it shrinks by one
+/// back towards the initial value.
+#[derive(Debug)]
+pub struct AutoScale {
+    initial: usize,
+    ceiling: usize,
+    cap: usize,
+    /// When each venv was last evicted to make room (LRU)
+    evicted_at: HashMap<PathBuf, Instant>,
+    /// Recent thrash re-creations per venv (within `THRASH_WINDOW`)
+    recreates: HashMap<PathBuf, Vec<Instant>>,
+    last_thrash: Option<Instant>,
+}
+
+impl AutoScale {
+    pub fn new(initial: usize, ceiling: usize) -> Self {
+        Self {
+            initial,
+            ceiling: ceiling.max(initial),
+            cap: initial,
+            evicted_at: HashMap::new(),
+            recreates: HashMap::new(),
+            last_thrash: None,
+        }
+    }
+
+    /// Current effective cap
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    /// Record that `venv` was evicted to make room for another backend
+    pub fn on_lru_eviction(&mut self, venv: &Path, now: Instant) {
+        self.evicted_at.insert(venv.to_path_buf(), now);
+    }
+
+    /// Record that a backend was created for `venv`. Returns true if this
+    /// re-creation tipped the venv over the thrash threshold and grew the cap.
+    pub fn on_created(&mut self, venv: &Path, now: Instant) -> bool {
+        let Some(evicted) = self.evicted_at.remove(venv) else {
+            return false;
+        };
+        if now.duration_since(evicted) > THRASH_WINDOW {
+            return false;
+        }
+
+        self.last_thrash = Some(now);
+        let times = self.recreates.entry(venv.to_path_buf()).or_default();
+        times.retain(|t| now.duration_since(*t) <= THRASH_WINDOW);
+        times.push(now);
+        if times.len() < THRASH_THRESHOLD || self.cap >= self.ceiling {
+            return false;
+        }
+
+        times.clear();
+        self.cap += 1;
+        true
+    }
+
+    /// Shrink the cap by one when no thrash was seen for a full window and
+    /// the pool is below the current cap. Returns true if the cap shrank.
+    pub fn on_idle(&mut self, now: Instant, pool_len: usize) -> bool {
+        let quiet = self
+            .last_thrash
+            .map_or(true, |t| now.duration_since(t) > THRASH_WINDOW);
+        if self.cap > self.initial && pool_len < self.cap && quiet {
+            self.cap -= 1;
+            return true;
+        }
+        false
+    }
+}
+
 /// Message from a backend reader task
 pub struct BackendMessage {
     pub venv_path: PathBuf,
     pub session: u64,
     pub result: Result<RpcMessage, BackendError>,
+    /// Body of the message in `result` as the backend sent it, for
+    /// forwarding it unchanged without serializing it again (`None` when it
+    /// must be serialized, e.g. strict mode dropped unknown fields)
+    pub raw: Option<Vec<u8>>,
 }
 
 /// A single backend instance in the pool
@@ -65,19 +144,26 @@ pub struct BackendInstance {
     pub warmup_state: WarmupState,
     pub warmup_deadline: Instant,
     pub warmup_queue: Vec<RpcMessage>,
+    /// Whether this session has received an `initialized` notification
+    pub initialized_sent: bool,
+    /// URIs opened by `--preindex-open-files` rather than the client
+    pub preindexed: HashSet<String>,
+    /// Project files `--preindex-open-files` has yet to open
+    pub preindex_queue: VecDeque<PathBuf>,
 }
 
 impl BackendInstance {
     /// Create a `BackendInstance` from a split backend, spawning the reader task
     /// and computing the warmup state. Does NOT insert into the pool.
+    /// A zero `warmup_timeout` disables warmup gating (immediate Ready).
     pub fn from_parts(
         parts: BackendParts,
         venv_path: PathBuf,
         session: u64,
         msg_sender: mpsc::Sender<BackendMessage>,
+        warmup_timeout: Duration,
     ) -> Self {
         let reader_task = spawn_reader_task(parts.reader, msg_sender, venv_path.clone(), session);
-        let timeout = warmup_timeout();
         Self {
             writer: parts.writer,
             child: parts.child,
@@ -86,13 +172,18 @@ impl BackendInstance {
             last_used: Instant::now(),
             reader_task,
             next_id: parts.next_id,
-            warmup_state: if timeout.is_zero() {
+            warmup_state: if warmup_timeout.is_zero() {
                 WarmupState::Ready
             } else {
                 WarmupState::Warming
             },
-            warmup_deadline: Instant::now() + timeout,
+            warmup_deadline: Instant::now() + warmup_timeout,
             warmup_queue: Vec::new(),
+            // Instances are only built after the initialize handshake,
+            // which ends by sending `initialized`
+            initialized_sent: true,
+            preindexed: HashSet::new(),
+            preindex_queue: VecDeque::new(),
         }
     }
 
@@ -115,6 +206,17 @@ impl BackendInstance {
         queued
     }
 
+    /// Queue a request until warmup completes.
+    /// When `limit` is reached, the oldest queued request is dropped and returned
+    /// so the caller can answer it instead of letting the queue grow unbounded.
+    pub fn enqueue_warmup_request(
+        &mut self,
+        msg: RpcMessage,
+        limit: Option<usize>,
+    ) -> Option<RpcMessage> {
+        push_bounded(&mut self.warmup_queue, msg, limit)
+    }
+
     /// Check if the warmup deadline has passed
     pub fn warmup_expired(&self) -> bool {
         Instant::now() >= self.warmup_deadline
@@ -135,6 +237,74 @@ impl BackendInstance {
     }
 }
 
+/// Push onto a FIFO queue, dropping (and returning) the oldest entry if `limit` is reached.
+fn push_bounded(
+    queue: &mut Vec<RpcMessage>,
+    msg: RpcMessage,
+    limit: Option<usize>,
+) -> Option<RpcMessage> {
+    let dropped = match limit {
+        Some(limit) if queue.len() >= limit && !queue.is_empty() => Some(queue.remove(0)),
+        _ => None,
+    };
+    queue.push(msg);
+    dropped
+}
+
+/// Pick the venv with the oldest `(last_used, session)`.
+/// `Instant` ties (bursts, tests) resolve to the oldest session instead of
+/// HashMap iteration order, so eviction is deterministic.
+fn least_recently_used<'a>(
+    candidates: impl Iterator<Item = (&'a PathBuf, Instant, u64)>,
+) -> Option<PathBuf> {
+    candidates
+        .min_by_key(|(_, last_used, session)| (*last_used, *session))
+        .map(|(venv, _, _)| venv.clone())
+}
+
+/// Candidates idle for at least `ttl` at `now`, least recently used first
+/// (ties broken by session, like [`least_recently_used`])
+fn expired_oldest_first<'a>(
+    candidates: impl Iterator<Item = (&'a PathBuf, Instant, u64)>,
+    ttl: Duration,
+    now: Instant,
+) -> Vec<PathBuf> {
+    let mut expired: Vec<_> = candidates
+        .filter(|(_, last_used, _)| now.saturating_duration_since(*last_used) >= ttl)
+        .collect();
+    expired.sort_by_key(|(_, last_used, session)| (*last_used, *session));
+    expired
+        .into_iter()
+        .map(|(venv, _, _)| venv.clone())
+        .collect()
+}
+
+/// Limits how many backends are spawned and initialized at the same time
+/// (`--max-concurrent-spawns`), so opening files across many venvs at once
+/// doesn't start every language server in parallel.
+#[derive(Debug, Clone)]
+pub struct SpawnLimiter {
+    semaphore: Arc<Semaphore>,
+}
+
+impl SpawnLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    /// Wait for a spawn slot; the slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        // The semaphore is never closed
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("spawn semaphore closed")
+    }
+}
+
 /// Pool of backend processes keyed by venv path
 pub struct BackendPool {
     backends: HashMap<PathBuf, BackendInstance>,
@@ -143,6 +313,10 @@ pub struct BackendPool {
     max_backends: usize,
     backend_ttl: Option<Duration>,
     next_session: u64,
+    autoscale: Option<AutoScale>,
+    warmup_timeout: Duration,
+    /// Venvs never chosen for TTL or LRU eviction (`--pin-venv`)
+    pinned: HashSet<PathBuf>,
 }
 
 impl BackendPool {
@@ -155,6 +329,59 @@ impl BackendPool {
             max_backends,
             backend_ttl,
             next_session: 0,
+            autoscale: None,
+            warmup_timeout: DEFAULT_WARMUP_TIMEOUT,
+            pinned: HashSet::new(),
+        }
+    }
+
+    /// Set how long new backends gate index-dependent requests (`--warmup-timeout`)
+    pub fn set_warmup_timeout(&mut self, timeout: Duration) {
+        self.warmup_timeout = timeout;
+    }
+
+    /// Warmup timeout applied to newly created backend instances
+    pub fn warmup_timeout(&self) -> Duration {
+        self.warmup_timeout
+    }
+
+    /// Exempt these venvs' backends from TTL and LRU eviction (`--pin-venv`)
+    pub fn set_pinned_venvs(&mut self, venvs: impl IntoIterator<Item = PathBuf>) {
+        self.pinned = venvs.into_iter().collect();
+    }
+
+    /// Whether `venv`'s backend is exempt from eviction
+    pub fn is_pinned(&self, venv: &Path) -> bool {
+        self.pinned.contains(venv)
+    }
+
+    /// Let the cap grow up to `ceiling` under eviction thrash (`--auto-scale-backends`)
+    pub fn enable_autoscale(&mut self, ceiling: usize) {
+        self.autoscale = Some(AutoScale::new(self.max_backends, ceiling));
+    }
+
+    /// Whether auto-scaling is enabled
+    pub fn autoscale_enabled(&self) -> bool {
+        self.autoscale.is_some()
+    }
+
+    /// Record an eviction made to free a slot for another venv
+    pub fn record_lru_eviction(&mut self, venv_path: &Path) {
+        if let Some(autoscale) = &mut self.autoscale {
+            autoscale.on_lru_eviction(venv_path, Instant::now());
+        }
+    }
+
+    /// Shrink an auto-scaled cap after a quiet period (called from the periodic sweep)
+    pub fn autoscale_idle_tick(&mut self) {
+        let len = self.backends.len();
+        if let Some(autoscale) = &mut self.autoscale {
+            if autoscale.on_idle(Instant::now(), len) {
+                tracing::info!(
+                    max_backends = autoscale.cap(),
+                    "Auto-scale: no eviction thrash, shrinking backend cap"
+                );
+            }
         }
     }
 
@@ -175,6 +402,15 @@ impl BackendPool {
 
     /// Insert a backend instance into the pool
     pub fn insert(&mut self, venv_path: PathBuf, instance: BackendInstance) {
+        if let Some(autoscale) = &mut self.autoscale {
+            if autoscale.on_created(&venv_path, Instant::now()) {
+                tracing::info!(
+                    venv = %venv_path.display(),
+                    max_backends = autoscale.cap(),
+                    "Auto-scale: eviction thrash detected, growing backend cap"
+                );
+            }
+        }
         self.backends.insert(venv_path, instance);
     }
 
@@ -185,25 +421,27 @@ impl BackendPool {
 
     /// Find the LRU (least recently used) venv path.
     /// Prefers backends with no pending requests (caller provides the count).
-    /// Returns None if pool is empty.
+    /// Pinned backends are never chosen. Returns None if no backend is evictable.
     pub fn lru_venv(&self, pending_count_fn: impl Fn(&PathBuf, u64) -> usize) -> Option<PathBuf> {
+        let unpinned = || {
+            self.backends
+                .iter()
+                .filter(|(venv, _)| !self.pinned.contains(*venv))
+        };
+
         // First try: find LRU among backends with 0 pending requests
-        let no_pending_lru = self
-            .backends
-            .iter()
-            .filter(|(venv, inst)| pending_count_fn(venv, inst.session) == 0)
-            .min_by_key(|(_, inst)| inst.last_used)
-            .map(|(venv, _)| venv.clone());
+        let no_pending_lru = least_recently_used(
+            unpinned()
+                .filter(|(venv, inst)| pending_count_fn(venv, inst.session) == 0)
+                .map(|(venv, inst)| (venv, inst.last_used, inst.session)),
+        );
 
         if no_pending_lru.is_some() {
             return no_pending_lru;
         }
 
-        // Fallback: LRU among all backends
-        self.backends
-            .iter()
-            .min_by_key(|(_, inst)| inst.last_used)
-            .map(|(venv, _)| venv.clone())
+        // Fallback: LRU among all unpinned backends
+        least_recently_used(unpinned().map(|(venv, inst)| (venv, inst.last_used, inst.session)))
     }
 
     /// Generate a new unique session ID
@@ -214,7 +452,7 @@ impl BackendPool {
 
     /// Check if pool is at capacity
     pub fn is_full(&self) -> bool {
-        self.backends.len() >= self.max_backends
+        self.backends.len() >= self.max_backends()
     }
 
     /// Number of backends in the pool
@@ -227,12 +465,15 @@ impl BackendPool {
         self.backends.is_empty()
     }
 
-    /// Get max backends setting
+    /// Get the effective max backends (the auto-scaled cap when enabled)
     pub fn max_backends(&self) -> usize {
-        self.max_backends
+        self.autoscale
+            .as_ref()
+            .map_or(self.max_backends, AutoScale::cap)
     }
 
-    /// Return venv paths of backends whose last_used exceeds the TTL.
+    /// Return venv paths of unpinned backends whose last_used exceeds the TTL,
+    /// least recently used first.
     /// Only checks TTL/last_used; pending request filtering is the caller's responsibility.
     pub fn expired_venvs(&self) -> Vec<PathBuf> {
         let ttl = match self.backend_ttl {
@@ -240,12 +481,14 @@ impl BackendPool {
             None => return Vec::new(),
         };
 
-        let now = Instant::now();
-        self.backends
-            .iter()
-            .filter(|(_, inst)| now.duration_since(inst.last_used) >= ttl)
-            .map(|(venv, _)| venv.clone())
-            .collect()
+        expired_oldest_first(
+            self.backends
+                .iter()
+                .filter(|(venv, _)| !self.pinned.contains(*venv))
+                .map(|(venv, inst)| (venv, inst.last_used, inst.session)),
+            ttl,
+            Instant::now(),
+        )
     }
 
     /// Get a clone of the sender for spawning reader tasks
@@ -272,6 +515,14 @@ impl BackendPool {
             .collect()
     }
 
+    /// A backend with project files left to preindex, if any
+    pub fn next_preindex_venv(&self) -> Option<PathBuf> {
+        self.backends
+            .iter()
+            .find(|(_, inst)| !inst.preindex_queue.is_empty())
+            .map(|(venv, _)| venv.clone())
+    }
+
     /// Return the nearest warmup deadline among all warming backends.
     /// Returns None if no backends are warming.
     pub fn nearest_warmup_deadline(&self) -> Option<Instant> {
@@ -292,10 +543,10 @@ pub fn spawn_reader_task(
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
-            let result = reader
-                .read_message()
-                .await
-                .map_err(BackendError::Communication);
+            let (result, raw) = match reader.read_message_with_raw().await {
+                Ok((message, raw)) => (Ok(message), raw),
+                Err(e) => (Err(BackendError::Communication(e)), None),
+            };
 
             let is_err = result.is_err();
 
@@ -303,6 +554,7 @@ pub fn spawn_reader_task(
                 venv_path: venv_path.clone(),
                 session,
                 result,
+                raw,
             };
 
             if tx.send(msg).await.is_err() {
@@ -329,7 +581,10 @@ pub fn spawn_reader_task(
 }
 
 /// Shutdown and clean up a backend instance (abort reader, fire-and-forget shutdown)
-pub fn shutdown_backend_instance(instance: BackendInstance) {
+pub fn shutdown_backend_instance(
+    instance: BackendInstance,
+    kill_signal: KillSignal,
+) -> tokio::task::JoinHandle<()> {
     instance.reader_task.abort();
     let venv_display = instance.venv_path.display().to_string();
     shutdown_fire_and_forget(
@@ -337,5 +592,156 @@ pub fn shutdown_backend_instance(instance: BackendInstance) {
         instance.child,
         instance.next_id,
         venv_display,
-    );
+        kill_signal,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: i64) -> RpcMessage {
+        RpcMessage::request(RpcId::Number(id), "textDocument/definition", None)
+    }
+
+    #[test]
+    fn push_bounded_drops_oldest_when_full() {
+        let mut queue = Vec::new();
+        assert!(push_bounded(&mut queue, request(1), Some(2)).is_none());
+        assert!(push_bounded(&mut queue, request(2), Some(2)).is_none());
+
+        let dropped = push_bounded(&mut queue, request(3), Some(2)).unwrap();
+        assert_eq!(dropped.id, Some(RpcId::Number(1)));
+        let ids: Vec<_> = queue.iter().map(|m| m.id.clone().unwrap()).collect();
+        assert_eq!(ids, vec![RpcId::Number(2), RpcId::Number(3)]);
+    }
+
+    #[test]
+    fn push_bounded_unlimited() {
+        let mut queue = Vec::new();
+        for id in 0..100 {
+            assert!(push_bounded(&mut queue, request(id), None).is_none());
+        }
+        assert_eq!(queue.len(), 100);
+    }
+
+    #[test]
+    fn least_recently_used_breaks_ties_by_session() {
+        let now = Instant::now();
+        let (a, b, c) = (
+            PathBuf::from("/a/.venv"),
+            PathBuf::from("/b/.venv"),
+            PathBuf::from("/c/.venv"),
+        );
+
+        // Same instant: the older session (lower id) is evicted, regardless of order
+        let tied = [(&b, now, 7), (&a, now, 3), (&c, now, 5)];
+        assert_eq!(least_recently_used(tied.into_iter()), Some(a.clone()));
+        assert_eq!(least_recently_used(tied.into_iter().rev()), Some(a));
+
+        // last_used still takes precedence over session
+        let later = now + Duration::from_secs(1);
+        let mixed = [(&b, now, 9), (&c, later, 1)];
+        assert_eq!(least_recently_used(mixed.into_iter()), Some(b));
+    }
+
+    #[test]
+    fn expired_oldest_first_orders_by_last_used() {
+        let start = Instant::now();
+        let (a, b, c) = (
+            PathBuf::from("/a/.venv"),
+            PathBuf::from("/b/.venv"),
+            PathBuf::from("/c/.venv"),
+        );
+        let ttl = Duration::from_secs(60);
+        let now = start + Duration::from_secs(100);
+
+        // c is still within the TTL; b went idle before a
+        let backends = [
+            (&a, start + Duration::from_secs(30), 1),
+            (&c, start + Duration::from_secs(50), 3),
+            (&b, start, 2),
+        ];
+        assert_eq!(
+            expired_oldest_first(backends.into_iter(), ttl, now),
+            vec![b, a]
+        );
+    }
+
+    #[tokio::test]
+    async fn spawn_limiter_caps_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let limiter = SpawnLimiter::new(2);
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let (limiter, active, peak) = (limiter.clone(), active.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn autoscale_grows_on_thrash() {
+        let venv = Path::new("/proj/.venv");
+        let mut autoscale = AutoScale::new(2, 3);
+        let t0 = Instant::now();
+
+        // First re-creation after eviction: below threshold
+        autoscale.on_lru_eviction(venv, t0);
+        assert!(!autoscale.on_created(venv, t0 + Duration::from_secs(10)));
+        assert_eq!(autoscale.cap(), 2);
+
+        // Second re-creation within the window: grow
+        autoscale.on_lru_eviction(venv, t0 + Duration::from_secs(20));
+        assert!(autoscale.on_created(venv, t0 + Duration::from_secs(30)));
+        assert_eq!(autoscale.cap(), 3);
+
+        // Ceiling reached: no further growth
+        for i in 0..4 {
+            let t = t0 + Duration::from_secs(40 + i * 10);
+            autoscale.on_lru_eviction(venv, t);
+            assert!(!autoscale.on_created(venv, t + Duration::from_secs(1)));
+        }
+        assert_eq!(autoscale.cap(), 3);
+    }
+
+    #[test]
+    fn autoscale_ignores_slow_recreation_and_shrinks_when_idle() {
+        let venv = Path::new("/proj/.venv");
+        let mut autoscale = AutoScale::new(1, 4);
+        let t0 = Instant::now();
+
+        // Fresh venv (never evicted) and re-creation outside the window don't count
+        assert!(!autoscale.on_created(venv, t0));
+        autoscale.on_lru_eviction(venv, t0);
+        assert!(!autoscale.on_created(venv, t0 + THRASH_WINDOW * 2));
+
+        autoscale.on_lru_eviction(venv, t0);
+        autoscale.on_created(venv, t0 + Duration::from_secs(1));
+        autoscale.on_lru_eviction(venv, t0);
+        assert!(autoscale.on_created(venv, t0 + Duration::from_secs(2)));
+        assert_eq!(autoscale.cap(), 2);
+
+        // Still within the thrash window: keep the cap
+        assert!(!autoscale.on_idle(t0 + Duration::from_secs(60), 1));
+        // Quiet for a full window with spare room: shrink back to initial
+        assert!(autoscale.on_idle(t0 + THRASH_WINDOW * 2, 1));
+        assert_eq!(autoscale.cap(), 1);
+        assert!(!autoscale.on_idle(t0 + THRASH_WINDOW * 3, 0));
+    }
 }
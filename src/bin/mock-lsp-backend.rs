@@ -10,7 +10,7 @@ use std::process;
 use tokio::io;
 use typemux_cc::error::FramingError;
 use typemux_cc::framing::{LspFrameReader, LspFrameWriter};
-use typemux_cc::message::RpcMessage;
+use typemux_cc::message::{RpcId, RpcMessage};
 
 // ── Scenario types ──────────────────────────────────────────────────
 
@@ -32,14 +32,42 @@ struct Step {
 #[derive(Debug, Deserialize)]
 struct Expect {
     method: String,
+    /// When set, the received message's id must match (used to verify
+    /// that client responses are routed back with the original id).
+    #[serde(default)]
+    id: Option<Value>,
+    /// When set, every field here must be present (recursively) in the
+    /// received params with the same value.
+    #[serde(default)]
+    params: Option<Value>,
+    /// Like `params`, but checked against a received response's result.
+    #[serde(default)]
+    result: Option<Value>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum Action {
-    Respond { body: Value },
-    Notify { method: String, params: Value },
-    SleepMs { ms: u64 },
+    Respond {
+        body: Value,
+        /// Respond with this id instead of the received request's id
+        /// (simulates a late response for a request owned by another backend).
+        #[serde(default)]
+        id: Option<i64>,
+    },
+    Notify {
+        method: String,
+        params: Value,
+    },
+    Request {
+        id: i64,
+        method: String,
+        #[serde(default)]
+        params: Option<Value>,
+    },
+    SleepMs {
+        ms: u64,
+    },
     Crash,
     Eof,
 }
@@ -84,6 +112,37 @@ async fn main() {
             process::exit(1);
         }
 
+        if let Some(expected_id) = &step.expect.id {
+            let got_id = msg.id.as_ref().map(|id| serde_json::to_value(id).unwrap());
+            if got_id.as_ref() != Some(expected_id) {
+                eprintln!(
+                    "mock-lsp-backend: step {i}: expected id {expected_id}, got {:?}",
+                    got_id
+                );
+                process::exit(1);
+            }
+        }
+
+        if let Some(expected_params) = &step.expect.params {
+            let got_params = msg.params.clone().unwrap_or(Value::Null);
+            if !json_contains(&got_params, expected_params) {
+                eprintln!(
+                    "mock-lsp-backend: step {i}: expected params {expected_params}, got {got_params}"
+                );
+                process::exit(1);
+            }
+        }
+
+        if let Some(expected_result) = &step.expect.result {
+            let got_result = msg.result.clone().unwrap_or(Value::Null);
+            if !json_contains(&got_result, expected_result) {
+                eprintln!(
+                    "mock-lsp-backend: step {i}: expected result {expected_result}, got {got_result}"
+                );
+                process::exit(1);
+            }
+        }
+
         for action in &step.actions {
             execute_action(action, Some(&msg), &mut writer).await;
         }
@@ -150,9 +209,12 @@ async fn execute_action<W: tokio::io::AsyncWrite + Unpin>(
     writer: &mut LspFrameWriter<W>,
 ) {
     match action {
-        Action::Respond { body } => {
+        Action::Respond { body, id } => {
             let req = request.expect("respond action requires a preceding request");
-            let response = RpcMessage::success_response(req, body.clone());
+            let mut response = RpcMessage::success_response(req, body.clone());
+            if let Some(id) = id {
+                response.id = Some(RpcId::Number(*id));
+            }
             writer.write_message(&response).await.unwrap_or_else(|e| {
                 eprintln!("mock-lsp-backend: write error: {e}");
                 process::exit(1);
@@ -168,6 +230,13 @@ async fn execute_action<W: tokio::io::AsyncWrite + Unpin>(
                     process::exit(1);
                 });
         }
+        Action::Request { id, method, params } => {
+            let request = RpcMessage::request(RpcId::Number(*id), method, params.clone());
+            writer.write_message(&request).await.unwrap_or_else(|e| {
+                eprintln!("mock-lsp-backend: write error: {e}");
+                process::exit(1);
+            });
+        }
         Action::SleepMs { ms } => {
             tokio::time::sleep(std::time::Duration::from_millis(*ms)).await;
         }
@@ -181,3 +250,16 @@ async fn execute_action<W: tokio::io::AsyncWrite + Unpin>(
         }
     }
 }
+
+/// Check that every field of `expected` is present in `actual` with the same
+/// value (objects are compared recursively, everything else by equality).
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual_value| json_contains(actual_value, value))
+        }),
+        _ => actual == expected,
+    }
+}
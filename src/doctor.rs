@@ -82,6 +82,7 @@ fn env_var_name(id: &str) -> &'static str {
         "backend" => "TYPEMUX_CC_BACKEND",
         "max_backends" => "TYPEMUX_CC_MAX_BACKENDS",
         "backend_ttl" => "TYPEMUX_CC_BACKEND_TTL",
+        "warmup_timeout" => "TYPEMUX_CC_WARMUP_TIMEOUT",
         "log_file" => "TYPEMUX_CC_LOG_FILE",
         _ => "UNKNOWN",
     }
@@ -211,11 +212,14 @@ pub async fn collect_doctor_report(
         source: arg_source(matches, "backend_ttl", config_report),
     };
 
-    let warmup_timeout = backend_pool::warmup_timeout();
+    let warmup_timeout_value: String = matches
+        .get_one::<u64>("warmup_timeout")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| backend_pool::DEFAULT_WARMUP_TIMEOUT.as_secs().to_string());
     let warmup_timeout_item = ConfigItem {
         name: "warmup_timeout".to_string(),
-        value: warmup_timeout.as_secs().to_string(),
-        source: env_only_source("TYPEMUX_CC_WARMUP_TIMEOUT", config_report),
+        value: warmup_timeout_value,
+        source: arg_source(matches, "warmup_timeout", config_report),
     };
 
     let fanout_timeout = backend_pool::fanout_timeout();
@@ -267,12 +271,21 @@ pub async fn collect_doctor_report(
     // Environment: git toplevel and fallback venv
     let cwd = std::env::current_dir().unwrap_or_default();
     let git_toplevel = venv::get_git_toplevel(&cwd).await.ok().flatten();
-    let fallback_venv = venv::find_fallback_venv(&cwd).await.ok().flatten();
+    let venv_dirs: Vec<String> = matches
+        .get_many::<String>("venv_dirs")
+        .map(|names| names.cloned().collect())
+        .unwrap_or_else(|| vec![venv::DEFAULT_VENV_DIR.to_string()]);
+    let prefer_python = matches.get_one::<String>("prefer_python");
+    let fallback_venv =
+        venv::find_fallback_venv(&cwd, &venv_dirs, prefer_python.map(String::as_str))
+            .await
+            .ok()
+            .flatten();
 
     let environment = EnvironmentReport {
         backend_binary,
         git_toplevel: git_toplevel.map(|p| p.display().to_string()),
-        fallback_venv: fallback_venv.map(|p| p.display().to_string()),
+        fallback_venv: fallback_venv.map(|found| found.venv.display().to_string()),
     };
 
     // System info
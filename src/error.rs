@@ -19,6 +19,9 @@ pub enum ProxyError {
 
     #[error("Venv error: {0}")]
     Venv(#[from] VenvError),
+
+    #[error("{crashes} backend crashes within {window_secs}s, exiting")]
+    CrashThreshold { crashes: usize, window_secs: u64 },
 }
 
 #[derive(Error, Debug)]
@@ -37,6 +40,24 @@ pub enum BackendError {
 
     #[error("Initialize response error: {0}")]
     InitializeResponseError(String),
+
+    #[error("Backend for {venv} failed to spawn, next attempt in {retry_in_ms}ms")]
+    SpawnBackoff { venv: String, retry_in_ms: u64 },
+
+    #[error("Backend startup script failed: {0}")]
+    StartupScriptFailed(String),
+
+    #[error("Backend disabled for {0} after initialize timeout")]
+    Disabled(String),
+
+    #[error("Backend disabled for {0} after repeated crashes")]
+    CircuitOpen(String),
+
+    #[error("No backend for {0}: every pool slot holds a pinned venv")]
+    PoolPinned(String),
+
+    #[error("venv at {0} has no usable Python interpreter")]
+    NoInterpreter(String),
 }
 
 #[derive(Error, Debug)]
@@ -47,11 +68,40 @@ pub enum FramingError {
     #[error("Invalid Content-Length value")]
     InvalidContentLength,
 
+    #[error("Content-Length {0} exceeds the maximum frame size")]
+    ContentLengthTooLarge(usize),
+
+    #[error("Decoded body exceeds the maximum frame size")]
+    DecodedBodyTooLarge,
+
+    #[error("Header line exceeds {0} bytes")]
+    HeaderLineTooLong(usize),
+
+    #[error("Unsupported Content-Encoding: {0}")]
+    UnsupportedContentEncoding(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("JSON error: {0}")]
-    Json(#[from] serde_json::Error),
+    /// A frame body that is not a JSON-RPC message. When reading, the stream
+    /// is still in sync, so readers may skip the frame and continue.
+    #[error("JSON error: {source}")]
+    Json {
+        source: serde_json::Error,
+        /// The request id, when the body is JSON with a usable `id`
+        id: Option<crate::message::RpcId>,
+    },
+
+    /// A batch frame that is empty or not a JSON array of objects. The stream
+    /// is still in sync.
+    #[error("Invalid batch: {0}")]
+    InvalidBatch(String),
+}
+
+impl From<serde_json::Error> for FramingError {
+    fn from(source: serde_json::Error) -> Self {
+        FramingError::Json { source, id: None }
+    }
 }
 
 #[derive(Error, Debug)]
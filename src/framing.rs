@@ -1,87 +1,546 @@
 use crate::error::FramingError;
-use crate::message::RpcMessage;
+use crate::message::{RpcId, RpcMessage};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 
-const CONTENT_LENGTH: &str = "Content-Length: ";
+const CONTENT_LENGTH: &str = "Content-Length";
+const CONTENT_ENCODING: &str = "Content-Encoding";
+
+/// Bodies at least this large are gzip-compressed once compression is enabled
+pub const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+/// Body encoding announced by a frame's `Content-Encoding` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentEncoding {
+    Identity,
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    fn parse(value: &str) -> Result<Self, FramingError> {
+        match value {
+            "identity" => Ok(Self::Identity),
+            "gzip" => Ok(Self::Gzip),
+            "deflate" => Ok(Self::Deflate),
+            other => Err(FramingError::UnsupportedContentEncoding(other.to_string())),
+        }
+    }
+
+    /// Decode a compressed frame body into plain JSON bytes
+    /// (`None` for identity: the body already is the JSON)
+    fn decode(self, content: &[u8]) -> Result<Option<Vec<u8>>, FramingError> {
+        self.decode_limited(content, MAX_CONTENT_LENGTH)
+    }
+
+    /// `decode`, failing once the decoded body would exceed `limit` bytes:
+    /// a small compressed frame must not inflate into gigabytes
+    fn decode_limited(self, content: &[u8], limit: usize) -> Result<Option<Vec<u8>>, FramingError> {
+        let mut decoded = Vec::new();
+        let take = limit as u64 + 1;
+        match self {
+            Self::Identity => return Ok(None),
+            Self::Gzip => GzDecoder::new(content)
+                .take(take)
+                .read_to_end(&mut decoded)?,
+            Self::Deflate => ZlibDecoder::new(content)
+                .take(take)
+                .read_to_end(&mut decoded)?,
+        };
+        if decoded.len() > limit {
+            return Err(FramingError::DecodedBodyTooLarge);
+        }
+        Ok(Some(decoded))
+    }
+}
+
+/// One frame's payload: a single message, or a JSON-RPC batch (top-level array)
+#[derive(Debug)]
+pub enum Incoming {
+    Single(RpcMessage),
+    /// Batch elements in order; an element that is not a JSON-RPC message is
+    /// a `FramingError::Json` and doesn't affect the others
+    Batch(Vec<Result<RpcMessage, FramingError>>),
+}
 
 /// LSP frame reader
 pub struct LspFrameReader<R> {
     reader: BufReader<R>,
+    strict_json: bool,
+    /// Frame body buffer, reused across messages (except by
+    /// `read_message_with_raw`, which hands it out)
+    body: Vec<u8>,
+    /// Header line buffer, reused across lines
+    header_line: Vec<u8>,
+    /// Headers of the frame being read so far. With `body_read`, this keeps
+    /// a read that was cancelled half-way (a losing `select!` branch)
+    /// resumable instead of losing the bytes already consumed
+    partial_headers: (Option<usize>, ContentEncoding),
+    /// Bytes of `body` read so far, once the frame's headers are complete
+    body_read: Option<usize>,
+    /// Encoding of the frame whose body is being read
+    body_encoding: ContentEncoding,
 }
 
+/// Default read buffer capacity (matches `BufReader::new`)
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A reused buffer grown past this by one huge frame is released afterwards
+/// rather than held for the life of the connection
+const MAX_RETAINED_BUFFER: usize = 1024 * 1024;
+
 impl<R: AsyncRead + Unpin> LspFrameReader<R> {
     pub fn new(reader: R) -> Self {
+        Self::with_capacity(DEFAULT_READ_BUFFER_SIZE, reader)
+    }
+
+    /// Create a reader whose internal buffer holds `capacity` bytes.
+    /// Larger buffers mean fewer read syscalls for big, frequent frames.
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
         Self {
-            reader: BufReader::new(reader),
+            reader: BufReader::with_capacity(capacity, reader),
+            strict_json: false,
+            body: Vec::new(),
+            header_line: Vec::new(),
+            partial_headers: (None, ContentEncoding::Identity),
+            body_read: None,
+            body_encoding: ContentEncoding::Identity,
         }
     }
 
+    /// Enable strict parsing: messages with unknown top-level fields are
+    /// flagged with a warning instead of being silently accepted.
+    pub fn set_strict_json(&mut self, strict_json: bool) {
+        self.strict_json = strict_json;
+    }
+
     /// Read one LSP message
     pub async fn read_message(&mut self) -> Result<RpcMessage, FramingError> {
-        // 1. Read header section
-        let content_length = self.read_headers().await?;
+        let encoding = self.read_frame().await?;
+        let result = match encoding.decode(&self.body) {
+            Ok(Some(decoded)) => self.parse_body(&decoded),
+            Ok(None) => self.parse_body(&self.body),
+            Err(e) => Err(e),
+        };
+        release_if_oversized(&mut self.body);
+        result
+    }
 
-        // 2. Read content section
-        let mut content = vec![0u8; content_length];
-        self.reader.read_exact(&mut content).await?;
+    /// Read one frame, accepting a JSON-RPC batch as well as a single message.
+    /// Only a body whose first non-whitespace byte is `[` takes the batch
+    /// path; everything else is parsed exactly like [`Self::read_message`].
+    pub async fn read_message_or_batch(&mut self) -> Result<Incoming, FramingError> {
+        let encoding = self.read_frame().await?;
+        let result = match encoding.decode(&self.body) {
+            Ok(Some(decoded)) => self.parse_incoming(&decoded),
+            Ok(None) => self.parse_incoming(&self.body),
+            Err(e) => Err(e),
+        };
+        release_if_oversized(&mut self.body);
+        result
+    }
 
-        // 3. Parse as JSON
-        let message: RpcMessage = serde_json::from_slice(&content)?;
+    /// Read one LSP message together with its (decoded) body, so a message
+    /// forwarded unchanged can be written with [`LspFrameWriter::write_raw`]
+    /// instead of being serialized again.
+    ///
+    /// In strict mode a body with unknown top-level fields is not handed out:
+    /// the message must be serialized again so the output is normalised.
+    ///
+    /// The body buffer itself is handed out, so unlike [`Self::read_message`]
+    /// every frame allocates its own; only the header buffer is reused.
+    pub async fn read_message_with_raw(
+        &mut self,
+    ) -> Result<(RpcMessage, Option<Vec<u8>>), FramingError> {
+        let encoding = self.read_frame().await?;
+        let content = match encoding.decode(&self.body) {
+            Ok(Some(decoded)) => {
+                release_if_oversized(&mut self.body);
+                decoded
+            }
+            Ok(None) => std::mem::take(&mut self.body),
+            Err(e) => {
+                release_if_oversized(&mut self.body);
+                return Err(e);
+            }
+        };
+        let (message, exact) = self.parse_body_checked(&content)?;
+        Ok((message, exact.then_some(content)))
+    }
 
-        Ok(message)
+    /// Read the header section and the (still encoded) body into `self.body`.
+    /// Cancel safe: a cancelled call is picked up by the next one.
+    async fn read_frame(&mut self) -> Result<ContentEncoding, FramingError> {
+        if self.body_read.is_none() {
+            let (content_length, encoding) = self.read_headers().await?;
+            self.body.clear();
+            self.body.resize(content_length, 0);
+            self.body_encoding = encoding;
+            self.body_read = Some(0);
+        }
+        while let Some(filled) = self.body_read.filter(|&filled| filled < self.body.len()) {
+            let n = self.reader.read(&mut self.body[filled..]).await?;
+            if n == 0 {
+                self.body_read = None;
+                return Err(FramingError::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "EOF while reading body",
+                )));
+            }
+            self.body_read = Some(filled + n);
+        }
+        self.body_read = None;
+        Ok(self.body_encoding)
+    }
+
+    /// Parse a decoded body as JSON
+    fn parse_body(&self, content: &[u8]) -> Result<RpcMessage, FramingError> {
+        self.parse_body_checked(content).map(|(message, _)| message)
+    }
+
+    /// Parse a decoded body as JSON, also telling whether the body may stand
+    /// in for the message (false when strict mode dropped unknown fields)
+    fn parse_body_checked(&self, content: &[u8]) -> Result<(RpcMessage, bool), FramingError> {
+        if self.strict_json {
+            if let Ok(message) = RpcMessage::from_slice_strict(content) {
+                return Ok((message, true));
+            }
+            // Strict parse failed: parse leniently (surfacing genuine JSON errors)
+            // and report which fields were rejected
+            let message = parse_message(content)?;
+            tracing::warn!(
+                method = ?message.method,
+                id = ?message.id,
+                unknown_fields = ?RpcMessage::unknown_top_level_fields(content),
+                "Message has unknown top-level fields (strict JSON mode)"
+            );
+            return Ok((message, false));
+        }
+
+        parse_message(content).map(|message| (message, true))
+    }
+
+    fn parse_incoming(&self, content: &[u8]) -> Result<Incoming, FramingError> {
+        let is_batch = content
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'[');
+        if !is_batch {
+            return self.parse_body(content).map(Incoming::Single);
+        }
+
+        let elements: Vec<serde_json::Value> = serde_json::from_slice(content)
+            .map_err(|e| FramingError::InvalidBatch(e.to_string()))?;
+        if elements.is_empty() {
+            return Err(FramingError::InvalidBatch("empty batch".to_string()));
+        }
+        let messages = elements
+            .iter()
+            .map(|element| match serde_json::to_vec(element) {
+                Ok(element) => self.parse_body(&element),
+                Err(source) => Err(FramingError::Json { source, id: None }),
+            })
+            .collect();
+        Ok(Incoming::Batch(messages))
     }
 
-    async fn read_headers(&mut self) -> Result<usize, FramingError> {
-        let mut content_length: Option<usize> = None;
+    /// Read the header section. Cancel safe: the current line and the
+    /// headers parsed so far are kept in `self`.
+    async fn read_headers(&mut self) -> Result<(usize, ContentEncoding), FramingError> {
+        let result = self.read_header_lines().await;
+        // Done with this header section, or it was rejected: start afresh
+        self.partial_headers = (None, ContentEncoding::Identity);
+        result
+    }
 
+    async fn read_header_lines(&mut self) -> Result<(usize, ContentEncoding), FramingError> {
         loop {
-            let mut line = String::new();
-            let bytes_read = self.reader.read_line(&mut line).await?;
+            // Read raw bytes: a non-UTF-8 header must surface as a framing
+            // error on that header, not as an I/O error from read_line.
+            // Bounded, so a peer that never sends a newline can't grow the
+            // buffer without limit
+            let remaining = (MAX_HEADER_LINE + 1).saturating_sub(self.header_line.len());
+            let bytes_read = (&mut self.reader)
+                .take(remaining as u64)
+                .read_until(b'\n', &mut self.header_line)
+                .await?;
+            if self.header_line.len() > MAX_HEADER_LINE {
+                self.header_line = Vec::new();
+                return Err(FramingError::HeaderLineTooLong(MAX_HEADER_LINE));
+            }
 
-            // Detect EOF (read_line returns 0)
+            // Detect EOF (read_until returns 0)
             if bytes_read == 0 {
+                self.header_line.clear();
                 return Err(FramingError::Io(std::io::Error::new(
                     std::io::ErrorKind::UnexpectedEof,
                     "EOF while reading headers",
                 )));
             }
 
-            // Empty line (\r\n only) marks end of headers
-            if line == "\r\n" {
+            let end_of_headers = parse_header_line(&self.header_line, &mut self.partial_headers);
+            self.header_line.clear();
+            if end_of_headers? {
                 break;
             }
-
-            // Parse Content-Length header
-            let line = line.trim();
-            if let Some(len_str) = line.strip_prefix(CONTENT_LENGTH) {
-                content_length = Some(
-                    len_str
-                        .parse()
-                        .map_err(|_| FramingError::InvalidContentLength)?,
-                );
-            }
-            // Ignore Content-Type (assume UTF-8)
         }
 
-        content_length.ok_or(FramingError::MissingContentLength)
+        release_if_oversized(&mut self.header_line);
+        let (content_length, encoding) = self.partial_headers;
+        let content_length = content_length.ok_or(FramingError::MissingContentLength)?;
+        Ok((content_length, encoding))
+    }
+}
+
+/// Apply one header line to the headers parsed so far; true for the empty
+/// line that ends the header section
+fn parse_header_line(
+    line: &[u8],
+    headers: &mut (Option<usize>, ContentEncoding),
+) -> Result<bool, FramingError> {
+    let line = String::from_utf8_lossy(line);
+
+    // Empty line (\r\n only) marks end of headers
+    if line == "\r\n" {
+        return Ok(true);
     }
+
+    // Header names are case-insensitive; whitespace around name and value is ignored
+    let Some((name, value)) = line.split_once(':') else {
+        return Ok(false);
+    };
+    let (name, value) = (name.trim(), value.trim());
+    if name.eq_ignore_ascii_case(CONTENT_LENGTH) {
+        headers.0 = Some(parse_content_length(value)?);
+    } else if name.eq_ignore_ascii_case(CONTENT_ENCODING) {
+        headers.1 = ContentEncoding::parse(value)?;
+    }
+    // Ignore Content-Type (assume UTF-8)
+    Ok(false)
+}
+
+fn release_if_oversized(buffer: &mut Vec<u8>) {
+    if buffer.capacity() > MAX_RETAINED_BUFFER {
+        *buffer = Vec::new();
+    }
+}
+
+/// Longest header line accepted from a peer, terminator included
+pub const MAX_HEADER_LINE: usize = 8 * 1024;
+
+/// Largest body accepted from a peer; a bogus header must not make us
+/// allocate gigabytes before the read fails
+pub const MAX_CONTENT_LENGTH: usize = 256 * 1024 * 1024;
+
+/// Parse a `Content-Length` value: ASCII digits only (surrounding whitespace
+/// allowed, no sign), without overflow, and at most [`MAX_CONTENT_LENGTH`].
+fn parse_content_length(value: &str) -> Result<usize, FramingError> {
+    let digits = value.trim();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(FramingError::InvalidContentLength);
+    }
+    // All digits, so the only possible failure is overflow
+    let length: usize = digits
+        .parse()
+        .map_err(|_| FramingError::InvalidContentLength)?;
+    if length > MAX_CONTENT_LENGTH {
+        return Err(FramingError::ContentLengthTooLarge(length));
+    }
+    Ok(length)
+}
+
+/// Id a request of batch `batch` is dispatched under; no client picks it
+fn batch_request_id(batch: u64, id: &RpcId) -> RpcId {
+    let id = match id {
+        RpcId::Number(n) => n.to_string(),
+        RpcId::String(s) => s.clone(),
+    };
+    RpcId::String(format!("typemux-batch-{batch}:{id}"))
+}
+
+/// Parse a frame body, recovering the request id for malformed messages when
+/// the body is at least valid JSON
+fn parse_message(content: &[u8]) -> Result<RpcMessage, FramingError> {
+    serde_json::from_slice(content).map_err(|source| {
+        let id = serde_json::from_slice::<serde_json::Value>(content)
+            .ok()
+            .and_then(|mut value| value.get_mut("id").map(serde_json::Value::take))
+            .and_then(|id| serde_json::from_value::<RpcId>(id).ok());
+        FramingError::Json { source, id }
+    })
+}
+
+/// Responses to a client batch, held until every request in it is answered
+struct PendingBatch {
+    /// `(id the request was dispatched under, id the client gave it)`
+    unanswered: Vec<(RpcId, RpcId)>,
+    responses: Vec<serde_json::Value>,
 }
 
 /// LSP frame writer
 pub struct LspFrameWriter<W> {
     writer: W,
+    compression_threshold: Option<usize>,
+    batches: Vec<PendingBatch>,
+    /// Sequence number of the next batch, to key its request ids
+    next_batch: u64,
 }
 
 impl<W: AsyncWrite + Unpin> LspFrameWriter<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            compression_threshold: None,
+            batches: Vec::new(),
+            next_batch: 0,
+        }
+    }
+
+    /// Gzip bodies of at least `threshold` bytes and mark them with
+    /// `Content-Encoding: gzip` (None = never compress). Only enable this once
+    /// the peer has advertised that it can decode compressed frames.
+    pub fn set_compression_threshold(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// Mutable access to the underlying writer (bypasses framing)
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Collect the responses to the requests in `messages` (one JSON-RPC
+    /// batch) and write them, with the error responses for its `invalid`
+    /// elements, as one batch array once the last is answered. A batch of
+    /// notifications only gets no response at all.
+    ///
+    /// The requests are given ids unique to this batch, so a response to a
+    /// request outside the batch can't be mistaken for one of them; the
+    /// client's ids are restored in the batch response.
+    pub async fn begin_batch(
+        &mut self,
+        messages: &mut [RpcMessage],
+        invalid: Vec<serde_json::Value>,
+    ) -> Result<(), FramingError> {
+        let batch = self.next_batch;
+        self.next_batch += 1;
+        let mut unanswered = Vec::new();
+        for msg in messages.iter_mut().filter(|m| m.is_request()) {
+            let Some(id) = msg.id.take() else { continue };
+            let key = batch_request_id(batch, &id);
+            msg.id = Some(key.clone());
+            unanswered.push((key, id));
+        }
+        if unanswered.is_empty() && invalid.is_empty() {
+            return Ok(());
+        }
+        self.batches.push(PendingBatch {
+            unanswered,
+            responses: invalid,
+        });
+        self.write_completed_batches().await
+    }
+
+    /// The id a request of a pending batch was dispatched under, for a
+    /// `$/cancelRequest` naming it by the client's id
+    pub fn batch_request_id(&self, id: &RpcId) -> Option<RpcId> {
+        self.batches
+            .iter()
+            .flat_map(|batch| &batch.unanswered)
+            .find(|(_, original)| original == id)
+            .map(|(key, _)| key.clone())
+    }
+
+    /// Write a JSON value as one frame, for responses `RpcMessage` can't
+    /// express (`"id": null`)
+    pub async fn write_value(&mut self, value: &serde_json::Value) -> Result<(), FramingError> {
+        self.write_body(serde_json::to_vec(value)?).await
     }
 
     /// Write LSP message
     pub async fn write_message(&mut self, message: &RpcMessage) -> Result<(), FramingError> {
-        let content = serde_json::to_vec(message)?;
+        if self.hold_for_batch(message) {
+            return self.write_completed_batches().await;
+        }
+        self.write_body(serde_json::to_vec(message)?).await
+    }
+
+    /// Write a message body exactly as it was read, skipping serialization.
+    ///
+    /// `body` must be the raw body of `message` from
+    /// [`LspFrameReader::read_message_with_raw`], and `message` must not have
+    /// been modified since; otherwise use [`Self::write_message`]. Unlike
+    /// re-serialization, top-level fields unknown to `RpcMessage` are kept
+    /// (except in a response held for a batch, which is serialized with it).
+    pub async fn write_raw(
+        &mut self,
+        message: &RpcMessage,
+        body: Vec<u8>,
+    ) -> Result<(), FramingError> {
+        tracing::trace!(
+            method = ?message.method,
+            id = ?message.id,
+            bytes = body.len(),
+            "Writing message body unchanged"
+        );
+        if self.hold_for_batch(message) {
+            return self.write_completed_batches().await;
+        }
+        self.write_body(body).await
+    }
+
+    /// Keep a response that answers a request of a pending batch
+    fn hold_for_batch(&mut self, message: &RpcMessage) -> bool {
+        let Some(id) = message.id.as_ref().filter(|_| message.is_response()) else {
+            return false;
+        };
+        for batch in &mut self.batches {
+            if let Some(pos) = batch.unanswered.iter().position(|(key, _)| key == id) {
+                let (_, original) = batch.unanswered.swap_remove(pos);
+                let mut response = message.clone();
+                response.id = Some(original);
+                match serde_json::to_value(&response) {
+                    Ok(value) => batch.responses.push(value),
+                    Err(e) => {
+                        tracing::error!(id = ?response.id, error = %e, "Dropping unserializable batch response");
+                    }
+                }
+                return true;
+            }
+        }
+        false
+    }
 
-        let header = format!("Content-Length: {}\r\n\r\n", content.len());
+    async fn write_completed_batches(&mut self) -> Result<(), FramingError> {
+        while let Some(pos) = self.batches.iter().position(|b| b.unanswered.is_empty()) {
+            let batch = self.batches.remove(pos);
+            tracing::debug!(responses = batch.responses.len(), "Writing batch response");
+            self.write_body(serde_json::to_vec(&batch.responses)?)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Frame `content` (compressing it when over the threshold) and write it
+    async fn write_body(&mut self, content: Vec<u8>) -> Result<(), FramingError> {
+        let compress = self
+            .compression_threshold
+            .is_some_and(|threshold| content.len() >= threshold);
+        let (content, header) = if compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(&content)?;
+            let compressed = encoder.finish()?;
+            let header = format!(
+                "Content-Length: {}\r\nContent-Encoding: gzip\r\n\r\n",
+                compressed.len()
+            );
+            (compressed, header)
+        } else {
+            let header = format!("Content-Length: {}\r\n\r\n", content.len());
+            (content, header)
+        };
 
         self.writer.write_all(header.as_bytes()).await?;
         self.writer.write_all(&content).await?;
@@ -94,6 +553,156 @@ impl<W: AsyncWrite + Unpin> LspFrameWriter<W> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_content_length() {
+        assert_eq!(parse_content_length("46").unwrap(), 46);
+        assert_eq!(parse_content_length("  46 \t").unwrap(), 46);
+        for bad in ["+5", "-5", "", "  ", "4 6", "0x10", "５"] {
+            assert!(
+                matches!(
+                    parse_content_length(bad),
+                    Err(FramingError::InvalidContentLength)
+                ),
+                "{bad:?} should be rejected"
+            );
+        }
+        // Overflows usize
+        assert!(matches!(
+            parse_content_length("99999999999999999999999999"),
+            Err(FramingError::InvalidContentLength)
+        ));
+        assert!(matches!(
+            parse_content_length(&(MAX_CONTENT_LENGTH + 1).to_string()),
+            Err(FramingError::ContentLengthTooLarge(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_message_malformed_body_keeps_stream_in_sync() {
+        let mut input = Vec::new();
+        for body in [
+            &b"not json"[..],
+            &br#"{"jsonrpc":"2.0","id":4,"method":17}"#[..],
+            &br#"{"jsonrpc":"2.0","method":"initialized"}"#[..],
+        ] {
+            input.extend_from_slice(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes());
+            input.extend_from_slice(body);
+        }
+        let mut reader = LspFrameReader::new(&input[..]);
+
+        assert!(matches!(
+            reader.read_message().await,
+            Err(FramingError::Json { id: None, .. })
+        ));
+        assert!(matches!(
+            reader.read_message().await,
+            Err(FramingError::Json {
+                id: Some(RpcId::Number(4)),
+                ..
+            })
+        ));
+        let next = reader.read_message().await.unwrap();
+        assert_eq!(next.method_name(), Some("initialized"));
+    }
+
+    #[tokio::test]
+    async fn test_read_headers_case_and_whitespace_tolerant() {
+        for header in [
+            "content-length:2\r\n\r\n{}",
+            "CONTENT-LENGTH: 2\r\n\r\n{}",
+            "Content-Length :\t2 \r\n\r\n{}",
+            "  Content-Length:2\r\ncontent-type: application/vscode-jsonrpc\r\n\r\n{}",
+        ] {
+            let mut reader = LspFrameReader::new(header.as_bytes());
+            let (length, encoding) = reader.read_headers().await.unwrap();
+            assert_eq!(length, 2, "{header:?}");
+            assert_eq!(encoding, ContentEncoding::Identity);
+        }
+
+        let mut reader =
+            LspFrameReader::new(&b"content-encoding:gzip\r\nCONTENT-LENGTH:5\r\n\r\n"[..]);
+        let (length, encoding) = reader.read_headers().await.unwrap();
+        assert_eq!((length, encoding), (5, ContentEncoding::Gzip));
+
+        // A header line that only looks like the length is not accepted as one
+        let mut reader = LspFrameReader::new(&b"Content-Length-X: 2\r\n\r\n{}"[..]);
+        assert!(matches!(
+            reader.read_headers().await,
+            Err(FramingError::MissingContentLength)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_headers_rejects_bad_content_length() {
+        for header in [
+            &b"Content-Length: +5\r\n\r\n{}"[..],
+            &b"Content-Length: \xff\xfe\r\n\r\n{}"[..],
+        ] {
+            let mut reader = LspFrameReader::new(header);
+            assert!(matches!(
+                reader.read_message().await,
+                Err(FramingError::InvalidContentLength)
+            ));
+        }
+        // Padding after the colon is tolerated
+        let input = b"Content-Length:   2  \r\n\r\n{}";
+        let mut reader = LspFrameReader::new(&input[..]);
+        assert!(reader.read_headers().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_headers_rejects_overlong_line() {
+        let mut input = b"X-Padding: ".to_vec();
+        input.resize(MAX_HEADER_LINE * 4, b'a');
+        let mut reader = LspFrameReader::new(&input[..]);
+        assert!(matches!(
+            reader.read_message().await,
+            Err(FramingError::HeaderLineTooLong(MAX_HEADER_LINE))
+        ));
+
+        // A line exactly at the limit is still accepted
+        let mut line = b"X-Padding: ".to_vec();
+        line.resize(MAX_HEADER_LINE - 2, b'a');
+        line.extend_from_slice(b"\r\nContent-Length: 2\r\n\r\n{}");
+        let mut reader = LspFrameReader::new(&line[..]);
+        assert!(reader.read_headers().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_message_resumes_after_cancel() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#;
+        let (mut tx, rx) = tokio::io::duplex(1024);
+        let mut reader = LspFrameReader::new(rx);
+
+        // Cancelled once with only the headers, once with half the body
+        tx.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+            .await
+            .unwrap();
+        let timeout = Duration::from_millis(10);
+        assert!(tokio::time::timeout(timeout, reader.read_message())
+            .await
+            .is_err());
+        tx.write_all(&body[..10]).await.unwrap();
+        assert!(tokio::time::timeout(timeout, reader.read_message())
+            .await
+            .is_err());
+
+        tx.write_all(&body[10..]).await.unwrap();
+        let msg = reader.read_message().await.unwrap();
+        assert_eq!(msg.method_name(), Some("shutdown"));
+    }
+
+    #[tokio::test]
+    async fn test_read_message_with_capacity() {
+        let input =
+            b"Content-Length: 46\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\"}";
+        // Smaller than a single frame: still reassembled correctly
+        let mut reader = LspFrameReader::with_capacity(16, &input[..]);
+        let msg = reader.read_message().await.unwrap();
+        assert_eq!(msg.method_name(), Some("initialize"));
+    }
 
     #[tokio::test]
     async fn test_read_message() {
@@ -105,6 +714,197 @@ mod tests {
         assert!(msg.is_request());
     }
 
+    #[tokio::test]
+    async fn test_reused_buffers_across_frame_sizes() {
+        let frame = |text: &str| {
+            let body = serde_json::to_vec(&RpcMessage::notification(
+                "test",
+                Some(serde_json::json!({ "text": text })),
+            ))
+            .unwrap();
+            let mut frame = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+            frame.extend_from_slice(&body);
+            frame
+        };
+        let texts = [
+            "a".repeat(10),
+            "b".repeat(MAX_RETAINED_BUFFER + 1),
+            "c".to_string(),
+            "d".repeat(4096),
+            String::new(),
+        ];
+        let input: Vec<u8> = texts.iter().flat_map(|t| frame(t)).collect();
+
+        let mut reader = LspFrameReader::new(&input[..]);
+        for text in &texts {
+            let msg = reader.read_message().await.unwrap();
+            assert_eq!(msg.params.unwrap()["text"], text.as_str());
+            // A shorter frame never sees leftovers of a longer one, and the
+            // huge frame's buffer is not kept
+            assert!(reader.body.capacity() <= MAX_RETAINED_BUFFER);
+        }
+
+        // The raw path hands out exactly the frame body
+        let mut reader = LspFrameReader::new(&input[..]);
+        for text in &texts {
+            let (msg, raw) = reader.read_message_with_raw().await.unwrap();
+            let raw = raw.unwrap();
+            assert!(frame(text).ends_with(&raw));
+            assert_eq!(msg.params.unwrap()["text"], text.as_str());
+            assert!(reader.body.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_message_strict_json_unknown_field() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"initialize","extra":true}"#;
+        let mut input = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+        input.extend_from_slice(body);
+
+        assert!(RpcMessage::from_slice_strict(body).is_err());
+        assert_eq!(RpcMessage::unknown_top_level_fields(body), vec!["extra"]);
+
+        // Strict mode flags the field but still delivers the message
+        let mut reader = LspFrameReader::new(&input[..]);
+        reader.set_strict_json(true);
+        let msg = reader.read_message().await.unwrap();
+        assert_eq!(msg.method_name(), Some("initialize"));
+
+        // ...but does not hand out the body, which would keep the field
+        let mut reader = LspFrameReader::new(&input[..]);
+        reader.set_strict_json(true);
+        let (msg, raw) = reader.read_message_with_raw().await.unwrap();
+        assert_eq!(msg.method_name(), Some("initialize"));
+        assert!(raw.is_none());
+        let (_, raw) = LspFrameReader::new(&input[..])
+            .read_message_with_raw()
+            .await
+            .unwrap();
+        assert_eq!(raw.as_deref(), Some(&body[..]));
+    }
+
+    #[test]
+    fn test_strict_json_accepts_standard_fields() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"result":null}"#;
+        assert!(RpcMessage::from_slice_strict(body).is_ok());
+        assert!(RpcMessage::unknown_top_level_fields(body).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_message_or_batch() {
+        let mut input = Vec::new();
+        for body in [
+            &br#"{"jsonrpc":"2.0","method":"initialized"}"#[..],
+            &b" \r\n[{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"a\"},{\"jsonrpc\":\"2.0\",\"method\":\"b\"}]"[..],
+            &b"[]"[..],
+            &br#"[{"jsonrpc":"2.0","id":2,"method":17}]"#[..],
+        ] {
+            input.extend_from_slice(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes());
+            input.extend_from_slice(body);
+        }
+        let mut reader = LspFrameReader::new(&input[..]);
+
+        assert!(matches!(
+            reader.read_message_or_batch().await.unwrap(),
+            Incoming::Single(msg) if msg.method_name() == Some("initialized")
+        ));
+        let Incoming::Batch(batch) = reader.read_message_or_batch().await.unwrap() else {
+            panic!("expected a batch");
+        };
+        assert_eq!(batch.len(), 2);
+        let (first, second) = (batch[0].as_ref().unwrap(), batch[1].as_ref().unwrap());
+        assert!(first.is_request() && second.is_notification());
+        // An empty batch is rejected whole
+        assert!(matches!(
+            reader.read_message_or_batch().await,
+            Err(FramingError::InvalidBatch(_))
+        ));
+        // A bad element is reported on its own, with its id
+        let Incoming::Batch(batch) = reader.read_message_or_batch().await.unwrap() else {
+            panic!("expected a batch");
+        };
+        assert!(matches!(
+            &batch[..],
+            [Err(FramingError::Json {
+                id: Some(RpcId::Number(2)),
+                ..
+            })]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_responses_written_together() {
+        let mut writer = LspFrameWriter::new(Vec::new());
+        let mut batch = vec![
+            RpcMessage::request(RpcId::Number(1), "a", None),
+            RpcMessage::request(RpcId::Number(2), "b", None),
+        ];
+        let invalid = RpcMessage::invalid_request_response(None, "bad element");
+        writer.begin_batch(&mut batch, vec![invalid]).await.unwrap();
+        assert_ne!(
+            batch[0].id,
+            Some(RpcId::Number(1)),
+            "dispatched under a batch id"
+        );
+        assert_eq!(
+            writer.batch_request_id(&RpcId::Number(1)),
+            batch[0].id.clone()
+        );
+
+        writer
+            .write_message(&RpcMessage::success_response(
+                &batch[1],
+                serde_json::json!(2),
+            ))
+            .await
+            .unwrap();
+        // Not part of the batch: written right away, even with a batch
+        // request's client id
+        let notification = RpcMessage::notification("window/logMessage", None);
+        writer.write_message(&notification).await.unwrap();
+        let outside = RpcMessage::request(RpcId::Number(1), "c", None);
+        writer
+            .write_message(&RpcMessage::success_response(
+                &outside,
+                serde_json::json!("outside"),
+            ))
+            .await
+            .unwrap();
+        writer
+            .write_message(&RpcMessage::success_response(
+                &batch[0],
+                serde_json::json!(1),
+            ))
+            .await
+            .unwrap();
+
+        let output = writer.writer;
+        let mut reader = LspFrameReader::new(&output[..]);
+        assert!(matches!(
+            reader.read_message_or_batch().await.unwrap(),
+            Incoming::Single(msg) if msg.is_notification()
+        ));
+        assert!(matches!(
+            reader.read_message_or_batch().await.unwrap(),
+            Incoming::Single(msg) if msg.result == Some(serde_json::json!("outside"))
+        ));
+        let encoding = reader.read_frame().await.unwrap();
+        assert_eq!(encoding, ContentEncoding::Identity);
+        let responses: serde_json::Value = serde_json::from_slice(&reader.body).unwrap();
+        assert_eq!(
+            responses,
+            serde_json::json!([
+                {
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32600, "message": "bad element" }
+                },
+                { "jsonrpc": "2.0", "id": 2, "result": 2 },
+                { "jsonrpc": "2.0", "id": 1, "result": 1 }
+            ])
+        );
+    }
+
     #[tokio::test]
     async fn test_write_message() {
         let mut output = Vec::new();
@@ -113,4 +913,111 @@ mod tests {
         writer.write_message(&msg).await.unwrap();
         assert!(output.starts_with(b"Content-Length: "));
     }
+
+    #[tokio::test]
+    async fn test_compressed_frame_round_trip() {
+        let big = "x".repeat(4096);
+        let large = RpcMessage::notification("test", Some(serde_json::json!({ "text": big })));
+        let small = RpcMessage::notification("initialized", None);
+
+        let mut writer = LspFrameWriter::new(Vec::new());
+        writer.set_compression_threshold(Some(1024));
+        writer.write_message(&large).await.unwrap();
+        writer.write_message(&small).await.unwrap();
+
+        let output = writer.writer;
+        let text = String::from_utf8_lossy(&output);
+        assert_eq!(text.matches("Content-Encoding: gzip").count(), 1);
+        assert!(output.len() < big.len(), "large body was compressed");
+
+        let mut reader = LspFrameReader::new(&output[..]);
+        let first = reader.read_message().await.unwrap();
+        assert_eq!(first.params.unwrap()["text"].as_str().unwrap().len(), 4096);
+        let second = reader.read_message().await.unwrap();
+        assert_eq!(second.method_name(), Some("initialized"));
+    }
+
+    #[tokio::test]
+    async fn test_read_deflate_frame() {
+        let body = br#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut input = format!(
+            "Content-Length: {}\r\nContent-Encoding: deflate\r\n\r\n",
+            compressed.len()
+        )
+        .into_bytes();
+        input.extend_from_slice(&compressed);
+
+        let mut reader = LspFrameReader::new(&input[..]);
+        let msg = reader.read_message().await.unwrap();
+        assert_eq!(msg.method_name(), Some("exit"));
+    }
+
+    #[test]
+    fn test_decode_stops_at_limit() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[b' '; 4096]).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let decoded = ContentEncoding::Gzip
+            .decode_limited(&compressed, 4096)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.len(), 4096);
+        assert!(matches!(
+            ContentEncoding::Gzip.decode_limited(&compressed, 4095),
+            Err(FramingError::DecodedBodyTooLarge)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_unsupported_encoding() {
+        let input = b"Content-Length: 2\r\nContent-Encoding: br\r\n\r\n{}";
+        let mut reader = LspFrameReader::new(&input[..]);
+        let err = reader.read_message().await.unwrap_err();
+        assert!(matches!(err, FramingError::UnsupportedContentEncoding(e) if e == "br"));
+    }
+
+    #[tokio::test]
+    async fn test_raw_and_reserialized_writes_are_equivalent() {
+        let bodies = [
+            &br#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.py","diagnostics":[{"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":3}},"message":"\u00e9 undefined"}]}}"#[..],
+            &br#"{"jsonrpc":"2.0","id":"abc","result":{"contents":{"kind":"markdown","value":"x: int"}}}"#[..],
+            &br#"{"jsonrpc":"2.0","id":7,"error":{"code":-32601,"message":"nope"}}"#[..],
+        ];
+        for threshold in [None, Some(16)] {
+            for body in bodies {
+                let mut input = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+                input.extend_from_slice(body);
+                let (message, raw) = LspFrameReader::new(&input[..])
+                    .read_message_with_raw()
+                    .await
+                    .unwrap();
+                let raw = raw.unwrap();
+                assert_eq!(raw, body);
+
+                let mut raw_writer = LspFrameWriter::new(Vec::new());
+                raw_writer.set_compression_threshold(threshold);
+                raw_writer.write_raw(&message, raw).await.unwrap();
+                let mut serde_writer = LspFrameWriter::new(Vec::new());
+                serde_writer.set_compression_threshold(threshold);
+                serde_writer.write_message(&message).await.unwrap();
+
+                let read_back = |frame: Vec<u8>| async move {
+                    let (_, body) = LspFrameReader::new(&frame[..])
+                        .read_message_with_raw()
+                        .await
+                        .unwrap();
+                    serde_json::from_slice::<serde_json::Value>(&body.unwrap()).unwrap()
+                };
+                assert_eq!(
+                    read_back(raw_writer.writer).await,
+                    read_back(serde_writer.writer).await
+                );
+            }
+        }
+    }
 }
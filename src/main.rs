@@ -2,16 +2,22 @@ mod backend;
 mod backend_pool;
 mod config;
 mod doctor;
+mod metrics;
 mod proxy;
 
 pub use typemux_cc::{error, framing, message};
 mod state;
 mod text_edit;
 mod venv;
+mod warmup_state;
 
-use backend::BackendKind;
+use backend::{BackendKind, KillSignal, PyrightDiagnosticMode};
 use clap::{CommandFactory, FromArgMatches, Parser};
 use proxy::LspProxy;
+use state::{
+    DiagnosticCodeRule, InitTimeoutAction, ProxyOptions, StaleResponsePolicy,
+    VersionRegressionPolicy,
+};
 use std::path::PathBuf;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -29,11 +35,23 @@ struct Args {
     #[arg(long, env = "TYPEMUX_CC_MAX_BACKENDS", default_value = "8", value_parser = clap::value_parser!(u64).range(1..))]
     max_backends: u64,
 
+    /// Maximum number of backends spawned and initialized at the same time;
+    /// further venvs wait for a slot instead of spawning immediately
+    /// Can also be set via TYPEMUX_CC_MAX_CONCURRENT_SPAWNS environment variable
+    #[arg(long, env = "TYPEMUX_CC_MAX_CONCURRENT_SPAWNS", default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrent_spawns: u64,
+
     /// Backend TTL in seconds (default: 1800 = 30 minutes). Set to 0 to disable TTL eviction.
     /// Can also be set via TYPEMUX_CC_BACKEND_TTL environment variable
     #[arg(long, env = "TYPEMUX_CC_BACKEND_TTL", default_value = "1800")]
     backend_ttl: u64,
 
+    /// Keep at least this many backends (the most recently used) alive past
+    /// their TTL, so returning to a project is instant; capped at --max-backends
+    /// Can also be set via TYPEMUX_CC_MIN_IDLE environment variable
+    #[arg(long, env = "TYPEMUX_CC_MIN_IDLE", default_value = "0")]
+    min_idle: u64,
+
     /// LSP backend to use: pyright, ty, or pyrefly
     /// Can also be set via TYPEMUX_CC_BACKEND environment variable
     #[arg(
@@ -44,6 +62,645 @@ struct Args {
     )]
     backend: BackendKind,
 
+    /// Warn about messages with unknown top-level JSON-RPC fields (protocol debugging)
+    /// Can also be set via TYPEMUX_CC_STRICT_JSON environment variable
+    #[arg(long, env = "TYPEMUX_CC_STRICT_JSON")]
+    strict_json: bool,
+
+    /// Seconds a new backend queues index-dependent requests while it builds its
+    /// index (default: 2). Set to 0 to disable warmup gating.
+    /// Can also be set via TYPEMUX_CC_WARMUP_TIMEOUT environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_WARMUP_TIMEOUT",
+        value_name = "SECS",
+        default_value = "2"
+    )]
+    warmup_timeout: u64,
+
+    /// Remember recently warmed venvs in this JSON file so a backend respawned
+    /// shortly after a restart skips the warmup queue.
+    /// Can also be set via TYPEMUX_CC_WARMUP_STATE_PERSIST environment variable
+    #[arg(long, env = "TYPEMUX_CC_WARMUP_STATE_PERSIST", value_name = "PATH")]
+    warmup_state_persist: Option<PathBuf>,
+
+    /// Seconds a persisted warmup stays valid (default: 300)
+    /// Can also be set via TYPEMUX_CC_WARMUP_STATE_WINDOW environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_WARMUP_STATE_WINDOW",
+        value_name = "SECS",
+        default_value = "300"
+    )]
+    warmup_state_window: u64,
+
+    /// Maximum number of requests queued per backend during warmup (default: 0 = unbounded).
+    /// When exceeded, the oldest queued request is cancelled.
+    /// Can also be set via TYPEMUX_CC_WARMUP_QUEUE_LIMIT environment variable
+    #[arg(long, env = "TYPEMUX_CC_WARMUP_QUEUE_LIMIT", default_value = "0")]
+    warmup_queue_limit: u64,
+
+    /// Forward this method immediately even while a backend is warming up (repeatable),
+    /// e.g. `--no-warmup-method textDocument/references`
+    /// Can also be set via TYPEMUX_CC_NO_WARMUP_METHODS environment variable (comma-separated)
+    #[arg(
+        long = "no-warmup-method",
+        env = "TYPEMUX_CC_NO_WARMUP_METHODS",
+        value_name = "METHOD",
+        value_delimiter = ','
+    )]
+    no_warmup_methods: Vec<String>,
+
+    /// Forward methods starting with this prefix (repeatable), e.g. `pyright/`:
+    /// routed by document URI when present, otherwise sent to every backend
+    /// Can also be set via TYPEMUX_CC_FORWARD_CUSTOM_METHODS environment variable (comma-separated)
+    #[arg(
+        long = "forward-custom-methods",
+        env = "TYPEMUX_CC_FORWARD_CUSTOM_METHODS",
+        value_name = "PREFIX",
+        value_delimiter = ','
+    )]
+    forward_custom_methods: Vec<String>,
+
+    /// What to do when a backend answers a request it no longer owns (e.g. after
+    /// its session was replaced): drop, retry (re-issue once), or cancel
+    /// Can also be set via TYPEMUX_CC_ON_STALE_RESPONSE environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_ON_STALE_RESPONSE",
+        default_value = "cancel",
+        value_enum
+    )]
+    on_stale_response: StaleResponsePolicy,
+
+    /// Talk LSP with a single client connecting over TCP on this address
+    /// (e.g. 127.0.0.1:2087) instead of stdin/stdout
+    /// Can also be set via TYPEMUX_CC_LISTEN environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_LISTEN",
+        value_name = "HOST:PORT",
+        conflicts_with = "replay"
+    )]
+    listen: Option<String>,
+
+    /// Serve Prometheus-format metrics over HTTP on this address (e.g. 127.0.0.1:9464)
+    /// Can also be set via TYPEMUX_CC_METRICS_ADDR environment variable
+    #[arg(long, env = "TYPEMUX_CC_METRICS_ADDR", value_name = "HOST:PORT")]
+    metrics_addr: Option<String>,
+
+    /// Serve a JSON snapshot of the pool and queues on this Unix socket, one
+    /// per connection (read it with --status)
+    /// Can also be set via TYPEMUX_CC_STATUS_SOCKET environment variable
+    #[arg(long, env = "TYPEMUX_CC_STATUS_SOCKET", value_name = "PATH")]
+    status_socket: Option<PathBuf>,
+
+    /// languageId assumed when the client's didOpen omits it
+    /// Can also be set via TYPEMUX_CC_DEFAULT_LANGUAGE_ID environment variable
+    #[arg(long, env = "TYPEMUX_CC_DEFAULT_LANGUAGE_ID", default_value = "python")]
+    default_language_id: String,
+
+    /// Do not forward documents whose languageId is not `python` to the backends
+    /// Can also be set via TYPEMUX_CC_SKIP_NON_PYTHON_DOCUMENTS environment variable
+    #[arg(long, env = "TYPEMUX_CC_SKIP_NON_PYTHON_DOCUMENTS")]
+    skip_non_python_documents: bool,
+
+    /// JSON object deep-merged into the client's `capabilities` before initialize
+    /// is forwarded to backends, e.g. '{"textDocument":{"hover":{"contentFormat":["markdown"]}}}'
+    /// Can also be set via TYPEMUX_CC_CLIENT_CAPABILITIES_AUGMENT environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_CLIENT_CAPABILITIES_AUGMENT",
+        value_name = "JSON",
+        value_parser = parse_json_object
+    )]
+    client_capabilities_augment: Option<serde_json::Value>,
+
+    /// Grow the backend cap (up to --max-backends-ceiling) when venvs keep getting
+    /// evicted and re-created, and shrink it back when idle
+    /// Can also be set via TYPEMUX_CC_AUTO_SCALE_BACKENDS environment variable
+    #[arg(long, env = "TYPEMUX_CC_AUTO_SCALE_BACKENDS")]
+    auto_scale_backends: bool,
+
+    /// Hard ceiling for --auto-scale-backends (default: 2 x --max-backends)
+    /// Can also be set via TYPEMUX_CC_MAX_BACKENDS_CEILING environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_MAX_BACKENDS_CEILING",
+        requires = "auto_scale_backends",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_backends_ceiling: Option<u64>,
+
+    /// Milliseconds to wait for a hover response before answering from the cached
+    /// document instead (default: 0 = wait for the backend)
+    /// Can also be set via TYPEMUX_CC_HOVER_TIMEOUT environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_HOVER_TIMEOUT",
+        value_name = "MS",
+        default_value = "0"
+    )]
+    hover_timeout: u64,
+
+    /// Seconds to wait for a backend to answer a request before answering the
+    /// client with RequestCancelled (default: 0 = wait forever)
+    /// Can also be set via TYPEMUX_CC_REQUEST_TIMEOUT environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_REQUEST_TIMEOUT",
+        value_name = "SECS",
+        default_value = "0"
+    )]
+    request_timeout: u64,
+
+    /// Script run before each backend is spawned, with the venv path as argument
+    /// and VIRTUAL_ENV/PATH set; a non-zero exit (or running over 60s) aborts
+    /// backend creation. It runs in the background: other backends keep
+    /// serving while requests for its venv wait
+    /// Can also be set via TYPEMUX_CC_BACKEND_STARTUP_SCRIPT environment variable
+    #[arg(long, env = "TYPEMUX_CC_BACKEND_STARTUP_SCRIPT", value_name = "PATH")]
+    backend_startup_script: Option<PathBuf>,
+
+    /// Suppress an empty publishDiagnostics for a URI whose last forwarded
+    /// diagnostics were already empty (the first clear is always sent)
+    /// Can also be set via TYPEMUX_CC_DEDUP_EMPTY_DIAGNOSTICS environment variable
+    #[arg(long, env = "TYPEMUX_CC_DEDUP_EMPTY_DIAGNOSTICS")]
+    dedup_empty_diagnostics: bool,
+
+    /// Send an empty publishDiagnostics for a document when it closes, for
+    /// editors that keep showing a closed file's last diagnostics
+    /// Can also be set via TYPEMUX_CC_CLEAR_DIAGNOSTICS_ON_CLOSE environment variable
+    #[arg(long, env = "TYPEMUX_CC_CLEAR_DIAGNOSTICS_ON_CLOSE")]
+    clear_diagnostics_on_close: bool,
+
+    /// Remove ANSI escape sequences from backend window/logMessage and
+    /// window/showMessage text before forwarding it
+    /// Can also be set via TYPEMUX_CC_STRIP_ANSI environment variable
+    #[arg(long, env = "TYPEMUX_CC_STRIP_ANSI")]
+    strip_ansi: bool,
+
+    /// Log one event per forwarded response with the request's method, id,
+    /// venv, backend session and elapsed time (target typemux_cc::correlation)
+    /// Can also be set via TYPEMUX_CC_LOG_REQUEST_RESPONSE_CORRELATION environment variable
+    #[arg(long, env = "TYPEMUX_CC_LOG_REQUEST_RESPONSE_CORRELATION")]
+    log_request_response_correlation: bool,
+
+    /// When a backend crashes, re-issue its pending read-only requests (hover,
+    /// definition, references, ...) to the respawned backend under the same id
+    /// instead of cancelling them; each request is retried at most once
+    /// Can also be set via TYPEMUX_CC_TRANSPARENT_RETRY environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_TRANSPARENT_RETRY",
+        alias = "backend-restart-preserve-request"
+    )]
+    transparent_retry: bool,
+
+    /// Reject requests that carry no document URI (other than fan-out methods)
+    /// even when only one backend is running, instead of forwarding them to it,
+    /// to surface client bugs that a single-backend pool would mask
+    /// Can also be set via TYPEMUX_CC_STRICT_URI_ROUTING environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_STRICT_URI_ROUTING",
+        alias = "disable-fallback-first-backend-routing"
+    )]
+    strict_uri_routing: bool,
+
+    /// Exit with an error (after shutting down the remaining backends) once this
+    /// many backend crashes happen within --backend-crash-window, so a supervisor
+    /// can restart the proxy cleanly (default: 0 = never)
+    /// Can also be set via TYPEMUX_CC_SHUTDOWN_ON_BACKEND_CRASH_THRESHOLD environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_SHUTDOWN_ON_BACKEND_CRASH_THRESHOLD",
+        value_name = "COUNT",
+        default_value = "0"
+    )]
+    shutdown_on_backend_crash_threshold: u64,
+
+    /// Sliding window in seconds for --shutdown-on-backend-crash-threshold and
+    /// --circuit-breaker-threshold
+    /// Can also be set via TYPEMUX_CC_BACKEND_CRASH_WINDOW environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_BACKEND_CRASH_WINDOW",
+        value_name = "SECS",
+        default_value = "60"
+    )]
+    backend_crash_window: u64,
+
+    /// Stop respawning a venv's backend once it crashes this many times within
+    /// --backend-crash-window; requests for the venv get an error until
+    /// --circuit-breaker-cooldown passes (default: 0 = never)
+    /// Can also be set via TYPEMUX_CC_CIRCUIT_BREAKER_THRESHOLD environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_CIRCUIT_BREAKER_THRESHOLD",
+        value_name = "COUNT",
+        default_value = "0"
+    )]
+    circuit_breaker_threshold: u64,
+
+    /// Seconds a venv stays disabled after --circuit-breaker-threshold trips
+    /// Can also be set via TYPEMUX_CC_CIRCUIT_BREAKER_COOLDOWN environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_CIRCUIT_BREAKER_COOLDOWN",
+        value_name = "SECS",
+        default_value = "120"
+    )]
+    circuit_breaker_cooldown: u64,
+
+    /// Name reported to the client as `serverInfo.name` and used as the prefix of
+    /// proxy-generated error responses and window/showMessage notifications
+    /// Can also be set via TYPEMUX_CC_PROXY_NAME environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_PROXY_NAME",
+        value_name = "NAME",
+        default_value = "typemux-cc"
+    )]
+    proxy_name: String,
+
+    /// Capacity in bytes of the read buffer over each backend's stdout; larger
+    /// values reduce syscalls for backends publishing large diagnostics
+    /// Can also be set via TYPEMUX_CC_READ_BUFFER_SIZE environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_READ_BUFFER_SIZE",
+        value_name = "BYTES",
+        default_value = "8192",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    read_buffer_size: u64,
+
+    /// What to do with a didChange whose version is not greater than the cached
+    /// one (duplicate or out of order): warn (apply anyway), skip (drop it),
+    /// or error (drop it and show the client an error)
+    /// Can also be set via TYPEMUX_CC_DID_CHANGE_VERSION_CHECK environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_DID_CHANGE_VERSION_CHECK",
+        value_enum,
+        default_value_t = VersionRegressionPolicy::Warn
+    )]
+    did_change_version_check: VersionRegressionPolicy,
+
+    /// Drop a didChange that doesn't advance the document version and show the
+    /// client an error; same as `--did-change-version-check error`
+    /// Can also be set via TYPEMUX_CC_STRICT_VERSIONS environment variable
+    #[arg(long, env = "TYPEMUX_CC_STRICT_VERSIONS")]
+    strict_versions: bool,
+
+    /// Rewrite or suppress diagnostics by code, as <code>:<drop|error|warning|info|hint>
+    /// (repeatable), e.g. `--diagnostic-code-rule reportMissingImports:warning`
+    /// Can also be set via TYPEMUX_CC_DIAGNOSTIC_CODE_RULES environment variable
+    /// (semicolon-separated, as codes may contain commas)
+    #[arg(
+        long = "diagnostic-code-rule",
+        env = "TYPEMUX_CC_DIAGNOSTIC_CODE_RULES",
+        value_name = "CODE:ACTION",
+        value_delimiter = ';'
+    )]
+    diagnostic_code_rules: Vec<DiagnosticCodeRule>,
+
+    /// Log a one-line pool summary (pool size, warming, open documents, pending
+    /// requests, spawn/evict/crash totals) every SECS seconds (default: 0 = off)
+    /// Can also be set via TYPEMUX_CC_POOL_METRICS_LOG_INTERVAL environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_POOL_METRICS_LOG_INTERVAL",
+        value_name = "SECS",
+        default_value = "0"
+    )]
+    pool_metrics_log_interval: u64,
+
+    /// Non-standard leniency: a didChange for a document that was never opened
+    /// (or whose open state was lost) is treated as its didOpen when it carries
+    /// the full text, instead of being ignored
+    /// Can also be set via TYPEMUX_CC_ALLOW_DID_CHANGE_BEFORE_DID_OPEN environment variable
+    #[arg(long, env = "TYPEMUX_CC_ALLOW_DID_CHANGE_BEFORE_DID_OPEN")]
+    allow_did_change_before_did_open: bool,
+
+    /// Forward every didChange to backends as a single full-document change
+    /// built from the proxy's cached text, ignoring incremental ranges (for
+    /// isolating or working around incremental-sync bugs)
+    /// Can also be set via TYPEMUX_CC_FORCE_FULL_SYNC environment variable
+    #[arg(long, env = "TYPEMUX_CC_FORCE_FULL_SYNC")]
+    force_full_sync: bool,
+
+    /// Milliseconds of quiet before forwarding a document's didChanges to its
+    /// backend, coalesced into one full-document change (default: 0 = forward
+    /// each change immediately). Any other message for the document flushes it.
+    /// Can also be set via TYPEMUX_CC_DIDCHANGE_DEBOUNCE environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_DIDCHANGE_DEBOUNCE",
+        value_name = "MS",
+        default_value = "0"
+    )]
+    didchange_debounce: u64,
+
+    /// Keep the text of at most this many open documents (default: 0 = unbounded).
+    /// Beyond that, the least recently touched documents lose their cached text
+    /// and are not restored to a respawned backend.
+    /// Can also be set via TYPEMUX_CC_MAX_CACHED_DOCUMENTS environment variable
+    #[arg(long, env = "TYPEMUX_CC_MAX_CACHED_DOCUMENTS", default_value = "0")]
+    max_cached_documents: u64,
+
+    /// Remove code actions of this kind, including its sub-kinds (repeatable),
+    /// from textDocument/codeAction responses, e.g. `source` also drops
+    /// `source.organizeImports`
+    /// Can also be set via TYPEMUX_CC_FILTER_CODE_ACTION_KINDS environment variable (comma-separated)
+    #[arg(
+        long = "filter-code-action-kind",
+        env = "TYPEMUX_CC_FILTER_CODE_ACTION_KINDS",
+        value_name = "KIND",
+        value_delimiter = ','
+    )]
+    filter_code_action_kinds: Vec<String>,
+
+    /// Keep duplicate entries (same name, kind, uri and range) when merging
+    /// workspace/symbol results from several backends
+    /// Can also be set via TYPEMUX_CC_NO_DEDUP environment variable
+    #[arg(long, env = "TYPEMUX_CC_NO_DEDUP")]
+    no_dedup: bool,
+
+    /// After a backend initializes, send it didOpen for the Python files of its
+    /// project (git ls-files, or a bounded walk) so cross-file navigation works
+    /// before the editor opens them. Costs startup CPU; files are sent with
+    /// their on-disk content
+    /// Can also be set via TYPEMUX_CC_PREINDEX_OPEN_FILES environment variable
+    #[arg(long, env = "TYPEMUX_CC_PREINDEX_OPEN_FILES")]
+    preindex_open_files: bool,
+
+    /// After the client's initialized notification, start a backend for every
+    /// venv under the git toplevel (shallowest first) until the pool is full,
+    /// trading startup cost for first-request latency. Backends start in the
+    /// background; requests are served meanwhile
+    /// Can also be set via TYPEMUX_CC_PREWARM environment variable
+    #[arg(long, env = "TYPEMUX_CC_PREWARM")]
+    prewarm: bool,
+
+    /// Never evict the backend of this venv (repeatable), e.g.
+    /// `--pin-venv /work/app/.venv`; it stays up past the TTL and is skipped
+    /// by LRU eviction. Relative paths are resolved against the working directory
+    /// Can also be set via TYPEMUX_CC_PIN_VENVS environment variable (comma-separated)
+    #[arg(
+        long = "pin-venv",
+        env = "TYPEMUX_CC_PIN_VENVS",
+        value_name = "PATH",
+        value_delimiter = ','
+    )]
+    pinned_venvs: Vec<PathBuf>,
+
+    /// When a didOpen arrives for an already-open document with a different
+    /// languageId, send the backend a didClose before the new didOpen
+    /// Can also be set via TYPEMUX_CC_REOPEN_ON_LANGUAGE_ID_CHANGE environment variable
+    #[arg(long, env = "TYPEMUX_CC_REOPEN_ON_LANGUAGE_ID_CHANGE")]
+    reopen_on_language_id_change: bool,
+
+    /// Maximum client requests per second (bursts up to the same amount); excess
+    /// requests get a -32000 "rate limited" error instead of being forwarded
+    /// (default: 0 = unlimited)
+    /// Can also be set via TYPEMUX_CC_MAX_MESSAGE_RATE environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_MAX_MESSAGE_RATE",
+        value_name = "PER_SEC",
+        default_value = "0"
+    )]
+    max_message_rate: u32,
+
+    /// When a backend is recreated after a crash or eviction, re-publish the last
+    /// diagnostics of its files (source prefixed with "(stale)") until the new
+    /// backend publishes fresh ones
+    /// Can also be set via TYPEMUX_CC_BACKEND_RESPAWN_PRESERVE_DIAGNOSTICS environment variable
+    #[arg(long, env = "TYPEMUX_CC_BACKEND_RESPAWN_PRESERVE_DIAGNOSTICS")]
+    backend_respawn_preserve_diagnostics: bool,
+
+    /// Log, at info level, every directory probed while resolving a file's venv
+    /// and the final decision (diagnoses "why this venv?")
+    /// Can also be set via TYPEMUX_CC_TRACE_VENV_RESOLUTION environment variable
+    #[arg(long, env = "TYPEMUX_CC_TRACE_VENV_RESOLUTION")]
+    trace_venv_resolution: bool,
+
+    /// Spawn backends with a cleared environment except for this variable
+    /// (repeatable) plus the computed VIRTUAL_ENV/PATH (default: inherit all)
+    /// Can also be set via TYPEMUX_CC_BACKEND_ENV_PASSTHROUGH environment variable (comma-separated)
+    #[arg(
+        long = "backend-env-passthrough",
+        env = "TYPEMUX_CC_BACKEND_ENV_PASSTHROUGH",
+        value_name = "KEY",
+        value_delimiter = ','
+    )]
+    backend_env_passthrough: Vec<String>,
+
+    /// Venv directory name to look for at each level, checked in order
+    /// (repeatable, default: .venv), e.g. `--venv-dir .venv --venv-dir venv`
+    /// Can also be set via TYPEMUX_CC_VENV_DIRS environment variable (comma-separated)
+    #[arg(
+        long = "venv-dir",
+        env = "TYPEMUX_CC_VENV_DIRS",
+        value_name = "NAME",
+        value_delimiter = ',',
+        default_value = venv::DEFAULT_VENV_DIR
+    )]
+    venv_dirs: Vec<String>,
+
+    /// When several `--venv-dir` venvs exist in the same directory, prefer the
+    /// one whose pyvenv.cfg reports this Python version (e.g. `3.12`)
+    /// Can also be set via TYPEMUX_CC_PREFER_PYTHON environment variable
+    #[arg(long, env = "TYPEMUX_CC_PREFER_PYTHON", value_name = "VERSION")]
+    prefer_python: Option<String>,
+
+    /// Shut down all backends and exit this many seconds after startup, even
+    /// while busy (default: 0 = never). Bounds the lifetime of CI/scripted runs.
+    /// Can also be set via TYPEMUX_CC_EXIT_AFTER environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_EXIT_AFTER",
+        value_name = "SECS",
+        default_value = "0"
+    )]
+    exit_after: u64,
+
+    /// Remember directories without a venv for a few seconds instead of
+    /// re-scanning the filesystem on every request for their files
+    /// Can also be set via TYPEMUX_CC_CACHE_VENV_NEGATIVE_RESULTS environment variable
+    #[arg(long, env = "TYPEMUX_CC_CACHE_VENV_NEGATIVE_RESULTS")]
+    cache_venv_negative_results: bool,
+
+    /// Use a different backend for venvs whose parent directory matches GLOB,
+    /// e.g. `--backend-map 'tools=ty'` (repeatable, first match wins; relative
+    /// globs are matched from the git toplevel, `**` spans directories)
+    /// Can also be set via TYPEMUX_CC_BACKEND_MAP environment variable (comma-separated)
+    #[arg(
+        long = "backend-map",
+        env = "TYPEMUX_CC_BACKEND_MAP",
+        value_name = "GLOB=KIND",
+        value_delimiter = ','
+    )]
+    backend_map: Vec<backend::BackendMapping>,
+
+    /// Observe every backend→client message with a built-in tap (repeatable):
+    /// `noop`, or `log` for one debug line per message (target `typemux_cc::tap`)
+    /// Can also be set via TYPEMUX_CC_BACKEND_RESPONSE_TAPS environment variable (comma-separated)
+    #[arg(
+        long = "backend-response-tap",
+        env = "TYPEMUX_CC_BACKEND_RESPONSE_TAPS",
+        value_name = "TAP",
+        value_delimiter = ','
+    )]
+    backend_response_taps: Vec<proxy::ResponseTapKind>,
+
+    /// When no .venv is found, use a PEP 582 __pypackages__ env, else run
+    /// `poetry env info -p` in the nearest pyproject.toml with a [tool.poetry]
+    /// table and use that env
+    /// Can also be set via TYPEMUX_CC_ENABLE_POETRY environment variable
+    #[arg(long, env = "TYPEMUX_CC_ENABLE_POETRY")]
+    enable_poetry: bool,
+
+    /// When no .venv is found, use a VIRTUAL_ENV assignment from an .envrc in
+    /// the same ancestor directories (plain line parse, no shell evaluation)
+    /// if it points at a directory with pyvenv.cfg
+    /// Can also be set via TYPEMUX_CC_DETECT_DIRENV environment variable
+    #[arg(long, env = "TYPEMUX_CC_DETECT_DIRENV")]
+    detect_direnv: bool,
+
+    /// Evict all idle backends once the last open document closes, after a grace
+    /// period (see --evict-on-empty-grace)
+    /// Can also be set via TYPEMUX_CC_EVICT_ON_EMPTY environment variable
+    #[arg(long, env = "TYPEMUX_CC_EVICT_ON_EMPTY")]
+    evict_on_empty: bool,
+
+    /// Seconds the document cache must stay empty before --evict-on-empty evicts
+    /// Can also be set via TYPEMUX_CC_EVICT_ON_EMPTY_GRACE environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_EVICT_ON_EMPTY_GRACE",
+        value_name = "SECS",
+        default_value = "5"
+    )]
+    evict_on_empty_grace: u64,
+
+    /// Replace backend responses larger than this many bytes (serialized JSON)
+    /// with an error (default: 0 = unlimited)
+    /// Can also be set via TYPEMUX_CC_RESPONSE_SIZE_LIMIT_BYTES environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_RESPONSE_SIZE_LIMIT_BYTES",
+        value_name = "BYTES",
+        default_value = "0"
+    )]
+    response_size_limit_bytes: u64,
+
+    /// Seconds to wait for a backend's initialize response
+    /// Can also be set via TYPEMUX_CC_INITIALIZE_TIMEOUT environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_INITIALIZE_TIMEOUT",
+        value_name = "SECS",
+        default_value = "10",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    initialize_timeout: u64,
+
+    /// What to do when a backend's initialize times out: retry (wait once more
+    /// with twice the timeout), disable (stop spawning backends for that venv
+    /// for --initialize-disable-cooldown), or minimal (give up; the client gets minimal capabilities or an error)
+    /// Can also be set via TYPEMUX_CC_INITIALIZE_RESPONSE_TIMEOUT_ACTION environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_INITIALIZE_RESPONSE_TIMEOUT_ACTION",
+        value_enum,
+        default_value = "minimal"
+    )]
+    initialize_response_timeout_action: InitTimeoutAction,
+
+    /// Seconds a venv stays disabled after an initialize timeout with
+    /// --initialize-response-timeout-action disable
+    /// Can also be set via TYPEMUX_CC_INITIALIZE_DISABLE_COOLDOWN environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_INITIALIZE_DISABLE_COOLDOWN",
+        value_name = "SECS",
+        default_value = "300"
+    )]
+    initialize_disable_cooldown: u64,
+
+    /// Dump pool, documents, pending requests and metrics to the log on SIGUSR1 (Unix)
+    /// Can also be set via TYPEMUX_CC_POOL_SNAPSHOT_ON_SIGNAL environment variable
+    #[arg(long, env = "TYPEMUX_CC_POOL_SNAPSHOT_ON_SIGNAL")]
+    pool_snapshot_on_signal: bool,
+
+    /// Never send a backend a second `initialized` notification (e.g. when the
+    /// client's `initialized` arrives after the backend's own handshake, or is repeated)
+    /// Can also be set via TYPEMUX_CC_FORWARD_INITIALIZED_ONCE environment variable
+    #[arg(long, env = "TYPEMUX_CC_FORWARD_INITIALIZED_ONCE")]
+    forward_initialized_once: bool,
+
+    /// Signal sent to a backend that does not exit after shutdown/exit
+    /// (term/int fall back to kill after 1s; non-Unix always kills)
+    /// Can also be set via TYPEMUX_CC_BACKEND_KILL_SIGNAL environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_BACKEND_KILL_SIGNAL",
+        value_enum,
+        default_value = "kill"
+    )]
+    backend_kill_signal: KillSignal,
+
+    /// Pyright `python.analysis.diagnosticMode` for pyright backends:
+    /// openFilesOnly or workspace (reports errors in unopened files, much heavier)
+    /// Can also be set via TYPEMUX_CC_PYRIGHT_DIAGNOSTIC_MODE environment variable
+    #[arg(long, env = "TYPEMUX_CC_PYRIGHT_DIAGNOSTIC_MODE", value_enum)]
+    pyright_diagnostic_mode: Option<PyrightDiagnosticMode>,
+
+    /// Gzip-compress large frames sent to the client, if its initialize params
+    /// advertise `capabilities.experimental.frameContentEncodings: ["gzip"]`
+    /// Can also be set via TYPEMUX_CC_COMPRESS_FRAMES environment variable
+    #[arg(long, env = "TYPEMUX_CC_COMPRESS_FRAMES")]
+    compress_frames: bool,
+
+    /// Every 60 seconds, warn about open documents whose backend has not
+    /// received the latest cached version
+    /// Can also be set via TYPEMUX_CC_DOCUMENT_SYNC_VERIFY environment variable
+    #[arg(long, env = "TYPEMUX_CC_DOCUMENT_SYNC_VERIFY")]
+    document_sync_verify: bool,
+
+    /// Seconds a backend progress token may stay idle before the proxy sends
+    /// the client a synthetic `end` and forgets it (default: 0 = never)
+    /// Can also be set via TYPEMUX_CC_PROGRESS_TOKEN_TTL environment variable
+    #[arg(
+        long,
+        env = "TYPEMUX_CC_PROGRESS_TOKEN_TTL",
+        value_name = "SECS",
+        default_value = "0"
+    )]
+    progress_token_ttl: u64,
+
+    /// Only log warnings and errors (ignored when RUST_LOG is set)
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Increase log verbosity: -v = debug, -vv = trace (ignored when RUST_LOG is set).
+    /// Without -q/-v the default level is debug.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Replay a recorded client-side JSONL transcript instead of reading stdin
+    /// (for reproducing reported issues)
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Initialize params (JSON file) to use when no client sends `initialize`,
+    /// e.g. together with --replay or when driving backends programmatically
+    #[arg(long, value_name = "FILE")]
+    synthetic_initialize: Option<PathBuf>,
+
     /// Run self-diagnosis and print configuration/environment info
     #[arg(long)]
     doctor: bool,
@@ -51,6 +708,28 @@ struct Args {
     /// Output doctor report as JSON (requires --doctor)
     #[arg(long, requires = "doctor")]
     json: bool,
+
+    /// Print the status of the instance serving --status-socket and exit
+    #[arg(long, requires = "status_socket")]
+    status: bool,
+}
+
+/// Log filter used when RUST_LOG is not set (explicit RUST_LOG always wins)
+fn default_log_directive(args: &Args) -> &'static str {
+    match (args.quiet, args.verbose) {
+        (true, _) => "typemux_cc=warn",
+        (false, 0 | 1) => "typemux_cc=debug",
+        (false, _) => "typemux_cc=trace",
+    }
+}
+
+/// Parse a CLI argument as a JSON object
+fn parse_json_object(s: &str) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    if !value.is_object() {
+        return Err("expected a JSON object".to_string());
+    }
+    Ok(value)
 }
 
 #[tokio::main]
@@ -66,6 +745,20 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    // clap guarantees --status-socket is present with --status
+    if let (true, Some(path)) = (args.status, &args.status_socket) {
+        match proxy::query_status(path).await {
+            Ok(body) => {
+                println!("{body}");
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: could not query {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Initialize logging (default: stderr, --log-file adds file output)
     if let Some(log_path) = &args.log_file {
         // File output specified: stderr + file
@@ -94,7 +787,7 @@ async fn main() -> anyhow::Result<()> {
             )
             .with(
                 EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| EnvFilter::new("typemux_cc=debug")),
+                    .unwrap_or_else(|_| EnvFilter::new(default_log_directive(&args))),
             )
             .init();
 
@@ -115,7 +808,7 @@ async fn main() -> anyhow::Result<()> {
             )
             .with(
                 EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| EnvFilter::new("typemux_cc=debug")),
+                    .unwrap_or_else(|_| EnvFilter::new(default_log_directive(&args))),
             )
             .init();
 
@@ -132,9 +825,155 @@ async fn main() -> anyhow::Result<()> {
         Some(std::time::Duration::from_secs(args.backend_ttl))
     };
 
+    if args.min_idle > args.max_backends {
+        tracing::warn!(
+            min_idle = args.min_idle,
+            max_backends = args.max_backends,
+            "--min-idle exceeds --max-backends, capping it"
+        );
+    }
+
+    // --pin-venv paths are compared against absolute venv paths. Pin the
+    // resolved path (`..`, symlinks) and, when it differs, the path as given,
+    // which is what venvs found through a symlinked workspace look like
+    let cwd = std::env::current_dir()?;
+    let mut pinned_venvs = Vec::new();
+    for venv in &args.pinned_venvs {
+        let joined = cwd.join(venv);
+        if !joined.join("pyvenv.cfg").is_file() {
+            tracing::warn!(
+                venv = %joined.display(),
+                "--pin-venv path has no pyvenv.cfg, it will never match a venv"
+            );
+        }
+        match joined.canonicalize() {
+            Ok(canonical) if canonical != joined => pinned_venvs.extend([canonical, joined]),
+            _ => pinned_venvs.push(joined),
+        }
+    }
+
+    let options = ProxyOptions {
+        strict_json: args.strict_json,
+        warmup_queue_limit: (args.warmup_queue_limit > 0)
+            .then_some(args.warmup_queue_limit as usize),
+        no_warmup_methods: args.no_warmup_methods,
+        forward_custom_methods: args.forward_custom_methods,
+        replay: args.replay,
+        listen: args.listen,
+        synthetic_initialize: args.synthetic_initialize,
+        max_concurrent_spawns: args.max_concurrent_spawns as usize,
+        dedup_empty_diagnostics: args.dedup_empty_diagnostics,
+        clear_diagnostics_on_close: args.clear_diagnostics_on_close,
+        strip_ansi: args.strip_ansi,
+        log_request_correlation: args.log_request_response_correlation,
+        transparent_retry: args.transparent_retry,
+        strict_uri_routing: args.strict_uri_routing,
+        venv_search: venv::VenvSearch {
+            trace: args.trace_venv_resolution,
+            detect_direnv: args.detect_direnv,
+            venv_dirs: args.venv_dirs,
+            prefer_python: args.prefer_python,
+            enable_poetry: args.enable_poetry,
+            backend: args.backend,
+            backend_map: args.backend_map.clone(),
+        },
+        crash_threshold: (args.shutdown_on_backend_crash_threshold > 0)
+            .then_some(args.shutdown_on_backend_crash_threshold as usize),
+        crash_window: std::time::Duration::from_secs(args.backend_crash_window),
+        circuit_breaker_threshold: (args.circuit_breaker_threshold > 0)
+            .then_some(args.circuit_breaker_threshold as usize),
+        circuit_breaker_cooldown: std::time::Duration::from_secs(args.circuit_breaker_cooldown),
+        proxy_name: args.proxy_name,
+        read_buffer_size: args.read_buffer_size as usize,
+        backend_env_passthrough: (!args.backend_env_passthrough.is_empty())
+            .then_some(args.backend_env_passthrough),
+        backend_map: args.backend_map,
+        cache_venv_negative_results: args.cache_venv_negative_results,
+        exit_after: (args.exit_after > 0).then(|| std::time::Duration::from_secs(args.exit_after)),
+        did_change_version_check: if args.strict_versions {
+            VersionRegressionPolicy::Error
+        } else {
+            args.did_change_version_check
+        },
+        diagnostic_code_rules: args.diagnostic_code_rules,
+        pool_metrics_log_interval: (args.pool_metrics_log_interval > 0)
+            .then(|| std::time::Duration::from_secs(args.pool_metrics_log_interval)),
+        allow_did_change_before_did_open: args.allow_did_change_before_did_open,
+        force_full_sync: args.force_full_sync,
+        didchange_debounce: (args.didchange_debounce > 0)
+            .then(|| std::time::Duration::from_millis(args.didchange_debounce)),
+        max_cached_documents: (args.max_cached_documents > 0)
+            .then_some(args.max_cached_documents as usize),
+        filter_code_action_kinds: args.filter_code_action_kinds,
+        no_dedup: args.no_dedup,
+        preindex_open_files: args.preindex_open_files,
+        prewarm: args.prewarm,
+        pinned_venvs,
+        min_idle: args.min_idle.min(args.max_backends) as usize,
+        reopen_on_language_id_change: args.reopen_on_language_id_change,
+        max_message_rate: (args.max_message_rate > 0).then_some(args.max_message_rate),
+        preserve_diagnostics_on_respawn: args.backend_respawn_preserve_diagnostics,
+        evict_on_empty: args
+            .evict_on_empty
+            .then(|| std::time::Duration::from_secs(args.evict_on_empty_grace)),
+        response_size_limit: (args.response_size_limit_bytes > 0)
+            .then_some(args.response_size_limit_bytes as usize),
+        backend_startup_script: args.backend_startup_script,
+        initialize_timeout: std::time::Duration::from_secs(args.initialize_timeout),
+        warmup_timeout: std::time::Duration::from_secs(args.warmup_timeout),
+        warmup_state_persist: args.warmup_state_persist,
+        warmup_state_window: std::time::Duration::from_secs(args.warmup_state_window),
+        init_timeout_action: args.initialize_response_timeout_action,
+        init_disable_cooldown: std::time::Duration::from_secs(args.initialize_disable_cooldown),
+        forward_initialized_once: args.forward_initialized_once,
+        backend_kill_signal: args.backend_kill_signal,
+        pyright_diagnostic_mode: args.pyright_diagnostic_mode,
+        compress_frames: args.compress_frames,
+        document_sync_verify: args.document_sync_verify,
+        progress_token_ttl: (args.progress_token_ttl > 0)
+            .then(|| std::time::Duration::from_secs(args.progress_token_ttl)),
+        on_stale_response: args.on_stale_response,
+        metrics_addr: args.metrics_addr,
+        status_socket: args.status_socket,
+        default_language_id: args.default_language_id,
+        skip_non_python_documents: args.skip_non_python_documents,
+        client_capabilities_augment: args.client_capabilities_augment,
+        auto_scale_ceiling: args
+            .auto_scale_backends
+            .then(|| args.max_backends_ceiling.unwrap_or(args.max_backends * 2) as usize),
+        hover_timeout: (args.hover_timeout > 0)
+            .then(|| std::time::Duration::from_millis(args.hover_timeout)),
+        request_timeout: (args.request_timeout > 0)
+            .then(|| std::time::Duration::from_secs(args.request_timeout)),
+    };
+
     // Start proxy
-    let mut proxy = LspProxy::new(args.backend, args.max_backends as usize, backend_ttl);
-    proxy.run().await?;
+    let mut proxy = LspProxy::new(
+        args.backend,
+        args.max_backends as usize,
+        backend_ttl,
+        options,
+    );
+    for tap in args.backend_response_taps {
+        proxy.add_response_tap(tap.build());
+    }
+    if args.pool_snapshot_on_signal {
+        #[cfg(unix)]
+        proxy.set_snapshot_signal(proxy::install_snapshot_signal()?);
+        #[cfg(not(unix))]
+        tracing::warn!("--pool-snapshot-on-signal is only supported on Unix, ignoring");
+    }
+    if let Err(e) = proxy.run().await {
+        tracing::error!(error = %e, "Proxy terminated with error");
+        eprintln!("Error: {e}");
+        // exit() skips destructors: drop the proxy first so kill_on_drop reaps
+        // any backend still in the pool
+        drop(proxy);
+        // The runtime would otherwise wait on the blocking stdin read at shutdown
+        std::process::exit(1);
+    }
 
-    Ok(())
+    // Same for a clean stop while the client still holds stdin open (--exit-after)
+    drop(proxy);
+    std::process::exit(0);
 }
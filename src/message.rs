@@ -17,6 +17,34 @@ pub struct RpcMessage {
     pub error: Option<RpcError>,
 }
 
+/// Top-level fields defined by JSON-RPC 2.0 for requests, notifications and responses
+const KNOWN_TOP_LEVEL_FIELDS: &[&str] = &["jsonrpc", "id", "method", "params", "result", "error"];
+
+/// Mirror of `RpcMessage` that rejects unknown top-level fields (used by `--strict-json`)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StrictRpcMessage {
+    jsonrpc: String,
+    id: Option<RpcId>,
+    method: Option<String>,
+    params: Option<Value>,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+impl From<StrictRpcMessage> for RpcMessage {
+    fn from(strict: StrictRpcMessage) -> Self {
+        RpcMessage {
+            jsonrpc: strict.jsonrpc,
+            id: strict.id,
+            method: strict.method,
+            params: strict.params,
+            result: strict.result,
+            error: strict.error,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum RpcId {
@@ -24,6 +52,26 @@ pub enum RpcId {
     String(String),
 }
 
+/// Proxy-specific JSON-RPC error codes, in the range JSON-RPC reserves for
+/// implementation-defined server errors (`-32099..=-32000`), from `-32010`
+/// up: LSP itself uses `-32002` (ServerNotInitialized) and `-32001`
+/// (UnknownErrorCode) there.
+///
+/// Backend errors are forwarded unchanged; these only mark errors the proxy
+/// produces itself, so clients can tell the failure classes apart.
+pub mod error_codes {
+    /// No venv was found for the document (strict mode)
+    pub const VENV_NOT_FOUND: i64 = -32010;
+    /// The backend could not be started, crashed, or did not accept the request
+    pub const BACKEND_UNAVAILABLE: i64 = -32011;
+    /// The request exceeded `--max-message-rate`
+    pub const RATE_LIMITED: i64 = -32012;
+    /// The proxy cannot tell which backend should handle the request
+    pub const UNROUTABLE: i64 = -32013;
+    /// The backend's response exceeded `--response-size-limit-bytes`
+    pub const RESPONSE_TOO_LARGE: i64 = -32014;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpcError {
     pub code: i64,
@@ -33,6 +81,24 @@ pub struct RpcError {
 }
 
 impl RpcMessage {
+    /// Deserialize a message, rejecting unknown top-level fields.
+    pub fn from_slice_strict(content: &[u8]) -> Result<RpcMessage, serde_json::Error> {
+        serde_json::from_slice::<StrictRpcMessage>(content).map(RpcMessage::from)
+    }
+
+    /// Return top-level field names that are not part of JSON-RPC 2.0.
+    /// Returns an empty list if the content is not a JSON object.
+    pub fn unknown_top_level_fields(content: &[u8]) -> Vec<String> {
+        match serde_json::from_slice::<Value>(content) {
+            Ok(Value::Object(map)) => map
+                .keys()
+                .filter(|k| !KNOWN_TOP_LEVEL_FIELDS.contains(&k.as_str()))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Check if this is a request
     pub fn is_request(&self) -> bool {
         self.id.is_some() && self.method.is_some()
@@ -91,6 +157,34 @@ impl RpcMessage {
         }
     }
 
+    /// Create a parse error (-32700) response for a request whose body could
+    /// not be read as a JSON-RPC message.
+    pub fn parse_error_response(id: RpcId, message: &str) -> RpcMessage {
+        RpcMessage {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: None,
+            params: None,
+            result: None,
+            error: Some(RpcError {
+                code: -32700,
+                message: message.to_string(),
+                data: None,
+            }),
+        }
+    }
+
+    /// Create an invalid request (-32600) response. Returned as JSON since
+    /// without a usable id it must carry `"id": null`, which `RpcMessage`
+    /// leaves out.
+    pub fn invalid_request_response(id: Option<RpcId>, message: &str) -> Value {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32600, "message": message }
+        })
+    }
+
     /// Create an error response for a given request.
     pub fn error_response(request: &RpcMessage, message: &str) -> RpcMessage {
         RpcMessage {
@@ -107,6 +201,43 @@ impl RpcMessage {
         }
     }
 
+    /// Create an error response with one of the proxy-specific `error_codes`
+    /// and structured context in `data`.
+    pub fn proxy_error_response(
+        request: &RpcMessage,
+        code: i64,
+        message: &str,
+        data: Value,
+    ) -> RpcMessage {
+        RpcMessage {
+            jsonrpc: "2.0".to_string(),
+            id: request.id.clone(),
+            method: None,
+            params: None,
+            result: None,
+            error: Some(RpcError {
+                code,
+                message: message.to_string(),
+                data: Some(data),
+            }),
+        }
+    }
+
+    /// Create a rate-limit error response (`RATE_LIMITED`) for a given request,
+    /// hinting when the client may retry.
+    pub fn rate_limited_response(
+        request: &RpcMessage,
+        message: &str,
+        retry_after_ms: u64,
+    ) -> RpcMessage {
+        Self::proxy_error_response(
+            request,
+            error_codes::RATE_LIMITED,
+            message,
+            serde_json::json!({ "retryAfterMs": retry_after_ms }),
+        )
+    }
+
     /// Create a cancellation error response with a specific id.
     pub fn cancelled_response(id: RpcId, message: &str) -> RpcMessage {
         RpcMessage {
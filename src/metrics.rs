@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+/// Cumulative proxy counters (monotonically increasing for the process lifetime)
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// Client requests received
+    pub requests_total: u64,
+    /// Client requests received, by LSP method (`other` for methods outside
+    /// [`LSP_REQUEST_METHODS`], so custom methods can't grow the label set)
+    pub requests_by_method: BTreeMap<&'static str, u64>,
+    /// Backend processes spawned
+    pub backend_spawns_total: u64,
+    /// Backends removed after crash/EOF
+    pub backend_crashes_total: u64,
+    /// Backends evicted, by reason (`lru`, `ttl`, `empty`)
+    pub evictions_by_reason: BTreeMap<&'static str, u64>,
+    /// Recent crash times, oldest first (`--shutdown-on-backend-crash-threshold`)
+    pub recent_crashes: VecDeque<Instant>,
+}
+
+/// Client → server request methods of the LSP specification
+const LSP_REQUEST_METHODS: &[&str] = &[
+    "initialize",
+    "shutdown",
+    "textDocument/willSaveWaitUntil",
+    "textDocument/declaration",
+    "textDocument/definition",
+    "textDocument/typeDefinition",
+    "textDocument/implementation",
+    "textDocument/references",
+    "textDocument/prepareCallHierarchy",
+    "callHierarchy/incomingCalls",
+    "callHierarchy/outgoingCalls",
+    "textDocument/prepareTypeHierarchy",
+    "typeHierarchy/supertypes",
+    "typeHierarchy/subtypes",
+    "textDocument/documentHighlight",
+    "textDocument/documentLink",
+    "documentLink/resolve",
+    "textDocument/hover",
+    "textDocument/codeLens",
+    "codeLens/resolve",
+    "textDocument/foldingRange",
+    "textDocument/selectionRange",
+    "textDocument/documentSymbol",
+    "textDocument/semanticTokens/full",
+    "textDocument/semanticTokens/full/delta",
+    "textDocument/semanticTokens/range",
+    "textDocument/inlineValue",
+    "textDocument/inlayHint",
+    "inlayHint/resolve",
+    "textDocument/moniker",
+    "textDocument/completion",
+    "completionItem/resolve",
+    "textDocument/diagnostic",
+    "workspace/diagnostic",
+    "textDocument/signatureHelp",
+    "textDocument/codeAction",
+    "codeAction/resolve",
+    "textDocument/documentColor",
+    "textDocument/colorPresentation",
+    "textDocument/formatting",
+    "textDocument/rangeFormatting",
+    "textDocument/rangesFormatting",
+    "textDocument/onTypeFormatting",
+    "textDocument/rename",
+    "textDocument/prepareRename",
+    "textDocument/linkedEditingRange",
+    "workspace/symbol",
+    "workspaceSymbol/resolve",
+    "workspace/executeCommand",
+    "workspace/willCreateFiles",
+    "workspace/willRenameFiles",
+    "workspace/willDeleteFiles",
+];
+
+/// Point-in-time values sampled from `ProxyState` when rendering
+#[derive(Debug, Clone, Default)]
+pub struct Gauges {
+    pub pool_size: usize,
+    pub warming_backends: usize,
+    pub pending_requests: usize,
+    pub open_documents: usize,
+}
+
+impl Metrics {
+    /// Count a client request for `method`
+    pub fn record_request(&mut self, method: &str) {
+        self.requests_total += 1;
+        let label = LSP_REQUEST_METHODS
+            .iter()
+            .find(|known| **known == method)
+            .copied()
+            .unwrap_or("other");
+        *self.requests_by_method.entry(label).or_default() += 1;
+    }
+
+    /// Count a backend eviction for `reason`
+    pub fn record_eviction(&mut self, reason: &'static str) {
+        *self.evictions_by_reason.entry(reason).or_default() += 1;
+    }
+
+    /// Backends evicted, all reasons together
+    pub fn backend_evictions_total(&self) -> u64 {
+        self.evictions_by_reason.values().sum()
+    }
+
+    /// Count a backend crash at `now` and return how many crashes happened
+    /// within the trailing `window` (this one included).
+    pub fn record_crash(&mut self, now: Instant, window: Duration) -> usize {
+        self.backend_crashes_total += 1;
+        self.recent_crashes.push_back(now);
+        while self
+            .recent_crashes
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) > window)
+        {
+            self.recent_crashes.pop_front();
+        }
+        self.recent_crashes.len()
+    }
+
+    /// Render counters and gauges in the Prometheus text exposition format
+    pub fn render_prometheus(&self, gauges: &Gauges) -> String {
+        let counters = [
+            (
+                "typemux_backend_spawns_total",
+                "Backend processes spawned",
+                self.backend_spawns_total,
+            ),
+            (
+                "typemux_backend_crashes_total",
+                "Backends removed after crash or EOF",
+                self.backend_crashes_total,
+            ),
+        ];
+        let gauges = [
+            (
+                "typemux_backends_active",
+                "Backends currently in the pool",
+                gauges.pool_size,
+            ),
+            (
+                "typemux_backends_warming",
+                "Backends still building their index",
+                gauges.warming_backends,
+            ),
+            (
+                "typemux_pending_requests",
+                "Client requests awaiting a backend response",
+                gauges.pending_requests,
+            ),
+            (
+                "typemux_open_documents",
+                "Documents currently open in the client",
+                gauges.open_documents,
+            ),
+        ];
+
+        let labelled = [
+            (
+                "typemux_requests_total",
+                "Client requests received",
+                "method",
+                self.requests_by_method
+                    .iter()
+                    .map(|(k, v)| (*k, *v))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "typemux_evictions_total",
+                "Backends evicted, by reason",
+                "reason",
+                self.evictions_by_reason
+                    .iter()
+                    .map(|(k, v)| (*k, *v))
+                    .collect(),
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, help, label, series) in labelled {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (value_label, value) in series {
+                let _ = writeln!(
+                    out,
+                    "{name}{{{label}=\"{}\"}} {value}",
+                    escape_label(value_label)
+                );
+            }
+        }
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {value}");
+        }
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+
+    /// Log counters and gauges as a single info line (`--pool-metrics-log-interval`)
+    pub fn log_summary(&self, gauges: &Gauges) {
+        tracing::info!(
+            pool_size = gauges.pool_size,
+            warming = gauges.warming_backends,
+            open_documents = gauges.open_documents,
+            pending_requests = gauges.pending_requests,
+            requests_total = self.requests_total,
+            backend_spawns_total = self.backend_spawns_total,
+            backend_evictions_total = self.backend_evictions_total(),
+            backend_crashes_total = self.backend_crashes_total,
+            "Pool metrics"
+        );
+    }
+}
+
+/// Escape a label value per the exposition format (`\\`, `"` and newline)
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Answer a single scrape on `stream` with `body` as a plain HTTP/1.1 response.
+///
+/// The request itself is not interpreted (any path returns the metrics); it is
+/// only drained up to the end of the headers so the client sees a clean close.
+pub async fn serve_scrape(mut stream: TcpStream, body: String) {
+    let mut buf = [0u8; 1024];
+    let mut request = Vec::new();
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        tracing::debug!(error = ?e, "Failed to write metrics response");
+    }
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_exposition() {
+        let mut metrics = Metrics {
+            backend_spawns_total: 2,
+            backend_crashes_total: 1,
+            ..Default::default()
+        };
+        for _ in 0..5 {
+            metrics.record_request("textDocument/hover");
+        }
+        metrics.record_request("textDocument/definition");
+        metrics.record_request("weird\"method");
+        metrics.record_request("pyright/custom");
+        metrics.record_eviction("ttl");
+        let gauges = Gauges {
+            pool_size: 1,
+            warming_backends: 0,
+            pending_requests: 3,
+            open_documents: 4,
+        };
+        let text = metrics.render_prometheus(&gauges);
+
+        // Every sample line is `<name> <number>` and preceded by HELP/TYPE
+        let mut samples = std::collections::HashMap::new();
+        let mut typed = std::collections::HashSet::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(kind == "counter" || kind == "gauge");
+                typed.insert(name.to_string());
+            } else if !line.starts_with("# HELP ") {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                let family = name.split('{').next().unwrap();
+                assert!(typed.contains(family), "{name} has no TYPE line");
+                samples.insert(name.to_string(), value.parse::<f64>().unwrap());
+            }
+        }
+
+        assert_eq!(
+            samples[r#"typemux_requests_total{method="textDocument/hover"}"#],
+            5.0
+        );
+        assert_eq!(
+            samples[r#"typemux_requests_total{method="textDocument/definition"}"#],
+            1.0
+        );
+        // Unknown and custom methods share one label
+        assert_eq!(samples[r#"typemux_requests_total{method="other"}"#], 2.0);
+        assert_eq!(samples[r#"typemux_evictions_total{reason="ttl"}"#], 1.0);
+        // Each eviction is one sample, in the labelled family only
+        assert!(!samples.contains_key("typemux_backend_evictions_total"));
+        assert_eq!(metrics.backend_evictions_total(), 1);
+        assert_eq!(metrics.requests_total, 8);
+        assert_eq!(samples["typemux_backend_spawns_total"], 2.0);
+        assert_eq!(samples["typemux_backend_crashes_total"], 1.0);
+        assert_eq!(samples["typemux_backends_active"], 1.0);
+        assert_eq!(samples["typemux_backends_warming"], 0.0);
+        assert_eq!(samples["typemux_pending_requests"], 3.0);
+        assert_eq!(samples["typemux_open_documents"], 4.0);
+    }
+
+    #[test]
+    fn test_record_crash_counts_within_window() {
+        let mut metrics = Metrics::default();
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+
+        assert_eq!(metrics.record_crash(start, window), 1);
+        assert_eq!(
+            metrics.record_crash(start + Duration::from_secs(30), window),
+            2
+        );
+        // The first crash has left the window
+        assert_eq!(
+            metrics.record_crash(start + Duration::from_secs(61), window),
+            2
+        );
+        assert_eq!(
+            metrics.record_crash(start + Duration::from_secs(200), window),
+            1
+        );
+        assert_eq!(metrics.backend_crashes_total, 4);
+    }
+}
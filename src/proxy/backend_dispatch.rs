@@ -1,7 +1,9 @@
+use super::transport::ClientOutput;
 use crate::backend_pool::BackendMessage;
 use crate::error::ProxyError;
 use crate::framing::LspFrameWriter;
-use crate::message::{RpcId, RpcMessage};
+use crate::message::{error_codes, RpcId, RpcMessage};
+use crate::state::StaleResponsePolicy;
 
 impl super::LspProxy {
     /// Handle a message received from a backend via the mpsc channel.
@@ -12,12 +14,13 @@ impl super::LspProxy {
     pub(crate) async fn dispatch_backend_message(
         &mut self,
         backend_msg: BackendMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let BackendMessage {
             venv_path,
             session,
             result,
+            raw,
         } = backend_msg;
 
         // Stale session check: discard messages from backends no longer in the pool
@@ -50,6 +53,7 @@ impl super::LspProxy {
 
         match result {
             Ok(msg) => {
+                let mut msg = Forwarded { msg, raw };
                 tracing::debug!(
                     venv = %venv_path.display(),
                     session = session,
@@ -58,6 +62,7 @@ impl super::LspProxy {
                     is_request = msg.is_request(),
                     "Backend -> Proxy"
                 );
+                self.run_response_taps(&venv_path, session, &msg);
 
                 // Trace-level: log response body for debugging definition/references issues
                 if msg.is_response() {
@@ -72,6 +77,22 @@ impl super::LspProxy {
 
                 // Check if this is a server→client request from the backend
                 if msg.is_request() {
+                    let duplicate = match msg.method_name() {
+                        Some("client/registerCapability") => {
+                            self.dedup_register_capability(&mut msg, &venv_path, session)
+                        }
+                        Some("client/unregisterCapability") => {
+                            self.dedup_unregister_capability(&mut msg, &venv_path, session)
+                        }
+                        Some("window/workDoneProgress/create") => {
+                            self.namespace_work_done_create(&mut msg, &venv_path, session);
+                            false
+                        }
+                        _ => false,
+                    };
+                    if duplicate {
+                        return self.answer_backend_request(&msg, &venv_path).await;
+                    }
                     if let Some(original_id) = &msg.id {
                         // Assign a proxy-unique ID to avoid collisions between backends
                         let proxy_id = self.state.alloc_proxy_request_id();
@@ -80,6 +101,10 @@ impl super::LspProxy {
                             original_id: original_id.clone(),
                             venv_path: venv_path.clone(),
                             session,
+                            registrations: super::registration::forwarded_registrations(&msg),
+                            configuration_sections: super::initialization::configuration_sections(
+                                &msg,
+                            ),
                         };
                         self.state
                             .pending_backend_requests
@@ -97,13 +122,34 @@ impl super::LspProxy {
                 }
 
                 // Handle response: check fan-out first, then pending + stale check
+                let mut response_method = None;
                 if msg.is_response() {
                     if let Some(id) = &msg.id {
+                        self.release_partial_result_token(id);
                         // Fan-out response check: must come before normal pending_requests handling
                         if self.handle_fanout_response(id, &msg, client_writer).await? {
                             return Ok(());
                         }
 
+                        self.state.pending_hovers.remove(id);
+                        if self.state.fallback_answered_hovers.remove(id).is_some() {
+                            tracing::debug!(
+                                id = ?id,
+                                venv = %venv_path.display(),
+                                "Discarding late hover response (fallback already sent)"
+                            );
+                            return Ok(());
+                        }
+
+                        if self.state.timed_out_requests.remove(id).is_some() {
+                            tracing::debug!(
+                                id = ?id,
+                                venv = %venv_path.display(),
+                                "Discarding late response (request already timed out)"
+                            );
+                            return Ok(());
+                        }
+
                         if let Some(pending) = self.state.pending_requests.get(id) {
                             if pending.backend_session != session || pending.venv_path != venv_path
                             {
@@ -115,7 +161,8 @@ impl super::LspProxy {
                                     msg_venv = %venv_path.display(),
                                     "Discarding stale response from old backend session"
                                 );
-                                self.state.pending_requests.remove(id);
+                                let id = id.clone();
+                                self.handle_stale_response(id, client_writer).await?;
                                 return Ok(());
                             }
                         } else if is_proxy_assigned_id(id) {
@@ -129,12 +176,40 @@ impl super::LspProxy {
                             );
                             return Ok(());
                         }
-                        self.state.pending_requests.remove(id);
+                        response_method = self.state.pending_requests.remove(id).map(|pending| {
+                            if self.state.options.log_request_correlation {
+                                log_request_correlation(id, &pending);
+                            }
+                            pending.method
+                        });
+                    }
+                }
+
+                // One backend per file: only the owning backend may publish diagnostics
+                if msg.method_name() == Some("textDocument/publishDiagnostics")
+                    && self.is_foreign_diagnostics(&msg, &venv_path)
+                {
+                    return Ok(());
+                }
+                if msg.method_name() == Some("textDocument/publishDiagnostics")
+                    && !self.state.options.diagnostic_code_rules.is_empty()
+                {
+                    self.apply_diagnostic_code_rules(&mut msg);
+                }
+                if let Some((uri, count)) = diagnostics_summary(&msg) {
+                    if !self.should_publish_diagnostics(&uri, count) {
+                        return Ok(());
                     }
+                    self.remember_diagnostics(&msg);
                 }
 
                 // Detect $/progress end → transition warming backend to ready
                 if msg.is_notification() {
+                    if msg.method_name() == Some("$/progress") {
+                        self.unmap_partial_result_token(&mut msg);
+                        self.namespace_work_done_progress(&mut msg, session);
+                        self.track_progress(&msg, &venv_path, session);
+                    }
                     if let Some(method) = msg.method_name() {
                         if method == "$/progress" && is_progress_end(&msg) {
                             if let Some(inst) = self.state.pool.get_mut(&venv_path) {
@@ -144,6 +219,9 @@ impl super::LspProxy {
                                         "Backend warmup complete (reason: progress), transitioning to Ready"
                                     );
                                     let queued = inst.mark_ready();
+                                    if let Some(store) = &mut self.state.warmup_state {
+                                        store.record(&venv_path, std::time::SystemTime::now());
+                                    }
                                     if !queued.is_empty() {
                                         self.drain_warmup_queue(
                                             &venv_path,
@@ -159,16 +237,40 @@ impl super::LspProxy {
                     }
                 }
 
+                if self.state.options.strip_ansi
+                    && matches!(
+                        msg.method_name(),
+                        Some("window/logMessage" | "window/showMessage")
+                    )
+                {
+                    strip_ansi_from_message(&mut msg);
+                }
+
                 // Forward to client
                 if msg.is_response() {
+                    if response_method.as_deref() == Some("textDocument/codeAction")
+                        && !self.state.options.filter_code_action_kinds.is_empty()
+                    {
+                        self.filter_code_action_kinds(&mut msg);
+                    }
                     tracing::trace!(
                         id = ?msg.id,
                         has_result = msg.result.is_some(),
                         has_error = msg.error.is_some(),
                         "Forwarding response to client"
                     );
+                    if let Some(error) =
+                        self.enforce_response_size_limit(&msg, response_method.as_deref())
+                    {
+                        client_writer.write_message(&error).await?;
+                        return Ok(());
+                    }
+                }
+                // Unmodified messages go out as the backend sent them
+                match msg.raw {
+                    Some(body) => client_writer.write_raw(&msg.msg, body).await?,
+                    None => client_writer.write_message(&msg.msg).await?,
                 }
-                client_writer.write_message(&msg).await?;
             }
             Err(e) => {
                 tracing::error!(
@@ -184,16 +286,181 @@ impl super::LspProxy {
 
         Ok(())
     }
+
+    /// Apply `--on-stale-response` to a pending request whose response arrived
+    /// from a backend that no longer owns it.
+    async fn handle_stale_response(
+        &mut self,
+        id: RpcId,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let Some(mut pending) = self.state.pending_requests.remove(&id) else {
+            return Ok(());
+        };
+
+        let policy = self.state.options.on_stale_response;
+        if policy == StaleResponsePolicy::Drop {
+            return Ok(());
+        }
+
+        // Retry at most once, and only on a backend that replaced the owner:
+        // a live owner is still working on the request. A second stale
+        // response falls back to cancel.
+        if policy == StaleResponsePolicy::Retry && !pending.retried {
+            let current_session = self
+                .state
+                .pool
+                .get(&pending.venv_path)
+                .map(|inst| inst.session)
+                .filter(|&session| session != pending.backend_session);
+            if let (Some(session), Some(request)) = (current_session, pending.request.clone()) {
+                tracing::info!(
+                    id = ?id,
+                    venv = %pending.venv_path.display(),
+                    session = session,
+                    "Re-issuing request to current backend after stale response"
+                );
+                let venv_path = pending.venv_path.clone();
+                pending.backend_session = session;
+                pending.retried = true;
+                self.state.pending_requests.insert(id, pending);
+                self.forward_to_backend(&venv_path, &request).await?;
+                return Ok(());
+            }
+        }
+
+        // The owning backend may still answer: cancel it there and drop that
+        // late response, the client already has its answer
+        let owner_alive = self
+            .state
+            .pool
+            .get(&pending.venv_path)
+            .is_some_and(|inst| inst.session == pending.backend_session);
+        if owner_alive {
+            self.state.timed_out_requests.insert(
+                id.clone(),
+                (
+                    pending.venv_path.clone(),
+                    pending.backend_session,
+                    tokio::time::Instant::now(),
+                ),
+            );
+            let cancel_msg =
+                RpcMessage::notification("$/cancelRequest", Some(serde_json::json!({ "id": id })));
+            self.forward_to_backend(&pending.venv_path, &cancel_msg)
+                .await?;
+        }
+
+        let response = RpcMessage::cancelled_response(
+            id,
+            &self.client_message("stale response from replaced backend, request cancelled"),
+        );
+        client_writer.write_message(&response).await?;
+        Ok(())
+    }
+
+    /// Apply `--filter-code-action-kind`: drop code actions whose kind is, or
+    /// is nested under, one of the filtered kinds. Commands (no kind) are kept.
+    fn filter_code_action_kinds(&self, msg: &mut RpcMessage) {
+        let filters = &self.state.options.filter_code_action_kinds;
+        if filters.is_empty() {
+            return;
+        }
+        let Some(actions) = msg.result.as_mut().and_then(|r| r.as_array_mut()) else {
+            return;
+        };
+        let before = actions.len();
+        actions.retain(|action| {
+            let kind = action.get("kind").and_then(|k| k.as_str());
+            !kind.is_some_and(|kind| filters.iter().any(|f| code_action_kind_matches(kind, f)))
+        });
+        if actions.len() != before {
+            tracing::debug!(
+                id = ?msg.id,
+                removed = before - actions.len(),
+                "Filtered code actions by kind"
+            );
+        }
+    }
+
+    /// Apply `--response-size-limit-bytes` to a response bound for the client.
+    /// Returns the error response to send instead when it is too large.
+    pub(crate) fn enforce_response_size_limit(
+        &self,
+        msg: &RpcMessage,
+        method: Option<&str>,
+    ) -> Option<RpcMessage> {
+        let limit = self.state.options.response_size_limit?;
+        let size = serde_json::to_vec(msg).map(|body| body.len()).ok()?;
+        if size <= limit {
+            return None;
+        }
+        tracing::warn!(
+            id = ?msg.id,
+            method = method.unwrap_or("<unknown>"),
+            size = size,
+            limit = limit,
+            "Replacing oversized backend response with an error"
+        );
+        Some(RpcMessage::proxy_error_response(
+            msg,
+            error_codes::RESPONSE_TOO_LARGE,
+            &self.client_message(format!("response too large, {size} bytes")),
+            serde_json::json!({ "size": size, "limit": limit }),
+        ))
+    }
+}
+
+/// A backend message on its way to the client, with the body it arrived as.
+/// Any mutable access drops the body, so an edited message is serialized
+/// again instead of going out as the backend sent it.
+struct Forwarded {
+    msg: RpcMessage,
+    raw: Option<Vec<u8>>,
+}
+
+impl std::ops::Deref for Forwarded {
+    type Target = RpcMessage;
+
+    fn deref(&self) -> &RpcMessage {
+        &self.msg
+    }
+}
+
+impl std::ops::DerefMut for Forwarded {
+    fn deref_mut(&mut self) -> &mut RpcMessage {
+        self.raw = None;
+        &mut self.msg
+    }
 }
 
 /// Check if an RPC ID was assigned by the proxy (negative numbers).
-/// Used to detect stale fan-out sub-request responses that should be dropped.
-fn is_proxy_assigned_id(id: &RpcId) -> bool {
+/// Used to detect stale fan-out sub-request responses that should be dropped,
+/// and client responses to backend requests whose backend is gone.
+pub(super) fn is_proxy_assigned_id(id: &RpcId) -> bool {
     matches!(id, RpcId::Number(n) if *n < 0)
 }
 
+/// Hierarchical CodeActionKind match: `source` matches `source` and
+/// `source.organizeImports`, but not `sourcery`
+fn code_action_kind_matches(kind: &str, filter: &str) -> bool {
+    kind.strip_prefix(filter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// URI and diagnostic count of a `textDocument/publishDiagnostics` notification
+fn diagnostics_summary(msg: &RpcMessage) -> Option<(String, usize)> {
+    if msg.method_name() != Some("textDocument/publishDiagnostics") {
+        return None;
+    }
+    let params = msg.params.as_ref()?;
+    let uri = params.get("uri")?.as_str()?;
+    let count = params.get("diagnostics")?.as_array()?.len();
+    Some((uri.to_string(), count))
+}
+
 /// Check if a `$/progress` notification has `params.value.kind == "end"`.
-fn is_progress_end(msg: &RpcMessage) -> bool {
+pub(super) fn is_progress_end(msg: &RpcMessage) -> bool {
     msg.params
         .as_ref()
         .and_then(|p| p.get("value"))
@@ -201,3 +468,98 @@ fn is_progress_end(msg: &RpcMessage) -> bool {
         .and_then(|k| k.as_str())
         == Some("end")
 }
+
+/// `--log-request-response-correlation`: one event per answered request, for
+/// finding slow methods per project from the logs alone
+fn log_request_correlation(id: &RpcId, pending: &crate::state::PendingRequest) {
+    tracing::info!(
+        target: "typemux_cc::correlation",
+        method = %pending.method,
+        id = ?id,
+        venv = %pending.venv_path.display(),
+        session = pending.backend_session,
+        elapsed_ms = pending.created_at.elapsed().as_millis() as u64,
+        "Request completed"
+    );
+}
+
+/// Apply `--strip-ansi` to the `params.message` of a log/show message notification
+fn strip_ansi_from_message(msg: &mut RpcMessage) {
+    let Some(message) = msg.params.as_mut().and_then(|p| p.get_mut("message")) else {
+        return;
+    };
+    if let Some(text) = message.as_str().filter(|t| t.contains('\x1b')) {
+        *message = serde_json::Value::String(strip_ansi(text));
+    }
+}
+
+/// Remove ANSI escape sequences: CSI (`ESC [ ... final`), OSC (`ESC ] ... BEL`
+/// or `ESC \`) and two-character escapes. An unterminated sequence at the end
+/// of the text is dropped.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameter/intermediate bytes, then one final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST (ESC \)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[31merror\x1b[0m: bad"), "error: bad");
+        assert_eq!(strip_ansi("\x1b[1;38;5;208mwarn\x1b[m"), "warn");
+        assert_eq!(
+            strip_ansi("\x1b]8;;file:///a.py\x07a.py\x1b]8;;\x1b\\ ok"),
+            "a.py ok"
+        );
+        assert_eq!(strip_ansi("plain \u{e9}t\u{e9}"), "plain \u{e9}t\u{e9}");
+        assert_eq!(strip_ansi("cut \x1b[3"), "cut ");
+    }
+
+    #[test]
+    fn test_forwarded_drops_body_on_mutable_access() {
+        let body = br#"{"jsonrpc":"2.0","method":"window/logMessage","params":{"message":"x"}}"#;
+        let msg = RpcMessage::from_slice_strict(body).unwrap();
+        let mut forwarded = Forwarded {
+            msg,
+            raw: Some(body.to_vec()),
+        };
+
+        assert_eq!(forwarded.method_name(), Some("window/logMessage"));
+        assert!(forwarded.raw.is_some());
+
+        strip_ansi_from_message(&mut forwarded);
+        assert!(forwarded.raw.is_none());
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Per-venv crash circuit breaker (`--circuit-breaker-threshold`).
+///
+/// A venv whose backend crashes `threshold` times within `window` is refused
+/// new backends for `cooldown`, so a backend that dies on startup is not
+/// respawned on every request. After the cooldown the breaker closes and the
+/// crash history starts over.
+#[derive(Debug)]
+pub struct CrashBreaker {
+    threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    crashes: HashMap<PathBuf, VecDeque<Instant>>,
+    open_until: HashMap<PathBuf, Instant>,
+}
+
+impl CrashBreaker {
+    pub fn new(threshold: usize, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            cooldown,
+            crashes: HashMap::new(),
+            open_until: HashMap::new(),
+        }
+    }
+
+    /// Count a crash of `venv`'s backend at `now`. Returns true when this
+    /// crash opens the breaker.
+    pub fn record_crash(&mut self, venv: &Path, now: Instant) -> bool {
+        let crashes = self.crashes.entry(venv.to_path_buf()).or_default();
+        crashes.push_back(now);
+        while crashes
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) > self.window)
+        {
+            crashes.pop_front();
+        }
+        if crashes.len() < self.threshold {
+            return false;
+        }
+        let count = crashes.len();
+        self.crashes.remove(venv);
+        self.open_until
+            .insert(venv.to_path_buf(), now + self.cooldown);
+        tracing::warn!(
+            venv = %venv.display(),
+            crashes = count,
+            window_secs = self.window.as_secs(),
+            cooldown_secs = self.cooldown.as_secs(),
+            "Circuit breaker open: not respawning backend until cooldown ends"
+        );
+        true
+    }
+
+    /// Whether `venv` is refused new backends at `now`. Closes the breaker
+    /// once its cooldown has passed.
+    pub fn is_open(&mut self, venv: &Path, now: Instant) -> bool {
+        let Some(&until) = self.open_until.get(venv) else {
+            return false;
+        };
+        if now < until {
+            return true;
+        }
+        self.open_until.remove(venv);
+        tracing::info!(venv = %venv.display(), "Circuit breaker closed after cooldown");
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_at_threshold_and_closes_after_cooldown() {
+        let mut breaker = CrashBreaker::new(3, Duration::from_secs(60), Duration::from_secs(120));
+        let venv = Path::new("/project/.venv");
+        let other = Path::new("/other/.venv");
+        let start = Instant::now();
+
+        assert!(!breaker.record_crash(venv, start));
+        // Falls out of the window before the next crashes
+        assert!(!breaker.record_crash(venv, start + Duration::from_secs(70)));
+        assert!(!breaker.record_crash(venv, start + Duration::from_secs(80)));
+        assert!(!breaker.is_open(venv, start + Duration::from_secs(80)));
+        assert!(breaker.record_crash(venv, start + Duration::from_secs(90)));
+
+        assert!(breaker.is_open(venv, start + Duration::from_secs(100)));
+        assert!(!breaker.is_open(other, start + Duration::from_secs(100)));
+        assert!(!breaker.is_open(venv, start + Duration::from_secs(210)));
+
+        // History was reset when the breaker opened
+        assert!(!breaker.record_crash(venv, start + Duration::from_secs(220)));
+    }
+}
@@ -1,10 +1,11 @@
+use super::transport::ClientOutput;
 use crate::backend::LspBackend;
-use crate::backend_pool::{shutdown_backend_instance, BackendInstance};
 use crate::error::ProxyError;
-use crate::framing::LspFrameWriter;
-use crate::message::{RpcId, RpcMessage};
-use crate::state::PendingRequest;
+use crate::framing::{LspFrameWriter, COMPRESSION_THRESHOLD};
+use crate::message::{error_codes, RpcId, RpcMessage};
+use crate::state::{PendingRequest, StaleResponsePolicy};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::time::Instant;
 
 /// LSP methods that depend on the cross-file index and should be queued during warmup.
@@ -15,6 +16,21 @@ const INDEX_DEPENDENT_METHODS: &[&str] = &[
     "textDocument/typeDefinition",
 ];
 
+/// Read-only LSP methods that `--transparent-retry` may re-issue to a
+/// restarted backend: answering them twice has no side effects.
+pub(super) const TRANSPARENT_RETRY_METHODS: &[&str] = &[
+    "textDocument/hover",
+    "textDocument/definition",
+    "textDocument/references",
+    "textDocument/documentSymbol",
+    "textDocument/typeDefinition",
+    "textDocument/implementation",
+];
+
+/// How long a late response to a timed-out request is waited for; a backend
+/// that never answers must not leave the id behind forever
+const TIMED_OUT_RESPONSE_TTL: Duration = Duration::from_secs(300);
+
 /// LSP methods that support fan-out to all backends when multiple are active.
 const FANOUT_METHODS: &[&str] = &["workspace/symbol"];
 
@@ -27,58 +43,83 @@ impl super::LspProxy {
         &mut self,
         msg: &RpcMessage,
         pending_initial_backend: &mut Option<(LspBackend, PathBuf)>,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         tracing::info!("Caching initialize message for backend initialization");
         self.state.client_initialize = Some(msg.clone());
 
-        if let Some((mut backend, venv)) = pending_initial_backend.take() {
+        let mut init_response = if let Some((backend, venv)) = pending_initial_backend.take() {
             // Forward initialize to the pre-spawned backend
             match self
-                .complete_backend_initialization(&mut backend, &venv, client_writer)
+                .install_initial_backend(backend, venv, client_writer)
                 .await
             {
-                Ok(init_response) => {
-                    // Split and insert into pool
-                    let session = self.state.pool.next_session_id();
-                    let parts = backend.into_split();
-                    let tx = self.state.pool.msg_sender();
-                    let instance = BackendInstance::from_parts(parts, venv.clone(), session, tx);
-                    self.state.pool.insert(venv, instance);
-
-                    // Send initialize response to client
-                    client_writer.write_message(&init_response).await?;
-                    tracing::info!("Initial backend inserted into pool");
-                }
+                Ok(init_response) => init_response,
                 Err(e) => {
                     tracing::error!(error = ?e, "Failed to initialize fallback backend, returning minimal response");
-                    let init_response =
-                        RpcMessage::success_response(msg, serde_json::json!({"capabilities": {}}));
-                    client_writer.write_message(&init_response).await?;
+                    self.minimal_initialize_response(msg)
                 }
             }
         } else {
             // No fallback backend — return minimal capabilities
             tracing::warn!("No fallback backend: returning minimal initialize response");
-            let init_response =
-                RpcMessage::success_response(msg, serde_json::json!({"capabilities": {}}));
-            client_writer.write_message(&init_response).await?;
+            self.minimal_initialize_response(msg)
+        };
+
+        // The client talks to the proxy, not to whichever backend answered first
+        if let Some(result) = init_response
+            .result
+            .as_mut()
+            .and_then(|r| r.as_object_mut())
+        {
+            result.insert(
+                "serverInfo".to_string(),
+                serde_json::json!({
+                    "name": self.state.options.proxy_name,
+                    "version": env!("CARGO_PKG_VERSION"),
+                }),
+            );
+        }
+        client_writer.write_message(&init_response).await?;
+
+        // The initialize response itself always goes out uncompressed
+        if self.state.options.compress_frames && client_accepts_gzip(msg) {
+            tracing::info!("Client accepts gzip frames, compressing large responses");
+            client_writer.set_compression_threshold(Some(COMPRESSION_THRESHOLD));
         }
 
         Ok(())
     }
 
+    /// Initialize response used when no backend could answer: a conservative
+    /// default capability set, so the client doesn't disable features before
+    /// a backend spawns.
+    fn minimal_initialize_response(&self, msg: &RpcMessage) -> RpcMessage {
+        RpcMessage::success_response(
+            msg,
+            serde_json::json!({ "capabilities": default_capabilities() }),
+        )
+    }
+
     /// Handle client "initialized" notification.
     ///
-    /// Forwards the notification to all backends in the pool.
+    /// Forwards the notification to all backends in the pool. With
+    /// `--forward-initialized-once`, backends that already received
+    /// `initialized` (from their handshake or an earlier client notification) are skipped.
     pub(crate) async fn dispatch_initialized(&mut self) -> Result<(), ProxyError> {
         tracing::info!("Client initialized");
         // Forward to all backends in the pool
         let initialized_msg = RpcMessage::notification("initialized", Some(serde_json::json!({})));
+        let once = self.state.options.forward_initialized_once;
         // Collect keys to avoid borrow issues
         let venvs: Vec<PathBuf> = self.state.pool.backends_keys();
         for venv in &venvs {
             if let Some(inst) = self.state.pool.get_mut(venv) {
+                if once && inst.initialized_sent {
+                    tracing::debug!(venv = %venv.display(), session = inst.session, "Backend already initialized, not forwarding duplicate");
+                    continue;
+                }
+                inst.initialized_sent = true;
                 if let Err(e) = inst.writer.write_message(&initialized_msg).await {
                     tracing::warn!(venv = %venv.display(), error = ?e, "Failed to forward initialized to backend");
                 }
@@ -90,22 +131,16 @@ impl super::LspProxy {
 
     /// Handle client "shutdown" request.
     ///
-    /// Shuts down all backends and sends a response to the client.
+    /// Shuts down all backends, waits for them to exit, and sends a response
+    /// to the client.
     pub(crate) async fn dispatch_shutdown(
         &mut self,
         msg: &RpcMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         tracing::info!("Received shutdown request from client");
 
-        // Shutdown all backends in the pool
-        let venvs: Vec<PathBuf> = self.state.pool.backends_keys();
-        for venv in &venvs {
-            if let Some(instance) = self.state.pool.remove(venv) {
-                tracing::info!(venv = %venv.display(), "Shutting down backend");
-                shutdown_backend_instance(instance);
-            }
-        }
+        self.shutdown_all_backends_and_wait().await;
 
         // Send shutdown response to client
         let shutdown_response = RpcMessage::success_response(msg, serde_json::Value::Null);
@@ -125,10 +160,28 @@ impl super::LspProxy {
     ) -> Result<bool, ProxyError> {
         if let Some(proxy_id) = &msg.id {
             if let Some(pending) = self.state.pending_backend_requests.remove(proxy_id) {
+                if msg.error.is_some() {
+                    self.forget_capability_registrations(&pending.registrations);
+                }
+
                 // Restore original backend ID and route to correct backend
                 let mut response_msg = msg.clone();
                 response_msg.id = Some(pending.original_id);
 
+                // Void requests (e.g. workspace/semanticTokens/refresh) are answered
+                // with `"result": null`, which deserializes to `None`. Restore it so
+                // the backend receives a valid response instead of a bare id.
+                if response_msg.result.is_none() && response_msg.error.is_none() {
+                    response_msg.result = Some(serde_json::Value::Null);
+                }
+                if let Some(result) = &mut response_msg.result {
+                    self.force_configuration_result(
+                        &pending.venv_path,
+                        &pending.configuration_sections,
+                        result,
+                    );
+                }
+
                 if let Some(inst) = self.state.pool.get_mut(&pending.venv_path) {
                     if inst.session == pending.session {
                         if let Err(e) = inst.writer.write_message(&response_msg).await {
@@ -155,7 +208,16 @@ impl super::LspProxy {
                 }
                 return Ok(true);
             }
-            // If not in pending_backend_requests, fall through (shouldn't happen normally)
+            // Entries are dropped when their backend is evicted or crashes;
+            // the late answer (e.g. a window/showMessageRequest choice) has nowhere to go
+            if super::backend_dispatch::is_proxy_assigned_id(proxy_id) {
+                tracing::info!(
+                    proxy_id = ?proxy_id,
+                    "Discarding client response: originating backend is gone"
+                );
+                return Ok(true);
+            }
+            // Otherwise fall through (shouldn't happen normally)
         }
 
         Ok(false)
@@ -167,23 +229,14 @@ impl super::LspProxy {
     pub(crate) async fn dispatch_client_request(
         &mut self,
         msg: &RpcMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
-        const VENV_CHECK_METHODS: &[&str] = &[
-            "textDocument/hover",
-            "textDocument/definition",
-            "textDocument/references",
-            "textDocument/documentSymbol",
-            "textDocument/typeDefinition",
-            "textDocument/implementation",
-        ];
-
         let method = msg.method_name();
         let mut target_venv: Option<PathBuf> = None;
 
-        // For VENV_CHECK_METHODS, ensure the correct backend is in the pool
+        // For document requests, ensure the correct backend is in the pool
         if let Some(method_name) = method {
-            if VENV_CHECK_METHODS.contains(&method_name) {
+            if method_name.starts_with("textDocument/") {
                 if let Some(url) = Self::extract_text_document_uri(msg) {
                     if let Ok(file_path) = url.to_file_path() {
                         match self
@@ -195,21 +248,30 @@ impl super::LspProxy {
                             }
                             Ok(None) => {
                                 // No venv found — return error
-                                let error_message = "lsp-proxy: .venv not found (strict mode). Create .venv or run hooks.";
+                                let error_message = &self.client_message(
+                                    ".venv not found (strict mode). Create .venv or run hooks.",
+                                );
                                 tracing::warn!(
                                     method = method_name,
                                     uri = %url,
                                     "No venv found, returning error"
                                 );
-                                let error_response = RpcMessage::error_response(msg, error_message);
+                                let error_response = RpcMessage::proxy_error_response(
+                                    msg,
+                                    error_codes::VENV_NOT_FOUND,
+                                    error_message,
+                                    serde_json::json!({ "uri": url.as_str() }),
+                                );
                                 client_writer.write_message(&error_response).await?;
                                 return Ok(());
                             }
                             Err(e) => {
                                 tracing::error!(error = ?e, "Failed to ensure backend in pool");
-                                let error_response = RpcMessage::error_response(
+                                let error_response = RpcMessage::proxy_error_response(
                                     msg,
-                                    &format!("lsp-proxy: backend error: {}", e),
+                                    error_codes::BACKEND_UNAVAILABLE,
+                                    &self.client_message(format!("backend error: {}", e)),
+                                    serde_json::json!({ "uri": url.as_str() }),
                                 );
                                 client_writer.write_message(&error_response).await?;
                                 return Ok(());
@@ -236,12 +298,14 @@ impl super::LspProxy {
                                 uri = %url,
                                 "Cannot resolve venv for non-file URI"
                             );
-                            let error_response = RpcMessage::error_response(
+                            let error_response = RpcMessage::proxy_error_response(
                                 msg,
-                                &format!(
-                                    "lsp-proxy: cannot resolve venv for non-file URI: {}",
+                                error_codes::UNROUTABLE,
+                                &self.client_message(format!(
+                                    "cannot resolve venv for non-file URI: {}",
                                     url
-                                ),
+                                )),
+                                serde_json::json!({ "uri": url.as_str() }),
                             );
                             client_writer.write_message(&error_response).await?;
                             return Ok(());
@@ -261,18 +325,24 @@ impl super::LspProxy {
                                 uri = %url,
                                 "No venv found for URI-bearing request"
                             );
-                            let error_response = RpcMessage::error_response(
+                            let error_response = RpcMessage::proxy_error_response(
                                 msg,
-                                "lsp-proxy: .venv not found (strict mode). Create .venv or run hooks.",
+                                error_codes::VENV_NOT_FOUND,
+                                &self.client_message(
+                                    ".venv not found (strict mode). Create .venv or run hooks.",
+                                ),
+                                serde_json::json!({ "uri": url.as_str() }),
                             );
                             client_writer.write_message(&error_response).await?;
                             return Ok(());
                         }
                         Err(e) => {
                             tracing::error!(error = ?e, "Failed to ensure backend in pool");
-                            let error_response = RpcMessage::error_response(
+                            let error_response = RpcMessage::proxy_error_response(
                                 msg,
-                                &format!("lsp-proxy: backend error: {}", e),
+                                error_codes::BACKEND_UNAVAILABLE,
+                                &self.client_message(format!("backend error: {}", e)),
+                                serde_json::json!({ "uri": url.as_str() }),
                             );
                             client_writer.write_message(&error_response).await?;
                             return Ok(());
@@ -289,8 +359,10 @@ impl super::LspProxy {
             let backend_info = self.state.pool.get_mut(venv_path).map(|inst| {
                 inst.last_used = Instant::now();
                 let session = inst.session;
-                let should_queue = method
-                    .is_some_and(|m| inst.is_warming() && INDEX_DEPENDENT_METHODS.contains(&m));
+                let should_queue = method.is_some_and(|m| {
+                    inst.is_warming()
+                        && queues_during_warmup(m, &self.state.options.no_warmup_methods)
+                });
                 (session, should_queue)
             });
 
@@ -304,8 +376,29 @@ impl super::LspProxy {
                         venv = %venv_path.display(),
                         "Queueing index-dependent request during warmup"
                     );
-                    if let Some(inst) = self.state.pool.get_mut(venv_path) {
-                        inst.warmup_queue.push(msg.clone());
+                    let limit = self.state.options.warmup_queue_limit;
+                    let dropped = self
+                        .state
+                        .pool
+                        .get_mut(venv_path)
+                        .and_then(|inst| inst.enqueue_warmup_request(msg.clone(), limit));
+                    if let Some(dropped) = dropped {
+                        tracing::warn!(
+                            method = ?dropped.method,
+                            id = ?dropped.id,
+                            venv = %venv_path.display(),
+                            limit = ?limit,
+                            "Warmup queue limit reached, dropping oldest queued request"
+                        );
+                        if let Some(dropped_id) = dropped.id {
+                            self.state.pending_requests.remove(&dropped_id);
+                            self.release_partial_result_token(&dropped_id);
+                            let response = RpcMessage::cancelled_response(
+                                dropped_id,
+                                &self.client_message("warmup queue limit reached, request dropped"),
+                            );
+                            client_writer.write_message(&response).await?;
+                        }
                     }
                     return Ok(());
                 }
@@ -314,43 +407,72 @@ impl super::LspProxy {
                 self.register_pending_request(msg, session, venv_path);
 
                 if let Some(inst) = self.state.pool.get_mut(venv_path) {
-                    if let Err(e) = inst.writer.write_message(msg).await {
-                        tracing::error!(venv = %venv_path.display(), error = ?e, "Failed to send request to backend");
+                    match inst.writer.write_message(msg).await {
+                        Ok(()) => {
+                            if method == Some("textDocument/hover") {
+                                self.track_hover_deadline(msg, venv_path);
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!(venv = %venv_path.display(), error = ?e, "Failed to send request to backend");
+                        }
                     }
                 }
             } else {
                 // Backend disappeared (race with crash handling)
-                let error_response =
-                    RpcMessage::error_response(msg, "lsp-proxy: backend not available");
+                let error_response = RpcMessage::proxy_error_response(
+                    msg,
+                    error_codes::BACKEND_UNAVAILABLE,
+                    &self.client_message("backend not available"),
+                    serde_json::json!({ "venv": venv_path.display().to_string() }),
+                );
                 client_writer.write_message(&error_response).await?;
             }
         } else {
             // No target venv resolved (URI-less request)
             if self.state.pool.is_empty() {
-                let error_message =
-                    "lsp-proxy: .venv not found (strict mode). Create .venv or run hooks.";
-                let error_response = RpcMessage::error_response(msg, error_message);
+                let error_message = &self
+                    .client_message(".venv not found (strict mode). Create .venv or run hooks.");
+                let error_response = RpcMessage::proxy_error_response(
+                    msg,
+                    error_codes::VENV_NOT_FOUND,
+                    error_message,
+                    serde_json::json!({ "method": msg.method_name() }),
+                );
                 client_writer.write_message(&error_response).await?;
-            } else if self.state.pool.len() == 1 {
+            } else if self.state.pool.len() == 1 && !self.state.options.strict_uri_routing {
                 // Single backend: no cross-contamination possible, forward unconditionally
                 self.forward_to_first_backend(msg).await?;
             } else {
-                // Multiple backends: fan-out or reject
+                // Multiple backends (or --strict-uri-routing): fan-out or reject
                 let method_name = msg.method_name().unwrap_or("");
-                if FANOUT_METHODS.contains(&method_name) {
+                if FANOUT_METHODS.contains(&method_name)
+                    || self.is_forwarded_custom_method(method_name)
+                {
                     self.dispatch_fanout_request(msg, client_writer).await?;
                 } else {
+                    let reason = if self.state.pool.len() > 1 {
+                        "multiple backends active"
+                    } else {
+                        "strict URI routing"
+                    };
                     tracing::warn!(
                         method = method_name,
                         pool_size = self.state.pool.len(),
-                        "Rejecting URI-less request: cannot determine target venv (multiple backends active)"
+                        reason = reason,
+                        "Rejecting URI-less request: cannot determine target venv"
                     );
-                    let error_response = RpcMessage::error_response(
+                    let error_response = RpcMessage::proxy_error_response(
                         msg,
-                        &format!(
-                            "lsp-proxy: cannot route '{}' without a document URI (multiple backends active)",
-                            method_name
-                        ),
+                        error_codes::UNROUTABLE,
+                        &self.client_message(format!(
+                            "cannot route '{}' without a document URI ({})",
+                            method_name, reason
+                        )),
+                        serde_json::json!({
+                            "method": method_name,
+                            "poolSize": self.state.pool.len(),
+                        }),
                     );
                     client_writer.write_message(&error_response).await?;
                 }
@@ -360,15 +482,38 @@ impl super::LspProxy {
         Ok(())
     }
 
+    /// Whether `method` is a backend-specific extension allowed by `--forward-custom-methods`
+    pub(crate) fn is_forwarded_custom_method(&self, method: &str) -> bool {
+        self.state
+            .options
+            .forward_custom_methods
+            .iter()
+            .any(|prefix| method.starts_with(prefix.as_str()))
+    }
+
     /// Register a pending request so that the response can be routed back
     /// to the correct backend session.
-    fn register_pending_request(&mut self, msg: &RpcMessage, session: u64, venv_path: &Path) {
+    pub(crate) fn register_pending_request(
+        &mut self,
+        msg: &RpcMessage,
+        session: u64,
+        venv_path: &Path,
+    ) {
         if let Some(id) = &msg.id {
+            let keep_request = self.state.options.on_stale_response == StaleResponsePolicy::Retry
+                || (self.state.options.transparent_retry
+                    && msg
+                        .method_name()
+                        .is_some_and(|m| TRANSPARENT_RETRY_METHODS.contains(&m)));
             self.state.pending_requests.insert(
                 id.clone(),
                 PendingRequest {
                     backend_session: session,
                     venv_path: venv_path.to_path_buf(),
+                    method: msg.method_name().unwrap_or_default().to_string(),
+                    request: keep_request.then(|| msg.clone()),
+                    retried: self.state.reissued_ids.remove(id),
+                    created_at: Instant::now(),
                 },
             );
         }
@@ -418,6 +563,18 @@ impl super::LspProxy {
         &mut self,
         msg: &RpcMessage,
     ) -> Result<(), ProxyError> {
+        // Allowlisted extensions about one document only concern its backend
+        if msg
+            .method_name()
+            .is_some_and(|m| self.is_forwarded_custom_method(m))
+        {
+            if let Some(venv) =
+                Self::extract_text_document_uri(msg).and_then(|url| self.venv_for_uri(&url))
+            {
+                return self.forward_to_backend(&venv, msg).await;
+            }
+        }
+
         let venvs: Vec<PathBuf> = self.state.pool.backends_keys();
         for venv in &venvs {
             if let Some(inst) = self.state.pool.get_mut(venv) {
@@ -430,6 +587,34 @@ impl super::LspProxy {
         Ok(())
     }
 
+    /// Handle `workspace/didChangeConfiguration` notification.
+    ///
+    /// Forwards the settings to every backend in the pool and remembers them so
+    /// backends spawned later start with the current configuration. Settings
+    /// the proxy forces (`--pyright-diagnostic-mode`) are merged on top, as
+    /// for new backends, so the client can't switch them off.
+    pub(crate) async fn dispatch_did_change_configuration(
+        &mut self,
+        msg: &RpcMessage,
+    ) -> Result<(), ProxyError> {
+        tracing::debug!(
+            backends = self.state.pool.len(),
+            "Forwarding didChangeConfiguration to all backends"
+        );
+        self.state.client_configuration = Some(msg.clone());
+        for venv in self.state.pool.backends_keys() {
+            let Some(configuration) = self.backend_configuration(&venv) else {
+                continue;
+            };
+            if let Some(inst) = self.state.pool.get_mut(&venv) {
+                if let Err(e) = inst.writer.write_message(&configuration).await {
+                    tracing::warn!(venv = %venv.display(), error = ?e, "Failed to forward notification to backend");
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Handle `$/cancelRequest` notification.
     ///
     /// If the target request is queued in a warmup queue, remove it without
@@ -437,8 +622,20 @@ impl super::LspProxy {
     pub(crate) async fn dispatch_cancel_request(
         &mut self,
         msg: &RpcMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
+        // A request of a pending batch runs under a batch-unique id
+        let batched;
+        let msg = match extract_cancel_id(msg).and_then(|id| client_writer.batch_request_id(&id)) {
+            Some(key) => {
+                batched = RpcMessage::notification(
+                    "$/cancelRequest",
+                    Some(serde_json::json!({ "id": key })),
+                );
+                &batched
+            }
+            None => msg,
+        };
         if let Some(cancelled_id) = extract_cancel_id(msg) {
             // Check if cancelled ID is a pending fan-out
             if self.state.pending_fanouts.contains_key(&cancelled_id) {
@@ -448,26 +645,123 @@ impl super::LspProxy {
             }
 
             if let Some(pending) = self.state.pending_requests.get(&cancelled_id).cloned() {
-                if let Some(inst) = self.state.pool.get_mut(&pending.venv_path) {
-                    if inst.session == pending.backend_session
-                        && inst.cancel_warmup_request(&cancelled_id).is_some()
-                    {
-                        tracing::info!(
-                            id = ?cancelled_id,
-                            venv = %pending.venv_path.display(),
-                            "Cancelled warmup-queued request"
-                        );
-                        self.state.pending_requests.remove(&cancelled_id);
-                        return Ok(());
-                    }
+                let Some(inst) = self
+                    .state
+                    .pool
+                    .get_mut(&pending.venv_path)
+                    .filter(|inst| inst.session == pending.backend_session)
+                else {
+                    // The owning backend is gone; its cleanup answers the request
+                    tracing::debug!(
+                        id = ?cancelled_id,
+                        venv = %pending.venv_path.display(),
+                        "Dropping $/cancelRequest for request of an evicted/crashed backend"
+                    );
+                    return Ok(());
+                };
+                if inst.cancel_warmup_request(&cancelled_id).is_some() {
+                    tracing::info!(
+                        id = ?cancelled_id,
+                        venv = %pending.venv_path.display(),
+                        "Cancelled warmup-queued request"
+                    );
+                    self.state.pending_requests.remove(&cancelled_id);
+                    self.release_partial_result_token(&cancelled_id);
+                    return Ok(());
                 }
+
+                // Only the backend that owns the request should see the cancel
+                return self.forward_to_backend(&pending.venv_path, msg).await;
             }
         }
 
-        // Not in warmup queue or fan-out — forward $/cancelRequest to all backends
+        // Unknown id — forward $/cancelRequest to all backends
         self.dispatch_client_notification(msg).await
     }
 
+    /// Answer requests the backend hasn't answered within `--request-timeout`
+    /// with `RequestCancelled`, and cancel them at the backend (or drop them
+    /// from its warmup queue). Fan-out sub-requests have their own deadline.
+    pub(crate) async fn expire_timed_out_requests(
+        &mut self,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let Some(timeout) = self.state.options.request_timeout else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        self.prune_timed_out_requests(now);
+        let expired: Vec<RpcId> = self
+            .state
+            .pending_requests
+            .iter()
+            .filter(|(id, p)| {
+                !super::backend_dispatch::is_proxy_assigned_id(id)
+                    && now.saturating_duration_since(p.created_at) >= timeout
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in expired {
+            let Some(pending) = self.state.pending_requests.remove(&id) else {
+                continue;
+            };
+            tracing::warn!(
+                id = ?id,
+                method = %pending.method,
+                venv = %pending.venv_path.display(),
+                timeout_secs = timeout.as_secs(),
+                "Request timed out, cancelling"
+            );
+            self.state.pending_hovers.remove(&id);
+            self.release_partial_result_token(&id);
+
+            let queued = self
+                .state
+                .pool
+                .get_mut(&pending.venv_path)
+                .filter(|inst| inst.session == pending.backend_session)
+                .and_then(|inst| inst.cancel_warmup_request(&id));
+            if queued.is_none() {
+                // The backend may still answer; that late response is dropped
+                self.state.timed_out_requests.insert(
+                    id.clone(),
+                    (pending.venv_path.clone(), pending.backend_session, now),
+                );
+                let cancel_msg = RpcMessage::notification(
+                    "$/cancelRequest",
+                    Some(serde_json::json!({ "id": id })),
+                );
+                self.forward_to_backend(&pending.venv_path, &cancel_msg)
+                    .await?;
+            }
+
+            let response = RpcMessage::cancelled_response(
+                id,
+                &self.client_message(format!("request timed out after {}s", timeout.as_secs())),
+            );
+            client_writer.write_message(&response).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forget timed-out ids whose late response hasn't come within
+    /// `TIMED_OUT_RESPONSE_TTL`
+    fn prune_timed_out_requests(&mut self, now: Instant) {
+        self.state
+            .timed_out_requests
+            .retain(|_, (_, _, at)| now.saturating_duration_since(*at) < TIMED_OUT_RESPONSE_TTL);
+    }
+
+    /// Forget timed-out ids of a backend (venv, session) that is gone: no late
+    /// response can arrive from it anymore
+    pub(crate) fn release_timed_out_requests(&mut self, venv_path: &Path, session: u64) {
+        self.state
+            .timed_out_requests
+            .retain(|_, (v, s, _)| !(v == venv_path && *s == session));
+    }
+
     /// Forward queued warmup requests to the backend now that it is ready.
     /// `expected_session` is checked to avoid forwarding to a replaced backend.
     pub(crate) async fn drain_warmup_queue(
@@ -475,7 +769,7 @@ impl super::LspProxy {
         venv_path: &PathBuf,
         expected_session: u64,
         queued: Vec<RpcMessage>,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         for request in queued {
             let method = request.method_name().unwrap_or("unknown").to_string();
@@ -524,9 +818,11 @@ impl super::LspProxy {
                         if let Some(req_id) = &request.id {
                             self.state.pending_requests.remove(req_id);
                         }
-                        let error_response = RpcMessage::error_response(
+                        let error_response = RpcMessage::proxy_error_response(
                             &request,
-                            "lsp-proxy: backend write failed during warmup drain",
+                            error_codes::BACKEND_UNAVAILABLE,
+                            &self.client_message("backend write failed during warmup drain"),
+                            serde_json::json!({ "venv": venv_path.display().to_string() }),
                         );
                         client_writer.write_message(&error_response).await?;
                     }
@@ -537,6 +833,12 @@ impl super::LspProxy {
     }
 }
 
+/// Check whether a request should wait for warmup: index-dependent methods
+/// queue unless explicitly excluded via `--no-warmup-method`.
+fn queues_during_warmup(method: &str, excluded: &[String]) -> bool {
+    INDEX_DEPENDENT_METHODS.contains(&method) && !excluded.iter().any(|m| m == method)
+}
+
 /// Extract the cancel target id from a `$/cancelRequest` params.
 fn extract_cancel_id(msg: &RpcMessage) -> Option<RpcId> {
     let params = msg.params.as_ref()?;
@@ -547,3 +849,87 @@ fn extract_cancel_id(msg: &RpcMessage) -> Option<RpcId> {
         id_value.as_str().map(|s| RpcId::String(s.to_string()))
     }
 }
+
+/// Whether the client advertised gzip frame support in its initialize params
+/// (`capabilities.experimental.frameContentEncodings` contains `"gzip"`).
+fn client_accepts_gzip(initialize: &RpcMessage) -> bool {
+    initialize
+        .params
+        .as_ref()
+        .and_then(|p| p.pointer("/capabilities/experimental/frameContentEncodings"))
+        .and_then(|e| e.as_array())
+        .is_some_and(|encodings| encodings.iter().any(|e| e == "gzip"))
+}
+
+/// Capabilities advertised before any backend has initialized: document sync
+/// (incremental) plus the core navigation features every backend provides.
+fn default_capabilities() -> serde_json::Value {
+    serde_json::json!({
+        "textDocumentSync": { "openClose": true, "change": 2 },
+        "hoverProvider": true,
+        "definitionProvider": true,
+        "referencesProvider": true
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_out_request_ids_are_released() {
+        use crate::backend::BackendKind;
+        use crate::state::ProxyOptions;
+
+        let mut proxy =
+            super::super::LspProxy::new(BackendKind::Pyright, 2, None, ProxyOptions::default());
+        let start = Instant::now();
+        let a = PathBuf::from("/a/.venv");
+        let b = PathBuf::from("/b/.venv");
+        let timed_out = &mut proxy.state.timed_out_requests;
+        timed_out.insert(RpcId::Number(1), (a.clone(), 1, start));
+        timed_out.insert(RpcId::Number(2), (a.clone(), 2, start));
+        timed_out.insert(
+            RpcId::Number(3),
+            (b.clone(), 1, start + Duration::from_secs(60)),
+        );
+
+        // The evicted backend's ids go; the same venv's next session keeps its own
+        proxy.release_timed_out_requests(&a, 1);
+        let mut ids: Vec<_> = proxy.state.timed_out_requests.keys().cloned().collect();
+        ids.sort_by_key(|id| format!("{id:?}"));
+        assert_eq!(ids, vec![RpcId::Number(2), RpcId::Number(3)]);
+
+        // A late response that never comes is given up on after the TTL
+        proxy.prune_timed_out_requests(start + TIMED_OUT_RESPONSE_TTL);
+        let ids: Vec<_> = proxy.state.timed_out_requests.keys().cloned().collect();
+        assert_eq!(ids, vec![RpcId::Number(3)]);
+    }
+
+    #[test]
+    fn queues_during_warmup_respects_exclusions() {
+        let excluded = vec!["textDocument/references".to_string()];
+        assert!(queues_during_warmup("textDocument/definition", &excluded));
+        assert!(!queues_during_warmup("textDocument/references", &excluded));
+        assert!(!queues_during_warmup("textDocument/hover", &excluded));
+        assert!(queues_during_warmup("textDocument/references", &[]));
+    }
+
+    #[test]
+    fn client_accepts_gzip_from_experimental_capabilities() {
+        let init = |caps: serde_json::Value| {
+            RpcMessage::request(
+                RpcId::Number(1),
+                "initialize",
+                Some(serde_json::json!({ "capabilities": caps })),
+            )
+        };
+        assert!(client_accepts_gzip(&init(serde_json::json!({
+            "experimental": { "frameContentEncodings": ["deflate", "gzip"] }
+        }))));
+        assert!(!client_accepts_gzip(&init(serde_json::json!({}))));
+        assert!(!client_accepts_gzip(&init(serde_json::json!({
+            "experimental": { "frameContentEncodings": ["deflate"] }
+        }))));
+    }
+}
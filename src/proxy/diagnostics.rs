@@ -1,6 +1,8 @@
+use super::transport::ClientOutput;
 use crate::error::ProxyError;
 use crate::framing::LspFrameWriter;
 use crate::message::RpcMessage;
+use crate::state::{DiagnosticAction, DiagnosticCodeRule};
 use std::path::Path;
 
 impl super::LspProxy {
@@ -9,33 +11,184 @@ impl super::LspProxy {
         &self,
         venv_path: &Path,
         error: &ProxyError,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) {
+        self.notify_client_error(
+            &format!(
+                "Failed to start LSP backend for {}: {}",
+                venv_path.display(),
+                error
+            ),
+            client_writer,
+        )
+        .await;
+    }
+
+    /// Send a window/showMessage error to the client
+    pub(crate) async fn notify_client_error(
+        &self,
+        text: &str,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) {
         let msg = RpcMessage::notification(
             "window/showMessage",
             Some(serde_json::json!({
                 "type": 1,
-                "message": format!(
-                    "typemux-cc: Failed to start LSP backend for {}: {}",
-                    venv_path.display(),
-                    error
-                )
+                "message": self.client_message(text)
             })),
         );
 
         if let Err(e) = client_writer.write_message(&msg).await {
             tracing::warn!(
                 error = ?e,
-                "Failed to send backend error notification to client"
+                "Failed to send error notification to client"
+            );
+        }
+    }
+
+    /// Whether a `publishDiagnostics` from `venv_path` targets a document owned
+    /// by a different backend (the venv the document resolved to). Such
+    /// publishes would clobber the owner's diagnostics on the client.
+    /// URIs that aren't open have no owner and are always accepted.
+    pub(crate) fn is_foreign_diagnostics(&self, msg: &RpcMessage, venv_path: &Path) -> bool {
+        let Some(url) = msg
+            .params
+            .as_ref()
+            .and_then(|p| p.get("uri"))
+            .and_then(|u| u.as_str())
+            .and_then(|u| url::Url::parse(u).ok())
+        else {
+            return false;
+        };
+        let Some(owner) = self.venv_for_uri(&url) else {
+            return false;
+        };
+        if owner == venv_path {
+            return false;
+        }
+        tracing::info!(
+            uri = %url,
+            from = %venv_path.display(),
+            owner = %owner.display(),
+            "Dropping publishDiagnostics from non-owning backend"
+        );
+        true
+    }
+
+    /// Apply `--diagnostic-code-rule` to a `publishDiagnostics` in place.
+    pub(crate) fn apply_diagnostic_code_rules(&self, msg: &mut RpcMessage) {
+        let rules = &self.state.options.diagnostic_code_rules;
+        if rules.is_empty() {
+            return;
+        }
+        if let Some(diagnostics) = msg
+            .params
+            .as_mut()
+            .and_then(|p| p.get_mut("diagnostics"))
+            .and_then(|d| d.as_array_mut())
+        {
+            let before = diagnostics.len();
+            apply_code_rules(diagnostics, rules);
+            if diagnostics.len() != before {
+                tracing::trace!(
+                    dropped = before - diagnostics.len(),
+                    "Dropped diagnostics by code rule"
+                );
+            }
+        }
+    }
+
+    /// `--dedup-empty-diagnostics`: record a publish of `count` diagnostics for
+    /// `uri` and decide whether it should reach the client. An empty publish is
+    /// suppressed only when the last one forwarded for the URI was empty too.
+    pub(crate) fn should_publish_diagnostics(&mut self, uri: &str, count: usize) -> bool {
+        if !self.state.options.dedup_empty_diagnostics {
+            return true;
+        }
+        let previous = self
+            .state
+            .published_diagnostic_counts
+            .insert(uri.to_string(), count);
+        if count == 0 && previous == Some(0) {
+            tracing::trace!(uri = uri, "Suppressing repeated empty publishDiagnostics");
+            return false;
+        }
+        true
+    }
+
+    /// Forget the last publish counts of URIs that are not open documents
+    /// (files the backend reported on without the client opening them)
+    fn prune_published_diagnostic_counts(&mut self) {
+        let open_documents = &self.state.open_documents;
+        self.state.published_diagnostic_counts.retain(|uri, _| {
+            url::Url::parse(uri).is_ok_and(|url| open_documents.contains_key(&url))
+        });
+    }
+
+    /// `--backend-respawn-preserve-diagnostics`: remember the diagnostics last
+    /// forwarded for a URI so they can be re-published when its backend is
+    /// recreated. An empty publish forgets the URI.
+    pub(crate) fn remember_diagnostics(&mut self, msg: &RpcMessage) {
+        if !self.state.options.preserve_diagnostics_on_respawn {
+            return;
+        }
+        let Some(params) = msg.params.as_ref() else {
+            return;
+        };
+        let (Some(uri), Some(diagnostics)) = (
+            params.get("uri").and_then(|u| u.as_str()),
+            params.get("diagnostics").and_then(|d| d.as_array()),
+        ) else {
+            return;
+        };
+        if diagnostics.is_empty() {
+            self.state.cached_diagnostics.remove(uri);
+        } else {
+            self.state
+                .cached_diagnostics
+                .insert(uri.to_string(), diagnostics.clone());
+        }
+    }
+
+    /// Re-publish cached diagnostics for `uris`, marked stale via their
+    /// `source`, until the recreated backend publishes fresh ones.
+    pub(crate) async fn replay_cached_diagnostics(
+        &mut self,
+        uris: &[url::Url],
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) {
+        for uri in uris {
+            let Some(cached) = self.state.cached_diagnostics.get(uri.as_str()) else {
+                continue;
+            };
+            let diagnostics = mark_stale(cached);
+            if !self.should_publish_diagnostics(uri.as_str(), diagnostics.len()) {
+                continue;
+            }
+            tracing::debug!(
+                uri = %uri,
+                count = diagnostics.len(),
+                "Re-publishing cached diagnostics for recreated backend"
+            );
+
+            let msg = RpcMessage::notification(
+                "textDocument/publishDiagnostics",
+                Some(serde_json::json!({
+                    "uri": uri.to_string(),
+                    "diagnostics": diagnostics
+                })),
             );
+            if let Err(e) = client_writer.write_message(&msg).await {
+                tracing::warn!(uri = %uri, error = ?e, "Failed to re-publish cached diagnostics");
+            }
         }
     }
 
     /// Clear diagnostics for all documents belonging to a venv
     pub(crate) async fn clear_diagnostics_for_venv(
-        &self,
+        &mut self,
         venv_path: &Path,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) {
         let uris_to_clear: Vec<url::Url> = self
             .state
@@ -48,6 +201,7 @@ impl super::LspProxy {
         let (ok, failed) = self
             .clear_diagnostics_for_uris(&uris_to_clear, client_writer)
             .await;
+        self.prune_published_diagnostic_counts();
 
         if !uris_to_clear.is_empty() {
             tracing::info!(
@@ -61,14 +215,17 @@ impl super::LspProxy {
 
     /// Clear diagnostics for specified URIs (send empty array)
     pub(crate) async fn clear_diagnostics_for_uris(
-        &self,
+        &mut self,
         uris: &[url::Url],
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> (usize, usize) {
         let mut ok = 0;
         let mut failed = 0;
 
         for uri in uris {
+            if !self.should_publish_diagnostics(uri.as_str(), 0) {
+                continue;
+            }
             tracing::trace!(uri = %uri, "Clearing diagnostics");
 
             let clear_msg = RpcMessage::notification(
@@ -91,3 +248,144 @@ impl super::LspProxy {
         (ok, failed)
     }
 }
+
+/// Drop or re-grade diagnostics whose `code` (string or number) has a rule.
+/// When several rules name the same code, the last one wins.
+fn apply_code_rules(diagnostics: &mut Vec<serde_json::Value>, rules: &[DiagnosticCodeRule]) {
+    diagnostics.retain_mut(|diagnostic| {
+        let code = match diagnostic.get("code") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Number(n)) => n.to_string(),
+            _ => return true,
+        };
+        match rules.iter().rev().find(|rule| rule.code == code) {
+            Some(DiagnosticCodeRule {
+                action: DiagnosticAction::Drop,
+                ..
+            }) => false,
+            Some(DiagnosticCodeRule {
+                action: DiagnosticAction::Severity(severity),
+                ..
+            }) => {
+                diagnostic["severity"] = (*severity).into();
+                true
+            }
+            None => true,
+        }
+    });
+}
+
+/// Prefix added to `source` of diagnostics replayed from the cache
+const STALE_SOURCE_PREFIX: &str = "(stale) ";
+
+/// Copy diagnostics with their `source` prefixed to flag them as stale
+fn mark_stale(diagnostics: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    diagnostics
+        .iter()
+        .cloned()
+        .map(|mut diagnostic| {
+            if let Some(obj) = diagnostic.as_object_mut() {
+                let source = obj.get("source").and_then(|s| s.as_str()).unwrap_or("");
+                let source = format!("{STALE_SOURCE_PREFIX}{source}");
+                obj.insert("source".to_string(), source.trim_end().into());
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_diagnostic_code_rule() {
+        let rule: DiagnosticCodeRule = "reportMissingImports:warning".parse().unwrap();
+        assert_eq!(rule.code, "reportMissingImports");
+        assert_eq!(rule.action, DiagnosticAction::Severity(2));
+        let rule: DiagnosticCodeRule = "E:501:drop".parse().unwrap();
+        assert_eq!(rule.code, "E:501");
+        assert_eq!(rule.action, DiagnosticAction::Drop);
+        assert!("reportFoo".parse::<DiagnosticCodeRule>().is_err());
+        assert!(":drop".parse::<DiagnosticCodeRule>().is_err());
+        assert!("reportFoo:loud".parse::<DiagnosticCodeRule>().is_err());
+    }
+
+    #[tokio::test]
+    async fn published_counts_are_kept_for_open_documents_only() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::{OpenDocument, ProxyOptions};
+
+        let options = ProxyOptions {
+            dedup_empty_diagnostics: true,
+            ..Default::default()
+        };
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let open = url::Url::parse("file:///a/open.py").unwrap();
+        proxy.state.open_documents.insert(
+            open.clone(),
+            OpenDocument {
+                language_id: "python".to_string(),
+                version: 1,
+                text: String::new(),
+                venv: None,
+                forwarded: None,
+                last_touched: tokio::time::Instant::now(),
+                text_dropped: false,
+            },
+        );
+        assert!(proxy.should_publish_diagnostics(open.as_str(), 0));
+        assert!(proxy.should_publish_diagnostics("file:///a/unopened.py", 0));
+
+        proxy.prune_published_diagnostic_counts();
+        let counts = &proxy.state.published_diagnostic_counts;
+        assert_eq!(counts.len(), 1);
+        assert!(counts.contains_key(open.as_str()));
+
+        let close = RpcMessage::notification(
+            "textDocument/didClose",
+            Some(serde_json::json!({ "textDocument": { "uri": open.as_str() } })),
+        );
+        proxy.handle_did_close(&close).await.unwrap();
+        assert!(proxy.state.published_diagnostic_counts.is_empty());
+    }
+
+    #[test]
+    fn code_rules_drop_and_regrade() {
+        let rules: Vec<DiagnosticCodeRule> =
+            ["reportMissingImports:hint", "2001:drop", "2001:info"]
+                .iter()
+                .map(|r| r.parse().unwrap())
+                .collect();
+        let mut diagnostics = vec![
+            serde_json::json!({ "message": "a", "code": "reportMissingImports", "severity": 1 }),
+            serde_json::json!({ "message": "b", "code": 2001, "severity": 1 }),
+            serde_json::json!({ "message": "c", "code": "other", "severity": 1 }),
+            serde_json::json!({ "message": "d" }),
+        ];
+        apply_code_rules(&mut diagnostics, &rules);
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics[0]["severity"], 4);
+        // Last rule for 2001 wins over the earlier drop
+        assert_eq!(diagnostics[1]["severity"], 3);
+        assert_eq!(diagnostics[2]["severity"], 1);
+
+        let drop: Vec<DiagnosticCodeRule> = vec!["2001:drop".parse().unwrap()];
+        apply_code_rules(&mut diagnostics, &drop);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.iter().all(|d| d["message"] != "b"));
+    }
+
+    #[test]
+    fn mark_stale_prefixes_source() {
+        let diagnostics = vec![
+            serde_json::json!({ "message": "a", "source": "Pyright" }),
+            serde_json::json!({ "message": "b" }),
+        ];
+        let stale = mark_stale(&diagnostics);
+        assert_eq!(stale[0]["source"], "(stale) Pyright");
+        assert_eq!(stale[0]["message"], "a");
+        assert_eq!(stale[1]["source"], "(stale)");
+    }
+}
@@ -1,9 +1,25 @@
+use super::transport::ClientOutput;
 use crate::error::ProxyError;
 use crate::framing::LspFrameWriter;
 use crate::message::RpcMessage;
-use crate::venv;
+use crate::state::{ForwardedVersion, OpenDocument, VersionRegressionPolicy};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// `languageId` the supported backends actually analyze
+const PYTHON_LANGUAGE_ID: &str = "python";
+
+/// What to do with a didChange once `handle_did_change` has seen it
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum DidChange {
+    /// Cached; forward it to the backend
+    Forward,
+    /// Dropped (`--did-change-version-check skip`)
+    Drop,
+    /// Dropped and reported to the client (`--did-change-version-check error`)
+    Reject(String),
+}
+
 impl super::LspProxy {
     /// Extract textDocument.uri from LSP request params
     pub(crate) fn extract_text_document_uri(msg: &RpcMessage) -> Option<url::Url> {
@@ -26,7 +42,7 @@ impl super::LspProxy {
         &mut self,
         msg: &RpcMessage,
         count: usize,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let Some(params) = &msg.params else {
             return Ok(());
@@ -50,10 +66,9 @@ impl super::LspProxy {
             return Ok(());
         };
 
-        let language_id = text_document
-            .get("languageId")
-            .and_then(|l| l.as_str())
-            .unwrap_or("unknown")
+        let client_language_id = text_document.get("languageId").and_then(|l| l.as_str());
+        let language_id = client_language_id
+            .unwrap_or(&self.state.options.default_language_id)
             .to_string();
 
         let version = text_document
@@ -68,8 +83,51 @@ impl super::LspProxy {
             "didOpen received"
         );
 
+        if language_id != PYTHON_LANGUAGE_ID {
+            if self.state.options.skip_non_python_documents {
+                tracing::info!(
+                    uri = uri_str,
+                    language_id = %language_id,
+                    "Skipping non-Python document"
+                );
+                return Ok(());
+            }
+            tracing::warn!(
+                uri = uri_str,
+                language_id = %language_id,
+                backend = self.state.backend_kind.display_name(),
+                "Opening non-Python document with a Python backend"
+            );
+        }
+
+        // Forward with the effective languageId so the backend does not ignore the file
+        let patched;
+        let msg = if client_language_id.is_none() {
+            let mut m = msg.clone();
+            if let Some(td) = m
+                .params
+                .as_mut()
+                .and_then(|p| p.get_mut("textDocument"))
+                .and_then(|td| td.as_object_mut())
+            {
+                td.insert(
+                    "languageId".to_string(),
+                    serde_json::Value::String(language_id.clone()),
+                );
+            }
+            patched = m;
+            &patched
+        } else {
+            msg
+        };
+
         // Search for .venv
-        let found_venv = venv::find_venv(&file_path, self.state.git_toplevel.as_deref()).await?;
+        let found_venv = self.find_venv(&file_path).await?;
+
+        // Re-open under a new languageId: the backend still has the old one
+        if self.state.options.reopen_on_language_id_change {
+            self.close_on_language_id_change(&url, &language_id).await?;
+        }
 
         // Cache document
         if let Some(text_content) = &text {
@@ -78,8 +136,13 @@ impl super::LspProxy {
                 version,
                 text: text_content.clone(),
                 venv: found_venv.clone(),
+                forwarded: None,
+                last_touched: tokio::time::Instant::now(),
+                text_dropped: false,
             };
             self.state.open_documents.insert(url.clone(), doc);
+            self.state.documents_empty_since = None;
+            self.enforce_document_cache_limit();
         }
 
         // Ensure backend in pool and forward didOpen
@@ -107,24 +170,97 @@ impl super::LspProxy {
         }
 
         // Backend exists in pool — forward didOpen
+        self.release_preindexed(&url, venv_path).await?;
         self.forward_to_backend(venv_path, msg).await?;
+        self.mark_document_forwarded(&url, venv_path);
 
         Ok(())
     }
 
-    /// Handle didChange
-    pub(crate) async fn handle_did_change(&mut self, msg: &RpcMessage) -> Result<(), ProxyError> {
-        let Some(params) = &msg.params else {
+    /// `--reopen-on-language-id-change`: when `url` is already open with a
+    /// different languageId, send its backend a didClose so the didOpen that
+    /// follows registers the document afresh under the new id.
+    async fn close_on_language_id_change(
+        &mut self,
+        url: &url::Url,
+        language_id: &str,
+    ) -> Result<(), ProxyError> {
+        let Some(doc) = self.state.open_documents.get(url) else {
             return Ok(());
         };
-        let Some(text_document) = params.get("textDocument") else {
+        if doc.language_id == language_id {
+            return Ok(());
+        }
+        let Some(venv_path) = doc.venv.clone() else {
             return Ok(());
         };
+        tracing::info!(
+            uri = %url,
+            from = %doc.language_id,
+            to = language_id,
+            "languageId changed, reopening document on backend"
+        );
+        if self.state.pool.contains(&venv_path) {
+            let close = RpcMessage::notification(
+                "textDocument/didClose",
+                Some(serde_json::json!({ "textDocument": { "uri": url.to_string() } })),
+            );
+            self.forward_to_backend(&venv_path, &close).await?;
+        }
+        Ok(())
+    }
+
+    /// Record that the cached version of `url` was just forwarded to `venv_path`.
+    /// No-op when that backend isn't running (the edit is replayed on restore).
+    pub(crate) fn mark_document_forwarded(&mut self, url: &url::Url, venv_path: &PathBuf) {
+        let Some(session) = self.state.pool.get(venv_path).map(|inst| inst.session) else {
+            return;
+        };
+        if let Some(doc) = self.state.open_documents.get_mut(url) {
+            doc.forwarded = Some(ForwardedVersion {
+                venv: venv_path.clone(),
+                session,
+                version: doc.version,
+            });
+        }
+    }
+
+    /// `--document-sync-verify`: warn about open documents whose running
+    /// backend has not received the latest cached version.
+    pub(crate) fn verify_document_sync(&self) {
+        if !self.state.options.document_sync_verify {
+            return;
+        }
+        for (url, cached, forwarded, venv) in version_skew(&self.state.open_documents, |v| {
+            self.state.pool.get(v).map(|inst| inst.session)
+        }) {
+            tracing::warn!(
+                uri = %url,
+                cached_version = cached,
+                forwarded_version = ?forwarded,
+                venv = %venv.display(),
+                "Document version skew: backend has stale content"
+            );
+        }
+    }
+
+    /// Handle didChange: update the cached text, or decide (per
+    /// `--did-change-version-check`) that the change must not be forwarded.
+    pub(crate) async fn handle_did_change(
+        &mut self,
+        msg: &RpcMessage,
+    ) -> Result<DidChange, ProxyError> {
+        let Some(params) = &msg.params else {
+            return Ok(DidChange::Forward);
+        };
+        let Some(text_document) = params.get("textDocument") else {
+            return Ok(DidChange::Forward);
+        };
         let Some(uri_str) = text_document.get("uri").and_then(|u| u.as_str()) else {
-            return Ok(());
+            return Ok(DidChange::Forward);
         };
         let Ok(url) = url::Url::parse(uri_str) else {
-            return Ok(());
+            return Ok(DidChange::Forward);
         };
 
         let version = text_document
@@ -133,10 +269,10 @@ impl super::LspProxy {
             .map(|v| v as i32);
 
         let Some(content_changes) = params.get("contentChanges") else {
-            return Ok(());
+            return Ok(DidChange::Forward);
         };
         let Some(changes_array) = content_changes.as_array() else {
-            return Ok(());
+            return Ok(DidChange::Forward);
         };
 
         if changes_array.is_empty() {
@@ -144,7 +280,7 @@ impl super::LspProxy {
                 uri = %url,
                 "didChange received with empty contentChanges, ignoring"
             );
-            return Ok(());
+            return Ok(DidChange::Forward);
         }
 
         let Some(doc) = self.state.open_documents.get_mut(&url) else {
@@ -152,21 +288,57 @@ impl super::LspProxy {
                 uri = %url,
                 "didChange for unopened document, ignoring"
             );
-            return Ok(());
+            return Ok(DidChange::Forward);
         };
 
+        if is_version_regression(doc.version, version) {
+            let policy = self.state.options.did_change_version_check;
+            if policy == VersionRegressionPolicy::Error {
+                tracing::error!(
+                    uri = %url,
+                    cached_version = doc.version,
+                    version = ?version,
+                    "didChange does not advance the document version, dropping it"
+                );
+                return Ok(DidChange::Reject(format!(
+                    "didChange for {} has version {:?}, not above cached version {}; change dropped",
+                    url, version, doc.version
+                )));
+            }
+            tracing::warn!(
+                uri = %url,
+                cached_version = doc.version,
+                version = ?version,
+                skip = policy == VersionRegressionPolicy::Skip,
+                "didChange does not advance the document version (duplicate or out of order)"
+            );
+            if policy == VersionRegressionPolicy::Skip {
+                return Ok(DidChange::Drop);
+            }
+        }
+
+        doc.last_touched = tokio::time::Instant::now();
+        // Changes are sequential, not simultaneous: each range refers to the text
+        // after the previous change (LSP TextDocumentContentChangeEvent). Backends
+        // apply them the same way, so they must not be sorted or reordered here.
         for change in changes_array {
             if let Some(range) = change.get("range") {
+                // Without the base text an incremental edit can't be applied
+                if doc.text_dropped {
+                    continue;
+                }
                 if let Some(new_text) = change.get("text").and_then(|t| t.as_str()) {
                     crate::text_edit::apply_incremental_change(&mut doc.text, range, new_text)?;
                 }
             } else if let Some(new_text) = change.get("text").and_then(|t| t.as_str()) {
                 doc.text = new_text.to_string();
+                doc.text_dropped = false;
             }
         }
 
+        // An out-of-order change applied under `warn` must not roll the version back
         if let Some(v) = version {
-            doc.version = v;
+            doc.version = doc.version.max(v);
         }
 
         tracing::debug!(
@@ -176,21 +348,207 @@ impl super::LspProxy {
             "Document text updated"
         );
 
+        Ok(DidChange::Forward)
+    }
+
+    /// `--allow-did-change-before-did-open`: for a didChange on a document the
+    /// proxy has no record of, build the didOpen it implies, so the change is
+    /// handled as an open instead of being dropped. Only possible when the
+    /// change carries the full text; incremental edits after it are applied.
+    pub(crate) fn did_change_as_open(&self, msg: &RpcMessage) -> Option<RpcMessage> {
+        if !self.state.options.allow_did_change_before_did_open {
+            return None;
+        }
+        let url = Self::extract_text_document_uri(msg)?;
+        if self.state.open_documents.contains_key(&url) {
+            return None;
+        }
+        let params = msg.params.as_ref()?;
+        let changes = params.get("contentChanges")?.as_array()?;
+        let Some(text) = text_from_changes(changes) else {
+            tracing::warn!(
+                uri = %url,
+                "didChange for unopened document has no full text, cannot recover"
+            );
+            return None;
+        };
+        let version = params
+            .get("textDocument")
+            .and_then(|td| td.get("version"))
+            .cloned()
+            .unwrap_or(serde_json::json!(0));
+        tracing::info!(
+            uri = %url,
+            version = %version,
+            "didChange before didOpen, treating as didOpen"
+        );
+        Some(RpcMessage::notification(
+            "textDocument/didOpen",
+            Some(serde_json::json!({
+                "textDocument": {
+                    "uri": url.to_string(),
+                    "languageId": self.state.options.default_language_id,
+                    "version": version,
+                    "text": text,
+                }
+            })),
+        ))
+    }
+
+    /// `--force-full-sync`: the didChange to forward instead of `msg`, with its
+    /// contentChanges replaced by the whole cached text. Call after
+    /// `handle_did_change` has applied the edit.
+    pub(crate) fn full_sync_did_change(&self, msg: &RpcMessage) -> Option<RpcMessage> {
+        if !self.state.options.force_full_sync {
+            return None;
+        }
+        let url = Self::extract_text_document_uri(msg)?;
+        let doc = self
+            .state
+            .open_documents
+            .get(&url)
+            .filter(|doc| !doc.text_dropped)?;
+        let mut full = msg.clone();
+        full.params.as_mut()?.as_object_mut()?.insert(
+            "contentChanges".to_string(),
+            serde_json::json!([{ "text": doc.text }]),
+        );
+        Some(full)
+    }
+
+    /// `--didchange-debounce`: hold back the forward of a (cached) didChange
+    /// until the document has been quiet for the debounce period.
+    /// Returns `false` when the change must be forwarded as is (no cached text
+    /// to rebuild it from).
+    pub(crate) fn debounce_did_change(&mut self, msg: &RpcMessage) -> bool {
+        let (Some(debounce), Some(url)) = (
+            self.state.options.didchange_debounce,
+            Self::extract_text_document_uri(msg),
+        ) else {
+            return false;
+        };
+        if self
+            .state
+            .open_documents
+            .get(&url)
+            .map_or(true, |doc| doc.text_dropped)
+        {
+            return false;
+        }
+        tracing::trace!(uri = %url, "Debouncing didChange");
+        self.state
+            .debounced_did_changes
+            .insert(url, tokio::time::Instant::now() + debounce);
+        true
+    }
+
+    /// Forward the coalesced changes of `url`, if any, as one full-document didChange.
+    pub(crate) async fn flush_did_change(&mut self, url: &url::Url) -> Result<(), ProxyError> {
+        if self.state.debounced_did_changes.remove(url).is_none() {
+            return Ok(());
+        }
+        let Some(doc) = self.state.open_documents.get(url) else {
+            return Ok(());
+        };
+        let (Some(venv_path), Some(forwarded)) =
+            (doc.venv.clone(), doc.forwarded.as_ref().map(|f| f.version))
+        else {
+            return Ok(());
+        };
+        if forwarded == doc.version {
+            return Ok(());
+        }
+        let msg = RpcMessage::notification(
+            "textDocument/didChange",
+            Some(serde_json::json!({
+                "textDocument": { "uri": url.as_str(), "version": doc.version },
+                "contentChanges": [{ "text": doc.text }]
+            })),
+        );
+        tracing::debug!(uri = %url, version = doc.version, "Flushing debounced didChange");
+        self.forward_to_backend(&venv_path, &msg).await?;
+        self.mark_document_forwarded(url, &venv_path);
+        Ok(())
+    }
+
+    /// Flush every document with debounced changes, for requests that may
+    /// read any of them (`workspace/symbol`, `workspace/executeCommand`, ...).
+    pub(crate) async fn flush_all_did_changes(&mut self) -> Result<(), ProxyError> {
+        let pending: Vec<url::Url> = self.state.debounced_did_changes.keys().cloned().collect();
+        for url in pending {
+            self.flush_did_change(&url).await?;
+        }
         Ok(())
     }
 
+    /// Flush every document whose debounce period has elapsed.
+    pub(crate) async fn flush_expired_did_changes(&mut self) -> Result<(), ProxyError> {
+        let now = tokio::time::Instant::now();
+        let due: Vec<url::Url> = self
+            .state
+            .debounced_did_changes
+            .iter()
+            .filter(|(_, deadline)| now >= **deadline)
+            .map(|(url, _)| url.clone())
+            .collect();
+        for url in due {
+            self.flush_did_change(&url).await?;
+        }
+        Ok(())
+    }
+
+    /// `--max-cached-documents`: drop the text of the least recently touched
+    /// documents until at most the limit still hold text.
+    fn enforce_document_cache_limit(&mut self) {
+        let Some(limit) = self.state.options.max_cached_documents else {
+            return;
+        };
+        let mut cached: Vec<(tokio::time::Instant, url::Url)> = self
+            .state
+            .open_documents
+            .iter()
+            // Changes still waiting to be forwarded are rebuilt from the text
+            .filter(|(url, doc)| {
+                !doc.text_dropped && !self.state.debounced_did_changes.contains_key(*url)
+            })
+            .map(|(url, doc)| (doc.last_touched, url.clone()))
+            .collect();
+        if cached.len() <= limit {
+            return;
+        }
+        cached.sort();
+        let excess = cached.len() - limit;
+        for (_, url) in cached.into_iter().take(excess) {
+            if let Some(doc) = self.state.open_documents.get_mut(&url) {
+                tracing::warn!(
+                    uri = %url,
+                    text_len = doc.text.len(),
+                    limit = limit,
+                    "Document cache limit reached, dropping cached text"
+                );
+                doc.text = String::new();
+                doc.text_dropped = true;
+            }
+        }
+    }
+
     /// Handle didClose: remove document from cache
     pub(crate) async fn handle_did_close(&mut self, msg: &RpcMessage) -> Result<(), ProxyError> {
         let Some(url) = Self::extract_text_document_uri(msg) else {
             return Ok(());
         };
 
+        self.state.cached_diagnostics.remove(url.as_str());
+        self.state.published_diagnostic_counts.remove(url.as_str());
         if self.state.open_documents.remove(&url).is_some() {
             tracing::debug!(
                 uri = %url,
                 remaining_docs = self.state.open_documents.len(),
                 "Document removed from cache"
             );
+            if self.state.open_documents.is_empty() && self.state.options.evict_on_empty.is_some() {
+                self.state.documents_empty_since = Some(tokio::time::Instant::now());
+            }
         } else {
             tracing::warn!(
                 uri = %url,
@@ -200,4 +558,426 @@ impl super::LspProxy {
 
         Ok(())
     }
+
+    /// Handle didSave: forward it to the backend owning the document only.
+    /// When the client sends the saved `text` (`includeText`), it replaces the
+    /// cached text so a restarted backend is restored with the saved content.
+    pub(crate) async fn handle_did_save(&mut self, msg: &RpcMessage) -> Result<(), ProxyError> {
+        let Some(url) = Self::extract_text_document_uri(msg) else {
+            return self.dispatch_client_notification(msg).await;
+        };
+        let Some(doc) = self.state.open_documents.get_mut(&url) else {
+            tracing::debug!(uri = %url, "didSave for unknown document, forwarding to all backends");
+            return self.dispatch_client_notification(msg).await;
+        };
+
+        let saved_text = msg
+            .params
+            .as_ref()
+            .and_then(|p| p.get("text"))
+            .and_then(|t| t.as_str());
+        if let Some(text) = saved_text {
+            if doc.text != text {
+                tracing::debug!(
+                    uri = %url,
+                    cached_len = doc.text.len(),
+                    saved_len = text.len(),
+                    "Saved text differs from cached text, updating cache"
+                );
+                doc.text = text.to_string();
+            }
+            doc.text_dropped = false;
+        }
+        doc.last_touched = tokio::time::Instant::now();
+
+        match doc.venv.clone() {
+            Some(venv_path) => self.forward_to_backend(&venv_path, msg).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Document text after `changes`, starting from the last full-text change.
+/// `None` when there is no full-text change or an edit cannot be applied.
+fn text_from_changes(changes: &[serde_json::Value]) -> Option<String> {
+    let start = changes.iter().rposition(|c| c.get("range").is_none())?;
+    let mut text = changes[start].get("text")?.as_str()?.to_string();
+    for change in &changes[start + 1..] {
+        let range = change.get("range")?;
+        let new_text = change.get("text")?.as_str()?;
+        crate::text_edit::apply_incremental_change(&mut text, range, new_text).ok()?;
+    }
+    Some(text)
+}
+
+/// Whether a didChange `version` fails to advance past the cached one.
+/// A missing version can't be compared and is never a regression.
+fn is_version_regression(cached: i32, incoming: Option<i32>) -> bool {
+    incoming.is_some_and(|v| v <= cached)
+}
+
+/// Documents whose running backend is behind the proxy's cached version, as
+/// (uri, cached version, version that backend has, venv). `running_session`
+/// gives the session of a venv's running backend.
+///
+/// Documents of backends that aren't running are skipped: they are re-sent
+/// in full when the backend is recreated.
+fn version_skew(
+    docs: &HashMap<url::Url, OpenDocument>,
+    running_session: impl Fn(&PathBuf) -> Option<u64>,
+) -> Vec<(&url::Url, i32, Option<i32>, &PathBuf)> {
+    let mut skewed: Vec<_> = docs
+        .iter()
+        .filter_map(|(url, doc)| {
+            let venv = doc.venv.as_ref()?;
+            let session = running_session(venv)?;
+            // What an earlier backend process received doesn't count
+            let forwarded = doc
+                .forwarded
+                .as_ref()
+                .filter(|f| &f.venv == venv && f.session == session)
+                .map(|f| f.version);
+            let behind = forwarded.map_or(true, |v| v < doc.version);
+            behind.then_some((url, doc.version, forwarded, venv))
+        })
+        .collect();
+    skewed.sort_by(|a, b| a.0.cmp(b.0));
+    skewed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A document whose `forwarded_version` went to session 1 of `venv`
+    fn doc(version: i32, forwarded_version: Option<i32>, venv: &str) -> OpenDocument {
+        OpenDocument {
+            language_id: PYTHON_LANGUAGE_ID.to_string(),
+            version,
+            text: String::new(),
+            venv: Some(PathBuf::from(venv)),
+            forwarded: forwarded_version.map(|version| ForwardedVersion {
+                venv: PathBuf::from(venv),
+                session: 1,
+                version,
+            }),
+            last_touched: tokio::time::Instant::now(),
+            text_dropped: false,
+        }
+    }
+
+    #[test]
+    fn version_skew_after_edit_during_eviction() {
+        let edited = url::Url::parse("file:///a/main.py").unwrap();
+        let synced = url::Url::parse("file:///a/util.py").unwrap();
+        let mut docs = HashMap::new();
+        // Edited to v3 while /a's backend was evicted; it last saw v2
+        docs.insert(edited.clone(), doc(3, Some(2), "/a/.venv"));
+        docs.insert(synced, doc(5, Some(5), "/a/.venv"));
+
+        let a = PathBuf::from("/a/.venv");
+        // Backend still gone: the edit will be replayed on restore, no skew
+        assert!(version_skew(&docs, |_| None).is_empty());
+
+        // Backend back without receiving the edit: the document is stale
+        let skewed = version_skew(&docs, |v| (v == &a).then_some(1));
+        assert_eq!(skewed, vec![(&edited, 3, Some(2), &a)]);
+
+        // A respawned backend has seen neither
+        let skewed = version_skew(&docs, |v| (v == &a).then_some(2));
+        assert_eq!(skewed.len(), 2);
+        assert!(skewed
+            .iter()
+            .all(|(_, _, forwarded, _)| forwarded.is_none()));
+    }
+
+    #[test]
+    fn version_regression_detection() {
+        assert!(!is_version_regression(3, Some(4)));
+        assert!(is_version_regression(3, Some(3)));
+        assert!(is_version_regression(3, Some(2)));
+        assert!(!is_version_regression(3, None));
+    }
+
+    #[tokio::test]
+    async fn did_change_applies_edits_in_sequence() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        let url = url::Url::parse("file:///a/main.py").unwrap();
+        let mut d = doc(1, Some(1), "/a/.venv");
+        d.text = "abcdef\n".to_string();
+        proxy.state.open_documents.insert(url.clone(), d);
+
+        let range = |start: u32, end: u32| {
+            serde_json::json!({
+                "start": { "line": 0, "character": start },
+                "end": { "line": 0, "character": end }
+            })
+        };
+        // The second range is relative to "aXYZbcdef", not the original line
+        let msg = RpcMessage::notification(
+            "textDocument/didChange",
+            Some(serde_json::json!({
+                "textDocument": { "uri": url.as_str(), "version": 2 },
+                "contentChanges": [
+                    { "range": range(1, 1), "text": "XYZ" },
+                    { "range": range(4, 5), "text": "" }
+                ]
+            })),
+        );
+        assert_eq!(
+            proxy.handle_did_change(&msg).await.unwrap(),
+            DidChange::Forward
+        );
+        assert_eq!(proxy.state.open_documents[&url].text, "aXYZcdef\n");
+    }
+
+    #[tokio::test]
+    async fn out_of_order_did_change_per_policy() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+
+        let url = url::Url::parse("file:///a/main.py").unwrap();
+        let change = |version: i32, text: &str| {
+            RpcMessage::notification(
+                "textDocument/didChange",
+                Some(serde_json::json!({
+                    "textDocument": { "uri": url.as_str(), "version": version },
+                    "contentChanges": [{ "text": text }]
+                })),
+            )
+        };
+
+        for policy in [
+            VersionRegressionPolicy::Warn,
+            VersionRegressionPolicy::Skip,
+            VersionRegressionPolicy::Error,
+        ] {
+            let options = ProxyOptions {
+                did_change_version_check: policy,
+                ..Default::default()
+            };
+            let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+            let mut d = doc(3, Some(3), "/a/.venv");
+            d.text = "x = 3\n".to_string();
+            proxy.state.open_documents.insert(url.clone(), d);
+
+            let result = proxy.handle_did_change(&change(2, "x = 2\n")).await;
+            let cached = &proxy.state.open_documents[&url];
+            assert_eq!(cached.version, 3, "{policy:?}");
+            match policy {
+                VersionRegressionPolicy::Warn => {
+                    assert_eq!(result.unwrap(), DidChange::Forward);
+                    assert_eq!(cached.text, "x = 2\n");
+                }
+                VersionRegressionPolicy::Skip => {
+                    assert_eq!(result.unwrap(), DidChange::Drop);
+                    assert_eq!(cached.text, "x = 3\n");
+                }
+                VersionRegressionPolicy::Error => {
+                    // Reported to the client; the session goes on
+                    assert!(matches!(result.unwrap(), DidChange::Reject(_)));
+                    assert_eq!(cached.text, "x = 3\n");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_from_changes_needs_a_full_text_change() {
+        let full = |text: &str| serde_json::json!({ "text": text });
+        let edit = |character: u32, text: &str| {
+            serde_json::json!({
+                "range": {
+                    "start": { "line": 0, "character": character },
+                    "end": { "line": 0, "character": character + 1 }
+                },
+                "text": text
+            })
+        };
+
+        assert_eq!(text_from_changes(&[edit(0, "x")]), None);
+        assert_eq!(
+            text_from_changes(&[full("a = 1\n")]),
+            Some("a = 1\n".to_string())
+        );
+        assert_eq!(
+            text_from_changes(&[edit(0, "x"), full("a = 1\n"), edit(4, "2")]),
+            Some("a = 2\n".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_limit_drops_least_recently_touched_text() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+
+        let options = ProxyOptions {
+            max_cached_documents: Some(2),
+            ..Default::default()
+        };
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let start = tokio::time::Instant::now();
+        let urls: Vec<url::Url> = ["old", "mid", "new"]
+            .iter()
+            .map(|name| url::Url::parse(&format!("file:///a/{name}.py")).unwrap())
+            .collect();
+        for (i, url) in urls.iter().enumerate() {
+            let mut d = doc(1, Some(1), "/a/.venv");
+            d.text = "x = 1\n".to_string();
+            d.last_touched = start + std::time::Duration::from_secs(i as u64);
+            proxy.state.open_documents.insert(url.clone(), d);
+        }
+
+        proxy.enforce_document_cache_limit();
+        let docs = &proxy.state.open_documents;
+        assert!(docs[&urls[0]].text_dropped);
+        assert!(docs[&urls[0]].text.is_empty());
+        assert!(!docs[&urls[1]].text_dropped);
+        assert!(!docs[&urls[2]].text_dropped);
+
+        // An incremental edit can't rebuild the text; a full-text change does
+        let change = |changes: serde_json::Value| {
+            RpcMessage::notification(
+                "textDocument/didChange",
+                Some(serde_json::json!({
+                    "textDocument": { "uri": urls[0].as_str(), "version": 2 },
+                    "contentChanges": changes
+                })),
+            )
+        };
+        proxy
+            .handle_did_change(&change(serde_json::json!([{
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                "text": "y"
+            }])))
+            .await
+            .unwrap();
+        assert!(proxy.state.open_documents[&urls[0]].text_dropped);
+        proxy
+            .handle_did_change(&change(serde_json::json!([{ "text": "z = 2\n" }])))
+            .await
+            .unwrap();
+        let restored = &proxy.state.open_documents[&urls[0]];
+        assert!(!restored.text_dropped);
+        assert_eq!(restored.text, "z = 2\n");
+    }
+
+    #[tokio::test]
+    async fn did_save_with_text_replaces_cached_text() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        let url = url::Url::parse("file:///a/main.py").unwrap();
+        let mut d = doc(3, Some(3), "/a/.venv");
+        d.text = "x = 1\n".to_string();
+        proxy.state.open_documents.insert(url.clone(), d);
+
+        let save = |params: serde_json::Value| {
+            RpcMessage::notification("textDocument/didSave", Some(params))
+        };
+        proxy
+            .handle_did_save(&save(
+                serde_json::json!({ "textDocument": { "uri": url.as_str() } }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(proxy.state.open_documents[&url].text, "x = 1\n");
+
+        proxy
+            .handle_did_save(&save(serde_json::json!({
+                "textDocument": { "uri": url.as_str() },
+                "text": "x = 2\n"
+            })))
+            .await
+            .unwrap();
+        let saved = &proxy.state.open_documents[&url];
+        assert_eq!(saved.text, "x = 2\n");
+        assert_eq!(saved.version, 3);
+    }
+
+    #[tokio::test]
+    async fn respawned_backend_without_document_is_reported() {
+        use crate::backend::{BackendKind, LspBackend};
+        use crate::backend_pool::BackendInstance;
+        use crate::framing::LspFrameWriter;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+        use std::time::Duration;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let venv = temp.path().join(".venv");
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "").unwrap();
+        let url = url::Url::from_file_path(temp.path().join("main.py")).unwrap();
+
+        let options = ProxyOptions {
+            max_cached_documents: Some(0),
+            ..Default::default()
+        };
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let backend = || {
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.arg("-c").arg("exec cat >/dev/null");
+            LspBackend::from_command(cmd, 8192).unwrap()
+        };
+        let instance = BackendInstance::from_parts(
+            backend().into_split(),
+            venv.clone(),
+            1,
+            proxy.state.pool.msg_sender(),
+            Duration::ZERO,
+        );
+        proxy.state.pool.insert(venv.clone(), instance);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut client_writer = LspFrameWriter::new(ClientOutput::Tcp(server.into_split().1));
+
+        let open = RpcMessage::notification(
+            "textDocument/didOpen",
+            Some(serde_json::json!({ "textDocument": {
+                "uri": url.as_str(), "languageId": "python", "version": 1, "text": "x = 1\n"
+            } })),
+        );
+        proxy
+            .handle_did_open(&open, 1, &mut client_writer)
+            .await
+            .unwrap();
+        let is_running = |v: &PathBuf| proxy.state.pool.get(v).map(|inst| inst.session);
+        assert!(version_skew(&proxy.state.open_documents, is_running).is_empty());
+
+        // The backend is respawned; the document's text was dropped
+        // (--max-cached-documents), so it can't be restored
+        proxy.state.pool.remove(&venv);
+        let mut respawned = backend();
+        proxy
+            .restore_documents_to_backend(&mut respawned, &venv, 2, &mut client_writer)
+            .await
+            .unwrap();
+        let instance = BackendInstance::from_parts(
+            respawned.into_split(),
+            venv.clone(),
+            2,
+            proxy.state.pool.msg_sender(),
+            Duration::ZERO,
+        );
+        proxy.state.pool.insert(venv.clone(), instance);
+
+        let is_running = |v: &PathBuf| proxy.state.pool.get(v).map(|inst| inst.session);
+        assert_eq!(
+            version_skew(&proxy.state.open_documents, is_running),
+            vec![(&url, 1, None, &venv)]
+        );
+    }
 }
@@ -1,7 +1,8 @@
+use super::transport::ClientOutput;
 use crate::backend_pool::fanout_timeout;
 use crate::error::ProxyError;
 use crate::framing::LspFrameWriter;
-use crate::message::{RpcId, RpcMessage};
+use crate::message::{error_codes, RpcId, RpcMessage};
 use crate::state::PendingFanout;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
@@ -13,18 +14,38 @@ impl super::LspProxy {
     pub(crate) async fn dispatch_fanout_request(
         &mut self,
         msg: &RpcMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let targets = self
+            .state
+            .pool
+            .backends_keys()
+            .into_iter()
+            .map(|venv_path| (venv_path, msg.clone()))
+            .collect();
+        self.dispatch_fanout_targets(msg, targets, client_writer)
+            .await
+    }
+
+    /// Dispatch a fan-out request with a per-backend sub-request.
+    /// Used when each backend should only see part of the params (e.g. file renames).
+    pub(crate) async fn dispatch_fanout_targets(
+        &mut self,
+        msg: &RpcMessage,
+        targets: Vec<(PathBuf, RpcMessage)>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let client_id = match &msg.id {
             Some(id) => id.clone(),
             None => return Ok(()), // notifications don't fan out
         };
 
-        let backend_keys = self.state.pool.backends_keys();
-        if backend_keys.is_empty() {
-            let error_response = RpcMessage::error_response(
+        if targets.is_empty() {
+            let error_response = RpcMessage::proxy_error_response(
                 msg,
-                "lsp-proxy: no backends available for fan-out request",
+                error_codes::BACKEND_UNAVAILABLE,
+                &self.client_message("no backends available for fan-out request"),
+                serde_json::json!({ "failedVenvs": [] }),
             );
             client_writer.write_message(&error_response).await?;
             return Ok(());
@@ -50,11 +71,11 @@ impl super::LspProxy {
 
         let mut total_dispatched = 0usize;
 
-        for venv_path in &backend_keys {
+        for (venv_path, mut sub_msg) in targets {
+            let venv_path = &venv_path;
             let proxy_id = self.state.alloc_proxy_request_id();
 
-            // Clone the request with the proxy-assigned ID
-            let mut sub_msg = msg.clone();
+            // Rewrite the sub-request with the proxy-assigned ID
             sub_msg.id = Some(proxy_id.clone());
 
             let session = match self.state.pool.get(venv_path) {
@@ -80,6 +101,10 @@ impl super::LspProxy {
                     crate::state::PendingRequest {
                         backend_session: session,
                         venv_path: venv_path.clone(),
+                        method: msg.method_name().unwrap_or_default().to_string(),
+                        request: None,
+                        retried: false,
+                        created_at: Instant::now(),
                     },
                 );
                 total_dispatched += 1;
@@ -94,9 +119,11 @@ impl super::LspProxy {
 
         if total_dispatched == 0 {
             // All backends failed to accept the write
-            let error_response = RpcMessage::error_response(
+            let error_response = RpcMessage::proxy_error_response(
                 msg,
-                "lsp-proxy: all backends failed to accept fan-out request",
+                error_codes::BACKEND_UNAVAILABLE,
+                &self.client_message("all backends failed to accept fan-out request"),
+                serde_json::json!({ "failedVenvs": venv_list(&fanout.failed_backends) }),
             );
             client_writer.write_message(&error_response).await?;
             return Ok(());
@@ -121,7 +148,7 @@ impl super::LspProxy {
         &mut self,
         response_id: &RpcId,
         msg: &RpcMessage,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<bool, ProxyError> {
         // Find which fanout owns this response_id
         let client_id = self
@@ -136,6 +163,12 @@ impl super::LspProxy {
             None => return Ok(false), // not a fan-out response
         };
 
+        // Extension results have no known shape: keep each whole
+        let custom = self.state.pending_fanouts[&client_id]
+            .client_request
+            .method_name()
+            .is_some_and(|m| self.is_forwarded_custom_method(m));
+
         // Remove the sub-request entry
         let fanout = self.state.pending_fanouts.get_mut(&client_id).unwrap();
         let (_venv_path, _session) = fanout.sub_requests.remove(response_id).unwrap();
@@ -147,11 +180,19 @@ impl super::LspProxy {
         if msg.error.is_some() {
             fanout.failed_backends.push(_venv_path);
         } else if let Some(result) = &msg.result {
+            if custom {
+                if !result.is_null() {
+                    fanout.results.push(result.clone());
+                }
+            }
             // workspace/symbol returns an array of SymbolInformation
-            if let Some(arr) = result.as_array() {
+            else if let Some(arr) = result.as_array() {
                 fanout.results.extend(arr.iter().cloned());
+            } else if result.is_object() {
+                // workspace/willRenameFiles returns a single WorkspaceEdit
+                fanout.results.push(result.clone());
             }
-            // null result = no symbols found, that's fine
+            // null result = no symbols/edits found, that's fine
         }
 
         fanout.expected_count = fanout.expected_count.saturating_sub(1);
@@ -166,35 +207,45 @@ impl super::LspProxy {
 
     /// Complete a fan-out: deduplicate and send merged results to the client.
     pub(crate) async fn complete_fanout(
-        &self,
+        &mut self,
         fanout: PendingFanout,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
+        self.release_partial_result_token(&fanout.client_request_id);
         if fanout.results.is_empty() && !fanout.failed_backends.is_empty() {
             // All backends failed, no results at all
-            let error_response = RpcMessage::error_response(
+            let failed = venv_list(&fanout.failed_backends);
+            let error_response = RpcMessage::proxy_error_response(
                 &fanout.client_request,
-                &format!(
-                    "lsp-proxy: all backends failed for fan-out request ({})",
-                    fanout
-                        .failed_backends
-                        .iter()
-                        .map(|p| p.display().to_string())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ),
+                error_codes::BACKEND_UNAVAILABLE,
+                &self.client_message(format!(
+                    "all backends failed for fan-out request ({})",
+                    failed.join(", ")
+                )),
+                serde_json::json!({ "failedVenvs": failed }),
             );
             client_writer.write_message(&error_response).await?;
         } else {
-            let deduped = dedupe_symbol_results(fanout.results);
+            let result = match fanout.client_request.method_name() {
+                Some("workspace/willRenameFiles") => merge_workspace_edits(fanout.results),
+                // Extension results have no known shape to deduplicate by
+                Some(m) if self.is_forwarded_custom_method(m) => {
+                    merge_custom_results(fanout.results)
+                }
+                _ if self.state.options.no_dedup => serde_json::Value::Array(fanout.results),
+                _ => serde_json::Value::Array(dedupe_symbol_results(fanout.results)),
+            };
             let response = RpcMessage {
                 jsonrpc: "2.0".to_string(),
                 id: Some(fanout.client_request_id),
                 method: None,
                 params: None,
-                result: Some(serde_json::Value::Array(deduped)),
+                result: Some(result),
                 error: None,
             };
+            let response = self
+                .enforce_response_size_limit(&response, fanout.client_request.method_name())
+                .unwrap_or(response);
             client_writer.write_message(&response).await?;
         }
         Ok(())
@@ -204,7 +255,7 @@ impl super::LspProxy {
     /// Sends partial results and a warning notification.
     pub(crate) async fn expire_fanout_requests(
         &mut self,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let now = Instant::now();
         let expired_ids: Vec<RpcId> = self
@@ -249,10 +300,10 @@ impl super::LspProxy {
                     "window/showMessage",
                     Some(serde_json::json!({
                         "type": 2, // Warning
-                        "message": format!(
-                            "typemux-cc: fan-out timeout, partial results returned. Timed out backends: {}",
+                        "message": self.client_message(format!(
+                            "fan-out timeout, partial results returned. Timed out backends: {}",
                             timed_out_venvs.join(", ")
-                        )
+                        ))
                     })),
                 );
                 let _ = client_writer.write_message(&warn_msg).await;
@@ -276,7 +327,7 @@ impl super::LspProxy {
     pub(crate) async fn cancel_fanout_request(
         &mut self,
         client_id: &RpcId,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         if let Some(fanout) = self.state.pending_fanouts.remove(client_id) {
             // Send $/cancelRequest to all remaining backends
@@ -342,8 +393,8 @@ impl super::LspProxy {
     }
 }
 
-/// Deduplicate workspace/symbol results.
-/// Key: (uri, range.start.line, range.start.character, name, kind)
+/// Deduplicate workspace/symbol results, keeping the first occurrence.
+/// Key: (name, kind, uri, range)
 /// Items with missing fields are kept (defensive).
 pub fn dedupe_symbol_results(results: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
     let mut seen = HashSet::new();
@@ -367,18 +418,116 @@ pub fn dedupe_symbol_results(results: Vec<serde_json::Value>) -> Vec<serde_json:
     deduped
 }
 
+/// Merge WorkspaceEdit results from several backends into one.
+/// `changes` are merged per URI, `documentChanges` are concatenated, and
+/// `changeAnnotations` are unioned. Clients that support `documentChanges`
+/// ignore `changes`, so once any edit has `documentChanges` the `changes`
+/// become unversioned `TextDocumentEdit`s there instead.
+/// Returns `null` when there is nothing to merge.
+pub fn merge_workspace_edits(edits: Vec<serde_json::Value>) -> serde_json::Value {
+    use serde_json::{Map, Value};
+
+    if edits.is_empty() {
+        return Value::Null;
+    }
+
+    let mut changes: Map<String, Value> = Map::new();
+    let mut document_changes: Vec<Value> = Vec::new();
+    let mut annotations: Map<String, Value> = Map::new();
+    let mut has_document_changes = false;
+
+    for edit in edits {
+        if let Some(map) = edit.get("changes").and_then(|c| c.as_object()) {
+            for (uri, text_edits) in map {
+                let entry = changes
+                    .entry(uri.clone())
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let (Some(acc), Some(new)) = (entry.as_array_mut(), text_edits.as_array()) {
+                    acc.extend(new.iter().cloned());
+                }
+            }
+        }
+        if let Some(arr) = edit.get("documentChanges").and_then(|c| c.as_array()) {
+            has_document_changes = true;
+            document_changes.extend(arr.iter().cloned());
+        }
+        if let Some(map) = edit.get("changeAnnotations").and_then(|c| c.as_object()) {
+            annotations.extend(map.clone());
+        }
+    }
+
+    let mut merged = Map::new();
+    if has_document_changes {
+        document_changes.extend(changes.into_iter().map(|(uri, edits)| {
+            serde_json::json!({
+                "textDocument": { "uri": uri, "version": null },
+                "edits": edits
+            })
+        }));
+        merged.insert(
+            "documentChanges".to_string(),
+            Value::Array(document_changes),
+        );
+    } else if !changes.is_empty() {
+        merged.insert("changes".to_string(), Value::Object(changes));
+    }
+    if !annotations.is_empty() {
+        merged.insert("changeAnnotations".to_string(), Value::Object(annotations));
+    }
+    Value::Object(merged)
+}
+
+/// Merge the non-null results of an extension method from several backends.
+/// A single result is returned unchanged and arrays are concatenated; other
+/// shapes can't be combined, so the first result wins.
+fn merge_custom_results(mut results: Vec<serde_json::Value>) -> serde_json::Value {
+    use serde_json::Value;
+
+    if results.len() > 1 && results.iter().all(Value::is_array) {
+        let merged = results
+            .into_iter()
+            .flat_map(|r| match r {
+                Value::Array(items) => items,
+                _ => Vec::new(),
+            })
+            .collect();
+        return Value::Array(merged);
+    }
+    if results.is_empty() {
+        Value::Null
+    } else {
+        results.swap_remove(0)
+    }
+}
+
+/// Display form of the venvs a fan-out failed on, for error messages and data
+fn venv_list(venvs: &[PathBuf]) -> Vec<String> {
+    venvs.iter().map(|p| p.display().to_string()).collect()
+}
+
+/// Dedup key: name, kind, uri, and start/end (line, character) of the range
+type SymbolKey = (String, u64, String, [u64; 4]);
+
 /// Extract dedup key from a SymbolInformation value.
-fn extract_dedupe_key(item: &serde_json::Value) -> Option<(String, u64, u64, String, u64)> {
+fn extract_dedupe_key(item: &serde_json::Value) -> Option<SymbolKey> {
     let name = item.get("name")?.as_str()?;
     let kind = item.get("kind")?.as_u64()?;
     let location = item.get("location")?;
     let uri = location.get("uri")?.as_str()?;
     let range = location.get("range")?;
-    let start = range.get("start")?;
-    let line = start.get("line")?.as_u64()?;
-    let character = start.get("character")?.as_u64()?;
-
-    Some((uri.to_string(), line, character, name.to_string(), kind))
+    let position = |key: &str| -> Option<(u64, u64)> {
+        let pos = range.get(key)?;
+        Some((pos.get("line")?.as_u64()?, pos.get("character")?.as_u64()?))
+    };
+    let (start_line, start_char) = position("start")?;
+    let (end_line, end_char) = position("end")?;
+
+    Some((
+        name.to_string(),
+        kind,
+        uri.to_string(),
+        [start_line, start_char, end_line, end_char],
+    ))
 }
 
 #[cfg(test)]
@@ -386,6 +535,27 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_merge_custom_results() {
+        // One backend: its result as is, whatever the shape
+        assert_eq!(merge_custom_results(vec![json!(42)]), json!(42));
+        assert_eq!(
+            merge_custom_results(vec![json!({ "stats": 1 })]),
+            json!({ "stats": 1 })
+        );
+        assert_eq!(merge_custom_results(vec![json!([1])]), json!([1]));
+        assert_eq!(merge_custom_results(vec![]), json!(null));
+        // Several: arrays are concatenated, anything else keeps the first
+        assert_eq!(
+            merge_custom_results(vec![json!([1, 2]), json!([3])]),
+            json!([1, 2, 3])
+        );
+        assert_eq!(
+            merge_custom_results(vec![json!("a"), json!({ "b": 1 })]),
+            json!("a")
+        );
+    }
+
     #[test]
     fn test_dedupe_removes_duplicates() {
         let results = vec![
@@ -469,4 +639,96 @@ mod tests {
         let deduped = dedupe_symbol_results(results);
         assert_eq!(deduped.len(), 2); // different URIs = different symbols
     }
+
+    #[test]
+    fn test_dedupe_distinguishes_range_end() {
+        let symbol = |end: u64| {
+            json!({
+                "name": "foo",
+                "kind": 12,
+                "location": {
+                    "uri": "file:///a.py",
+                    "range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": end}}
+                }
+            })
+        };
+
+        let deduped = dedupe_symbol_results(vec![symbol(3), symbol(9), symbol(3)]);
+        assert_eq!(deduped, vec![symbol(3), symbol(9)]);
+    }
+
+    #[test]
+    fn test_merge_workspace_edits_combines_changes() {
+        let merged = merge_workspace_edits(vec![
+            json!({
+                "changes": {
+                    "file:///a/x.py": [{"range": {}, "newText": "a1"}]
+                }
+            }),
+            json!({
+                "changes": {
+                    "file:///a/x.py": [{"range": {}, "newText": "a2"}],
+                    "file:///b/y.py": [{"range": {}, "newText": "b"}]
+                }
+            }),
+        ]);
+        assert_eq!(
+            merged["changes"]["file:///a/x.py"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            merged["changes"]["file:///b/y.py"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(merged.get("documentChanges").is_none());
+    }
+
+    #[test]
+    fn test_merge_workspace_edits_concatenates_document_changes() {
+        let merged = merge_workspace_edits(vec![
+            json!({"documentChanges": [{"kind": "rename", "oldUri": "file:///a", "newUri": "file:///b"}]}),
+            json!({"documentChanges": [], "changeAnnotations": {"x": {"label": "x"}}}),
+        ]);
+        assert_eq!(merged["documentChanges"].as_array().unwrap().len(), 1);
+        assert_eq!(merged["changeAnnotations"]["x"]["label"], "x");
+    }
+
+    #[test]
+    fn test_merge_workspace_edits_mixed_emits_document_changes_only() {
+        let merged = merge_workspace_edits(vec![
+            json!({
+                "changes": {
+                    "file:///a/x.py": [{"range": {}, "newText": "a"}]
+                }
+            }),
+            json!({
+                "documentChanges": [{
+                    "textDocument": {"uri": "file:///b/y.py", "version": 3},
+                    "edits": [{"range": {}, "newText": "b"}]
+                }]
+            }),
+        ]);
+        assert!(merged.get("changes").is_none());
+        let document_changes = merged["documentChanges"].as_array().unwrap();
+        assert_eq!(document_changes.len(), 2);
+        assert_eq!(document_changes[0]["textDocument"]["version"], 3);
+        assert_eq!(
+            document_changes[1],
+            json!({
+                "textDocument": {"uri": "file:///a/x.py", "version": null},
+                "edits": [{"range": {}, "newText": "a"}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_workspace_edits_empty_is_null() {
+        assert!(merge_workspace_edits(vec![]).is_null());
+    }
 }
@@ -0,0 +1,147 @@
+use super::transport::ClientOutput;
+use crate::error::ProxyError;
+use crate::framing::LspFrameWriter;
+use crate::message::RpcMessage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+impl super::LspProxy {
+    /// Handle `workspace/willRenameFiles`.
+    ///
+    /// Each backend only sees the renames that touch its project. A rename
+    /// owned by a single backend is forwarded as a normal request; renames
+    /// spanning several projects fan out and the WorkspaceEdits are merged.
+    pub(crate) async fn dispatch_will_rename_files(
+        &mut self,
+        msg: &RpcMessage,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let mut targets = self.rename_targets(msg);
+
+        match targets.len() {
+            0 => {
+                // No backend owns any of the files: nothing to edit
+                tracing::debug!("willRenameFiles: no owning backend, returning null edit");
+                let response = RpcMessage::success_response(msg, serde_json::Value::Null);
+                client_writer.write_message(&response).await?;
+            }
+            1 => {
+                let (venv_path, sub_msg) = targets.remove(0);
+                let session = self.state.pool.get(&venv_path).map(|inst| inst.session);
+                if let Some(session) = session {
+                    self.register_pending_request(msg, session, &venv_path);
+                }
+                tracing::info!(venv = %venv_path.display(), "Routing willRenameFiles to owning backend");
+                self.forward_to_backend(&venv_path, &sub_msg).await?;
+            }
+            _ => {
+                tracing::info!(
+                    backends = targets.len(),
+                    "willRenameFiles spans multiple projects, fanning out"
+                );
+                self.dispatch_fanout_targets(msg, targets, client_writer)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle `workspace/didRenameFiles`: notify each backend about the
+    /// renames that touch its project only.
+    pub(crate) async fn dispatch_did_rename_files(
+        &mut self,
+        msg: &RpcMessage,
+    ) -> Result<(), ProxyError> {
+        for (venv_path, sub_msg) in self.rename_targets(msg) {
+            self.forward_to_backend(&venv_path, &sub_msg).await?;
+        }
+        Ok(())
+    }
+
+    /// Split a rename message into per-backend copies whose `files` array only
+    /// contains renames where the old or new path belongs to that backend.
+    fn rename_targets(&self, msg: &RpcMessage) -> Vec<(PathBuf, RpcMessage)> {
+        let venvs = self.state.pool.backends_keys();
+        let projects: Vec<(PathBuf, PathBuf)> = venvs
+            .iter()
+            .filter_map(|venv| Some((venv.clone(), self.state.project_root(venv)?)))
+            .collect();
+        let files = msg
+            .params
+            .as_ref()
+            .and_then(|p| p.get("files"))
+            .and_then(|f| f.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut per_backend: HashMap<PathBuf, Vec<serde_json::Value>> = HashMap::new();
+        for file in files {
+            let mut owners: Vec<&PathBuf> = ["oldUri", "newUri"]
+                .iter()
+                .filter_map(|key| file.get(*key).and_then(|u| u.as_str()))
+                .filter_map(|uri| url::Url::parse(uri).ok()?.to_file_path().ok())
+                .filter_map(|path| owning_venv(&projects, &path))
+                .collect();
+            owners.dedup();
+            for venv in owners {
+                per_backend
+                    .entry(venv.clone())
+                    .or_default()
+                    .push(file.clone());
+            }
+        }
+
+        // Keep pool order so fan-out and notification order is deterministic
+        venvs
+            .iter()
+            .filter_map(|venv| {
+                let files = per_backend.remove(venv)?;
+                let mut sub_msg = msg.clone();
+                sub_msg.params = Some(serde_json::json!({ "files": files }));
+                Some((venv.clone(), sub_msg))
+            })
+            .collect()
+    }
+}
+
+/// Find the backend whose project (given as `(venv, project root)`) contains
+/// `path`. With nested projects, the innermost one wins.
+fn owning_venv<'a>(projects: &'a [(PathBuf, PathBuf)], path: &Path) -> Option<&'a PathBuf> {
+    projects
+        .iter()
+        .filter(|(_, root)| path.starts_with(root))
+        .max_by_key(|(_, root)| root.components().count())
+        .map(|(venv, _)| venv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owning_venv_picks_innermost_project() {
+        let projects = vec![
+            (PathBuf::from("/repo/.venv"), PathBuf::from("/repo")),
+            (PathBuf::from("/repo/pkg/.venv"), PathBuf::from("/repo/pkg")),
+            // A Poetry env kept outside its project
+            (
+                PathBuf::from("/cache/virtualenvs/svc-AbC123-py3.12"),
+                PathBuf::from("/repo/svc"),
+            ),
+        ];
+        assert_eq!(
+            owning_venv(&projects, Path::new("/repo/pkg/mod.py")),
+            Some(&projects[1].0)
+        );
+        assert_eq!(
+            owning_venv(&projects, Path::new("/repo/other.py")),
+            Some(&projects[0].0)
+        );
+        assert_eq!(
+            owning_venv(&projects, Path::new("/repo/svc/app.py")),
+            Some(&projects[2].0)
+        );
+        assert_eq!(owning_venv(&projects, Path::new("/elsewhere/x.py")), None);
+    }
+}
@@ -0,0 +1,186 @@
+use super::transport::ClientOutput;
+use crate::error::ProxyError;
+use crate::framing::LspFrameWriter;
+use crate::message::{RpcId, RpcMessage};
+use crate::state::{OpenDocument, PendingHover};
+use std::path::Path;
+use tokio::time::Instant;
+
+impl super::LspProxy {
+    /// Start the `--hover-timeout` clock for a hover just forwarded to `venv_path`.
+    pub(crate) fn track_hover_deadline(&mut self, msg: &RpcMessage, venv_path: &Path) {
+        let Some(timeout) = self.state.options.hover_timeout else {
+            return;
+        };
+        let (Some(id), Some(uri)) = (&msg.id, Self::extract_text_document_uri(msg)) else {
+            return;
+        };
+        let line = msg
+            .params
+            .as_ref()
+            .and_then(|p| p.get("position"))
+            .and_then(|p| p.get("line"))
+            .and_then(|l| l.as_u64())
+            .unwrap_or(0) as usize;
+
+        self.state.pending_hovers.insert(
+            id.clone(),
+            PendingHover {
+                deadline: Instant::now() + timeout,
+                uri,
+                line,
+                venv_path: venv_path.to_path_buf(),
+            },
+        );
+    }
+
+    /// Answer hovers whose backend missed the deadline with a fallback built
+    /// from the cached document, and cancel the backend request.
+    pub(crate) async fn expire_hover_requests(
+        &mut self,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let now = Instant::now();
+        let expired: Vec<RpcId> = self
+            .state
+            .pending_hovers
+            .iter()
+            .filter(|(_, h)| now >= h.deadline)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in expired {
+            let Some(hover) = self.state.pending_hovers.remove(&id) else {
+                continue;
+            };
+            // Already answered, cancelled, or cleaned up after a crash
+            let Some(pending) = self.state.pending_requests.remove(&id) else {
+                continue;
+            };
+
+            tracing::warn!(
+                id = ?id,
+                uri = %hover.uri,
+                venv = %hover.venv_path.display(),
+                "Hover timed out, returning fallback from cached document"
+            );
+
+            let cancel_msg =
+                RpcMessage::notification("$/cancelRequest", Some(serde_json::json!({ "id": id })));
+            self.forward_to_backend(&hover.venv_path, &cancel_msg)
+                .await?;
+
+            let line_text = self
+                .state
+                .open_documents
+                .get(&hover.uri)
+                .and_then(|doc| cached_line(doc, hover.line));
+            let response = RpcMessage {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id.clone()),
+                method: None,
+                params: None,
+                result: Some(fallback_hover(line_text)),
+                error: None,
+            };
+            client_writer.write_message(&response).await?;
+            self.state
+                .fallback_answered_hovers
+                .insert(id, (hover.venv_path, pending.backend_session));
+        }
+
+        Ok(())
+    }
+
+    /// A backend is gone: the hovers it still owed a response will never be
+    /// answered late
+    pub(crate) fn release_fallback_hovers(&mut self, venv_path: &Path, session: u64) {
+        self.state
+            .fallback_answered_hovers
+            .retain(|_, (v, s)| !(v == venv_path && *s == session));
+    }
+}
+
+/// The trimmed, non-empty source line `line` of a cached document. None when
+/// the text was dropped (`--max-cached-documents`): it is stale or empty then.
+fn cached_line(doc: &OpenDocument, line: usize) -> Option<&str> {
+    if doc.text_dropped {
+        return None;
+    }
+    doc.text
+        .lines()
+        .nth(line)
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+}
+
+/// Build a minimal Hover result: the source line under the cursor (if known)
+/// plus a note that type information is unavailable.
+fn fallback_hover(line_text: Option<&str>) -> serde_json::Value {
+    let note = "_Type information unavailable: backend busy._";
+    let value = match line_text {
+        Some(line) => format!("```python\n{}\n```\n\n{}", line, note),
+        None => note.to_string(),
+    };
+    serde_json::json!({
+        "contents": { "kind": "markdown", "value": value }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_hover_includes_line() {
+        let hover = fallback_hover(Some("x = compute()"));
+        let value = hover["contents"]["value"].as_str().unwrap();
+        assert!(value.contains("x = compute()"));
+        assert!(value.contains("backend busy"));
+        assert_eq!(hover["contents"]["kind"], "markdown");
+    }
+
+    #[test]
+    fn cached_line_skips_dropped_text() {
+        let mut doc = OpenDocument {
+            language_id: "python".to_string(),
+            version: 1,
+            text: "import os\n\n    x = compute()\n".to_string(),
+            venv: None,
+            forwarded: None,
+            last_touched: Instant::now(),
+            text_dropped: false,
+        };
+        assert_eq!(cached_line(&doc, 2), Some("x = compute()"));
+        assert_eq!(cached_line(&doc, 1), None);
+        doc.text_dropped = true;
+        assert_eq!(cached_line(&doc, 2), None);
+    }
+
+    #[test]
+    fn fallback_hovers_are_released_with_their_backend() {
+        use crate::backend::BackendKind;
+        use crate::proxy::LspProxy;
+        use crate::state::ProxyOptions;
+        use std::path::PathBuf;
+
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 2, None, ProxyOptions::default());
+        let venv = PathBuf::from("/a/.venv");
+        let answered = &mut proxy.state.fallback_answered_hovers;
+        answered.insert(RpcId::Number(1), (venv.clone(), 1));
+        answered.insert(RpcId::Number(2), (venv.clone(), 2));
+        answered.insert(RpcId::Number(3), (PathBuf::from("/b/.venv"), 1));
+
+        proxy.release_fallback_hovers(&venv, 1);
+        let answered = &proxy.state.fallback_answered_hovers;
+        assert_eq!(answered.len(), 2);
+        assert!(answered.contains_key(&RpcId::Number(2)));
+        assert!(answered.contains_key(&RpcId::Number(3)));
+    }
+
+    #[test]
+    fn fallback_hover_without_document() {
+        let hover = fallback_hover(None);
+        assert!(!hover["contents"]["value"].as_str().unwrap().contains("```"));
+    }
+}
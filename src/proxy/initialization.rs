@@ -1,28 +1,34 @@
-use crate::backend::LspBackend;
+use super::transport::ClientOutput;
+use crate::backend::{
+    run_startup_script, BackendKind, LspBackend, SpawnRequest, SPAWN_FAILURE_COOLDOWN,
+};
 use crate::backend_pool::BackendInstance;
-use crate::error::ProxyError;
+use crate::error::{BackendError, ProxyError};
 use crate::framing::LspFrameWriter;
 use crate::message::{RpcId, RpcMessage};
+use crate::state::{ForwardedVersion, InitTimeoutAction, SpawnFailure};
+use crate::venv;
 use serde_json::Value;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use url::Url;
 
 /// Rewrite rootUri, rootPath, and workspaceFolders in initialize params
-/// to point to the venv's parent directory (the project root).
+/// to point to the project root that owns the venv.
 ///
 /// This ensures each backend indexes only the project that owns the venv,
 /// which is critical for worktree paths (dot-prefixed directories like
 /// `.worktree/` are excluded from indexing when rootUri points to the
 /// main repo root).
-fn rewrite_root_uri(init_params: &mut Value, venv: &Path) {
-    let project_root = match venv.parent() {
-        Some(p) => p,
-        None => return,
-    };
-
-    let root_uri = match Url::from_file_path(project_root) {
-        Ok(u) => u.to_string(),
-        Err(()) => return,
+///
+/// Without a usable project root the client's roots are kept, with whichever
+/// of `rootUri` / `workspaceFolders` it left out filled in.
+fn rewrite_root_uri(init_params: &mut Value, project_root: Option<&Path>) {
+    let Some((project_root, root_uri)) =
+        project_root.and_then(|root| Some((root, Url::from_file_path(root).ok()?.to_string())))
+    else {
+        ensure_workspace_roots(init_params);
+        return;
     };
 
     let dir_name = project_root
@@ -48,18 +54,155 @@ fn rewrite_root_uri(init_params: &mut Value, venv: &Path) {
     }
 }
 
+/// Fill in whichever of `rootUri` / `workspaceFolders` the client left out.
+///
+/// Older clients send only `rootPath`/`rootUri` while newer backends expect
+/// `workspaceFolders` (and vice versa).
+fn ensure_workspace_roots(init_params: &mut Value) {
+    let Some(obj) = init_params.as_object_mut() else {
+        return;
+    };
+
+    let has_folders = obj
+        .get("workspaceFolders")
+        .and_then(|f| f.as_array())
+        .is_some_and(|f| !f.is_empty());
+
+    let root_uri = obj
+        .get("rootUri")
+        .and_then(|u| u.as_str())
+        .map(str::to_string)
+        .or_else(|| {
+            let root_path = obj.get("rootPath")?.as_str()?;
+            Url::from_directory_path(root_path)
+                .or_else(|()| Url::from_file_path(root_path))
+                .ok()
+                .map(|u| u.to_string())
+        });
+
+    match root_uri {
+        Some(root_uri) if !has_folders => {
+            let name = Url::parse(&root_uri)
+                .ok()
+                .and_then(|u| {
+                    u.path_segments()?
+                        .rfind(|s| !s.is_empty())
+                        .map(str::to_string)
+                })
+                .unwrap_or_else(|| "workspace".to_string());
+            tracing::debug!(root_uri = %root_uri, "Synthesizing workspaceFolders from rootUri");
+            obj.insert("rootUri".to_string(), Value::String(root_uri.clone()));
+            obj.insert(
+                "workspaceFolders".to_string(),
+                serde_json::json!([{ "uri": root_uri, "name": name }]),
+            );
+        }
+        None if has_folders => {
+            // Only workspaceFolders given: backends still reading rootUri get the first folder
+            if let Some(first) = obj["workspaceFolders"][0].get("uri").cloned() {
+                tracing::debug!(root_uri = %first, "Synthesizing rootUri from workspaceFolders");
+                obj.insert("rootUri".to_string(), first);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse `--synthetic-initialize` file content into initialize params.
+///
+/// Accepts either a bare params object or a full `initialize` request (as
+/// copied from a log), and checks the fields the proxy and backends rely on.
+fn parse_synthetic_initialize(content: &str) -> Result<Value, ProxyError> {
+    let invalid = |reason: &str| {
+        ProxyError::InvalidMessage(format!("synthetic initialize params: {}", reason))
+    };
+
+    let mut value: Value = serde_json::from_str(content)?;
+    if value.get("method").and_then(|m| m.as_str()) == Some("initialize") {
+        value = value.get("params").cloned().unwrap_or(Value::Null);
+    }
+
+    let obj = value
+        .as_object()
+        .ok_or_else(|| invalid("expected a JSON object"))?;
+    match obj.get("capabilities") {
+        Some(Value::Object(_)) => {}
+        Some(_) => return Err(invalid("`capabilities` must be an object")),
+        None => return Err(invalid("missing `capabilities`")),
+    }
+    if !matches!(
+        obj.get("processId"),
+        None | Some(Value::Null | Value::Number(_))
+    ) {
+        return Err(invalid("`processId` must be a number or null"));
+    }
+    for key in ["rootUri", "rootPath"] {
+        if !matches!(obj.get(key), None | Some(Value::Null | Value::String(_))) {
+            return Err(invalid(&format!("`{}` must be a string or null", key)));
+        }
+    }
+    if !matches!(
+        obj.get("workspaceFolders"),
+        None | Some(Value::Null | Value::Array(_))
+    ) {
+        return Err(invalid("`workspaceFolders` must be an array or null"));
+    }
+
+    Ok(value)
+}
+
+/// Deep-merge `patch` into `target`: objects are merged key by key,
+/// any other value in `patch` replaces the one in `target`.
+fn merge_json(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// The item sections a forwarded `workspace/configuration` asks the client
+/// for, in order; empty for any other message
+pub(crate) fn configuration_sections(msg: &RpcMessage) -> Vec<Option<String>> {
+    if msg.method_name() != Some("workspace/configuration") {
+        return Vec::new();
+    }
+    msg.params
+        .as_ref()
+        .and_then(|p| p.get("items"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|item| {
+            item.get("section")
+                .and_then(Value::as_str)
+                .map(String::from)
+        })
+        .collect()
+}
+
 /// Perform the LSP initialize handshake with a backend:
 /// 1. Send `initialize` request with the given params
-/// 2. Wait for the initialize response (10s timeout, skip notifications)
+/// 2. Wait for the initialize response (`timeout`, skip notifications);
+///    with `retry`, wait once more for twice as long before giving up
 /// 3. Send `initialized` notification
 ///
 /// Returns the initialize response from the backend.
 async fn perform_initialize_handshake(
     backend: &mut LspBackend,
-    mut init_params: Value,
+    init_params: Value,
     venv: &Path,
+    timeout: Duration,
+    retry: bool,
 ) -> Result<RpcMessage, ProxyError> {
-    rewrite_root_uri(&mut init_params, venv);
     tracing::trace!(
         venv = %venv.display(),
         init_params = %init_params,
@@ -71,14 +214,77 @@ async fn perform_initialize_handshake(
     backend.send_message(&init_msg).await?;
 
     // Receive initialize response
+    let init_response = match wait_for_initialize_response(backend, venv, timeout).await {
+        Err(ProxyError::Backend(BackendError::InitializeTimeout(_))) if retry => {
+            tracing::warn!(
+                venv = %venv.display(),
+                timeout_secs = timeout.as_secs(),
+                "Initialize timed out, waiting once more with a longer timeout"
+            );
+            wait_for_initialize_response(backend, venv, timeout * 2).await?
+        }
+        result => result?,
+    };
+
+    // Send initialized notification
+    let initialized_msg = RpcMessage::notification("initialized", Some(serde_json::json!({})));
+
+    tracing::info!(venv = %venv.display(), "Sending initialized to backend");
+    backend.send_message(&initialized_msg).await?;
+
+    Ok(init_response)
+}
+
+/// Initialize handshake for a new backend, detached from the proxy so it can
+/// run in a launch task
+pub(crate) struct Handshake {
+    venv: PathBuf,
+    init_params: Value,
+    timeout: Duration,
+    retry: bool,
+    /// Sent right after `initialized`; a backend spawned after a settings
+    /// change would otherwise use defaults
+    configuration: Option<RpcMessage>,
+}
+
+impl Handshake {
+    /// The configuration this handshake sends, captured when it was prepared
+    pub(crate) fn configuration(&self) -> Option<&RpcMessage> {
+        self.configuration.as_ref()
+    }
+
+    pub(crate) async fn run(self, backend: &mut LspBackend) -> Result<RpcMessage, ProxyError> {
+        let init_response = perform_initialize_handshake(
+            backend,
+            self.init_params,
+            &self.venv,
+            self.timeout,
+            self.retry,
+        )
+        .await?;
+        if let Some(configuration) = &self.configuration {
+            tracing::info!(venv = %self.venv.display(), "Sending cached configuration to backend");
+            backend.send_message(configuration).await?;
+        }
+        Ok(init_response)
+    }
+}
+
+/// Wait up to `timeout` for the response to the `initialize` request (id 1),
+/// skipping notifications the backend sends in the meantime.
+async fn wait_for_initialize_response(
+    backend: &mut LspBackend,
+    venv: &Path,
+    timeout: Duration,
+) -> Result<RpcMessage, ProxyError> {
     let init_id = 1i64;
-    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(10);
-    let init_response = loop {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
         let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
         if remaining.is_zero() {
-            return Err(ProxyError::Backend(
-                crate::error::BackendError::InitializeTimeout(10),
-            ));
+            return Err(ProxyError::Backend(BackendError::InitializeTimeout(
+                timeout.as_secs(),
+            )));
         }
 
         let wait_result = tokio::time::timeout(remaining, backend.read_message()).await;
@@ -90,7 +296,7 @@ async fn perform_initialize_handshake(
                         if *id == init_id {
                             if let Some(error) = &msg.error {
                                 return Err(ProxyError::Backend(
-                                    crate::error::BackendError::InitializeResponseError(format!(
+                                    BackendError::InitializeResponseError(format!(
                                         "code={}, message={}",
                                         error.code, error.message
                                     )),
@@ -100,7 +306,7 @@ async fn perform_initialize_handshake(
                                 venv = %venv.display(),
                                 "Received initialize response from backend"
                             );
-                            break msg;
+                            return Ok(msg);
                         }
                     }
                 } else {
@@ -111,80 +317,346 @@ async fn perform_initialize_handshake(
                 }
             }
             Ok(Err(e)) => {
-                return Err(ProxyError::Backend(
-                    crate::error::BackendError::InitializeFailed(format!(
-                        "Error reading initialize response: {}",
-                        e
-                    )),
-                ));
+                return Err(ProxyError::Backend(BackendError::InitializeFailed(
+                    format!("Error reading initialize response: {}", e),
+                )));
             }
             Err(_) => {
-                return Err(ProxyError::Backend(
-                    crate::error::BackendError::InitializeTimeout(10),
-                ));
+                return Err(ProxyError::Backend(BackendError::InitializeTimeout(
+                    timeout.as_secs(),
+                )));
             }
         }
-    };
-
-    // Send initialized notification
-    let initialized_msg = RpcMessage::notification("initialized", Some(serde_json::json!({})));
-
-    tracing::info!(venv = %venv.display(), "Sending initialized to backend");
-    backend.send_message(&initialized_msg).await?;
-
-    Ok(init_response)
+    }
 }
 
 impl super::LspProxy {
+    /// Load `--synthetic-initialize` params and cache them as if the client
+    /// had sent `initialize`, so backends can be created without an editor.
+    pub(crate) async fn load_synthetic_initialize(
+        &mut self,
+        path: &Path,
+    ) -> Result<(), ProxyError> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let params = parse_synthetic_initialize(&content)?;
+        tracing::info!(path = %path.display(), "Using synthetic initialize params");
+        self.state.client_initialize = Some(RpcMessage::request(
+            RpcId::Number(0),
+            "initialize",
+            Some(params),
+        ));
+        Ok(())
+    }
+
+    /// Initialize a pre-spawned backend with the cached initialize params and
+    /// insert it into the pool. Returns the backend's initialize response.
+    pub(crate) async fn install_initial_backend(
+        &mut self,
+        mut backend: LspBackend,
+        venv: PathBuf,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<RpcMessage, ProxyError> {
+        let init_response = self
+            .complete_backend_initialization(&mut backend, &venv, client_writer)
+            .await?;
+        let session = self.state.pool.next_session_id();
+        let parts = backend.into_split();
+        let tx = self.state.pool.msg_sender();
+        let instance = BackendInstance::from_parts(
+            parts,
+            venv.clone(),
+            session,
+            tx,
+            self.state.warmup_timeout_for(&venv),
+        );
+        self.state.pool.insert(venv.clone(), instance);
+        tracing::info!("Initial backend inserted into pool");
+        self.preindex_project_files(&venv).await;
+        Ok(init_response)
+    }
+
     /// Extract cached initialize params, returning an error if not available.
-    fn cached_init_params(&self) -> Result<Value, ProxyError> {
-        self.state
+    /// Applies `--client-capabilities-augment` on top of the client's capabilities.
+    fn cached_init_params(&self, venv: &Path) -> Result<Value, ProxyError> {
+        let mut params = self
+            .state
             .client_initialize
             .as_ref()
             .and_then(|msg| msg.params.clone())
-            .ok_or_else(|| ProxyError::InvalidMessage("No initialize params cached".to_string()))
+            .ok_or_else(|| ProxyError::InvalidMessage("No initialize params cached".to_string()))?;
+
+        if let (Some(augment), Some(obj)) = (
+            &self.state.options.client_capabilities_augment,
+            params.as_object_mut(),
+        ) {
+            let capabilities = obj
+                .entry("capabilities")
+                .or_insert_with(|| serde_json::json!({}));
+            merge_json(capabilities, augment);
+        }
+
+        if let (Some(settings), Some(obj)) = (self.pyright_settings(venv), params.as_object_mut()) {
+            let options = obj
+                .entry("initializationOptions")
+                .or_insert_with(|| serde_json::json!({}));
+            merge_json(options, &settings);
+        }
+
+        Ok(params)
+    }
+
+    /// `workspace/didChangeConfiguration` for a new backend: the client's latest
+    /// settings with any proxy-forced settings merged on top
+    pub(crate) fn backend_configuration(&self, venv: &Path) -> Option<RpcMessage> {
+        let Some(forced) = self.pyright_settings(venv) else {
+            return self.state.client_configuration.clone();
+        };
+        let mut params = self
+            .state
+            .client_configuration
+            .as_ref()
+            .and_then(|msg| msg.params.clone())
+            .unwrap_or_else(|| serde_json::json!({}));
+        merge_json(&mut params, &serde_json::json!({ "settings": forced }));
+        Some(RpcMessage::notification(
+            "workspace/didChangeConfiguration",
+            Some(params),
+        ))
+    }
+
+    /// Bring a launched backend up to date with a `workspace/didChangeConfiguration`
+    /// that arrived after its handshake was prepared with `sent`
+    pub(crate) async fn send_configuration_changed_during_launch(
+        &self,
+        backend: &mut LspBackend,
+        venv: &Path,
+        sent: Option<&RpcMessage>,
+    ) -> Result<(), ProxyError> {
+        let Some(current) = self.backend_configuration(venv) else {
+            return Ok(());
+        };
+        if sent.is_some_and(|sent| sent.params == current.params) {
+            return Ok(());
+        }
+        tracing::info!(venv = %venv.display(), "Configuration changed during launch, resending");
+        backend.send_message(&current).await?;
+        Ok(())
+    }
+
+    /// Patch the settings forced onto the backend for `venv` into the client's
+    /// answer to its `workspace/configuration`, which would override them
+    pub(crate) fn force_configuration_result(
+        &self,
+        venv: &Path,
+        sections: &[Option<String>],
+        result: &mut Value,
+    ) {
+        let Some(forced) = self.pyright_settings(venv) else {
+            return;
+        };
+        let Some(items) = result.as_array_mut() else {
+            return;
+        };
+        for (item, section) in items.iter_mut().zip(sections) {
+            let mut patch = Some(&forced);
+            for key in section.iter().flat_map(|s| s.split('.')) {
+                patch = patch.and_then(|p| p.get(key));
+            }
+            if let Some(patch) = patch {
+                merge_json(item, patch);
+            }
+        }
+    }
+
+    /// Settings forced onto a pyright backend for `venv` (`--pyright-diagnostic-mode`)
+    fn pyright_settings(&self, venv: &Path) -> Option<Value> {
+        let mode = self.state.options.pyright_diagnostic_mode?;
+        (self.state.backend_kind_for(venv) == BackendKind::Pyright).then(|| mode.settings())
     }
 
     /// Complete backend initialization: forward initialize, receive response, send initialized.
     /// Returns the initialize response to forward to the client.
     pub(crate) async fn complete_backend_initialization(
-        &self,
+        &mut self,
         backend: &mut LspBackend,
         venv: &Path,
-        _client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        _client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<RpcMessage, ProxyError> {
-        let init_params = self.cached_init_params()?;
-        perform_initialize_handshake(backend, init_params, venv).await
+        self.initialize_backend(backend, venv).await
     }
 
-    /// Create a new backend, initialize it, split it, and return a BackendInstance.
-    /// Does NOT insert into the pool — caller is responsible for that.
-    pub(crate) async fn create_backend_instance(
+    /// Everything the initialize handshake with a new backend for `venv` needs,
+    /// so it can run without borrowing the proxy
+    pub(crate) fn handshake(&self, venv: &Path) -> Result<Handshake, ProxyError> {
+        let mut init_params = self.cached_init_params(venv)?;
+        rewrite_root_uri(&mut init_params, self.state.project_root(venv).as_deref());
+        Ok(Handshake {
+            venv: venv.to_path_buf(),
+            init_params,
+            timeout: self.state.options.initialize_timeout,
+            retry: self.state.options.init_timeout_action == InitTimeoutAction::Retry,
+            configuration: self.backend_configuration(venv),
+        })
+    }
+
+    /// Run the initialize handshake with the cached params, applying
+    /// `--initialize-timeout` and `--initialize-response-timeout-action`.
+    async fn initialize_backend(
         &mut self,
+        backend: &mut LspBackend,
         venv: &Path,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
-    ) -> Result<BackendInstance, ProxyError> {
-        let session = self.state.pool.next_session_id();
+    ) -> Result<RpcMessage, ProxyError> {
+        let result = self.handshake(venv)?.run(backend).await;
+        self.record_initialize_result(venv, result.as_ref());
+        result
+    }
 
-        tracing::info!(
-            session = session,
+    /// Disable the venv for a while after an initialize timeout
+    /// (`--initialize-response-timeout-action disable`), count a backend that died or failed during initialize toward
+    /// the circuit breaker.
+    pub(crate) fn record_initialize_result(
+        &mut self,
+        venv: &Path,
+        result: Result<&RpcMessage, &ProxyError>,
+    ) {
+        match result {
+            Err(ProxyError::Backend(BackendError::InitializeTimeout(_)))
+                if self.state.options.init_timeout_action == InitTimeoutAction::Disable =>
+            {
+                let cooldown = self.state.options.init_disable_cooldown;
+                tracing::warn!(
+                    venv = %venv.display(),
+                    cooldown_secs = cooldown.as_secs(),
+                    "Initialize timed out, disabling backend for this venv"
+                );
+                self.state
+                    .disabled_venvs
+                    .insert(venv.to_path_buf(), tokio::time::Instant::now() + cooldown);
+            }
+            Err(ProxyError::Backend(
+                BackendError::Communication(_)
+                | BackendError::InitializeFailed(_)
+                | BackendError::InitializeResponseError(_),
+            )) => {
+                if let Some(breaker) = &mut self.state.crash_breaker {
+                    breaker.record_crash(venv, tokio::time::Instant::now());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Check the venv has a usable interpreter and isn't waiting out the
+    /// backoff after failed spawns.
+    pub(crate) fn check_spawnable(&self, venv: &Path) -> Result<(), ProxyError> {
+        // A pyvenv.cfg without a working interpreter would fail opaquely later
+        if !venv::has_usable_interpreter(venv) {
+            tracing::error!(
+                venv = %venv.display(),
+                python = %venv::venv_python(venv).display(),
+                "venv has no usable Python interpreter, not spawning backend"
+            );
+            return Err(BackendError::NoInterpreter(venv.display().to_string()).into());
+        }
+
+        // A venv whose last launch used up its spawn retries waits out the
+        // cooldown. The next attempt is made by a later request
+        if let Some(failure) = self.state.spawn_failures.get(venv) {
+            let wait = SPAWN_FAILURE_COOLDOWN.saturating_sub(failure.last.elapsed());
+            if !wait.is_zero() {
+                return Err(BackendError::SpawnBackoff {
+                    venv: venv.display().to_string(),
+                    retry_in_ms: wait.as_millis() as u64,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// What the spawner needs to start a backend for `venv`
+    pub(crate) fn spawn_request(&self, venv: &Path) -> SpawnRequest {
+        SpawnRequest {
+            kind: self.state.backend_kind_for(venv),
+            venv: venv.to_path_buf(),
+            read_buffer_size: self.state.options.read_buffer_size,
+            env_passthrough: self.state.options.backend_env_passthrough.clone(),
+        }
+    }
+
+    /// Count a spawn, or a failed spawn towards the venv's backoff
+    pub(crate) fn record_spawn_result(&mut self, venv: &Path, error: Option<&BackendError>) {
+        let Some(e) = error else {
+            self.state.spawn_failures.remove(venv);
+            self.state.metrics.backend_spawns_total += 1;
+            return;
+        };
+        let now = tokio::time::Instant::now();
+        let failure = self
+            .state
+            .spawn_failures
+            .entry(venv.to_path_buf())
+            .or_insert(SpawnFailure {
+                attempts: 0,
+                last: now,
+            });
+        failure.attempts += 1;
+        failure.last = now;
+        tracing::error!(
             venv = %venv.display(),
-            "Creating new backend instance"
+            error = %e,
+            failures = failure.attempts,
+            retry_in_ms = SPAWN_FAILURE_COOLDOWN.as_millis() as u64,
+            "Failed to spawn backend"
         );
+    }
 
-        // 1. Spawn
-        let mut backend = LspBackend::spawn(self.state.backend_kind, Some(venv)).await?;
+    /// Check the venv has a usable interpreter, run `--backend-startup-script`
+    /// (if any), then spawn a backend for `venv`.
+    pub(crate) async fn spawn_backend(&mut self, venv: &Path) -> Result<LspBackend, ProxyError> {
+        self.check_spawnable(venv)?;
+        let request = self.spawn_request(venv);
+        if let Some(script) = &self.state.options.backend_startup_script {
+            run_startup_script(script, request.kind, venv).await?;
+        }
+        let result = {
+            let _permit = self.state.spawn_limiter.acquire().await;
+            (self.spawner)(request).await
+        };
+        self.record_spawn_result(venv, result.as_ref().err());
+        let mut backend = result?;
+        backend.set_strict_json(self.state.options.strict_json);
+        Ok(backend)
+    }
 
-        // 2. Initialize handshake
-        let init_params = self.cached_init_params()?;
-        perform_initialize_handshake(&mut backend, init_params, venv).await?;
+    /// Create a new backend, initialize it, split it, and return a BackendInstance.
+    /// Does NOT insert into the pool — caller is responsible for that.
+    pub(crate) async fn create_backend_instance(
+        &mut self,
+        venv: &Path,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<BackendInstance, ProxyError> {
+        let launch = self.prepare_launch(venv)?;
+        let launched = launch.run().await;
+        let backend = self.finish_launch(venv, launched)?;
+        self.install_launched_backend(backend, venv, client_writer)
+            .await
+    }
+
+    /// Restore the venv's documents to a freshly initialized backend, then
+    /// split it into a pool instance.
+    pub(crate) async fn install_launched_backend(
+        &mut self,
+        mut backend: LspBackend,
+        venv: &Path,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<BackendInstance, ProxyError> {
+        let session = self.state.pool.next_session_id();
         tracing::info!(session = session, venv = %venv.display(), "Backend initialized");
 
-        // 3. Document restoration for this venv
         self.restore_documents_to_backend(&mut backend, venv, session, client_writer)
             .await?;
 
-        // 4. Split and create instance
         let parts = backend.into_split();
         let tx = self.state.pool.msg_sender();
         Ok(BackendInstance::from_parts(
@@ -192,22 +664,24 @@ impl super::LspProxy {
             venv.to_path_buf(),
             session,
             tx,
+            self.state.warmup_timeout_for(venv),
         ))
     }
 
     /// Restore documents belonging to a venv to a backend
     pub(crate) async fn restore_documents_to_backend(
-        &self,
+        &mut self,
         backend: &mut LspBackend,
         venv: &Path,
         session: u64,
-        _client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
-        let venv_parent = venv.parent().map(|p| p.to_path_buf());
+        let venv_parent = self.state.project_root(venv);
         let total_docs = self.state.open_documents.len();
         let mut restored = 0;
         let mut skipped = 0;
         let mut failed = 0;
+        let mut restored_urls = Vec::new();
 
         tracing::info!(
             session = session,
@@ -228,6 +702,15 @@ impl super::LspProxy {
                 skipped += 1;
                 continue;
             }
+            if doc.text_dropped {
+                tracing::warn!(
+                    session = session,
+                    uri = %url,
+                    "Not restoring document: cached text was dropped (--max-cached-documents)"
+                );
+                skipped += 1;
+                continue;
+            }
 
             let uri_str = url.to_string();
             let language_id = doc.language_id.clone();
@@ -250,6 +733,7 @@ impl super::LspProxy {
             match backend.send_message(&didopen_msg).await {
                 Ok(_) => {
                     restored += 1;
+                    restored_urls.push((url.clone(), version));
                     tracing::info!(
                         session = session,
                         uri = %uri_str,
@@ -269,6 +753,22 @@ impl super::LspProxy {
             }
         }
 
+        for (url, version) in &restored_urls {
+            if let Some(doc) = self.state.open_documents.get_mut(url) {
+                doc.forwarded = Some(ForwardedVersion {
+                    venv: venv.to_path_buf(),
+                    session,
+                    version: *version,
+                });
+            }
+        }
+
+        // Bridge the gap until the new backend re-analyzes the restored files
+        if self.state.options.preserve_diagnostics_on_respawn {
+            let urls: Vec<url::Url> = restored_urls.into_iter().map(|(url, _)| url).collect();
+            self.replay_cached_diagnostics(&urls, client_writer).await;
+        }
+
         tracing::info!(
             session = session,
             restored = restored,
@@ -281,3 +781,194 @@ impl super::LspProxy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_roots_from_root_uri() {
+        let mut params = serde_json::json!({ "rootUri": "file:///work/proj" });
+        rewrite_root_uri(&mut params, None);
+        assert_eq!(
+            params["workspaceFolders"],
+            serde_json::json!([{ "uri": "file:///work/proj", "name": "proj" }])
+        );
+    }
+
+    #[test]
+    fn workspace_roots_from_root_path() {
+        let mut params = serde_json::json!({ "rootPath": "/work/proj" });
+        // A relative venv has no usable project root
+        rewrite_root_uri(&mut params, Some(Path::new("venv-parent")));
+        assert_eq!(params["rootUri"], "file:///work/proj/");
+        assert_eq!(params["workspaceFolders"][0]["name"], "proj");
+    }
+
+    #[test]
+    fn workspace_roots_from_folders() {
+        let mut params = serde_json::json!({
+            "workspaceFolders": [{ "uri": "file:///work/a", "name": "a" }]
+        });
+        rewrite_root_uri(&mut params, None);
+        assert_eq!(params["rootUri"], "file:///work/a");
+        assert_eq!(params["workspaceFolders"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn handshake_roots_backend_at_venv_project() {
+        use crate::state::ProxyOptions;
+
+        let mut proxy =
+            super::super::LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        proxy.state.client_initialize = Some(RpcMessage::request(
+            RpcId::Number(0),
+            "initialize",
+            Some(serde_json::json!({ "rootUri": "file:///work", "capabilities": {} })),
+        ));
+
+        let in_tree = Path::new("/work/app/.venv");
+        let params = proxy.handshake(in_tree).unwrap().init_params;
+        assert_eq!(params["rootUri"], "file:///work/app");
+
+        // A Poetry env in the cache is rooted at its pyproject.toml directory
+        let cached = Path::new("/cache/virtualenvs/svc-AbC123-py3.12");
+        proxy
+            .state
+            .venv_projects
+            .insert(cached.to_path_buf(), PathBuf::from("/work/svc"));
+        let params = proxy.handshake(cached).unwrap().init_params;
+        assert_eq!(params["rootUri"], "file:///work/svc");
+        assert_eq!(params["rootPath"], "/work/svc");
+        assert_eq!(params["workspaceFolders"][0]["name"], "svc");
+    }
+
+    #[test]
+    fn synthetic_initialize_accepts_params_and_requests() {
+        let params = parse_synthetic_initialize(
+            r#"{"processId": null, "rootUri": "file:///w", "capabilities": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(params["rootUri"], "file:///w");
+
+        let from_request = parse_synthetic_initialize(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}"#,
+        )
+        .unwrap();
+        assert!(from_request["capabilities"].is_object());
+    }
+
+    #[test]
+    fn synthetic_initialize_rejects_invalid_params() {
+        for content in [
+            "[]",
+            r#"{"rootUri": "file:///w"}"#,
+            r#"{"capabilities": []}"#,
+            r#"{"capabilities": {}, "rootUri": 1}"#,
+            r#"{"capabilities": {}, "processId": "x"}"#,
+        ] {
+            assert!(
+                parse_synthetic_initialize(content).is_err(),
+                "accepted: {content}"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn transient_spawn_failures_are_retried_within_the_launch() {
+        use crate::state::ProxyOptions;
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let venv = dir.path().join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        let python = venv.join("bin/python");
+        std::fs::write(&python, "").unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut proxy =
+            super::super::LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        proxy.state.client_initialize = Some(RpcMessage::request(
+            RpcId::Number(0),
+            "initialize",
+            Some(serde_json::json!({ "capabilities": {} })),
+        ));
+
+        // Fails twice, then spawns a backend that answers initialize
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        proxy.set_spawner(Arc::new(move |request: SpawnRequest| {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                if attempt < 2 {
+                    return Err(BackendError::SpawnFailed(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "pyright-langserver",
+                    )));
+                }
+                let mut cmd = tokio::process::Command::new("sh");
+                cmd.arg("-c").arg(concat!(
+                    r#"printf 'Content-Length: 36\r\n\r\n{"jsonrpc":"2.0","id":1,"result":{}}'; "#,
+                    "exec cat >/dev/null"
+                ));
+                LspBackend::from_command(cmd, request.read_buffer_size)
+            })
+        }));
+
+        let launch = proxy.prepare_launch(&venv).unwrap();
+        let result = launch.run().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(proxy.finish_launch(&venv, result).is_ok());
+        assert!(proxy.state.spawn_failures.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn initialize_timeout_disables_venv_until_cooldown() {
+        use crate::state::ProxyOptions;
+
+        let options = ProxyOptions {
+            init_timeout_action: InitTimeoutAction::Disable,
+            init_disable_cooldown: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut proxy = super::super::LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let venv = Path::new("/work/pkg/.venv");
+
+        let timeout = ProxyError::Backend(BackendError::InitializeTimeout(10));
+        proxy.record_initialize_result(venv, Err(&timeout));
+        assert!(matches!(
+            proxy.check_launchable(venv),
+            Err(ProxyError::Backend(BackendError::Disabled(_)))
+        ));
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert!(proxy.check_launchable(venv).is_ok());
+        assert!(proxy.state.disabled_venvs.is_empty());
+    }
+
+    #[test]
+    fn merge_json_deep_merges_objects() {
+        let mut target = serde_json::json!({
+            "textDocument": { "hover": { "dynamicRegistration": true } },
+            "window": { "workDoneProgress": false }
+        });
+        merge_json(
+            &mut target,
+            &serde_json::json!({
+                "textDocument": { "hover": { "contentFormat": ["markdown"] } },
+                "window": { "workDoneProgress": true }
+            }),
+        );
+        assert_eq!(
+            target,
+            serde_json::json!({
+                "textDocument": { "hover": { "dynamicRegistration": true, "contentFormat": ["markdown"] } },
+                "window": { "workDoneProgress": true }
+            })
+        );
+    }
+}
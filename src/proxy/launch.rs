@@ -0,0 +1,338 @@
+//! Backend launches that run off the main loop. The startup script, spawn
+//! and initialize handshake happen in a task; the result comes back through
+//! a channel and the main loop installs the backend into the pool, serving
+//! clients and other backends in the meantime.
+
+use super::initialization::Handshake;
+use super::transport::ClientOutput;
+use super::LspProxy;
+use crate::backend::{
+    run_startup_script, shutdown_fire_and_forget, BackendSpawner, LspBackend, SpawnRequest,
+    SPAWN_RETRY_DELAYS,
+};
+use crate::backend_pool::SpawnLimiter;
+use crate::error::{BackendError, ProxyError};
+use crate::framing::LspFrameWriter;
+use crate::message::RpcMessage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Where a launch failed
+pub(crate) enum LaunchError {
+    /// Before the backend process existed (`--backend-startup-script`)
+    Start(ProxyError),
+    /// The backend process could not be spawned
+    Spawn(BackendError),
+    /// The backend was spawned but its initialize handshake failed
+    Initialize(ProxyError),
+}
+
+impl From<LaunchError> for ProxyError {
+    fn from(e: LaunchError) -> Self {
+        match e {
+            LaunchError::Start(e) | LaunchError::Initialize(e) => e,
+            LaunchError::Spawn(e) => e.into(),
+        }
+    }
+}
+
+/// An initialized backend and its initialize response, or where the launch failed
+pub(crate) type LaunchResult = Result<(LspBackend, RpcMessage), LaunchError>;
+
+/// Everything needed to start and initialize a backend, detached from the proxy
+pub(crate) struct Launch {
+    request: SpawnRequest,
+    spawner: BackendSpawner,
+    startup_script: Option<PathBuf>,
+    strict_json: bool,
+    limiter: SpawnLimiter,
+    handshake: Handshake,
+}
+
+impl Launch {
+    /// Wait for a spawn slot (`--max-concurrent-spawns`), run the startup
+    /// script, spawn the backend and complete its initialize handshake.
+    pub(crate) async fn run(self) -> LaunchResult {
+        let _permit = self.limiter.acquire().await;
+        if let Some(script) = &self.startup_script {
+            run_startup_script(script, self.request.kind, &self.request.venv)
+                .await
+                .map_err(|e| LaunchError::Start(e.into()))?;
+        }
+        let mut backend = self.spawn().await.map_err(LaunchError::Spawn)?;
+        backend.set_strict_json(self.strict_json);
+        let init_response = self
+            .handshake
+            .run(&mut backend)
+            .await
+            .map_err(LaunchError::Initialize)?;
+        Ok((backend, init_response))
+    }
+
+    /// Spawn the backend, trying again after each of `SPAWN_RETRY_DELAYS`
+    /// while it fails. Runs off the main loop, so the waits stall nobody.
+    async fn spawn(&self) -> Result<LspBackend, BackendError> {
+        let mut delays = SPAWN_RETRY_DELAYS.iter();
+        loop {
+            let error = match (self.spawner)(self.request.clone()).await {
+                Ok(backend) => return Ok(backend),
+                Err(e) => e,
+            };
+            let Some(&delay) = delays.next() else {
+                return Err(error);
+            };
+            tracing::warn!(
+                venv = %self.request.venv.display(),
+                error = %error,
+                retry_in_ms = delay.as_millis() as u64,
+                "Failed to spawn backend, retrying"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// A background launch that finished
+pub(crate) struct Launched {
+    pub venv: PathBuf,
+    pub result: LaunchResult,
+    /// The configuration the handshake sent, to catch changes made meanwhile
+    pub configuration: Option<RpcMessage>,
+}
+
+/// Launches running in the background, by venv
+pub(crate) struct Launches {
+    tx: mpsc::UnboundedSender<Launched>,
+    rx: mpsc::UnboundedReceiver<Launched>,
+    in_flight: HashMap<PathBuf, JoinHandle<()>>,
+    /// Why the latest launches failed, for the client message that waited on
+    /// one (cleared when the next message arrives)
+    failures: HashMap<PathBuf, ProxyError>,
+}
+
+impl Launches {
+    pub(crate) fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx,
+            in_flight: HashMap::new(),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Number of launches not finished yet
+    pub(crate) fn len(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Whether a launch for `venv` is running
+    pub(crate) fn contains(&self, venv: &Path) -> bool {
+        self.in_flight.contains_key(venv)
+    }
+
+    fn start(&mut self, venv: PathBuf, launch: Launch) {
+        let tx = self.tx.clone();
+        let task_venv = venv.clone();
+        let configuration = launch.handshake.configuration().cloned();
+        let task = tokio::spawn(async move {
+            let result = launch.run().await;
+            let _ = tx.send(Launched {
+                venv: task_venv,
+                result,
+                configuration,
+            });
+        });
+        self.in_flight.insert(venv, task);
+    }
+
+    /// Wait for the next launch to finish
+    pub(crate) async fn next(&mut self) -> Launched {
+        // `self.tx` keeps the channel open
+        let launched = self.rx.recv().await.expect("launch channel closed");
+        self.in_flight.remove(&launched.venv);
+        launched
+    }
+
+    /// The failure of the last launch for `venv`, if it failed
+    pub(crate) fn take_failure(&mut self, venv: &Path) -> Option<ProxyError> {
+        self.failures.remove(venv)
+    }
+
+    /// Forget launch failures once no waiting message can see them
+    pub(crate) fn clear_failures(&mut self) {
+        self.failures.clear();
+    }
+
+    /// Abandon every running launch; a half-started backend is killed on drop
+    pub(crate) fn abort_all(&mut self) {
+        for (_, task) in self.in_flight.drain() {
+            task.abort();
+        }
+    }
+}
+
+impl LspProxy {
+    /// Everything needed to launch a backend for `venv`, or why none may be
+    /// launched now.
+    pub(crate) fn prepare_launch(&self, venv: &Path) -> Result<Launch, ProxyError> {
+        self.check_spawnable(venv)?;
+        tracing::info!(venv = %venv.display(), "Creating new backend instance");
+        Ok(Launch {
+            request: self.spawn_request(venv),
+            spawner: self.spawner.clone(),
+            startup_script: self.state.options.backend_startup_script.clone(),
+            strict_json: self.state.options.strict_json,
+            limiter: self.state.spawn_limiter.clone(),
+            handshake: self.handshake(venv)?,
+        })
+    }
+
+    /// Record the outcome of a launch (spawn backoff, metrics, initialize
+    /// timeout action) and hand back the backend when it succeeded.
+    pub(crate) fn finish_launch(
+        &mut self,
+        venv: &Path,
+        result: LaunchResult,
+    ) -> Result<LspBackend, ProxyError> {
+        match result {
+            Ok((backend, init_response)) => {
+                self.record_spawn_result(venv, None);
+                self.record_initialize_result(venv, Ok(&init_response));
+                Ok(backend)
+            }
+            Err(LaunchError::Start(e)) => Err(e),
+            Err(LaunchError::Spawn(e)) => {
+                self.record_spawn_result(venv, Some(&e));
+                Err(e.into())
+            }
+            Err(LaunchError::Initialize(e)) => {
+                self.record_spawn_result(venv, None);
+                self.record_initialize_result(venv, Err(&e));
+                Err(e)
+            }
+        }
+    }
+
+    /// Whether another backend fits under `--max-backends`, counting
+    /// launches still running
+    pub(crate) fn has_room_for_launch(&self) -> bool {
+        self.state.pool.len() + self.launches.len() < self.state.pool.max_backends()
+    }
+
+    /// Start launching a backend for `venv` in the background
+    pub(crate) fn start_launch(&mut self, venv: PathBuf) -> Result<(), ProxyError> {
+        let launch = self.prepare_launch(&venv)?;
+        self.launches.start(venv, launch);
+        Ok(())
+    }
+
+    /// If `msg` needs a backend for a venv that isn't pooled yet, start
+    /// launching it in the background (evicting the LRU backend when the pool
+    /// is full) and return the venv, so the message can wait for the launch
+    /// instead of the main loop. Launches already running are waited for too.
+    pub(crate) async fn launch_for_message(
+        &mut self,
+        msg: &RpcMessage,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<Option<PathBuf>, ProxyError> {
+        let Some(venv) = self.launch_target(msg).await else {
+            return Ok(None);
+        };
+        if self.launches.contains(&venv) {
+            return Ok(Some(venv));
+        }
+        // The dispatch path reports why no backend can be created
+        if self.check_launchable(&venv).is_err() {
+            return Ok(None);
+        }
+        if !self.has_room_for_launch() {
+            self.evict_lru_backend(client_writer).await?;
+            if !self.has_room_for_launch() {
+                return Ok(None);
+            }
+        }
+        match self.start_launch(venv.clone()) {
+            Ok(()) => Ok(Some(venv)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The venv whose backend a didOpen or document request will be routed
+    /// to, when that backend isn't in the pool
+    async fn launch_target(&mut self, msg: &RpcMessage) -> Option<PathBuf> {
+        let method = msg.method_name()?;
+        if method != "textDocument/didOpen"
+            && !(msg.is_request() && method.starts_with("textDocument/"))
+        {
+            return None;
+        }
+        let url = Self::extract_text_document_uri(msg)?;
+        let cached = self
+            .state
+            .open_documents
+            .get(&url)
+            .and_then(|doc| doc.venv.clone());
+        let venv = match cached {
+            Some(venv) => venv,
+            None => self.find_venv(&url.to_file_path().ok()?).await.ok()??,
+        };
+        (!self.state.pool.contains(&venv)).then_some(venv)
+    }
+
+    /// Install the backend of a finished launch into the pool. A backend a
+    /// client message is `awaited` by may evict the LRU backend to fit;
+    /// otherwise it is discarded when the pool filled up meanwhile.
+    pub(crate) async fn handle_launched(
+        &mut self,
+        launched: Launched,
+        awaited: bool,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let Launched {
+            venv,
+            result,
+            configuration,
+        } = launched;
+        let mut backend = match self.finish_launch(&venv, result) {
+            Ok(backend) => backend,
+            Err(e) => {
+                tracing::warn!(venv = %venv.display(), error = %e, "Failed to launch backend");
+                self.launches.failures.insert(venv.clone(), e);
+                self.prewarm_launch_finished(&venv, false);
+                return Ok(());
+            }
+        };
+
+        if awaited && !self.state.pool.contains(&venv) && self.state.pool.is_full() {
+            self.evict_lru_backend(client_writer).await?;
+        }
+        // A request created a backend for this venv, or filled the pool,
+        // while the launch was running
+        if self.state.pool.contains(&venv) || self.state.pool.is_full() {
+            tracing::info!(venv = %venv.display(), "Pool changed during launch, discarding backend");
+            let parts = backend.into_split();
+            shutdown_fire_and_forget(
+                parts.writer,
+                parts.child,
+                parts.next_id,
+                venv.display().to_string(),
+                self.state.options.backend_kill_signal,
+            );
+            self.prewarm_launch_finished(&venv, false);
+            return Ok(());
+        }
+
+        self.send_configuration_changed_during_launch(&mut backend, &venv, configuration.as_ref())
+            .await?;
+        let instance = self
+            .install_launched_backend(backend, &venv, client_writer)
+            .await?;
+        self.state.pool.insert(venv.clone(), instance);
+        self.preindex_project_files(&venv).await;
+        self.prewarm_launch_finished(&venv, true);
+        Ok(())
+    }
+}
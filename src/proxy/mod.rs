@@ -1,24 +1,57 @@
 mod backend_dispatch;
+mod circuit_breaker;
 mod client_dispatch;
 mod diagnostics;
 mod document;
 mod fanout;
+mod file_operations;
+mod hover;
 mod initialization;
+mod launch;
 mod pool_management;
+mod preindex;
+mod prewarm;
+mod progress;
+mod rate_limit;
+mod registration;
+mod snapshot;
+mod tap;
+mod termination;
+mod transport;
 
-use crate::backend::{BackendKind, LspBackend};
-use crate::error::ProxyError;
-use crate::framing::{LspFrameReader, LspFrameWriter};
-use crate::state::ProxyState;
+use crate::backend::{default_spawner, BackendKind, BackendSpawner, LspBackend};
+use crate::error::{BackendError, FramingError, ProxyError};
+use crate::framing::{Incoming, LspFrameReader, LspFrameWriter};
+use crate::message::RpcMessage;
+use crate::metrics;
+use crate::state::{ProxyOptions, ProxyState};
 use crate::venv;
+use document::DidChange;
+use snapshot::SnapshotSignal;
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::time::Duration;
+use termination::TerminationSignal;
 use tokio::io::{stdin, stdout};
+use tokio::net::TcpListener;
 use tokio::time::MissedTickBehavior;
+use transport::{accept_client, ClientOutput, ClientReader, ReplayReader};
+
+pub use circuit_breaker::CrashBreaker;
+pub use rate_limit::TokenBucket;
+#[cfg(unix)]
+pub use snapshot::install_snapshot_signal;
+pub use snapshot::{bind_status_socket, query_status};
+pub use tap::{ResponseTap, ResponseTapKind};
 
 pub struct LspProxy {
     state: ProxyState,
     backend_ttl: Option<Duration>,
+    snapshot_signal: Option<SnapshotSignal>,
+    response_taps: Vec<Box<dyn ResponseTap>>,
+    spawner: BackendSpawner,
+    launches: launch::Launches,
+    prewarm_run: Option<prewarm::PrewarmRun>,
 }
 
 impl LspProxy {
@@ -26,16 +59,56 @@ impl LspProxy {
         backend_kind: BackendKind,
         max_backends: usize,
         backend_ttl: Option<Duration>,
+        options: ProxyOptions,
     ) -> Self {
         Self {
-            state: ProxyState::new(backend_kind, max_backends, backend_ttl),
+            state: ProxyState::new(backend_kind, max_backends, backend_ttl, options),
             backend_ttl,
+            snapshot_signal: None,
+            response_taps: Vec::new(),
+            spawner: default_spawner(),
+            launches: launch::Launches::new(),
+            prewarm_run: None,
         }
     }
 
+    /// Replace how backend processes are started
+    #[cfg(test)]
+    pub(crate) fn set_spawner(&mut self, spawner: BackendSpawner) {
+        self.spawner = spawner;
+    }
+
+    /// Dump state to the log whenever `signal` fires (`--pool-snapshot-on-signal`)
+    #[cfg(unix)]
+    pub fn set_snapshot_signal(&mut self, signal: SnapshotSignal) {
+        self.snapshot_signal = Some(signal);
+    }
+
+    /// Prefix a client-facing message with the proxy name (`--proxy-name`)
+    pub(crate) fn client_message(&self, text: impl std::fmt::Display) -> String {
+        format!("{}: {}", self.state.options.proxy_name, text)
+    }
+
     pub async fn run(&mut self) -> Result<(), ProxyError> {
-        let mut client_reader = LspFrameReader::new(stdin());
-        let mut client_writer = LspFrameWriter::new(stdout());
+        let (mut client_reader, client_output) =
+            match (&self.state.options.replay, &self.state.options.listen) {
+                (Some(path), _) => (
+                    ClientReader::Replay(ReplayReader::from_file(path).await?),
+                    ClientOutput::Stdio(stdout()),
+                ),
+                (None, Some(addr)) => {
+                    let (read_half, write_half) = accept_client(addr).await?;
+                    let mut reader = LspFrameReader::new(read_half);
+                    reader.set_strict_json(self.state.options.strict_json);
+                    (ClientReader::Tcp(reader), ClientOutput::Tcp(write_half))
+                }
+                (None, None) => {
+                    let mut reader = LspFrameReader::new(stdin());
+                    reader.set_strict_json(self.state.options.strict_json);
+                    (ClientReader::Stdio(reader), ClientOutput::Stdio(stdout()))
+                }
+            };
+        let mut client_writer = LspFrameWriter::new(client_output);
 
         let cwd = std::env::current_dir()?;
         tracing::info!(
@@ -50,7 +123,13 @@ impl LspProxy {
         self.state.git_toplevel = venv::get_git_toplevel(&cwd).await?;
 
         // Search for fallback venv
-        let fallback_venv = venv::find_fallback_venv(&cwd).await?;
+        let fallback_venv = venv::find_fallback_venv(
+            &cwd,
+            &self.state.options.venv_search.venv_dirs,
+            self.state.options.venv_search.prefer_python.as_deref(),
+        )
+        .await?
+        .map(|found| self.state.remember_project(found));
 
         // Pre-spawn backend if fallback venv found (but don't insert into pool yet —
         // wait for client's `initialize` to complete the handshake first)
@@ -58,14 +137,81 @@ impl LspProxy {
             fallback_venv
         {
             tracing::info!(venv = %venv.display(), "Using fallback .venv, pre-spawning backend");
-            let backend = LspBackend::spawn(self.state.backend_kind, Some(&venv)).await?;
-            Some((backend, venv))
+            match self.spawn_backend(&venv).await {
+                Ok(backend) => Some((backend, venv)),
+                Err(ProxyError::Backend(
+                    e @ (BackendError::StartupScriptFailed(_) | BackendError::NoInterpreter(_)),
+                )) => {
+                    tracing::error!(error = %e, "Not pre-spawning fallback backend");
+                    None
+                }
+                Err(e) => return Err(e),
+            }
         } else {
             tracing::warn!("No fallback .venv found, starting with empty pool");
             None
         };
 
+        // Optional Prometheus scrape endpoint
+        let metrics_listener = match &self.state.options.metrics_addr {
+            Some(addr) => {
+                let listener = TcpListener::bind(addr).await?;
+                tracing::info!(addr = %listener.local_addr()?, "Serving metrics");
+                Some(listener)
+            }
+            None => None,
+        };
+
+        // Optional live introspection socket
+        let status_listener = match &self.state.options.status_socket {
+            Some(path) => {
+                let listener = bind_status_socket(path)?;
+                tracing::info!(path = %path.display(), "Serving status");
+                Some(listener)
+            }
+            None => None,
+        };
+        // However run() returns, the socket file goes with the listener
+        let _status_socket_file = self
+            .state
+            .options
+            .status_socket
+            .clone()
+            .map(snapshot::StatusSocketFile);
+
+        // Headless mode: initialize the pre-spawned backend right away
+        if let Some(path) = self.state.options.synthetic_initialize.clone() {
+            self.load_synthetic_initialize(&path).await?;
+            if let Some((backend, venv)) = pending_initial_backend.take() {
+                if let Err(e) = self
+                    .install_initial_backend(backend, venv, &mut client_writer)
+                    .await
+                {
+                    tracing::error!(error = ?e, "Failed to initialize fallback backend with synthetic params");
+                }
+            }
+        }
+
         let mut didopen_count = 0;
+        // `--prewarm` venv scan, started once the client is initialized
+        let mut prewarm_scan: Option<prewarm::PrewarmScan> = None;
+        // Batch elements not dispatched yet
+        let mut batch_queue = VecDeque::new();
+        // Client message held (with client reading paused) until the backend
+        // launch it needs finishes, then dispatched before anything else
+        let mut awaiting_launch: Option<(PathBuf, RpcMessage)> = None;
+        let mut resumed: Option<RpcMessage> = None;
+        // `--transparent-retry` request taking the client message path again
+        let mut reissued: Option<RpcMessage> = None;
+        let mut snapshot_signal = self.snapshot_signal.take();
+        let mut termination_signal = TerminationSignal::install()?;
+
+        // Hard lifetime limit (--exit-after), unaffected by activity
+        let exit_deadline = self
+            .state
+            .options
+            .exit_after
+            .map(|lifetime| tokio::time::Instant::now() + lifetime);
 
         // TTL sweep timer: checks every 60 seconds for expired backends
         let mut ttl_interval = tokio::time::interval(std::time::Duration::from_secs(60));
@@ -73,15 +219,105 @@ impl LspProxy {
         // Consume the first immediate tick so the first real tick fires after 60s
         ttl_interval.tick().await;
 
+        // Periodic one-line pool summary (--pool-metrics-log-interval)
+        let mut metrics_log_interval = self.state.options.pool_metrics_log_interval.map(|period| {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
+        // Request timeout sweep (--request-timeout), once per second
+        let mut request_timeout_interval = self.state.options.request_timeout.map(|_| {
+            let period = std::time::Duration::from_secs(1);
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
         loop {
             // Compute deadlines before entering select! to avoid borrow conflicts
             let warmup_deadline = self.state.pool.nearest_warmup_deadline();
             let fanout_deadline = self.state.nearest_fanout_deadline();
+            let hover_deadline = self.state.nearest_hover_deadline();
+            let progress_deadline = self.state.nearest_progress_deadline();
+            let empty_eviction_deadline = self.state.empty_eviction_deadline();
+            let did_change_deadline = self.state.nearest_did_change_deadline();
+            let preindex_venv = self.state.pool.next_preindex_venv();
+            if reissued.is_none() {
+                reissued = self.state.reissued_requests.pop_front();
+            }
 
             tokio::select! {
                 // Messages from client
-                result = client_reader.read_message() => {
-                    let msg = result?;
+                // Elements of a batch go through the same dispatch one by one
+                (result, is_resumed) = async {
+                    if let Some(msg) = resumed.take() {
+                        return (Ok(Incoming::Single(msg)), true);
+                    }
+                    let result = match reissued.take().or_else(|| batch_queue.pop_front()) {
+                        Some(msg) => Ok(Incoming::Single(msg)),
+                        None => client_reader.read_message().await,
+                    };
+                    (result, false)
+                }, if awaiting_launch.is_none() => {
+                    let msg = match result {
+                        Ok(Incoming::Single(msg)) => msg,
+                        Ok(Incoming::Batch(elements)) => {
+                            tracing::debug!(size = elements.len(), "Client sent a batch");
+                            let mut messages = Vec::new();
+                            let mut invalid = Vec::new();
+                            for element in elements {
+                                match element {
+                                    Ok(msg) => messages.push(msg),
+                                    Err(FramingError::Json { source, id }) => {
+                                        tracing::error!(error = %source, id = ?id, "Skipping malformed batch element");
+                                        invalid.push(RpcMessage::invalid_request_response(
+                                            id,
+                                            &self.client_message(format!("invalid request: {source}")),
+                                        ));
+                                    }
+                                    Err(e) => return Err(e.into()),
+                                }
+                            }
+                            client_writer.begin_batch(&mut messages, invalid).await?;
+                            batch_queue.extend(messages);
+                            continue;
+                        }
+                        Err(FramingError::InvalidBatch(reason)) => {
+                            tracing::error!(reason = %reason, "Rejecting invalid client batch");
+                            let response = RpcMessage::invalid_request_response(
+                                None,
+                                &self.client_message(format!("invalid batch: {reason}")),
+                            );
+                            client_writer.write_value(&response).await?;
+                            continue;
+                        }
+                        // One bad body must not take every backend down with it
+                        Err(FramingError::Json { source, id }) => {
+                            tracing::error!(error = %source, id = ?id, "Skipping malformed client message");
+                            if let Some(id) = id {
+                                let response = RpcMessage::parse_error_response(
+                                    id,
+                                    &self.client_message(format!("parse error: {source}")),
+                                );
+                                client_writer.write_message(&response).await?;
+                            }
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
+
+                    // A new backend is launched off the loop; backends already
+                    // running keep being served while this message waits
+                    if !is_resumed {
+                        self.launches.clear_failures();
+                        if let Some(venv) = self.launch_for_message(&msg, &mut client_writer).await? {
+                            awaiting_launch = Some((venv, msg));
+                            continue;
+                        }
+                    }
                     let method = msg.method_name();
 
                     tracing::debug!(
@@ -90,6 +326,31 @@ impl LspProxy {
                         is_notification = msg.is_notification(),
                         "Client -> Proxy"
                     );
+                    let is_reissued = msg
+                        .id
+                        .as_ref()
+                        .is_some_and(|id| self.state.reissued_ids.contains(id));
+                    if msg.is_request() && !is_reissued {
+                        self.state.metrics.record_request(method.unwrap_or_default());
+                        if let Some(response) = self.check_request_rate(&msg) {
+                            client_writer.write_message(&response).await?;
+                            continue;
+                        }
+                    }
+
+                    // Anything else about a debounced document sees its latest text;
+                    // requests that aren't about one document may read any of them
+                    if !self.state.debounced_did_changes.is_empty()
+                        && method != Some("textDocument/didChange")
+                    {
+                        if let Some(url) = Self::extract_text_document_uri(&msg) {
+                            self.flush_did_change(&url).await?;
+                        } else if msg.is_request()
+                            && !method.is_some_and(|m| m.starts_with("textDocument/"))
+                        {
+                            self.flush_all_did_changes().await?;
+                        }
+                    }
 
                     // Dispatch based on method, preserving original if-chain order
                     match method {
@@ -98,38 +359,57 @@ impl LspProxy {
                         }
                         Some("initialized") => {
                             self.dispatch_initialized().await?;
+                            prewarm_scan = self.start_prewarm_scan();
                         }
                         Some("shutdown") => {
                             self.dispatch_shutdown(&msg, &mut client_writer).await?;
                         }
                         Some("exit") => {
                             tracing::info!("Received exit notification, terminating proxy");
+                            // Backends left by an exit without a prior shutdown
+                            self.shutdown_all_backends_and_wait().await;
                             return Ok(());
                         }
-                        _ if msg.is_response() => {
-                            if self.dispatch_client_response(&msg).await? {
-                                continue;
-                            }
-                            // Fall through: not a pending backend request
-                            // (original code fell through to didOpen check etc.)
+                        // Not a pending backend request: fall through to the remaining arms
+                        _ if msg.is_response() && self.dispatch_client_response(&msg).await? => {
+                            continue;
                         }
                         Some("textDocument/didOpen") => {
                             didopen_count += 1;
                             self.handle_did_open(&msg, didopen_count, &mut client_writer).await?;
                         }
                         Some("textDocument/didChange") => {
-                            self.handle_did_change(&msg).await?;
+                            if let Some(open) = self.did_change_as_open(&msg) {
+                                didopen_count += 1;
+                                self.handle_did_open(&open, didopen_count, &mut client_writer).await?;
+                                continue;
+                            }
+                            match self.handle_did_change(&msg).await? {
+                                DidChange::Forward => {}
+                                DidChange::Drop => continue,
+                                DidChange::Reject(reason) => {
+                                    self.notify_client_error(&reason, &mut client_writer).await;
+                                    continue;
+                                }
+                            }
+                            if self.debounce_did_change(&msg) {
+                                continue;
+                            }
+                            let full_sync = self.full_sync_did_change(&msg);
+                            let forwarded = full_sync.as_ref().unwrap_or(&msg);
                             // Forward to appropriate backend
                             if let Some(url) = Self::extract_text_document_uri(&msg) {
                                 if let Some(venv_path) = self.venv_for_uri(&url) {
-                                    self.forward_to_backend(&venv_path, &msg).await?;
+                                    self.forward_to_backend(&venv_path, forwarded).await?;
+                                    self.mark_document_forwarded(&url, &venv_path);
                                 }
                             }
                         }
                         Some("textDocument/didClose") => {
                             // Get venv before removing from cache
-                            let venv_for_close = Self::extract_text_document_uri(&msg)
-                                .and_then(|url| self.venv_for_uri(&url));
+                            let closed_uri = Self::extract_text_document_uri(&msg);
+                            let venv_for_close =
+                                closed_uri.as_ref().and_then(|url| self.venv_for_uri(url));
 
                             self.handle_did_close(&msg).await?;
 
@@ -137,11 +417,37 @@ impl LspProxy {
                             if let Some(venv_path) = venv_for_close {
                                 self.forward_to_backend(&venv_path, &msg).await?;
                             }
+
+                            if let Some(url) = closed_uri.filter(|_| self.state.options.clear_diagnostics_on_close) {
+                                self.clear_diagnostics_for_uris(&[url], &mut client_writer).await;
+                            }
+                        }
+                        Some("textDocument/didSave") => {
+                            self.handle_did_save(&msg).await?;
+                        }
+                        Some("workspace/willRenameFiles") => {
+                            self.dispatch_will_rename_files(&msg, &mut client_writer).await?;
+                        }
+                        Some("workspace/didRenameFiles") => {
+                            self.dispatch_did_rename_files(&msg).await?;
+                        }
+                        Some("workspace/didChangeConfiguration") => {
+                            self.dispatch_did_change_configuration(&msg).await?;
                         }
                         Some("$/cancelRequest") => {
                             self.dispatch_cancel_request(&msg, &mut client_writer).await?;
                         }
+                        Some("window/workDoneProgress/cancel") => {
+                            match self.route_work_done_cancel(&msg) {
+                                Some((venv_path, cancel)) => {
+                                    self.forward_to_backend(&venv_path, &cancel).await?;
+                                }
+                                None => self.dispatch_client_notification(&msg).await?,
+                            }
+                        }
                         _ if msg.is_request() => {
+                            let mut msg = msg;
+                            self.remap_partial_result_token(&mut msg);
                             self.dispatch_client_request(&msg, &mut client_writer).await?;
                         }
                         _ if msg.is_notification() => {
@@ -156,9 +462,56 @@ impl LspProxy {
                     self.dispatch_backend_message(backend_msg, &mut client_writer).await?;
                 }
 
-                // TTL-based auto-eviction sweep
-                _ = ttl_interval.tick(), if self.backend_ttl.is_some() => {
+                // `--preindex-open-files`: the next batch of a backend's project
+                // files, taking turns with the other branches
+                _ = std::future::ready(()), if preindex_venv.is_some() => {
+                    if let Some(venv) = &preindex_venv {
+                        self.preindex_batch(venv).await;
+                    }
+                }
+
+                // A background backend launch finished
+                launched = self.launches.next() => {
+                    let awaited = awaiting_launch
+                        .as_ref()
+                        .is_some_and(|(venv, _)| *venv == launched.venv);
+                    self.handle_launched(launched, awaited, &mut client_writer).await?;
+                    if awaited {
+                        resumed = awaiting_launch.take().map(|(_, msg)| msg);
+                    }
+                }
+
+                // `--prewarm` venv scan finished: launch what it found
+                result = async {
+                    match &mut prewarm_scan {
+                        Some(scan) => scan.await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    prewarm_scan = None;
+                    match result {
+                        Ok((root, venvs)) => self.prewarm_workspace_venvs(root, venvs),
+                        Err(e) => tracing::warn!(error = %e, "Prewarm venv scan failed"),
+                    }
+                }
+
+                // TTL-based auto-eviction sweep (also shrinks an auto-scaled cap)
+                // and periodic document sync verification
+                _ = ttl_interval.tick(), if self.backend_ttl.is_some()
+                    || self.state.pool.autoscale_enabled()
+                    || self.state.options.document_sync_verify => {
                     self.evict_expired_backends(&mut client_writer).await?;
+                    self.verify_document_sync();
+                }
+
+                // Request timeout: cancel requests the backend never answered
+                _ = async {
+                    match &mut request_timeout_interval {
+                        Some(interval) => interval.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    self.expire_timed_out_requests(&mut client_writer).await?;
                 }
 
                 // Warmup timeout: fail-open transition for warming backends
@@ -171,6 +524,107 @@ impl LspProxy {
                     self.expire_warmup_backends(&mut client_writer).await?;
                 }
 
+                // Hover timeout: answer from the cached document (--hover-timeout)
+                _ = async {
+                    match hover_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    self.expire_hover_requests(&mut client_writer).await?;
+                }
+
+                // Progress token TTL: close indicators the backend never ended
+                _ = async {
+                    match progress_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    self.reap_stale_progress(&mut client_writer).await?;
+                }
+
+                // Debounced didChanges: forward documents that went quiet
+                _ = async {
+                    match did_change_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    self.flush_expired_did_changes().await?;
+                }
+
+                // Last document closed: evict idle backends after the grace period
+                _ = async {
+                    match empty_eviction_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    self.evict_on_empty(&mut client_writer).await?;
+                }
+
+                // Periodic pool summary in the log
+                _ = async {
+                    match &mut metrics_log_interval {
+                        Some(interval) => interval.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    self.state.metrics.log_summary(&self.state.gauges());
+                }
+
+                // SIGUSR1: dump state to the log (--pool-snapshot-on-signal)
+                _ = snapshot::next_snapshot_request(&mut snapshot_signal) => {
+                    self.log_state_snapshot();
+                }
+
+                // Metrics scrape: render in-loop, write the response off-loop
+                Ok((stream, _)) = async {
+                    match &metrics_listener {
+                        Some(listener) => listener.accept().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let body = self.state.metrics.render_prometheus(&self.state.gauges());
+                    tokio::spawn(metrics::serve_scrape(stream, body));
+                }
+
+                // Status query: snapshot in-loop, write the response off-loop
+                stream = snapshot::next_status_connection(&status_listener) => {
+                    let body = self.status_json().to_string().into_bytes();
+                    tokio::spawn(snapshot::serve_status(stream, body));
+                }
+
+                // Lifetime limit: shut every backend down, then stop
+                _ = async {
+                    match exit_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    tracing::info!(
+                        exit_after_secs = self.state.options.exit_after.map(|d| d.as_secs()),
+                        "Lifetime limit reached, shutting down proxy"
+                    );
+                    self.shutdown_all_backends_and_wait().await;
+                    return Ok(());
+                }
+
+                // SIGTERM/SIGINT: cancel in-flight requests, shut backends down, then stop
+                signal = termination_signal.recv() => {
+                    tracing::info!(signal = signal, "Termination signal received, draining backends");
+                    let held = awaiting_launch
+                        .take()
+                        .map(|(_, msg)| msg)
+                        .into_iter()
+                        .chain(resumed.take())
+                        .chain(reissued.take())
+                        .chain(batch_queue.drain(..));
+                    self.drain_for_termination(held, &mut client_writer).await?;
+                    return Ok(());
+                }
+
                 // Fan-out timeout: return partial results for timed-out fan-out requests
                 _ = async {
                     match fanout_deadline {
@@ -1,5 +1,7 @@
+use super::client_dispatch::TRANSPARENT_RETRY_METHODS;
+use super::transport::ClientOutput;
 use crate::backend_pool::{shutdown_backend_instance, BackendInstance};
-use crate::error::ProxyError;
+use crate::error::{BackendError, ProxyError, VenvError};
 use crate::framing::LspFrameWriter;
 use crate::message::{RpcId, RpcMessage};
 use crate::venv;
@@ -12,7 +14,7 @@ impl super::LspProxy {
         &mut self,
         url: &url::Url,
         file_path: &Path,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<Option<PathBuf>, ProxyError> {
         // Get venv from cache (clone to avoid borrow conflict with later get_mut)
         let cached_venv = self
@@ -26,18 +28,22 @@ impl super::LspProxy {
             Some(None) => {
                 // venv was not found when the document was opened.
                 // Re-search in case .venv was created after didOpen.
-                let found = venv::find_venv(file_path, self.state.git_toplevel.as_deref()).await?;
+                let found = self.find_venv(file_path).await?;
                 if let Some(ref venv_path) = found {
                     if let Some(doc) = self.state.open_documents.get_mut(url) {
                         doc.venv = Some(venv_path.clone());
                     }
+                    // The tree changed; older misses may be wrong now too
+                    if let Some(cache) = &mut self.state.venv_negative_cache {
+                        cache.clear();
+                    }
                     tracing::info!(uri = %url, venv = %venv_path.display(), "venv discovered after didOpen, cache updated");
                 }
                 found
             }
             None => {
                 tracing::debug!(uri = %url, "URI not in cache, searching venv");
-                venv::find_venv(file_path, self.state.git_toplevel.as_deref()).await?
+                self.find_venv(file_path).await?
             }
         };
 
@@ -51,9 +57,22 @@ impl super::LspProxy {
             return Ok(Some(target_venv));
         }
 
+        // The launch this message waited for failed; don't retry it inline
+        if let Some(e) = self.launches.take_failure(&target_venv) {
+            return Err(e);
+        }
+
+        self.check_launchable(&target_venv)?;
+
         // Need to create a new backend. Evict if full.
         if self.state.pool.is_full() {
             self.evict_lru_backend(client_writer).await?;
+            // Nothing was evictable
+            if self.state.pool.is_full() {
+                return Err(ProxyError::Backend(BackendError::PoolPinned(
+                    target_venv.display().to_string(),
+                )));
+            }
         }
 
         // Create backend instance
@@ -61,14 +80,60 @@ impl super::LspProxy {
             .create_backend_instance(&target_venv, client_writer)
             .await?;
         self.state.pool.insert(target_venv.clone(), instance);
+        self.preindex_project_files(&target_venv).await;
 
         Ok(Some(target_venv))
     }
 
+    /// Refuse a new backend for a venv behind an open circuit breaker or
+    /// disabled after an initialize timeout
+    pub(crate) fn check_launchable(&mut self, venv: &Path) -> Result<(), ProxyError> {
+        if let Some(breaker) = &mut self.state.crash_breaker {
+            if breaker.is_open(venv, tokio::time::Instant::now()) {
+                return Err(ProxyError::Backend(BackendError::CircuitOpen(
+                    venv.display().to_string(),
+                )));
+            }
+        }
+
+        if self.state.is_disabled(venv, tokio::time::Instant::now()) {
+            return Err(ProxyError::Backend(BackendError::Disabled(
+                venv.display().to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Search the venv for `file_path`, consulting the negative cache when
+    /// enabled, and remember the project of a venv kept outside it
+    pub(crate) async fn find_venv(
+        &mut self,
+        file_path: &Path,
+    ) -> Result<Option<PathBuf>, VenvError> {
+        let toplevel = self.state.git_toplevel.as_deref();
+        let search = &self.state.options.venv_search;
+        let poetry_envs = &mut self.state.poetry_envs;
+        let found = match &mut self.state.venv_negative_cache {
+            Some(cache) => {
+                cache
+                    .find_venv(
+                        file_path,
+                        toplevel,
+                        search,
+                        poetry_envs,
+                        tokio::time::Instant::now(),
+                    )
+                    .await?
+            }
+            None => venv::find_venv(file_path, toplevel, search, poetry_envs).await?,
+        };
+        Ok(found.map(|found| self.state.remember_project(found)))
+    }
+
     /// Evict the LRU backend from the pool
     pub(crate) async fn evict_lru_backend(
         &mut self,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let pending_requests = &self.state.pending_requests;
         let lru_venv = self.state.pool.lru_venv(|venv, session| {
@@ -86,7 +151,23 @@ impl super::LspProxy {
             );
 
             if let Some(instance) = self.state.pool.remove(&venv_to_evict) {
+                self.state.metrics.record_eviction("lru");
+                self.state.pool.record_lru_eviction(&venv_to_evict);
                 let evict_session = instance.session;
+
+                // Every backend was busy: the victim's requests will never be
+                // answered. The cleanup below fails them with RequestCancelled.
+                let (client_pending, backend_pending) =
+                    self.pending_work(&venv_to_evict, evict_session);
+                if client_pending > 0 {
+                    tracing::warn!(
+                        venv = %venv_to_evict.display(),
+                        pending_requests = client_pending,
+                        pending_backend_requests = backend_pending,
+                        "Pool full of busy backends, evicting LRU backend and cancelling its pending requests"
+                    );
+                }
+
                 self.cleanup_evicted_backend(
                     instance,
                     &venv_to_evict,
@@ -105,26 +186,39 @@ impl super::LspProxy {
     /// Skips backends that have pending client→backend or backend→client requests.
     pub(crate) async fn evict_expired_backends(
         &mut self,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
+        self.state.pool.autoscale_idle_tick();
+
         let expired = self.state.pool.expired_venvs();
         if expired.is_empty() {
+            self.state.ttl_eviction_suppressed = false;
             return Ok(());
         }
 
-        for venv_path in expired {
-            let session = match self.state.pool.get(&venv_path) {
+        let min_idle = self.state.options.min_idle;
+        let mut suppressed = false;
+        for (i, venv_path) in expired.iter().enumerate() {
+            // Oldest first, so the most recently used backends are the ones kept
+            if self.state.pool.len() <= min_idle {
+                if !self.state.ttl_eviction_suppressed {
+                    tracing::info!(
+                        kept = expired.len() - i,
+                        min_idle = min_idle,
+                        pool_size = self.state.pool.len(),
+                        "TTL eviction suppressed to keep the minimum idle pool"
+                    );
+                }
+                suppressed = true;
+                break;
+            }
+            let session = match self.state.pool.get(venv_path) {
                 Some(inst) => inst.session,
                 None => continue,
             };
 
             // Skip if there are pending client→backend requests
-            let pending_count = self
-                .state
-                .pending_requests
-                .values()
-                .filter(|p| p.venv_path == venv_path && p.backend_session == session)
-                .count();
+            let (pending_count, pending_backend_count) = self.pending_work(venv_path, session);
             if pending_count > 0 {
                 tracing::debug!(
                     venv = %venv_path.display(),
@@ -135,12 +229,6 @@ impl super::LspProxy {
             }
 
             // Skip if there are pending backend→client requests
-            let pending_backend_count = self
-                .state
-                .pending_backend_requests
-                .values()
-                .filter(|p| p.venv_path == venv_path && p.session == session)
-                .count();
             if pending_backend_count > 0 {
                 tracing::debug!(
                     venv = %venv_path.display(),
@@ -156,11 +244,12 @@ impl super::LspProxy {
                 "Evicting expired backend (TTL)"
             );
 
-            if let Some(instance) = self.state.pool.remove(&venv_path) {
+            if let Some(instance) = self.state.pool.remove(venv_path) {
+                self.state.metrics.record_eviction("ttl");
                 let evict_session = instance.session;
                 self.cleanup_evicted_backend(
                     instance,
-                    &venv_path,
+                    venv_path,
                     evict_session,
                     client_writer,
                     true,
@@ -168,16 +257,81 @@ impl super::LspProxy {
                 .await?;
             }
         }
+        self.state.ttl_eviction_suppressed = suppressed;
 
         Ok(())
     }
 
+    /// Evict every backend once the document cache has stayed empty for the
+    /// `--evict-on-empty` grace period. Backends with requests in flight are
+    /// left to the regular TTL sweep.
+    pub(crate) async fn evict_on_empty(
+        &mut self,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        self.state.documents_empty_since = None;
+        if !self.state.open_documents.is_empty() {
+            return Ok(());
+        }
+
+        for venv_path in self.state.pool.backends_keys() {
+            let session = match self.state.pool.get(&venv_path) {
+                Some(inst) => inst.session,
+                None => continue,
+            };
+            if self.state.pool.is_pinned(&venv_path) {
+                continue;
+            }
+            let (pending_count, pending_backend_count) = self.pending_work(&venv_path, session);
+            if pending_count > 0 || pending_backend_count > 0 {
+                tracing::debug!(
+                    venv = %venv_path.display(),
+                    pending_count = pending_count,
+                    pending_backend_count = pending_backend_count,
+                    "Skipping empty-cache eviction: has pending requests"
+                );
+                continue;
+            }
+
+            tracing::info!(
+                venv = %venv_path.display(),
+                pool_size = self.state.pool.len(),
+                "Evicting backend (no open documents)"
+            );
+
+            if let Some(instance) = self.state.pool.remove(&venv_path) {
+                self.state.metrics.record_eviction("empty");
+                self.cleanup_evicted_backend(instance, &venv_path, session, client_writer, true)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count in-flight (client→backend, backend→client) requests for a backend session.
+    fn pending_work(&self, venv_path: &PathBuf, session: u64) -> (usize, usize) {
+        let client = self
+            .state
+            .pending_requests
+            .values()
+            .filter(|p| p.venv_path == *venv_path && p.backend_session == session)
+            .count();
+        let backend = self
+            .state
+            .pending_backend_requests
+            .values()
+            .filter(|p| p.venv_path == *venv_path && p.session == session)
+            .count();
+        (client, backend)
+    }
+
     /// Handle backend crash: remove from pool, cancel pending, clean up
     pub(crate) async fn handle_backend_crash(
         &mut self,
         venv_path: &PathBuf,
         session: u64,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         // Verify session matches (avoid double-crash handling)
         let should_remove = self
@@ -202,6 +356,15 @@ impl super::LspProxy {
         );
 
         if let Some(instance) = self.state.pool.remove(venv_path) {
+            let now = tokio::time::Instant::now();
+            let recent_crashes = self
+                .state
+                .metrics
+                .record_crash(now, self.state.options.crash_window);
+            if let Some(breaker) = &mut self.state.crash_breaker {
+                breaker.record_crash(venv_path, now);
+            }
+            let retries = self.take_transparent_retries(venv_path, session);
             // do_shutdown=false: process is already dead, just abort reader + clean up
             self.cleanup_evicted_backend(instance, venv_path, session, client_writer, false)
                 .await?;
@@ -211,11 +374,98 @@ impl super::LspProxy {
                 session = session,
                 "Backend removed from pool after crash"
             );
+
+            // Let a supervisor restart everything rather than limp along
+            if let Some(threshold) = self.state.options.crash_threshold {
+                if recent_crashes >= threshold {
+                    let window_secs = self.state.options.crash_window.as_secs();
+                    tracing::error!(
+                        crashes = recent_crashes,
+                        window_secs = window_secs,
+                        "Backend crash threshold reached, shutting down proxy"
+                    );
+                    self.shutdown_all_backends_and_wait().await;
+                    return Err(ProxyError::CrashThreshold {
+                        crashes: recent_crashes,
+                        window_secs,
+                    });
+                }
+            }
+
+            self.reissue_transparent_retries(retries);
         }
 
         Ok(())
     }
 
+    /// `--transparent-retry`: take the crashed backend's read-only requests
+    /// out of `pending_requests` so the cleanup doesn't cancel them. Requests
+    /// already retried once are left to be cancelled.
+    fn take_transparent_retries(&mut self, venv_path: &PathBuf, session: u64) -> Vec<RpcMessage> {
+        if !self.state.options.transparent_retry {
+            return Vec::new();
+        }
+        let ids: Vec<RpcId> = self
+            .state
+            .pending_requests
+            .iter()
+            .filter(|(_, p)| {
+                p.venv_path == *venv_path
+                    && p.backend_session == session
+                    && !p.retried
+                    && p.request.is_some()
+                    && TRANSPARENT_RETRY_METHODS.contains(&p.method.as_str())
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter()
+            .filter_map(|id| self.state.pending_requests.remove(id))
+            .filter_map(|p| p.request)
+            .collect()
+    }
+
+    /// Queue requests taken by `take_transparent_retries` for the main loop,
+    /// which routes them like client messages (launching the respawned
+    /// backend off the loop), keeping the client's ids
+    fn reissue_transparent_retries(&mut self, requests: Vec<RpcMessage>) {
+        for request in requests {
+            tracing::info!(
+                id = ?request.id,
+                method = ?request.method_name(),
+                "Transparently re-issuing request after backend crash"
+            );
+            if let Some(id) = &request.id {
+                self.state.reissued_ids.insert(id.clone());
+            }
+            self.state.reissued_requests.push_back(request);
+        }
+    }
+
+    /// Remove every backend from the pool and shut it down, abandoning
+    /// launches still running. Returns the shutdown tasks for callers that must wait for them.
+    pub(crate) fn shutdown_all_backends(&mut self) -> Vec<tokio::task::JoinHandle<()>> {
+        self.launches.abort_all();
+        let mut shutdowns = Vec::new();
+        for venv in self.state.pool.backends_keys() {
+            if let Some(instance) = self.state.pool.remove(&venv) {
+                tracing::info!(venv = %venv.display(), "Shutting down backend");
+                shutdowns.push(shutdown_backend_instance(
+                    instance,
+                    self.state.options.backend_kill_signal,
+                ));
+            }
+        }
+        shutdowns
+    }
+
+    /// Shut every backend down and wait until each has exited (or been killed),
+    /// so none outlives the proxy
+    pub(crate) async fn shutdown_all_backends_and_wait(&mut self) {
+        for shutdown in self.shutdown_all_backends() {
+            let _ = shutdown.await;
+        }
+    }
+
     /// Clean up after removing a backend instance from the pool.
     /// Cancels pending requests, clears diagnostics, and shuts down the process.
     /// Set `do_shutdown` to false for crashed backends (process already dead).
@@ -224,16 +474,20 @@ impl super::LspProxy {
         instance: BackendInstance,
         venv_path: &PathBuf,
         session: u64,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
         do_shutdown: bool,
     ) -> Result<(), ProxyError> {
         self.cancel_pending_requests_for_backend(client_writer, venv_path, session)
             .await?;
         self.clean_pending_backend_requests(venv_path, session);
+        self.release_timed_out_requests(venv_path, session);
+        self.release_capability_registrations(venv_path, session);
+        self.release_work_done_tokens(venv_path, session);
+        self.release_fallback_hovers(venv_path, session);
         self.clear_diagnostics_for_venv(venv_path, client_writer)
             .await;
         if do_shutdown {
-            shutdown_backend_instance(instance);
+            shutdown_backend_instance(instance, self.state.options.backend_kill_signal);
         } else {
             instance.reader_task.abort();
         }
@@ -245,7 +499,7 @@ impl super::LspProxy {
     /// completes any fanouts that have no remaining sub-requests.
     pub(crate) async fn cancel_pending_requests_for_backend(
         &mut self,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
         venv_path: &PathBuf,
         session: u64,
     ) -> Result<(), ProxyError> {
@@ -263,6 +517,8 @@ impl super::LspProxy {
 
         for id in to_cancel {
             self.state.pending_requests.remove(&id);
+            self.state.pending_hovers.remove(&id);
+            self.release_partial_result_token(&id);
             let msg = RpcMessage::cancelled_response(
                 id.clone(),
                 "Request cancelled due to backend eviction",
@@ -291,7 +547,7 @@ impl super::LspProxy {
     /// Transition all warming backends past their deadline to Ready (fail-open).
     pub(crate) async fn expire_warmup_backends(
         &mut self,
-        client_writer: &mut LspFrameWriter<tokio::io::Stdout>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
     ) -> Result<(), ProxyError> {
         let expired: Vec<PathBuf> = self
             .state
@@ -328,3 +584,66 @@ impl super::LspProxy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{BackendKind, LspBackend};
+    use crate::proxy::LspProxy;
+    use crate::state::ProxyOptions;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn ttl_eviction_keeps_most_recently_used_up_to_min_idle() {
+        let options = ProxyOptions {
+            min_idle: 2,
+            ..Default::default()
+        };
+        let ttl = Duration::from_secs(60);
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 4, Some(ttl), options);
+        let now = tokio::time::Instant::now();
+        for (session, idle_secs) in [(1, 300), (2, 100), (3, 200)] {
+            let venv = PathBuf::from(format!("/p{session}/.venv"));
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.arg("-c").arg("exec cat >/dev/null");
+            let backend = LspBackend::from_command(cmd, 8192).unwrap();
+            let mut instance = BackendInstance::from_parts(
+                backend.into_split(),
+                venv.clone(),
+                session,
+                proxy.state.pool.msg_sender(),
+                Duration::ZERO,
+            );
+            instance.last_used = now - Duration::from_secs(idle_secs);
+            proxy.state.pool.insert(venv, instance);
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut client_writer = LspFrameWriter::new(ClientOutput::Tcp(server.into_split().1));
+
+        // All three expired; only the least recently used one goes
+        proxy
+            .evict_expired_backends(&mut client_writer)
+            .await
+            .unwrap();
+        let mut kept = proxy.state.pool.backends_keys();
+        kept.sort();
+        assert_eq!(
+            kept,
+            vec![PathBuf::from("/p2/.venv"), PathBuf::from("/p3/.venv")]
+        );
+        assert!(proxy.state.ttl_eviction_suppressed);
+
+        // Later sweeps keep them too
+        proxy
+            .evict_expired_backends(&mut client_writer)
+            .await
+            .unwrap();
+        assert_eq!(proxy.state.pool.len(), 2);
+        assert!(proxy.state.ttl_eviction_suppressed);
+    }
+}
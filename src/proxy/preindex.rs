@@ -0,0 +1,346 @@
+//! `--preindex-open-files`: open a new backend's project files up front so
+//! cross-file navigation works before the editor has opened them.
+
+use super::LspProxy;
+use crate::error::ProxyError;
+use crate::message::RpcMessage;
+use crate::venv;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Upper bound on files opened per backend
+const MAX_PREINDEX_FILES: usize = 2000;
+
+/// didOpens sent per main loop turn
+const PREINDEX_BATCH: usize = 16;
+
+/// Larger files are left for the backend to discover on its own
+const MAX_PREINDEX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Directories the fallback walk (and the `--prewarm` venv scan) never descends into
+pub(super) const SKIPPED_DIRS: &[&str] = &["__pycache__", "node_modules", "site-packages"];
+
+impl LspProxy {
+    /// Queue `didOpen`s for the Python files of `venv`'s project to that
+    /// venv's backend and send the first batch. Files the client already has
+    /// open are skipped; the rest are remembered so a later client didOpen
+    /// can replace them.
+    pub(crate) async fn preindex_project_files(&mut self, venv: &PathBuf) {
+        if !self.state.options.preindex_open_files {
+            return;
+        }
+        let Some(root) = self.state.project_root(venv) else {
+            return;
+        };
+        let files = discover_python_files(
+            &root,
+            venv,
+            &self.state.options.venv_search.venv_dirs,
+            MAX_PREINDEX_FILES,
+        )
+        .await;
+
+        let Some(inst) = self.state.pool.get_mut(venv) else {
+            return;
+        };
+        inst.preindex_queue = files.into();
+        self.preindex_batch(venv).await;
+    }
+
+    /// Send the next `PREINDEX_BATCH` queued didOpens to `venv`'s backend.
+    /// The rest wait for the main loop, so a backend publishing diagnostics
+    /// while it receives them keeps being read instead of filling its
+    /// stdout and blocking the proxy on its stdin.
+    pub(crate) async fn preindex_batch(&mut self, venv: &PathBuf) {
+        let Some(inst) = self.state.pool.get_mut(venv) else {
+            return;
+        };
+        let mut sent = 0;
+        while sent < PREINDEX_BATCH {
+            let Some(path) = inst.preindex_queue.pop_front() else {
+                break;
+            };
+            let Ok(url) = url::Url::from_file_path(&path) else {
+                continue;
+            };
+            if self.state.open_documents.contains_key(&url) {
+                continue;
+            }
+            let Some(text) = read_source(&path).await else {
+                continue;
+            };
+            let didopen_msg = RpcMessage::notification(
+                "textDocument/didOpen",
+                Some(serde_json::json!({
+                    "textDocument": {
+                        "uri": url.to_string(),
+                        "languageId": "python",
+                        "version": 0,
+                        "text": text,
+                    }
+                })),
+            );
+            if let Err(e) = inst.writer.write_message(&didopen_msg).await {
+                tracing::warn!(
+                    venv = %venv.display(),
+                    error = ?e,
+                    "Failed to preindex project file, stopping"
+                );
+                inst.preindex_queue.clear();
+                break;
+            }
+            inst.preindexed.insert(url.to_string());
+            sent += 1;
+        }
+
+        if inst.preindex_queue.is_empty() {
+            tracing::info!(
+                venv = %venv.display(),
+                session = inst.session,
+                opened = inst.preindexed.len(),
+                "Preindexed project files"
+            );
+        }
+    }
+
+    /// A client didOpen for a file the proxy preindexed: close the proxy's copy
+    /// first so the backend accepts the client's version.
+    pub(crate) async fn release_preindexed(
+        &mut self,
+        url: &url::Url,
+        venv_path: &PathBuf,
+    ) -> Result<(), ProxyError> {
+        let uri = url.to_string();
+        let Some(inst) = self.state.pool.get_mut(venv_path) else {
+            return Ok(());
+        };
+        if !inst.preindexed.remove(&uri) {
+            return Ok(());
+        }
+        tracing::debug!(uri = %uri, "Client opened preindexed file, replacing proxy copy");
+        let close = RpcMessage::notification(
+            "textDocument/didClose",
+            Some(serde_json::json!({ "textDocument": { "uri": uri } })),
+        );
+        inst.writer.write_message(&close).await?;
+        Ok(())
+    }
+}
+
+/// Python files under `root` that belong to `venv`, at most `limit` of them.
+/// Prefers `git ls-files` (honours `.gitignore`) and falls back to a directory walk.
+/// The walk and the per-file venv checks run on the blocking pool.
+async fn discover_python_files(
+    root: &Path,
+    venv: &Path,
+    venv_dirs: &[String],
+    limit: usize,
+) -> Vec<PathBuf> {
+    let tracked = git_python_files(root).await;
+    let (root, venv, venv_dirs) = (root.to_path_buf(), venv.to_path_buf(), venv_dirs.to_vec());
+    tokio::task::spawn_blocking(move || {
+        let candidates = match tracked {
+            Some(files) => files,
+            None => walk_python_files(&root, &venv_dirs, limit),
+        };
+        candidates
+            .into_iter()
+            .filter(|path| belongs_to_project(&root, &venv, &venv_dirs, path))
+            .take(limit)
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Tracked and untracked-but-not-ignored `.py` files under `root`
+async fn git_python_files(root: &Path) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+            "--",
+            "*.py",
+        ])
+        .current_dir(root)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        tracing::debug!(root = %root.display(), "git ls-files failed, walking project instead");
+        return None;
+    }
+    let mut files: Vec<PathBuf> = output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| root.join(String::from_utf8_lossy(name).as_ref()))
+        .collect();
+    files.sort();
+    files.dedup();
+    Some(files)
+}
+
+/// Breadth-first walk for `.py` files, skipping hidden and cache directories
+/// and nested projects with their own venv
+fn walk_python_files(root: &Path, venv_dirs: &[String], limit: usize) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut queue = std::collections::VecDeque::from([root.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        entries.sort();
+        for path in entries {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if path.is_dir() {
+                if !name.starts_with('.')
+                    && !SKIPPED_DIRS.contains(&name)
+                    && !venv::has_venv(&path, venv_dirs)
+                {
+                    queue.push_back(path);
+                }
+            } else if name.ends_with(".py") {
+                files.push(path);
+                if files.len() >= limit {
+                    return files;
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Whether `path` resolves to `venv`: not inside the venv itself and not under
+/// a nested directory that has its own venv
+fn belongs_to_project(root: &Path, venv: &Path, venv_dirs: &[String], path: &Path) -> bool {
+    if path.starts_with(venv) {
+        return false;
+    }
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| *dir != root && dir.starts_with(root))
+        .all(|dir| !venv::has_venv(dir, venv_dirs))
+}
+
+/// File contents, or `None` for large or non-UTF-8 files
+async fn read_source(path: &Path) -> Option<String> {
+    let meta = tokio::fs::metadata(path).await.ok()?;
+    if meta.len() > MAX_PREINDEX_FILE_BYTES {
+        return None;
+    }
+    tokio::fs::read_to_string(path).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn walk_skips_venvs_hidden_dirs_and_nested_projects() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        let dirs = vec![venv::DEFAULT_VENV_DIR.to_string()];
+        touch(&root.join(".venv/pyvenv.cfg"));
+        touch(&root.join(".venv/lib/site.py"));
+        touch(&root.join("main.py"));
+        touch(&root.join("pkg/util.py"));
+        touch(&root.join("pkg/__pycache__/util.py"));
+        touch(&root.join(".tox/env.py"));
+        touch(&root.join("nested/.venv/pyvenv.cfg"));
+        touch(&root.join("nested/other.py"));
+        touch(&root.join("notes.txt"));
+
+        let files = walk_python_files(root, &dirs, 100);
+        assert_eq!(files, vec![root.join("main.py"), root.join("pkg/util.py")]);
+        assert_eq!(
+            walk_python_files(root, &dirs, 1),
+            vec![root.join("main.py")]
+        );
+    }
+
+    #[test]
+    fn belongs_to_project_excludes_venv_and_nested_projects() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        let dirs = vec![venv::DEFAULT_VENV_DIR.to_string()];
+        let venv = root.join(".venv");
+        touch(&venv.join("pyvenv.cfg"));
+        touch(&root.join("nested/.venv/pyvenv.cfg"));
+
+        assert!(belongs_to_project(root, &venv, &dirs, &root.join("a/b.py")));
+        assert!(!belongs_to_project(
+            root,
+            &venv,
+            &dirs,
+            &venv.join("lib/x.py")
+        ));
+        assert!(!belongs_to_project(
+            root,
+            &venv,
+            &dirs,
+            &root.join("nested/c.py")
+        ));
+        assert!(!belongs_to_project(
+            root,
+            &venv,
+            &dirs,
+            &root.join("nested/deep/d.py")
+        ));
+    }
+
+    #[tokio::test]
+    async fn project_files_are_opened_in_batches() {
+        use crate::backend::{BackendKind, LspBackend};
+        use crate::backend_pool::BackendInstance;
+        use crate::state::ProxyOptions;
+        use std::time::Duration;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        let venv = root.join(".venv");
+        touch(&venv.join("pyvenv.cfg"));
+        for i in 0..PREINDEX_BATCH + 4 {
+            touch(&root.join(format!("mod_{i:02}.py")));
+        }
+
+        let options = ProxyOptions {
+            preindex_open_files: true,
+            ..Default::default()
+        };
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg("exec cat >/dev/null");
+        let backend = LspBackend::from_command(cmd, 8192).unwrap();
+        let instance = BackendInstance::from_parts(
+            backend.into_split(),
+            venv.clone(),
+            1,
+            proxy.state.pool.msg_sender(),
+            Duration::ZERO,
+        );
+        proxy.state.pool.insert(venv.clone(), instance);
+
+        proxy.preindex_project_files(&venv).await;
+        let inst = proxy.state.pool.get(&venv).unwrap();
+        assert_eq!(inst.preindexed.len(), PREINDEX_BATCH);
+        assert_eq!(inst.preindex_queue.len(), 4);
+        assert_eq!(proxy.state.pool.next_preindex_venv(), Some(venv.clone()));
+
+        proxy.preindex_batch(&venv).await;
+        let inst = proxy.state.pool.get(&venv).unwrap();
+        assert_eq!(inst.preindexed.len(), PREINDEX_BATCH + 4);
+        assert_eq!(proxy.state.pool.next_preindex_venv(), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! `--prewarm`: start a backend for every venv in the workspace once the
+//! client is initialized, so the first request in each subproject doesn't
+//! pay for the spawn and handshake.
+
+use super::preindex::SKIPPED_DIRS;
+use super::LspProxy;
+use crate::venv;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use tokio::task::JoinHandle;
+
+/// Upper bound on directories visited while looking for venvs
+const MAX_SCANNED_DIRS: usize = 10_000;
+
+/// Workspace root and the venvs found under it
+pub(crate) type PrewarmScan = JoinHandle<(PathBuf, Vec<PathBuf>)>;
+
+/// A prewarm pass whose launches are still running; summarized in the log
+/// once the last one finishes
+pub(crate) struct PrewarmRun {
+    root: PathBuf,
+    found: usize,
+    pending: HashSet<PathBuf>,
+    prewarmed: usize,
+    skipped: usize,
+}
+
+impl PrewarmRun {
+    fn log_summary(&self) {
+        tracing::info!(
+            root = %self.root.display(),
+            found = self.found,
+            prewarmed = self.prewarmed,
+            skipped = self.skipped,
+            "Prewarmed workspace backends"
+        );
+    }
+}
+
+impl LspProxy {
+    /// Scan the workspace (the git toplevel, or the working directory outside
+    /// git) for venvs on a blocking thread. Returns None without `--prewarm`.
+    pub(crate) fn start_prewarm_scan(&self) -> Option<PrewarmScan> {
+        if !self.state.options.prewarm {
+            return None;
+        }
+        let root = match &self.state.git_toplevel {
+            Some(toplevel) => toplevel.clone(),
+            None => std::env::current_dir().ok()?,
+        };
+        let venv_dirs = self.state.options.venv_search.venv_dirs.clone();
+        Some(tokio::task::spawn_blocking(move || {
+            let venvs = discover_workspace_venvs(&root, &venv_dirs);
+            (root, venvs)
+        }))
+    }
+
+    /// Launch backends in the background for the scanned venvs until the pool
+    /// is full. Venvs that already have a backend (or a launch), or that are
+    /// disabled, are left alone.
+    pub(crate) fn prewarm_workspace_venvs(&mut self, root: PathBuf, venvs: Vec<PathBuf>) {
+        let mut run = PrewarmRun {
+            root,
+            found: venvs.len(),
+            pending: HashSet::new(),
+            prewarmed: 0,
+            skipped: 0,
+        };
+        for venv in venvs {
+            if self.state.pool.contains(&venv)
+                || self.launches.contains(&venv)
+                || self.state.is_disabled(&venv, tokio::time::Instant::now())
+            {
+                continue;
+            }
+            if !self.has_room_for_launch() {
+                run.skipped += 1;
+                continue;
+            }
+            match self.start_launch(venv.clone()) {
+                Ok(()) => {
+                    run.pending.insert(venv);
+                }
+                Err(e) => {
+                    tracing::warn!(venv = %venv.display(), error = %e, "Failed to prewarm backend");
+                    run.skipped += 1;
+                }
+            }
+        }
+
+        if run.pending.is_empty() {
+            run.log_summary();
+        } else {
+            self.prewarm_run = Some(run);
+        }
+    }
+
+    /// Count a finished prewarm launch, logging the summary after the last one
+    pub(crate) fn prewarm_launch_finished(&mut self, venv: &Path, installed: bool) {
+        let Some(run) = &mut self.prewarm_run else {
+            return;
+        };
+        if !run.pending.remove(venv) {
+            return;
+        }
+        if installed {
+            run.prewarmed += 1;
+        } else {
+            run.skipped += 1;
+        }
+        if run.pending.is_empty() {
+            run.log_summary();
+            self.prewarm_run = None;
+        }
+    }
+}
+
+/// Venvs of the projects under `root` (including `root` itself), breadth-first
+/// so shallower projects win when the pool can't hold them all
+fn discover_workspace_venvs(root: &Path, venv_dirs: &[String]) -> Vec<PathBuf> {
+    let mut venvs = Vec::new();
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    let mut scanned = 0;
+    while let Some(dir) = queue.pop_front() {
+        scanned += 1;
+        if scanned > MAX_SCANNED_DIRS {
+            tracing::debug!(root = %root.display(), "Venv scan limit reached");
+            break;
+        }
+        if let Some(venv) = venv::venv_in(&dir, venv_dirs) {
+            venvs.push(venv);
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut subdirs: Vec<_> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|name| {
+                        !name.starts_with('.')
+                            && !SKIPPED_DIRS.contains(&name)
+                            && !venv_dirs.iter().any(|d| d == name)
+                    })
+                    && path.is_dir()
+            })
+            .collect();
+        subdirs.sort();
+        queue.extend(subdirs);
+    }
+    venvs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn discovers_nested_venvs_shallowest_first() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        for dir in ["", "b/deep", "a", "node_modules/pkg", ".hidden/proj"] {
+            let venv = root.join(dir).join(".venv");
+            std::fs::create_dir_all(&venv).unwrap();
+            std::fs::write(venv.join("pyvenv.cfg"), "").unwrap();
+        }
+        // A directory inside a venv is never scanned
+        std::fs::create_dir_all(root.join(".venv/lib/.venv")).unwrap();
+
+        let venvs = discover_workspace_venvs(root, &[".venv".to_string()]);
+        assert_eq!(
+            venvs,
+            vec![
+                root.join(".venv"),
+                root.join("a/.venv"),
+                root.join("b/deep/.venv"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use super::backend_dispatch::is_progress_end;
+use super::transport::ClientOutput;
+use crate::error::ProxyError;
+use crate::framing::LspFrameWriter;
+use crate::message::{RpcId, RpcMessage};
+use crate::state::ActiveProgress;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::time::Instant;
+
+/// Prefix of proxy-assigned `partialResultToken`s sent to backends
+const PARTIAL_RESULT_TOKEN_PREFIX: &str = "typemux-partial-";
+
+/// Prefix of client-visible tokens for backend-created work-done progress
+const WORK_DONE_TOKEN_PREFIX: &str = "typemux-progress-";
+
+/// The token the client sees for `token` created by backend `session`.
+/// Backends pick tokens independently, so the same token from two backends
+/// would otherwise drive a single progress indicator.
+fn namespaced_work_done_token(session: u64, token: &Value) -> Option<String> {
+    match token {
+        Value::String(s) => Some(format!("{WORK_DONE_TOKEN_PREFIX}{session}-{s}")),
+        Value::Number(n) => Some(format!("{WORK_DONE_TOKEN_PREFIX}{session}-{n}")),
+        _ => None,
+    }
+}
+
+impl super::LspProxy {
+    /// Replace a request's `partialResultToken` with a proxy-unique token, so
+    /// streamed results from different backends never share a token on the
+    /// backend side. The original is restored by `unmap_partial_result_token`.
+    pub(crate) fn remap_partial_result_token(&mut self, msg: &mut RpcMessage) {
+        let Some(id) = msg.id.clone() else {
+            return;
+        };
+        let Some(token) = msg
+            .params
+            .as_mut()
+            .and_then(|p| p.get_mut("partialResultToken"))
+        else {
+            return;
+        };
+
+        self.state.next_partial_result_token += 1;
+        let proxy_token = format!(
+            "{}{}",
+            PARTIAL_RESULT_TOKEN_PREFIX, self.state.next_partial_result_token
+        );
+        let client_token = std::mem::replace(token, Value::String(proxy_token.clone()));
+        tracing::debug!(
+            id = ?id,
+            client_token = %client_token,
+            proxy_token = %proxy_token,
+            "Remapped partialResultToken"
+        );
+        self.state
+            .partial_result_tokens
+            .insert(proxy_token, (client_token, id));
+    }
+
+    /// Restore the client's token in a streamed `$/progress` partial result.
+    pub(crate) fn unmap_partial_result_token(&self, msg: &mut RpcMessage) {
+        let Some(token) = msg.params.as_mut().and_then(|p| p.get_mut("token")) else {
+            return;
+        };
+        let Some((client_token, _)) = token
+            .as_str()
+            .and_then(|t| self.state.partial_result_tokens.get(t))
+        else {
+            return;
+        };
+        *token = client_token.clone();
+    }
+
+    /// Forget the partial result token of a request once it is answered, or
+    /// cancelled without a backend response to come.
+    pub(crate) fn release_partial_result_token(&mut self, id: &RpcId) {
+        if !self.state.partial_result_tokens.is_empty() {
+            self.state
+                .partial_result_tokens
+                .retain(|_, (_, request_id)| request_id != id);
+        }
+    }
+
+    /// Rewrite the token of a backend's `window/workDoneProgress/create` to a
+    /// per-session token before it reaches the client.
+    pub(crate) fn namespace_work_done_create(
+        &mut self,
+        msg: &mut RpcMessage,
+        venv_path: &Path,
+        session: u64,
+    ) {
+        let Some(token) = msg.params.as_mut().and_then(|p| p.get_mut("token")) else {
+            return;
+        };
+        let Some(client_token) = namespaced_work_done_token(session, token) else {
+            return;
+        };
+        let backend_token = std::mem::replace(token, Value::String(client_token.clone()));
+        tracing::debug!(
+            backend_token = %backend_token,
+            client_token = %client_token,
+            venv = %venv_path.display(),
+            "Namespaced work-done progress token"
+        );
+        self.state.work_done_tokens.insert(
+            client_token,
+            (venv_path.to_path_buf(), session, backend_token),
+        );
+    }
+
+    /// Rewrite a backend `$/progress` for a token it created to the client's
+    /// token; the mapping is dropped with the `end` report.
+    pub(crate) fn namespace_work_done_progress(&mut self, msg: &mut RpcMessage, session: u64) {
+        let end = is_progress_end(msg);
+        let Some(params) = msg.params.as_mut() else {
+            return;
+        };
+        let Some(client_token) = params
+            .get("token")
+            .and_then(|token| namespaced_work_done_token(session, token))
+            .filter(|t| self.state.work_done_tokens.contains_key(t))
+        else {
+            return;
+        };
+        if end {
+            self.state.work_done_tokens.remove(&client_token);
+        }
+        params["token"] = Value::String(client_token);
+    }
+
+    /// Route a client `window/workDoneProgress/cancel` to the backend that
+    /// created the token, with the backend's own token. None for tokens the
+    /// proxy didn't namespace.
+    pub(crate) fn route_work_done_cancel(&self, msg: &RpcMessage) -> Option<(PathBuf, RpcMessage)> {
+        let client_token = msg.params.as_ref()?.get("token")?.as_str()?;
+        let (venv, session, backend_token) = self.state.work_done_tokens.get(client_token)?;
+        if !self
+            .state
+            .pool
+            .get(venv)
+            .is_some_and(|inst| inst.session == *session)
+        {
+            return None;
+        }
+        let cancel = RpcMessage::notification(
+            "window/workDoneProgress/cancel",
+            Some(serde_json::json!({ "token": backend_token })),
+        );
+        Some((venv.clone(), cancel))
+    }
+
+    /// Forget the work-done tokens of a backend that left the pool
+    pub(crate) fn release_work_done_tokens(&mut self, venv_path: &Path, session: u64) {
+        self.state
+            .work_done_tokens
+            .retain(|_, (v, s, _)| !(v == venv_path && *s == session));
+    }
+
+    /// Record activity for a backend `$/progress` notification.
+    /// `begin`/`report` refresh the token, `end` forgets it.
+    pub(crate) fn track_progress(&mut self, msg: &RpcMessage, venv_path: &Path, session: u64) {
+        if self.state.options.progress_token_ttl.is_none() {
+            return;
+        }
+        let Some(params) = msg.params.as_ref() else {
+            return;
+        };
+        let Some(token) = params.get("token") else {
+            return;
+        };
+        let key = (session, token.to_string());
+
+        match params
+            .get("value")
+            .and_then(|v| v.get("kind"))
+            .and_then(|k| k.as_str())
+        {
+            Some("end") => {
+                self.state.active_progress.remove(&key);
+            }
+            Some("begin") | Some("report") => {
+                self.state.active_progress.insert(
+                    key,
+                    ActiveProgress {
+                        token: token.clone(),
+                        venv_path: venv_path.to_path_buf(),
+                        last_activity: Instant::now(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Reap progress tokens idle for longer than `--progress-token-ttl` and
+    /// close the matching client progress indicators with a synthetic `end`.
+    pub(crate) async fn reap_stale_progress(
+        &mut self,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        let Some(ttl) = self.state.options.progress_token_ttl else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let stale: Vec<_> = self
+            .state
+            .active_progress
+            .iter()
+            .filter(|(_, p)| now >= p.last_activity + ttl)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in stale {
+            let Some(progress) = self.state.active_progress.remove(&key) else {
+                continue;
+            };
+            tracing::warn!(
+                token = %progress.token,
+                venv = %progress.venv_path.display(),
+                "Progress token idle past TTL, sending synthetic end"
+            );
+            if let Some(token) = progress.token.as_str() {
+                self.state.work_done_tokens.remove(token);
+            }
+            client_writer
+                .write_message(&progress_end(progress.token))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Build a `$/progress` end notification for `token`
+fn progress_end(token: Value) -> RpcMessage {
+    RpcMessage::notification(
+        "$/progress",
+        Some(serde_json::json!({
+            "token": token,
+            "value": { "kind": "end" }
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::transport::ClientOutput;
+    use crate::backend::BackendKind;
+    use crate::framing::{LspFrameReader, LspFrameWriter};
+    use crate::message::{RpcId, RpcMessage};
+    use crate::proxy::LspProxy;
+    use crate::state::ProxyOptions;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn partial_result_token_released_when_backend_is_cleaned_up() {
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        let venv = PathBuf::from("/a/.venv");
+        let mut request = RpcMessage::request(
+            RpcId::Number(7),
+            "textDocument/references",
+            Some(serde_json::json!({ "partialResultToken": "client-token" })),
+        );
+        proxy.remap_partial_result_token(&mut request);
+        proxy.register_pending_request(&request, 1, &venv);
+        assert_eq!(proxy.state.partial_result_tokens.len(), 1);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut client_writer = LspFrameWriter::new(ClientOutput::Tcp(server.into_split().1));
+
+        proxy
+            .cancel_pending_requests_for_backend(&mut client_writer, &venv, 1)
+            .await
+            .unwrap();
+        assert!(proxy.state.partial_result_tokens.is_empty());
+
+        let cancelled = LspFrameReader::new(client).read_message().await.unwrap();
+        assert_eq!(cancelled.id, Some(RpcId::Number(7)));
+    }
+}
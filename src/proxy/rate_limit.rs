@@ -0,0 +1,143 @@
+use crate::message::RpcMessage;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Requests that are never rate limited (lifecycle)
+const EXEMPT_METHODS: &[&str] = &["initialize", "shutdown"];
+
+/// Token bucket refilled at `rate` tokens per second, holding at most `rate`
+/// tokens (i.e. bursts of up to one second's worth of requests).
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: f64::from(rate),
+            tokens: f64::from(rate),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Add the tokens earned since the last refill. Returns whether the
+    /// bucket is full, i.e. requests stayed under the rate for a whole burst.
+    fn refill(&mut self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+        self.tokens >= self.rate
+    }
+
+    /// Take one token at `now`. On exhaustion, returns how long until the
+    /// next token is available.
+    fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+impl super::LspProxy {
+    /// Apply `--max-message-rate` to a client request. Returns the error
+    /// response to send instead of forwarding when the limit is exceeded.
+    pub(crate) fn check_request_rate(&mut self, msg: &RpcMessage) -> Option<RpcMessage> {
+        let bucket = self.state.request_rate_limiter.as_mut()?;
+        if msg
+            .method_name()
+            .is_some_and(|m| EXEMPT_METHODS.contains(&m))
+        {
+            return None;
+        }
+        let now = Instant::now();
+        if bucket.refill(now) && self.state.rate_limit_engaged {
+            self.state.rate_limit_engaged = false;
+            tracing::info!("Client request rate back under --max-message-rate");
+        }
+        let retry_after = bucket.try_acquire(now).err()?;
+
+        if !self.state.rate_limit_engaged {
+            self.state.rate_limit_engaged = true;
+            tracing::warn!(
+                method = ?msg.method_name(),
+                "Client request rate exceeded --max-message-rate, rejecting requests"
+            );
+        }
+        tracing::debug!(id = ?msg.id, method = ?msg.method_name(), "Rate limited client request");
+        let retry_after_ms = retry_after.as_millis().max(1) as u64;
+        Some(RpcMessage::rate_limited_response(
+            msg,
+            &self.client_message("rate limited"),
+            retry_after_ms,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+    use crate::message::RpcId;
+    use crate::proxy::LspProxy;
+    use crate::state::ProxyOptions;
+
+    #[test]
+    fn token_bucket_bursts_then_refills() {
+        let mut bucket = TokenBucket::new(2);
+        let start = bucket.last_refill;
+
+        assert!(bucket.try_acquire(start).is_ok());
+        assert!(bucket.try_acquire(start).is_ok());
+        let wait = bucket.try_acquire(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        // Half a second refills one token, but never more than the burst size
+        assert!(bucket
+            .try_acquire(start + Duration::from_millis(500))
+            .is_ok());
+        assert!(bucket
+            .try_acquire(start + Duration::from_millis(500))
+            .is_err());
+        let later = start + Duration::from_secs(10);
+        assert!(bucket.try_acquire(later).is_ok());
+        assert!(bucket.try_acquire(later).is_ok());
+        assert!(bucket.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn rate_limit_disengages_once_rate_drops() {
+        let options = ProxyOptions {
+            max_message_rate: Some(1),
+            ..Default::default()
+        };
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, options);
+        let hover = |id| {
+            RpcMessage::request(
+                RpcId::Number(id),
+                "textDocument/hover",
+                Some(serde_json::json!({})),
+            )
+        };
+
+        assert!(proxy.check_request_rate(&hover(1)).is_none());
+        assert!(proxy.check_request_rate(&hover(2)).is_some());
+        assert!(proxy.state.rate_limit_engaged);
+
+        // A quiet second refills the bucket: the limit is reported again next time
+        let bucket = proxy.state.request_rate_limiter.as_mut().unwrap();
+        bucket.last_refill -= Duration::from_secs(1);
+        assert!(proxy.check_request_rate(&hover(3)).is_none());
+        assert!(!proxy.state.rate_limit_engaged);
+        assert!(proxy.check_request_rate(&hover(4)).is_some());
+        assert!(proxy.state.rate_limit_engaged);
+    }
+}
@@ -0,0 +1,292 @@
+//! Deduplication of dynamic capability registrations.
+//!
+//! Every backend sends its own `client/registerCapability` (file watchers and
+//! the like) after `initialized`. The client only needs each registration
+//! once, so a registration whose method and options are already active is
+//! answered by the proxy and not forwarded. A registration is unregistered at
+//! the client only when the last backend holding it lets go.
+
+use crate::error::ProxyError;
+use crate::message::RpcMessage;
+use crate::state::CapabilityRegistration;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Registrations are the same when method and registerOptions match; the id
+/// is chosen by each backend and differs between them
+fn registration_key(method: &str, options: Option<&Value>) -> String {
+    format!("{method}\0{}", options.unwrap_or(&Value::Null))
+}
+
+/// The registrations a forwarded `client/registerCapability` asks the client
+/// for, as (key, id); empty for any other message
+pub(crate) fn forwarded_registrations(msg: &RpcMessage) -> Vec<(String, String)> {
+    if msg.method_name() != Some("client/registerCapability") {
+        return Vec::new();
+    }
+    msg.params
+        .as_ref()
+        .and_then(|p| p.get("registrations"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|registration| {
+            let id = registration.get("id").and_then(Value::as_str)?;
+            let method = registration.get("method").and_then(Value::as_str)?;
+            let key = registration_key(method, registration.get("registerOptions"));
+            Some((key, id.to_string()))
+        })
+        .collect()
+}
+
+impl super::LspProxy {
+    /// Drop the registrations of a `client/registerCapability` from `venv_path`
+    /// that the client already holds, recording the backend as a co-owner.
+    /// Returns true when nothing is left to forward: the caller answers the
+    /// backend itself.
+    pub(crate) fn dedup_register_capability(
+        &mut self,
+        msg: &mut RpcMessage,
+        venv_path: &Path,
+        session: u64,
+    ) -> bool {
+        let Some(registrations) = msg
+            .params
+            .as_mut()
+            .and_then(|p| p.get_mut("registrations"))
+            .and_then(Value::as_array_mut)
+        else {
+            return false;
+        };
+        if registrations.is_empty() {
+            return false;
+        }
+
+        let registered = &mut self.state.registered_capabilities;
+        registrations.retain(|registration| {
+            let (Some(id), Some(method)) = (
+                registration.get("id").and_then(Value::as_str),
+                registration.get("method").and_then(Value::as_str),
+            ) else {
+                return true;
+            };
+            let key = registration_key(method, registration.get("registerOptions"));
+            let owner = (venv_path.to_path_buf(), session, id.to_string());
+            match registered.get_mut(&key) {
+                Some(existing) => {
+                    tracing::debug!(
+                        method = method,
+                        id = id,
+                        client_id = %existing.client_id,
+                        venv = %venv_path.display(),
+                        "Capability already registered at the client, not forwarding duplicate"
+                    );
+                    existing.owners.push(owner);
+                    false
+                }
+                None => {
+                    registered.insert(
+                        key,
+                        CapabilityRegistration {
+                            client_id: id.to_string(),
+                            owners: vec![owner],
+                        },
+                    );
+                    true
+                }
+            }
+        });
+        registrations.is_empty()
+    }
+
+    /// Keep only the unregistrations of a `client/unregisterCapability` from
+    /// `venv_path` that release a registration no other backend holds,
+    /// rewritten to the id the client knows. Returns true when nothing is
+    /// left to forward.
+    pub(crate) fn dedup_unregister_capability(
+        &mut self,
+        msg: &mut RpcMessage,
+        venv_path: &Path,
+        session: u64,
+    ) -> bool {
+        // The LSP field name really is misspelled
+        let Some(unregistrations) = msg
+            .params
+            .as_mut()
+            .and_then(|p| p.get_mut("unregisterations"))
+            .and_then(Value::as_array_mut)
+        else {
+            return false;
+        };
+        if unregistrations.is_empty() {
+            return false;
+        }
+
+        let registered = &mut self.state.registered_capabilities;
+        unregistrations.retain_mut(|unregistration| {
+            let Some(id) = unregistration.get("id").and_then(Value::as_str) else {
+                return true;
+            };
+            let is_owner =
+                |(v, s, i): &(PathBuf, u64, String)| v == venv_path && *s == session && i == id;
+            let Some(key) = registered
+                .iter()
+                .find(|(_, r)| r.owners.iter().any(is_owner))
+                .map(|(key, _)| key.clone())
+            else {
+                // Not ours to track
+                return true;
+            };
+            let registration = registered.get_mut(&key).expect("key just found");
+            registration.owners.retain(|owner| !is_owner(owner));
+            if !registration.owners.is_empty() {
+                tracing::debug!(
+                    id = id,
+                    remaining_owners = registration.owners.len(),
+                    "Capability still registered by other backends, not unregistering"
+                );
+                return false;
+            }
+            let client_id = registered.remove(&key).expect("key just found").client_id;
+            unregistration["id"] = Value::String(client_id);
+            true
+        });
+        unregistrations.is_empty()
+    }
+
+    /// The client refused a forwarded `client/registerCapability`, so it holds
+    /// none of its registrations. Forget them, together with the backends
+    /// deduplicated against them meanwhile, so the next registration reaches
+    /// the client again.
+    pub(crate) fn forget_capability_registrations(&mut self, registrations: &[(String, String)]) {
+        let registered = &mut self.state.registered_capabilities;
+        for (key, id) in registrations {
+            if registered.get(key).is_some_and(|r| &r.client_id == id) {
+                tracing::debug!(id = %id, "Client refused capability registration, forgetting it");
+                registered.remove(key);
+            }
+        }
+    }
+
+    /// A backend is gone: it no longer holds any registration. The client's
+    /// registrations stay, so the backend's successor re-registering them
+    /// is deduplicated as well.
+    pub(crate) fn release_capability_registrations(&mut self, venv_path: &Path, session: u64) {
+        for registration in self.state.registered_capabilities.values_mut() {
+            registration
+                .owners
+                .retain(|(v, s, _)| !(v == venv_path && *s == session));
+        }
+    }
+
+    /// Answer a backend's (un)registerCapability on the client's behalf
+    pub(crate) async fn answer_backend_request(
+        &mut self,
+        msg: &RpcMessage,
+        venv_path: &PathBuf,
+    ) -> Result<(), ProxyError> {
+        let Some(inst) = self.state.pool.get_mut(venv_path) else {
+            return Ok(());
+        };
+        let response = RpcMessage::success_response(msg, Value::Null);
+        inst.writer.write_message(&response).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::BackendKind;
+    use crate::message::{RpcId, RpcMessage};
+    use crate::proxy::LspProxy;
+    use crate::state::{PendingBackendRequest, ProxyOptions};
+    use std::path::Path;
+
+    fn watchers(id: &str) -> RpcMessage {
+        RpcMessage::request(
+            RpcId::Number(1),
+            "client/registerCapability",
+            Some(serde_json::json!({
+                "registrations": [{
+                    "id": id,
+                    "method": "workspace/didChangeWatchedFiles",
+                    "registerOptions": { "watchers": [{ "globPattern": "**/*.py" }] }
+                }]
+            })),
+        )
+    }
+
+    fn unregister(id: &str) -> RpcMessage {
+        RpcMessage::request(
+            RpcId::Number(2),
+            "client/unregisterCapability",
+            Some(serde_json::json!({
+                "unregisterations": [{ "id": id, "method": "workspace/didChangeWatchedFiles" }]
+            })),
+        )
+    }
+
+    #[test]
+    fn duplicate_registration_is_held_until_last_owner_unregisters() {
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 2, None, ProxyOptions::default());
+        let (a, b) = (Path::new("/a/.venv"), Path::new("/b/.venv"));
+
+        let mut first = watchers("a-1");
+        assert!(!proxy.dedup_register_capability(&mut first, a, 1));
+        assert_eq!(first.params.unwrap()["registrations"][0]["id"], "a-1");
+        let mut duplicate = watchers("b-1");
+        assert!(proxy.dedup_register_capability(&mut duplicate, b, 2));
+
+        // a lets go first: b still needs the watcher
+        assert!(proxy.dedup_unregister_capability(&mut unregister("a-1"), a, 1));
+
+        // b's unregistration is sent under the id the client knows
+        let mut last = unregister("b-1");
+        assert!(!proxy.dedup_unregister_capability(&mut last, b, 2));
+        assert_eq!(last.params.unwrap()["unregisterations"][0]["id"], "a-1");
+        assert!(proxy.state.registered_capabilities.is_empty());
+
+        // Registering again reaches the client
+        assert!(!proxy.dedup_register_capability(&mut watchers("b-2"), b, 2));
+    }
+
+    #[test]
+    fn registration_survives_its_backend() {
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 2, None, ProxyOptions::default());
+        let a = Path::new("/a/.venv");
+
+        assert!(!proxy.dedup_register_capability(&mut watchers("a-1"), a, 1));
+        proxy.release_capability_registrations(a, 1);
+
+        // The restarted backend re-registers: the client still has it
+        assert!(proxy.dedup_register_capability(&mut watchers("a-1"), a, 3));
+        let mut unregistration = unregister("a-1");
+        assert!(!proxy.dedup_unregister_capability(&mut unregistration, a, 3));
+    }
+
+    #[tokio::test]
+    async fn registration_refused_by_client_is_forgotten() {
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 2, None, ProxyOptions::default());
+        let (a, b) = (Path::new("/a/.venv"), Path::new("/b/.venv"));
+
+        let mut first = watchers("a-1");
+        assert!(!proxy.dedup_register_capability(&mut first, a, 1));
+        proxy.state.pending_backend_requests.insert(
+            RpcId::Number(-1),
+            PendingBackendRequest {
+                original_id: RpcId::Number(1),
+                venv_path: a.to_path_buf(),
+                session: 1,
+                registrations: super::forwarded_registrations(&first),
+                configuration_sections: Vec::new(),
+            },
+        );
+        let forwarded = RpcMessage::request(RpcId::Number(-1), "client/registerCapability", None);
+        let refused = RpcMessage::error_response(&forwarded, "unsupported");
+        assert!(proxy.dispatch_client_response(&refused).await.unwrap());
+        assert!(proxy.state.registered_capabilities.is_empty());
+
+        // The next backend's registration reaches the client
+        assert!(!proxy.dedup_register_capability(&mut watchers("b-1"), b, 2));
+    }
+}
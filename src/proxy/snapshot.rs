@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+use tokio::time::Instant;
+
+/// Signal stream that triggers a state dump (`--pool-snapshot-on-signal`)
+#[cfg(unix)]
+pub type SnapshotSignal = tokio::signal::unix::Signal;
+/// Signal stream that triggers a state dump (unsupported on this platform)
+#[cfg(not(unix))]
+pub type SnapshotSignal = std::convert::Infallible;
+
+/// Install the SIGUSR1 handler for `--pool-snapshot-on-signal`.
+#[cfg(unix)]
+pub fn install_snapshot_signal() -> std::io::Result<SnapshotSignal> {
+    use tokio::signal::unix::{signal, SignalKind};
+    signal(SignalKind::user_defined1())
+}
+
+/// Wait for the next snapshot request; never completes without a handler.
+pub(crate) async fn next_snapshot_request(signal: &mut Option<SnapshotSignal>) {
+    #[cfg(unix)]
+    if let Some(signal) = signal {
+        if signal.recv().await.is_some() {
+            return;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = signal;
+    std::future::pending().await
+}
+
+/// Listener for `--status-socket`
+#[cfg(unix)]
+pub type StatusListener = tokio::net::UnixListener;
+/// Listener for `--status-socket` (unsupported on this platform)
+#[cfg(not(unix))]
+pub type StatusListener = std::convert::Infallible;
+
+/// A connected `--status-socket` client
+#[cfg(unix)]
+pub(crate) type StatusConnection = tokio::net::UnixStream;
+/// A connected `--status-socket` client (unsupported on this platform)
+#[cfg(not(unix))]
+pub(crate) type StatusConnection = std::convert::Infallible;
+
+/// Bind the `--status-socket` listener, replacing a stale socket file left by
+/// an earlier run. A socket another instance still listens on is left alone.
+#[cfg(unix)]
+pub fn bind_status_socket(path: &Path) -> std::io::Result<StatusListener> {
+    use std::os::unix::fs::FileTypeExt;
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!("{} is in use by a running instance", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                std::fs::remove_file(path)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    tokio::net::UnixListener::bind(path)
+}
+
+/// Removes the `--status-socket` file when the proxy stops serving it
+pub(crate) struct StatusSocketFile(pub PathBuf);
+
+impl Drop for StatusSocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Bind the `--status-socket` listener (unsupported on this platform)
+#[cfg(not(unix))]
+pub fn bind_status_socket(_path: &Path) -> std::io::Result<StatusListener> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--status-socket is only supported on Unix",
+    ))
+}
+
+/// Wait for the next status client; never completes without a listener.
+pub(crate) async fn next_status_connection(listener: &Option<StatusListener>) -> StatusConnection {
+    #[cfg(unix)]
+    if let Some(listener) = listener {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => return stream,
+                Err(e) => tracing::warn!(error = ?e, "Failed to accept status connection"),
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = listener;
+    std::future::pending().await
+}
+
+/// Write a rendered status snapshot to a client and close the connection.
+pub(crate) async fn serve_status(stream: StatusConnection, body: Vec<u8>) {
+    #[cfg(unix)]
+    {
+        use tokio::io::AsyncWriteExt;
+        let mut stream = stream;
+        if let Err(e) = stream.write_all(&body).await {
+            tracing::debug!(error = ?e, "Failed to write status response");
+        }
+        let _ = stream.shutdown().await;
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = body;
+        match stream {}
+    }
+}
+
+/// Read the status snapshot of the instance listening on `path` (`--status`).
+#[cfg(unix)]
+pub async fn query_status(path: &Path) -> std::io::Result<String> {
+    use tokio::io::AsyncReadExt;
+    let mut stream = tokio::net::UnixStream::connect(path).await?;
+    let mut body = String::new();
+    stream.read_to_string(&mut body).await?;
+    Ok(body)
+}
+
+/// Read the status snapshot of a running instance (unsupported on this platform)
+#[cfg(not(unix))]
+pub async fn query_status(_path: &Path) -> std::io::Result<String> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--status is only supported on Unix",
+    ))
+}
+
+impl super::LspProxy {
+    /// Pool, backend and queue state as JSON for `--status-socket`.
+    pub(crate) fn status_json(&self) -> serde_json::Value {
+        let state = &self.state;
+        let now = Instant::now();
+        let backends: Vec<serde_json::Value> = state
+            .pool
+            .backends_keys()
+            .iter()
+            .filter_map(|venv| {
+                let inst = state.pool.get(venv)?;
+                Some(serde_json::json!({
+                    "venv": venv.display().to_string(),
+                    "session": inst.session,
+                    "idle_secs": now.duration_since(inst.last_used).as_secs(),
+                    "warmup_state": if inst.is_warming() { "warming" } else { "ready" },
+                }))
+            })
+            .collect();
+        serde_json::json!({
+            "pool_size": state.pool.len(),
+            "max_backends": state.pool.max_backends(),
+            "backends": backends,
+            "open_documents": state.open_documents.len(),
+            "pending_requests": state.pending_requests.len(),
+        })
+    }
+
+    /// Dump pool, documents, pending requests and metrics to the log at info level.
+    pub(crate) fn log_state_snapshot(&self) {
+        let state = &self.state;
+        let now = Instant::now();
+        let gauges = state.gauges();
+        tracing::info!(
+            backends = gauges.pool_size,
+            max_backends = state.pool.max_backends(),
+            warming = gauges.warming_backends,
+            open_documents = gauges.open_documents,
+            pending_requests = gauges.pending_requests,
+            pending_backend_requests = state.pending_backend_requests.len(),
+            pending_fanouts = state.pending_fanouts.len(),
+            requests_total = state.metrics.requests_total,
+            backend_spawns_total = state.metrics.backend_spawns_total,
+            backend_crashes_total = state.metrics.backend_crashes_total,
+            backend_evictions_total = state.metrics.backend_evictions_total(),
+            "State snapshot"
+        );
+
+        for venv in state.pool.backends_keys() {
+            let Some(inst) = state.pool.get(&venv) else {
+                continue;
+            };
+            let pending = state
+                .pending_requests
+                .values()
+                .filter(|p| p.venv_path == venv && p.backend_session == inst.session)
+                .count();
+            tracing::info!(
+                venv = %venv.display(),
+                session = inst.session,
+                warming = inst.is_warming(),
+                idle_secs = now.duration_since(inst.last_used).as_secs(),
+                queued = inst.warmup_queue.len(),
+                pending = pending,
+                "Snapshot: backend"
+            );
+        }
+
+        for (url, doc) in &state.open_documents {
+            tracing::info!(
+                uri = %url,
+                version = doc.version,
+                language_id = %doc.language_id,
+                venv = ?doc.venv.as_ref().map(|v| v.display().to_string()),
+                text_len = doc.text.len(),
+                "Snapshot: document"
+            );
+        }
+
+        for (id, pending) in &state.pending_requests {
+            tracing::info!(
+                id = ?id,
+                venv = %pending.venv_path.display(),
+                session = pending.backend_session,
+                "Snapshot: pending request"
+            );
+        }
+    }
+}
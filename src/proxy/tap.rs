@@ -0,0 +1,97 @@
+use crate::message::RpcMessage;
+use std::path::Path;
+
+/// Observer for backend→client traffic (`--backend-response-tap`).
+///
+/// Taps see every message from a current backend session exactly as the
+/// backend sent it (before ID rewriting or filtering) and cannot modify it.
+/// Implementations must be cheap: they run inline in the dispatch loop.
+pub trait ResponseTap: Send {
+    fn on_response(&self, _venv: &Path, _session: u64, _msg: &RpcMessage) {}
+}
+
+/// Tap that ignores everything
+pub struct NoopTap;
+
+impl ResponseTap for NoopTap {}
+
+/// Example tap: one debug line per backend message
+pub struct LogTap;
+
+impl ResponseTap for LogTap {
+    fn on_response(&self, venv: &Path, session: u64, msg: &RpcMessage) {
+        tracing::debug!(
+            target: "typemux_cc::tap",
+            venv = %venv.display(),
+            session = session,
+            id = ?msg.id,
+            method = ?msg.method_name(),
+            is_error = msg.error.is_some(),
+            "Backend message"
+        );
+    }
+}
+
+/// Taps compiled into the proxy, selectable by name on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ResponseTapKind {
+    Noop,
+    Log,
+}
+
+impl ResponseTapKind {
+    pub fn build(self) -> Box<dyn ResponseTap> {
+        match self {
+            Self::Noop => Box::new(NoopTap),
+            Self::Log => Box::new(LogTap),
+        }
+    }
+}
+
+impl super::LspProxy {
+    /// Register a tap to observe backend→client messages
+    pub fn add_response_tap(&mut self, tap: Box<dyn ResponseTap>) {
+        self.response_taps.push(tap);
+    }
+
+    pub(crate) fn run_response_taps(&self, venv: &Path, session: u64, msg: &RpcMessage) {
+        for tap in &self.response_taps {
+            tap.on_response(venv, session, msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+    use crate::proxy::LspProxy;
+    use crate::state::ProxyOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingTap(Arc<AtomicUsize>);
+
+    impl ResponseTap for CountingTap {
+        fn on_response(&self, _venv: &Path, _session: u64, _msg: &RpcMessage) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_response_taps_observe_each_message() {
+        let mut proxy = LspProxy::new(BackendKind::Pyright, 1, None, ProxyOptions::default());
+        let count = Arc::new(AtomicUsize::new(0));
+        proxy.add_response_tap(Box::new(CountingTap(count.clone())));
+        proxy.add_response_tap(ResponseTapKind::Noop.build());
+        proxy.add_response_tap(ResponseTapKind::Log.build());
+
+        let msg: RpcMessage =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).unwrap();
+        let venv = Path::new("/tmp/project/.venv");
+        proxy.run_response_taps(venv, 1, &msg);
+        proxy.run_response_taps(venv, 1, &msg);
+
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
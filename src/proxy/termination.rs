@@ -0,0 +1,87 @@
+use super::transport::ClientOutput;
+use crate::error::ProxyError;
+use crate::framing::LspFrameWriter;
+use crate::message::RpcMessage;
+
+/// SIGTERM/SIGINT listener: the proxy drains its backends before exiting
+/// instead of leaving them to `kill_on_drop`
+#[cfg(unix)]
+pub(crate) struct TerminationSignal {
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl TerminationSignal {
+    pub(crate) fn install() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    /// Wait for the next signal; returns its name
+    pub(crate) async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.terminate.recv() => "SIGTERM",
+            _ = self.interrupt.recv() => "SIGINT",
+        }
+    }
+}
+
+/// Ctrl-C listener (SIGTERM has no equivalent on this platform)
+#[cfg(not(unix))]
+pub(crate) struct TerminationSignal;
+
+#[cfg(not(unix))]
+impl TerminationSignal {
+    pub(crate) fn install() -> std::io::Result<Self> {
+        Ok(Self)
+    }
+
+    pub(crate) async fn recv(&mut self) -> &'static str {
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => "Ctrl-C",
+            Err(_) => std::future::pending().await,
+        }
+    }
+}
+
+impl super::LspProxy {
+    /// Answer every in-flight client request with `RequestCancelled`, then
+    /// shut each backend down (shutdown/exit) and wait for it to finish
+    pub(crate) async fn drain_for_termination(
+        &mut self,
+        held: impl IntoIterator<Item = RpcMessage>,
+        client_writer: &mut LspFrameWriter<ClientOutput>,
+    ) -> Result<(), ProxyError> {
+        // Requests the main loop holds (waiting on a launch, re-issued after a
+        // crash, later batch elements) are not pending on any backend yet
+        let mut cancelled: Vec<_> = held
+            .into_iter()
+            .chain(self.state.reissued_requests.drain(..))
+            .filter(|msg| msg.is_request())
+            .filter_map(|msg| msg.id)
+            .collect();
+        self.state.reissued_ids.clear();
+        // A fan-out is answered once under the client's id; its sub-requests are not
+        for (client_id, fanout) in self.state.pending_fanouts.drain() {
+            for proxy_id in fanout.sub_requests.keys() {
+                self.state.pending_requests.remove(proxy_id);
+            }
+            cancelled.push(client_id);
+        }
+        cancelled.extend(self.state.pending_requests.drain().map(|(id, _)| id));
+        self.state.pending_hovers.clear();
+
+        for id in cancelled {
+            tracing::info!(id = ?id, "Cancelling in-flight request before shutdown");
+            let msg = RpcMessage::cancelled_response(id, "Request cancelled: proxy shutting down");
+            client_writer.write_message(&msg).await?;
+        }
+
+        self.shutdown_all_backends_and_wait().await;
+        Ok(())
+    }
+}
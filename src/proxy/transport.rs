@@ -0,0 +1,206 @@
+use crate::error::{FramingError, ProxyError};
+use crate::framing::{Incoming, LspFrameReader};
+use crate::message::RpcMessage;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, Stdin, Stdout};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+
+/// Source of client → proxy messages.
+pub enum ClientReader {
+    /// Live client over stdin (default)
+    Stdio(LspFrameReader<Stdin>),
+    /// Live client over a TCP connection (`--listen`)
+    Tcp(LspFrameReader<OwnedReadHalf>),
+    /// Recorded transcript (`--replay`)
+    Replay(ReplayReader),
+}
+
+impl ClientReader {
+    /// Read the next client message (or JSON-RPC batch) from the active source
+    pub async fn read_message(&mut self) -> Result<Incoming, FramingError> {
+        match self {
+            Self::Stdio(reader) => reader.read_message_or_batch().await,
+            Self::Tcp(reader) => reader.read_message_or_batch().await,
+            Self::Replay(reader) => Ok(Incoming::Single(reader.read_message().await)),
+        }
+    }
+}
+
+/// Sink for proxy → client messages: stdout, or the `--listen` connection.
+pub enum ClientOutput {
+    Stdio(Stdout),
+    Tcp(OwnedWriteHalf),
+}
+
+impl AsyncWrite for ClientOutput {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Stdio(w) => Pin::new(w).poll_write(cx, buf),
+            Self::Tcp(w) => Pin::new(w).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Stdio(w) => Pin::new(w).poll_flush(cx),
+            Self::Tcp(w) => Pin::new(w).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Stdio(w) => Pin::new(w).poll_shutdown(cx),
+            Self::Tcp(w) => Pin::new(w).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Bind `addr` and wait for a single client connection (`--listen`).
+/// The listener is dropped afterwards, so later connections are refused.
+pub async fn accept_client(addr: &str) -> io::Result<(OwnedReadHalf, OwnedWriteHalf)> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(addr = %listener.local_addr()?, "Waiting for client connection");
+    let (stream, peer) = listener.accept().await?;
+    tracing::info!(peer = %peer, "Client connected");
+    stream.set_nodelay(true)?;
+    Ok(stream.into_split())
+}
+
+/// One transcript line: either a bare JSON-RPC message, or an envelope
+/// carrying the recorded offset from the start of the session.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TranscriptLine {
+    Timed {
+        elapsed_ms: u64,
+        message: RpcMessage,
+    },
+    Bare(RpcMessage),
+}
+
+/// A transcript message scheduled at an offset from replay start.
+/// `None` means "as fast as possible".
+struct ReplayEntry {
+    offset: Option<Duration>,
+    message: RpcMessage,
+}
+
+/// Replays a recorded client-side JSONL transcript as if it came from stdin.
+///
+/// Each non-empty line (lines starting with `#` are comments) is either a
+/// JSON-RPC message or `{"elapsed_ms": N, "message": {...}}`. Timed messages
+/// are delivered no earlier than N ms after replay start.
+/// Once the transcript is exhausted, the reader never yields again, so the proxy
+/// keeps serving backends until the transcript's own `exit` (if any).
+pub struct ReplayReader {
+    entries: VecDeque<ReplayEntry>,
+    started: Instant,
+    exhausted_logged: bool,
+}
+
+impl ReplayReader {
+    /// Load and validate a transcript file
+    pub async fn from_file(path: &Path) -> Result<Self, ProxyError> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let reader = Self::parse(&content)?;
+        tracing::info!(
+            path = %path.display(),
+            messages = reader.entries.len(),
+            "Replaying client transcript"
+        );
+        Ok(reader)
+    }
+
+    fn parse(content: &str) -> Result<Self, ProxyError> {
+        let mut entries = VecDeque::new();
+        for (i, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let parsed: TranscriptLine = serde_json::from_str(trimmed).map_err(|e| {
+                ProxyError::InvalidMessage(format!("replay transcript line {}: {}", i + 1, e))
+            })?;
+            entries.push_back(match parsed {
+                TranscriptLine::Timed {
+                    elapsed_ms,
+                    message,
+                } => ReplayEntry {
+                    offset: Some(Duration::from_millis(elapsed_ms)),
+                    message,
+                },
+                TranscriptLine::Bare(message) => ReplayEntry {
+                    offset: None,
+                    message,
+                },
+            });
+        }
+
+        Ok(Self {
+            entries,
+            started: Instant::now(),
+            exhausted_logged: false,
+        })
+    }
+
+    /// Return the next message at its recorded time.
+    /// Cancel-safe: the entry is only consumed after its delay has elapsed.
+    async fn read_message(&mut self) -> RpcMessage {
+        let offset = match self.entries.front() {
+            Some(entry) => entry.offset,
+            None => {
+                if !self.exhausted_logged {
+                    self.exhausted_logged = true;
+                    tracing::info!("Replay transcript exhausted, waiting for backends");
+                }
+                return std::future::pending().await;
+            }
+        };
+
+        if let Some(offset) = offset {
+            tokio::time::sleep_until(self.started + offset).await;
+        }
+
+        // front() was Some above and nothing else consumes entries
+        self.entries.pop_front().unwrap().message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bare_and_timed_lines() {
+        let content = r#"
+# recorded session
+{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}
+{"elapsed_ms":250,"message":{"jsonrpc":"2.0","method":"exit"}}
+"#;
+        let reader = ReplayReader::parse(content).unwrap();
+        assert_eq!(reader.entries.len(), 2);
+        assert!(reader.entries[0].offset.is_none());
+        assert_eq!(reader.entries[0].message.method_name(), Some("initialize"));
+        assert_eq!(reader.entries[1].offset, Some(Duration::from_millis(250)));
+        assert_eq!(reader.entries[1].message.method_name(), Some("exit"));
+    }
+
+    #[test]
+    fn parse_reports_line_number() {
+        let content = "{\"jsonrpc\":\"2.0\",\"method\":\"initialized\"}\nnot json\n";
+        let err = ReplayReader::parse(content).err().unwrap();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
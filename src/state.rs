@@ -1,8 +1,12 @@
-use crate::backend::BackendKind;
-use crate::backend_pool::BackendPool;
+use crate::backend::{self, BackendKind, BackendMapping, KillSignal, PyrightDiagnosticMode};
+use crate::backend_pool::{BackendPool, SpawnLimiter};
 use crate::message::{RpcId, RpcMessage};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use crate::metrics::{Gauges, Metrics};
+use crate::proxy::{CrashBreaker, TokenBucket};
+use crate::venv::{NegativeVenvCache, PoetryEnvCache, ProjectVenv, VenvSearch};
+use crate::warmup_state::WarmupStateStore;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::time::Instant;
 use url::Url;
@@ -14,6 +18,28 @@ pub struct PendingRequest {
     pub backend_session: u64,
     /// Venv path of the backend this request was sent to
     pub venv_path: PathBuf,
+    /// Request method (for logging once the response arrives)
+    pub method: String,
+    /// Original request, kept only when `--on-stale-response retry` may re-issue it
+    pub request: Option<RpcMessage>,
+    /// Whether the request was already re-issued once (prevents retry loops)
+    pub retried: bool,
+    /// When the client sent the request (`--request-timeout`,
+    /// `--log-request-response-correlation`)
+    pub created_at: Instant,
+}
+
+/// What to do when a backend answers a request it no longer owns
+/// (e.g. its session was replaced, or the id belongs to another backend).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StaleResponsePolicy {
+    /// Discard the response and forget the request
+    Drop,
+    /// Re-issue the request once to the current backend for its venv
+    Retry,
+    /// Answer the client with `RequestCancelled` so it can retry
+    #[default]
+    Cancel,
 }
 
 /// Information about a pending server→client request (backend → proxy → client)
@@ -26,6 +52,22 @@ pub struct PendingBackendRequest {
     pub venv_path: PathBuf,
     /// Session of the originating backend
     pub session: u64,
+    /// Capability registrations forwarded by this request as (key, id),
+    /// forgotten again when the client answers with an error
+    pub registrations: Vec<(String, String)>,
+    /// Item sections of a forwarded `workspace/configuration`, so settings the
+    /// proxy forces can be patched into the client's answer; empty otherwise
+    pub configuration_sections: Vec<Option<String>>,
+}
+
+/// A dynamic capability registration the client holds on behalf of one or
+/// more backends (`client/registerCapability` deduplication)
+#[derive(Debug, Clone)]
+pub struct CapabilityRegistration {
+    /// Registration id the client knows (from the first backend to register)
+    pub client_id: String,
+    /// Backends that registered it: (venv, session, backend registration id)
+    pub owners: Vec<(PathBuf, u64, String)>,
 }
 
 /// State for a fan-out request (dispatched to all backends, results merged)
@@ -49,6 +91,108 @@ pub struct PendingFanout {
     pub client_request: RpcMessage,
 }
 
+/// A forwarded hover request that falls back to a proxy-built answer
+/// when its backend is silent past the deadline (`--hover-timeout`)
+#[derive(Debug, Clone)]
+pub struct PendingHover {
+    /// When the fallback hover is sent
+    pub deadline: Instant,
+    /// Document the hover was requested for
+    pub uri: Url,
+    /// Zero-based line under the cursor
+    pub line: usize,
+    /// Backend the request was forwarded to (receives `$/cancelRequest` on timeout)
+    pub venv_path: PathBuf,
+}
+
+/// A backend work-done progress that has begun but not ended
+#[derive(Debug, Clone)]
+pub struct ActiveProgress {
+    /// Token as sent by the backend (number or string)
+    pub token: serde_json::Value,
+    /// Backend that owns the progress
+    pub venv_path: PathBuf,
+    /// Last `begin`/`report` seen for this token
+    pub last_activity: Instant,
+}
+
+/// Consecutive failed spawns of a venv's backend
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnFailure {
+    pub attempts: usize,
+    pub last: Instant,
+}
+
+/// What to do when a backend doesn't answer `initialize` in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitTimeoutAction {
+    /// Wait once more with twice the timeout before giving up
+    Retry,
+    /// Give up and spawn no backend for that venv until the cooldown passes
+    Disable,
+    /// Give up; the client gets minimal capabilities or a backend error
+    #[default]
+    Minimal,
+}
+
+/// What to do with a didChange whose version is not above the cached one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum VersionRegressionPolicy {
+    /// Log a warning and apply the change anyway, keeping the higher version
+    #[default]
+    Warn,
+    /// Log a warning and drop the change (neither cached nor forwarded)
+    Skip,
+    /// Drop the change and report it to the client (`--strict-versions`)
+    Error,
+}
+
+/// What `--diagnostic-code-rule` does with diagnostics carrying a given code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticAction {
+    /// Remove the diagnostic
+    Drop,
+    /// Rewrite `severity` (LSP DiagnosticSeverity: 1 = error … 4 = hint)
+    Severity(u8),
+}
+
+/// A `<code>:<drop|error|warning|info|hint>` rule (`--diagnostic-code-rule`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticCodeRule {
+    pub code: String,
+    pub action: DiagnosticAction,
+}
+
+impl std::str::FromStr for DiagnosticCodeRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split on the last ':' so codes may themselves contain colons
+        let (code, action) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected <code>:<action>, got {s:?}"))?;
+        if code.is_empty() {
+            return Err(format!("missing diagnostic code in {s:?}"));
+        }
+        let action = match action {
+            "drop" => DiagnosticAction::Drop,
+            "error" => DiagnosticAction::Severity(1),
+            "warning" => DiagnosticAction::Severity(2),
+            "info" => DiagnosticAction::Severity(3),
+            "hint" => DiagnosticAction::Severity(4),
+            other => {
+                return Err(format!(
+                    "unknown action {other:?} (expected drop, error, warning, info or hint)"
+                ))
+            }
+        };
+        Ok(Self {
+            code: code.to_string(),
+            action,
+        })
+    }
+}
+
 /// Open document
 #[derive(Debug, Clone)]
 pub struct OpenDocument {
@@ -56,6 +200,231 @@ pub struct OpenDocument {
     pub version: i32,
     pub text: String,
     pub venv: Option<PathBuf>,
+    /// Last version delivered to a backend (didOpen, didChange, or restore)
+    pub forwarded: Option<ForwardedVersion>,
+    /// Last didOpen/didChange for this document (`--max-cached-documents`)
+    pub last_touched: Instant,
+    /// `text` was dropped to honour `--max-cached-documents`; it is restored by
+    /// the next full-text change and the document is skipped on backend restore
+    pub text_dropped: bool,
+}
+
+/// A document version delivered to one backend process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardedVersion {
+    pub venv: PathBuf,
+    /// Session of the backend that received it; a respawned backend
+    /// has not seen it
+    pub session: u64,
+    pub version: i32,
+}
+
+/// Optional proxy behaviors configured from the CLI
+#[derive(Debug, Clone)]
+pub struct ProxyOptions {
+    /// Warn about messages with unknown top-level JSON-RPC fields
+    pub strict_json: bool,
+    /// Maximum number of requests queued per warming backend (None = unbounded)
+    pub warmup_queue_limit: Option<usize>,
+    /// Index-dependent methods forwarded immediately even while a backend warms up
+    pub no_warmup_methods: Vec<String>,
+    /// Method prefixes of backend-specific extensions to forward (`--forward-custom-methods`)
+    pub forward_custom_methods: Vec<String>,
+    /// Read client messages from a recorded JSONL transcript instead of stdin
+    pub replay: Option<PathBuf>,
+    /// Accept a single client over TCP on this address instead of stdio
+    pub listen: Option<String>,
+    /// Handling of responses that arrive from a backend that no longer owns the request
+    pub on_stale_response: StaleResponsePolicy,
+    /// Serve Prometheus metrics on this TCP address (`host:port`)
+    pub metrics_addr: Option<String>,
+    /// Unix socket serving a JSON state snapshot per connection
+    pub status_socket: Option<PathBuf>,
+    /// `languageId` assumed when a didOpen omits it
+    pub default_language_id: String,
+    /// Keep non-Python documents away from the (Python-only) backends
+    pub skip_non_python_documents: bool,
+    /// Deep-merged into the client's `capabilities` in initialize params sent to backends
+    pub client_capabilities_augment: Option<serde_json::Value>,
+    /// Answer hovers from the cached document after this long without a backend response
+    pub hover_timeout: Option<Duration>,
+    /// Cancel requests a backend hasn't answered after this long (`--request-timeout`)
+    pub request_timeout: Option<Duration>,
+    /// Grow the backend cap up to this ceiling under eviction thrash (`--auto-scale-backends`)
+    pub auto_scale_ceiling: Option<usize>,
+    /// Initialize params used when no client `initialize` arrives (headless runs)
+    pub synthetic_initialize: Option<PathBuf>,
+    /// Script run (and awaited) before each backend is spawned
+    pub backend_startup_script: Option<PathBuf>,
+    /// Suppress an empty publishDiagnostics when the URI's last one was empty too
+    pub dedup_empty_diagnostics: bool,
+    /// Clear a document's diagnostics on the client when it closes
+    pub clear_diagnostics_on_close: bool,
+    /// Strip ANSI escape sequences from backend log/show message text
+    pub strip_ansi: bool,
+    /// Log method/venv/elapsed time for each answered client request
+    pub log_request_correlation: bool,
+    /// Re-issue read-only requests to the respawned backend after a crash
+    pub transparent_retry: bool,
+    /// Reject URI-less requests even when only one backend is running
+    pub strict_uri_routing: bool,
+    /// Maximum number of backends spawned/initialized at the same time
+    pub max_concurrent_spawns: usize,
+    /// Replace backend responses larger than this (serialized bytes) with an error
+    pub response_size_limit: Option<usize>,
+    /// Exit once this many backend crashes happen within `crash_window`
+    pub crash_threshold: Option<usize>,
+    /// Sliding window for `crash_threshold` and `circuit_breaker_threshold`
+    pub crash_window: Duration,
+    /// Stop respawning a venv's backend after this many crashes within `crash_window`
+    pub circuit_breaker_threshold: Option<usize>,
+    /// How long a tripped circuit breaker keeps the venv disabled
+    pub circuit_breaker_cooldown: Duration,
+    /// Name reported as `serverInfo.name` and prefixed to client-facing messages
+    pub proxy_name: String,
+    /// Capacity (bytes) of the buffer over each backend's stdout
+    pub read_buffer_size: usize,
+    /// Environment variables backends inherit; `None` inherits everything
+    pub backend_env_passthrough: Option<Vec<String>>,
+    /// Per-venv backend overrides, first match wins (`--backend-map`)
+    pub backend_map: Vec<BackendMapping>,
+    /// Handling of didChange notifications that don't advance the document version
+    pub did_change_version_check: VersionRegressionPolicy,
+    /// Per-code diagnostic rewrites; later rules win
+    pub diagnostic_code_rules: Vec<DiagnosticCodeRule>,
+    /// Log a one-line pool summary this often
+    pub pool_metrics_log_interval: Option<Duration>,
+    /// Treat a full-text didChange for an unknown document as its didOpen
+    pub allow_did_change_before_did_open: bool,
+    /// Forward every didChange as a full-document replace built from the cache
+    pub force_full_sync: bool,
+    /// Keep the text of at most this many documents (`--max-cached-documents`)
+    pub max_cached_documents: Option<usize>,
+    /// Coalesce a document's didChanges until it has been quiet this long (`--didchange-debounce`)
+    pub didchange_debounce: Option<Duration>,
+    /// Code action kinds (and their sub-kinds) removed from codeAction responses
+    pub filter_code_action_kinds: Vec<String>,
+    /// Keep duplicate symbols when merging workspace/symbol fan-out results
+    pub no_dedup: bool,
+    /// Open every project file in a backend once it is initialized
+    pub preindex_open_files: bool,
+    /// Start backends for every workspace venv after `initialized`
+    pub prewarm: bool,
+    /// Venvs whose backends are never evicted (`--pin-venv`)
+    pub pinned_venvs: Vec<PathBuf>,
+    /// TTL eviction never shrinks the pool below this many backends
+    pub min_idle: usize,
+    /// Close and re-open a document on its backend when a didOpen changes its languageId
+    pub reopen_on_language_id_change: bool,
+    /// Client requests allowed per second (`--max-message-rate`)
+    pub max_message_rate: Option<u32>,
+    /// Re-publish cached diagnostics (marked stale) when a backend is recreated
+    pub preserve_diagnostics_on_respawn: bool,
+    /// Venv discovery behaviour (`--trace-venv-resolution`, `--detect-direnv`)
+    pub venv_search: VenvSearch,
+    /// Shut down this long after startup regardless of activity (`--exit-after`)
+    pub exit_after: Option<Duration>,
+    /// Remember directories without a venv for a few seconds (`--cache-venv-negative-results`)
+    pub cache_venv_negative_results: bool,
+    /// Evict idle backends this long after the last open document closes (`--evict-on-empty`)
+    pub evict_on_empty: Option<Duration>,
+    /// How long to wait for a backend's initialize response
+    pub initialize_timeout: Duration,
+    /// What to do when that wait times out
+    pub init_timeout_action: InitTimeoutAction,
+    /// How long the `disable` action keeps a venv disabled
+    pub init_disable_cooldown: Duration,
+    /// How long a new backend queues index-dependent requests (`--warmup-timeout`, zero = no gating)
+    pub warmup_timeout: Duration,
+    /// File recording recently warmed venvs (`--warmup-state-persist`)
+    pub warmup_state_persist: Option<PathBuf>,
+    /// How long a persisted warmup is trusted (`--warmup-state-window`)
+    pub warmup_state_window: Duration,
+    /// Send `initialized` to each backend session at most once
+    pub forward_initialized_once: bool,
+    /// Signal used when a backend doesn't exit after shutdown/exit
+    pub backend_kill_signal: KillSignal,
+    /// Injected into pyright backends' settings (`--pyright-diagnostic-mode`)
+    pub pyright_diagnostic_mode: Option<PyrightDiagnosticMode>,
+    /// Gzip large frames to the client if it advertised support
+    pub compress_frames: bool,
+    /// Periodically warn about documents whose backend is behind the cached version
+    pub document_sync_verify: bool,
+    /// Close backend progress tokens idle for this long (None = never)
+    pub progress_token_ttl: Option<Duration>,
+}
+
+/// The options of a run without flags (keep in sync with the CLI defaults)
+impl Default for ProxyOptions {
+    fn default() -> Self {
+        Self {
+            strict_json: false,
+            warmup_queue_limit: None,
+            no_warmup_methods: Vec::new(),
+            forward_custom_methods: Vec::new(),
+            replay: None,
+            listen: None,
+            on_stale_response: StaleResponsePolicy::default(),
+            metrics_addr: None,
+            status_socket: None,
+            default_language_id: "python".to_string(),
+            skip_non_python_documents: false,
+            client_capabilities_augment: None,
+            hover_timeout: None,
+            request_timeout: None,
+            auto_scale_ceiling: None,
+            synthetic_initialize: None,
+            backend_startup_script: None,
+            dedup_empty_diagnostics: false,
+            clear_diagnostics_on_close: false,
+            strip_ansi: false,
+            log_request_correlation: false,
+            transparent_retry: false,
+            strict_uri_routing: false,
+            max_concurrent_spawns: 2,
+            response_size_limit: None,
+            crash_threshold: None,
+            crash_window: Duration::from_secs(60),
+            circuit_breaker_threshold: None,
+            circuit_breaker_cooldown: Duration::from_secs(120),
+            proxy_name: "typemux-cc".to_string(),
+            read_buffer_size: crate::framing::DEFAULT_READ_BUFFER_SIZE,
+            backend_env_passthrough: None,
+            backend_map: Vec::new(),
+            did_change_version_check: VersionRegressionPolicy::default(),
+            diagnostic_code_rules: Vec::new(),
+            pool_metrics_log_interval: None,
+            allow_did_change_before_did_open: false,
+            force_full_sync: false,
+            max_cached_documents: None,
+            didchange_debounce: None,
+            filter_code_action_kinds: Vec::new(),
+            no_dedup: false,
+            preindex_open_files: false,
+            prewarm: false,
+            pinned_venvs: Vec::new(),
+            min_idle: 0,
+            reopen_on_language_id_change: false,
+            max_message_rate: None,
+            preserve_diagnostics_on_respawn: false,
+            venv_search: VenvSearch::default(),
+            exit_after: None,
+            cache_venv_negative_results: false,
+            evict_on_empty: None,
+            initialize_timeout: Duration::from_secs(10),
+            init_timeout_action: InitTimeoutAction::default(),
+            init_disable_cooldown: Duration::from_secs(300),
+            warmup_timeout: crate::backend_pool::DEFAULT_WARMUP_TIMEOUT,
+            warmup_state_persist: None,
+            warmup_state_window: Duration::from_secs(300),
+            forward_initialized_once: false,
+            backend_kill_signal: KillSignal::default(),
+            pyright_diagnostic_mode: None,
+            compress_frames: false,
+            document_sync_verify: false,
+            progress_token_ttl: None,
+        }
+    }
 }
 
 /// State held by proxy
@@ -63,22 +432,48 @@ pub struct ProxyState {
     /// Which LSP backend to use
     pub backend_kind: BackendKind,
 
+    /// Optional behaviors configured from the CLI
+    pub options: ProxyOptions,
+
     /// Git toplevel (search boundary, cached on first retrieval)
     pub git_toplevel: Option<PathBuf>,
+    /// Recent venv misses by directory (`--cache-venv-negative-results`)
+    pub venv_negative_cache: Option<NegativeVenvCache>,
+    /// Poetry envs by project directory (`--enable-poetry`)
+    pub poetry_envs: PoetryEnvCache,
+    /// Project directory of venvs found outside their project (e.g. Poetry's
+    /// cache); other venvs belong to their parent directory
+    pub venv_projects: HashMap<PathBuf, PathBuf>,
+    /// Recently warmed venvs shared across restarts (`--warmup-state-persist`)
+    pub warmup_state: Option<WarmupStateStore>,
 
     /// Initialize message from Claude Code (reused for backend initialization)
     pub client_initialize: Option<RpcMessage>,
 
+    /// Latest `workspace/didChangeConfiguration` from the client (replayed to new backends)
+    pub client_configuration: Option<RpcMessage>,
+
     /// Open documents
     pub open_documents: HashMap<Url, OpenDocument>,
 
     /// Pending requests (client → backend)
     pub pending_requests: HashMap<RpcId, PendingRequest>,
 
+    /// `--transparent-retry`: requests of a crashed backend waiting to go
+    /// through the main loop again, like client messages
+    pub reissued_requests: VecDeque<RpcMessage>,
+
+    /// Ids of re-issued requests not registered as pending yet; they are
+    /// registered as already retried
+    pub reissued_ids: HashSet<RpcId>,
+
     /// Pending backend requests (backend → client, keyed by proxy_id)
     /// Maps proxy_id → PendingBackendRequest to route client responses back to correct backend
     pub pending_backend_requests: HashMap<RpcId, PendingBackendRequest>,
 
+    /// Dynamic registrations forwarded to the client, keyed by method + options
+    pub registered_capabilities: HashMap<String, CapabilityRegistration>,
+
     /// Next proxy ID for server→client requests (monotonically increasing to avoid collisions)
     pub next_proxy_request_id: i64,
 
@@ -87,6 +482,73 @@ pub struct ProxyState {
 
     /// Pending fan-out requests (keyed by client request ID)
     pub pending_fanouts: HashMap<RpcId, PendingFanout>,
+
+    /// Cumulative counters exported via `--metrics-addr`
+    pub metrics: Metrics,
+
+    /// Hover requests awaiting a backend response with a fallback deadline
+    pub pending_hovers: HashMap<RpcId, PendingHover>,
+
+    /// Hover ids already answered by a fallback (late backend responses are
+    /// dropped), with the (venv, session) that still owes the response
+    pub fallback_answered_hovers: HashMap<RpcId, (PathBuf, u64)>,
+
+    /// Request ids the proxy already answered with RequestCancelled
+    /// (`--request-timeout`, `--on-stale-response cancel`), with the backend
+    /// (venv, session) and when (late backend responses are dropped)
+    pub timed_out_requests: HashMap<RpcId, (PathBuf, u64, Instant)>,
+
+    /// Backend progress tokens that have begun but not ended, keyed by
+    /// (session, serialized token) so equal tokens from different backends don't collide
+    pub active_progress: HashMap<(u64, String), ActiveProgress>,
+
+    /// Gate for concurrent backend spawns (`--max-concurrent-spawns`)
+    pub spawn_limiter: SpawnLimiter,
+
+    /// Proxy-assigned partialResultToken → (client's token, request id)
+    pub partial_result_tokens: HashMap<String, (serde_json::Value, RpcId)>,
+
+    /// Counter for proxy-assigned partialResultTokens
+    pub next_partial_result_token: u64,
+
+    /// Client-visible work-done progress token → (venv, session, backend's token),
+    /// for tokens a backend created with `window/workDoneProgress/create`
+    pub work_done_tokens: HashMap<String, (PathBuf, u64, serde_json::Value)>,
+
+    /// Documents with didChanges not yet forwarded, by flush deadline (`--didchange-debounce`)
+    pub debounced_did_changes: HashMap<Url, Instant>,
+
+    /// Diagnostic count of the last publishDiagnostics forwarded per URI
+    /// (`--dedup-empty-diagnostics`)
+    pub published_diagnostic_counts: HashMap<String, usize>,
+
+    /// Venvs whose backend timed out on initialize, and until when they stay
+    /// disabled (`--initialize-response-timeout-action disable`)
+    pub disabled_venvs: HashMap<PathBuf, Instant>,
+
+    /// Consecutive failed launches per venv; the next one waits for
+    /// `backend::SPAWN_FAILURE_COOLDOWN`
+    pub spawn_failures: HashMap<PathBuf, SpawnFailure>,
+
+    /// Venvs refused new backends after repeated crashes (`--circuit-breaker-threshold`)
+    pub crash_breaker: Option<CrashBreaker>,
+
+    /// Last non-empty diagnostics forwarded per URI
+    /// (`--backend-respawn-preserve-diagnostics`)
+    pub cached_diagnostics: HashMap<String, Vec<serde_json::Value>>,
+
+    /// Token bucket for client requests (`--max-message-rate`)
+    pub request_rate_limiter: Option<TokenBucket>,
+    /// Whether rate limiting has already been reported in the log (cleared
+    /// once requests stay under the limit for a whole burst)
+    pub rate_limit_engaged: bool,
+
+    /// When the document cache last became empty (`--evict-on-empty`)
+    pub documents_empty_since: Option<Instant>,
+
+    /// Whether the last TTL sweep kept expired backends for `--min-idle`,
+    /// so the log reports it once rather than every sweep
+    pub ttl_eviction_suppressed: bool,
 }
 
 impl ProxyState {
@@ -94,17 +556,69 @@ impl ProxyState {
         backend_kind: BackendKind,
         max_backends: usize,
         backend_ttl: Option<Duration>,
+        options: ProxyOptions,
     ) -> Self {
+        let mut pool = BackendPool::new(max_backends, backend_ttl);
+        if let Some(ceiling) = options.auto_scale_ceiling {
+            pool.enable_autoscale(ceiling);
+        }
+        pool.set_warmup_timeout(options.warmup_timeout);
+        pool.set_pinned_venvs(options.pinned_venvs.iter().cloned());
+        let spawn_limiter = SpawnLimiter::new(options.max_concurrent_spawns);
+        let venv_negative_cache = options
+            .cache_venv_negative_results
+            .then(|| NegativeVenvCache::new(crate::venv::NEGATIVE_CACHE_TTL));
+        let request_rate_limiter = options.max_message_rate.map(TokenBucket::new);
+        let crash_breaker = options.circuit_breaker_threshold.map(|threshold| {
+            CrashBreaker::new(
+                threshold,
+                options.crash_window,
+                options.circuit_breaker_cooldown,
+            )
+        });
+        let warmup_state = options
+            .warmup_state_persist
+            .clone()
+            .map(|path| WarmupStateStore::load(path, options.warmup_state_window));
+
         Self {
             backend_kind,
+            options,
             git_toplevel: None,
+            venv_negative_cache,
+            poetry_envs: PoetryEnvCache::default(),
+            venv_projects: HashMap::new(),
+            warmup_state,
             client_initialize: None,
+            client_configuration: None,
             open_documents: HashMap::new(),
             pending_requests: HashMap::new(),
+            reissued_requests: VecDeque::new(),
+            reissued_ids: HashSet::new(),
             pending_backend_requests: HashMap::new(),
+            registered_capabilities: HashMap::new(),
             next_proxy_request_id: -1, // Use negative IDs to avoid collision with client IDs
-            pool: BackendPool::new(max_backends, backend_ttl),
+            pool,
             pending_fanouts: HashMap::new(),
+            metrics: Metrics::default(),
+            pending_hovers: HashMap::new(),
+            fallback_answered_hovers: HashMap::new(),
+            timed_out_requests: HashMap::new(),
+            debounced_did_changes: HashMap::new(),
+            active_progress: HashMap::new(),
+            disabled_venvs: HashMap::new(),
+            spawn_failures: HashMap::new(),
+            crash_breaker,
+            documents_empty_since: None,
+            ttl_eviction_suppressed: false,
+            request_rate_limiter,
+            rate_limit_engaged: false,
+            cached_diagnostics: HashMap::new(),
+            published_diagnostic_counts: HashMap::new(),
+            partial_result_tokens: HashMap::new(),
+            next_partial_result_token: 0,
+            work_done_tokens: HashMap::new(),
+            spawn_limiter,
         }
     }
 
@@ -116,6 +630,70 @@ impl ProxyState {
         RpcId::Number(id)
     }
 
+    /// Backend kind to spawn for `venv` (`--backend-map`, else the global `--backend`)
+    pub fn backend_kind_for(&self, venv: &Path) -> BackendKind {
+        backend::backend_kind_for(
+            &self.options.backend_map,
+            venv,
+            self.git_toplevel.as_deref(),
+            self.backend_kind,
+        )
+    }
+
+    /// Remember which project a found venv belongs to and return the venv
+    /// Whether `venv` is disabled after an initialize timeout; an expired
+    /// disable is forgotten
+    pub fn is_disabled(&mut self, venv: &Path, now: Instant) -> bool {
+        match self.disabled_venvs.get(venv) {
+            Some(&until) if now < until => true,
+            Some(_) => {
+                tracing::info!(venv = %venv.display(), "Initialize timeout cooldown over, re-enabling backend");
+                self.disabled_venvs.remove(venv);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn remember_project(&mut self, found: ProjectVenv) -> PathBuf {
+        if found.venv.parent() == Some(found.project.as_path()) {
+            self.venv_projects.remove(&found.venv);
+        } else {
+            self.venv_projects.insert(found.venv.clone(), found.project);
+        }
+        found.venv
+    }
+
+    /// Project directory a backend for `venv` is rooted at
+    pub fn project_root(&self, venv: &Path) -> Option<PathBuf> {
+        match self.venv_projects.get(venv) {
+            Some(project) => Some(project.clone()),
+            None => venv.parent().map(Path::to_path_buf),
+        }
+    }
+
+    /// Warmup timeout for a new backend for `venv`: zero when a previous
+    /// process warmed it recently (`--warmup-state-persist`)
+    pub fn warmup_timeout_for(&self, venv: &Path) -> Duration {
+        match &self.warmup_state {
+            Some(store) if store.is_fresh(venv, std::time::SystemTime::now()) => {
+                tracing::info!(venv = %venv.display(), "Venv warmed recently, skipping warmup");
+                Duration::ZERO
+            }
+            _ => self.pool.warmup_timeout(),
+        }
+    }
+
+    /// Sample the current gauge values for metrics export
+    pub fn gauges(&self) -> Gauges {
+        Gauges {
+            pool_size: self.pool.len(),
+            warming_backends: self.pool.warming_backends().len(),
+            pending_requests: self.pending_requests.len(),
+            open_documents: self.open_documents.len(),
+        }
+    }
+
     /// Return the nearest fan-out deadline among all pending fan-outs.
     /// Returns None if no fan-outs are pending.
     pub fn nearest_fanout_deadline(&self) -> Option<Instant> {
@@ -124,4 +702,29 @@ impl ProxyState {
             .filter_map(|f| f.deadline)
             .min()
     }
+
+    /// Return the nearest hover fallback deadline, if any hover is pending.
+    pub fn nearest_hover_deadline(&self) -> Option<Instant> {
+        self.pending_hovers.values().map(|h| h.deadline).min()
+    }
+
+    /// Return when the next debounced didChange is due (`--didchange-debounce`).
+    pub fn nearest_did_change_deadline(&self) -> Option<Instant> {
+        self.debounced_did_changes.values().min().copied()
+    }
+
+    /// Return when the empty-document-cache grace period ends (`--evict-on-empty`).
+    pub fn empty_eviction_deadline(&self) -> Option<Instant> {
+        let grace = self.options.evict_on_empty?;
+        self.documents_empty_since.map(|since| since + grace)
+    }
+
+    /// Return when the next idle progress token expires (`--progress-token-ttl`).
+    pub fn nearest_progress_deadline(&self) -> Option<Instant> {
+        let ttl = self.options.progress_token_ttl?;
+        self.active_progress
+            .values()
+            .map(|p| p.last_activity + ttl)
+            .min()
+    }
 }
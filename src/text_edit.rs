@@ -52,7 +52,8 @@ pub(crate) fn apply_incremental_change(
 }
 
 /// Convert LSP position (line, character) to byte offset
-/// LSP character is UTF-16 code unit count
+/// LSP character is UTF-16 code unit count. For a `\r\n` line ending the `\r`
+/// belongs to the terminator, so characters past the line clamp before it.
 pub(crate) fn position_to_offset(
     text: &str,
     line: usize,
@@ -64,7 +65,12 @@ pub(crate) fn position_to_offset(
     for (idx, ch) in text.char_indices() {
         if ch == '\n' {
             if current_line == line {
-                return find_offset_in_line(text, line_start_offset, idx, character);
+                let line_end = if text[line_start_offset..idx].ends_with('\r') {
+                    idx - 1
+                } else {
+                    idx
+                };
+                return find_offset_in_line(text, line_start_offset, line_end, character);
             }
             current_line += 1;
             line_start_offset = idx + 1;
@@ -203,6 +209,32 @@ mod tests {
         assert_eq!(position_to_offset(text, 1, 100).unwrap(), 7);
     }
 
+    #[test]
+    fn test_position_to_offset_crlf() {
+        let text = "abc\r\ndef\r\n";
+
+        assert_eq!(position_to_offset(text, 0, 0).unwrap(), 0);
+        assert_eq!(position_to_offset(text, 0, 3).unwrap(), 3);
+        // Past the end of the line: before the \r, not between \r and \n
+        assert_eq!(position_to_offset(text, 0, 4).unwrap(), 3);
+        assert_eq!(position_to_offset(text, 1, 0).unwrap(), 5);
+        assert_eq!(position_to_offset(text, 1, 3).unwrap(), 8);
+        assert_eq!(position_to_offset(text, 1, 100).unwrap(), 8);
+        assert_eq!(position_to_offset(text, 2, 0).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_apply_incremental_change_crlf_line_end() {
+        let mut text = "abc\r\ndef\r\n".to_string();
+        let range = json!({
+            "start": { "line": 0, "character": 3 },
+            "end": { "line": 0, "character": 10 }
+        });
+
+        apply_incremental_change(&mut text, &range, "!").unwrap();
+        assert_eq!(text, "abc!\r\ndef\r\n");
+    }
+
     #[test]
     fn test_position_to_offset_line_out_of_range() {
         let text = "abc\ndef\n";
@@ -1,9 +1,170 @@
+use crate::backend::{backend_kind_for, BackendKind, BackendMapping};
 use crate::error::VenvError;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
-const VENV_DIR: &str = ".venv";
+/// Venv directory name used when `--venv-dir` is not given
+pub const DEFAULT_VENV_DIR: &str = ".venv";
 const PYVENV_CFG: &str = "pyvenv.cfg";
+const ENVRC: &str = ".envrc";
+const PYPROJECT: &str = "pyproject.toml";
+/// PEP 582 local packages directory, holding one `<X.Y>` env per Python version
+const PYPACKAGES: &str = "__pypackages__";
+const PYRIGHTCONFIG: &str = "pyrightconfig.json";
+
+/// Upper bound for `poetry env info -p`
+const POETRY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a lookup waits for `poetry env info -p` before answering without
+/// it; the run goes on in the background and a later lookup picks it up
+const POETRY_WAIT: Duration = Duration::from_millis(500);
+
+/// How long a project without a Poetry env is not asked again
+const POETRY_MISS_TTL: Duration = Duration::from_secs(30);
+
+/// How long a negative venv lookup is trusted (`--cache-venv-negative-results`)
+pub const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Venv discovery behaviour
+#[derive(Debug, Clone)]
+pub struct VenvSearch {
+    /// Log the full search path at info level (`--trace-venv-resolution`)
+    pub trace: bool,
+    /// Fall back to a `VIRTUAL_ENV` assignment in an ancestor `.envrc` (`--detect-direnv`)
+    pub detect_direnv: bool,
+    /// Venv directory names checked in order at every level (`--venv-dir`)
+    pub venv_dirs: Vec<String>,
+    /// Ask Poetry for the env of an enclosing `[tool.poetry]` project (`--enable-poetry`)
+    pub enable_poetry: bool,
+    /// Backend for venvs no `backend_map` entry matches (`--backend`).
+    /// `venvPath`/`venv` from pyright config files only count for venvs
+    /// served by pyright.
+    pub backend: BackendKind,
+    /// Per-directory backends (`--backend-map`)
+    pub backend_map: Vec<BackendMapping>,
+    /// Among venvs in the same directory, prefer this Python version (`--prefer-python`)
+    pub prefer_python: Option<String>,
+}
+
+impl Default for VenvSearch {
+    fn default() -> Self {
+        Self {
+            trace: false,
+            detect_direnv: false,
+            venv_dirs: vec![DEFAULT_VENV_DIR.to_string()],
+            enable_poetry: false,
+            backend: BackendKind::Pyright,
+            backend_map: Vec::new(),
+            prefer_python: None,
+        }
+    }
+}
+
+/// The first of `venv_dirs` under `dir` that contains a `pyvenv.cfg`
+pub fn venv_in(dir: &Path, venv_dirs: &[String]) -> Option<PathBuf> {
+    venv_dirs
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|venv| venv.join(PYVENV_CFG).exists())
+}
+
+/// Like [`venv_in`], but when several of `venv_dirs` hold a venv, the first
+/// whose Python version matches `prefer_python` wins
+pub fn preferred_venv_in(
+    dir: &Path,
+    venv_dirs: &[String],
+    prefer_python: Option<&str>,
+) -> Option<PathBuf> {
+    let Some(preferred) = prefer_python else {
+        return venv_in(dir, venv_dirs);
+    };
+    let candidates: Vec<PathBuf> = venv_dirs
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|venv| venv.join(PYVENV_CFG).exists())
+        .collect();
+    if candidates.len() > 1 {
+        if let Some(venv) = candidates.iter().find(|venv| {
+            venv_python_version(venv).is_some_and(|v| python_version_matches(&v, preferred))
+        }) {
+            tracing::debug!(
+                venv = %venv.display(),
+                prefer_python = preferred,
+                "Preferring venv by Python version"
+            );
+            return Some(venv.clone());
+        }
+    }
+    candidates.into_iter().next()
+}
+
+/// Python version recorded in a venv's `pyvenv.cfg` (`version`, or
+/// `version_info` as written by virtualenv/uv)
+pub fn venv_python_version(venv: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(venv.join(PYVENV_CFG)).ok()?;
+    let mut version_info = None;
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "version" => return Some(value.trim().to_string()),
+            "version_info" => version_info = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    version_info
+}
+
+/// Whether `version` (e.g. `3.12.1`) is `preferred` (e.g. `3.12`) or a
+/// release of it, compared component by component
+fn python_version_matches(version: &str, preferred: &str) -> bool {
+    let mut version = version.split('.');
+    preferred
+        .split('.')
+        .all(|component| version.next() == Some(component))
+}
+
+/// Whether `dir` contains a venv under any of `venv_dirs`
+pub fn has_venv(dir: &Path, venv_dirs: &[String]) -> bool {
+    venv_in(dir, venv_dirs).is_some()
+}
+
+/// Path of the venv's Python interpreter
+pub fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// Whether the venv's interpreter exists and is executable. A `pyvenv.cfg`
+/// alone is not enough: `bin/python` is usually a symlink to the base
+/// interpreter and dangles once that is removed.
+pub fn has_usable_interpreter(venv: &Path) -> bool {
+    // A PEP 582 env has no interpreter of its own; the system one runs it
+    if is_pep582_env(venv) {
+        return true;
+    }
+    // metadata() follows symlinks, so a dangling link counts as missing
+    let Ok(meta) = std::fs::metadata(venv_python(venv)) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        meta.is_file()
+    }
+}
 
 /// Execute git rev-parse --show-toplevel and get result
 pub async fn get_git_toplevel(working_dir: &Path) -> Result<Option<PathBuf>, VenvError> {
@@ -31,21 +192,173 @@ pub async fn get_git_toplevel(working_dir: &Path) -> Result<Option<PathBuf>, Ven
     }
 }
 
+/// A venv and the directory of the project it belongs to. For an in-tree
+/// venv that is its parent; an env kept elsewhere (e.g. Poetry's cache)
+/// belongs to the directory that configured it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectVenv {
+    pub venv: PathBuf,
+    pub project: PathBuf,
+}
+
+impl ProjectVenv {
+    /// A venv inside its project directory
+    pub fn in_tree(venv: PathBuf) -> Self {
+        let project = venv.parent().unwrap_or(&venv).to_path_buf();
+        Self { venv, project }
+    }
+}
+
+/// Outcome of a `.venv` search, with every directory probed along the way
+#[derive(Debug, Default)]
+pub struct VenvResolution {
+    /// Directories checked, in order, and whether `.venv/pyvenv.cfg` existed there
+    pub probed: Vec<(PathBuf, bool)>,
+    /// First directory outside the git toplevel, if the search stopped there
+    pub boundary: Option<PathBuf>,
+    /// The selected venv
+    pub venv: Option<PathBuf>,
+    /// `.envrc` the venv was read from, when no `.venv` was found
+    pub envrc: Option<PathBuf>,
+    /// Directory that selected the venv, when that isn't the venv's parent
+    pub project: Option<PathBuf>,
+}
+
+impl VenvResolution {
+    /// The selected venv with the project it belongs to
+    pub fn project_venv(&self) -> Option<ProjectVenv> {
+        let venv = self.venv.clone()?;
+        Some(match &self.project {
+            Some(project) => ProjectVenv {
+                venv,
+                project: project.clone(),
+            },
+            None => ProjectVenv::in_tree(venv),
+        })
+    }
+
+    /// Render probed directories as `dir (found|missing)`, comma-separated
+    fn probed_summary(&self) -> String {
+        self.probed
+            .iter()
+            .map(|(dir, found)| {
+                format!(
+                    "{} ({})",
+                    dir.display(),
+                    if *found { "found" } else { "missing" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 /// Search for .venv by traversing parent directories from file path
 ///
 /// # Arguments
 /// * `file_path` - Starting file path
 /// * `git_toplevel` - Search boundary (if None, search up to root)
+/// * `search` - Optional discovery behaviour (tracing, direnv)
+/// * `poetry_envs` - Earlier `poetry env info` answers (`--enable-poetry`,
+///   which also enables PEP 582 `__pypackages__` envs)
 pub async fn find_venv(
     file_path: &Path,
     git_toplevel: Option<&Path>,
-) -> Result<Option<PathBuf>, VenvError> {
+    search: &VenvSearch,
+    poetry_envs: &mut PoetryEnvCache,
+) -> Result<Option<ProjectVenv>, VenvError> {
+    let resolution = resolve_venv(file_path, git_toplevel, search);
+    let mut found = resolution.project_venv();
+    if found.is_none() && search.enable_poetry {
+        found = find_pep582_env(file_path, git_toplevel, search.prefer_python.as_deref());
+    }
+    if found.is_none() && search.enable_poetry {
+        found = find_poetry_venv(file_path, git_toplevel, poetry_envs).await;
+    }
+    if search.trace {
+        tracing::info!(
+            file = %file_path.display(),
+            toplevel = ?git_toplevel.map(|p| p.display().to_string()),
+            probed = %resolution.probed_summary(),
+            boundary = ?resolution.boundary.as_ref().map(|p| p.display().to_string()),
+            venv = ?found.as_ref().map(|f| f.venv.display().to_string()),
+            project = ?found.as_ref().map(|f| f.project.display().to_string()),
+            envrc = ?resolution.envrc.as_ref().map(|p| p.display().to_string()),
+            "Venv resolution trace"
+        );
+    }
+    Ok(found)
+}
+
+/// Directories recently found to have no venv (`--cache-venv-negative-results`).
+///
+/// Keyed by the searched file's parent directory: every file there walks the
+/// same ancestors, so a miss holds for all of them until the entry expires.
+#[derive(Debug)]
+pub struct NegativeVenvCache {
+    ttl: Duration,
+    dirs: HashMap<PathBuf, Instant>,
+}
+
+impl NegativeVenvCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// `find_venv`, skipped while `file_path`'s directory has a fresh miss
+    pub async fn find_venv(
+        &mut self,
+        file_path: &Path,
+        git_toplevel: Option<&Path>,
+        search: &VenvSearch,
+        poetry_envs: &mut PoetryEnvCache,
+        now: Instant,
+    ) -> Result<Option<ProjectVenv>, VenvError> {
+        let Some(dir) = file_path.parent() else {
+            return find_venv(file_path, git_toplevel, search, poetry_envs).await;
+        };
+        let ttl = self.ttl;
+        self.dirs
+            .retain(|_, missed_at| now.saturating_duration_since(*missed_at) < ttl);
+        if self.dirs.contains_key(dir) {
+            tracing::trace!(dir = %dir.display(), "Negative venv cache hit");
+            return Ok(None);
+        }
+
+        let found = find_venv(file_path, git_toplevel, search, poetry_envs).await?;
+        // Poetry may still answer for this directory
+        if found.is_none() && !poetry_envs.is_running() {
+            self.dirs.insert(dir.to_path_buf(), now);
+        }
+        Ok(found)
+    }
+
+    /// Forget every miss (e.g. after a venv was found somewhere new)
+    pub fn clear(&mut self) {
+        self.dirs.clear();
+    }
+}
+
+/// Walk up from `file_path` until one of the venv directory names holds a
+/// `pyvenv.cfg` or the git toplevel (or filesystem root) is passed. With
+/// `detect_direnv`, the same directories are then checked for an `.envrc`
+/// exporting `VIRTUAL_ENV`.
+fn resolve_venv(
+    file_path: &Path,
+    git_toplevel: Option<&Path>,
+    search: &VenvSearch,
+) -> VenvResolution {
     tracing::debug!(
         file = %file_path.display(),
         toplevel = ?git_toplevel.map(|p| p.display().to_string()),
         "Starting .venv search"
     );
 
+    let mut resolution = VenvResolution::default();
+
     // Start from file's parent directory
     let mut current = file_path.parent();
     let mut depth = 0;
@@ -65,21 +378,36 @@ pub async fn find_venv(
                     toplevel = %toplevel.display(),
                     "Reached git toplevel boundary"
                 );
+                resolution.boundary = Some(dir.to_path_buf());
                 break;
             }
         }
 
-        // Check for .venv/pyvenv.cfg existence
-        let venv_path = dir.join(VENV_DIR);
-        let pyvenv_cfg = venv_path.join(PYVENV_CFG);
+        // An explicit pyright venv setting wins over directory names
+        let configured = resolve_configured_venv(dir, |venv| {
+            backend_kind_for(&search.backend_map, venv, git_toplevel, search.backend)
+                == BackendKind::Pyright
+        });
+
+        // A configured venv (often `venvPath = ".."`) belongs to the config's directory
+        if configured.is_some() {
+            resolution.project = Some(dir.to_path_buf());
+        }
+
+        // Check each venv directory name for pyvenv.cfg, in order
+        let found = configured
+            .or_else(|| preferred_venv_in(dir, &search.venv_dirs, search.prefer_python.as_deref()));
+        resolution.probed.push((dir.to_path_buf(), found.is_some()));
 
-        if pyvenv_cfg.exists() {
+        if let Some(venv_path) = found {
             tracing::info!(
                 venv = %venv_path.display(),
+                marker = ?venv_path.file_name().unwrap_or_default(),
                 depth = depth,
-                ".venv found"
+                "venv found"
             );
-            return Ok(Some(venv_path));
+            resolution.venv = Some(venv_path);
+            return resolution;
         }
 
         // Move to parent directory
@@ -87,63 +415,499 @@ pub async fn find_venv(
         depth += 1;
     }
 
+    if search.detect_direnv {
+        for (dir, _) in &resolution.probed {
+            if let Some(venv) = envrc_virtual_env(dir) {
+                tracing::info!(
+                    venv = %venv.display(),
+                    envrc = %dir.join(ENVRC).display(),
+                    "venv found via .envrc"
+                );
+                // e.g. `.direnv/python-3.12`: the project is the `.envrc` directory
+                resolution.envrc = Some(dir.join(ENVRC));
+                resolution.project = Some(dir.clone());
+                resolution.venv = Some(venv);
+                return resolution;
+            }
+        }
+    }
+
     tracing::warn!(
         file = %file_path.display(),
         depth = depth,
         "No .venv found"
     );
-    Ok(None)
+    resolution
+}
+
+/// The venv a `pyrightconfig.json` or `pyproject.toml` `[tool.pyright]` in
+/// `dir` points at (`venvPath`/`venv`, relative to the config file), if it
+/// has a `pyvenv.cfg` and `is_pyright` (it is served by a pyright backend).
+/// `pyrightconfig.json` takes precedence, as in pyright.
+pub fn resolve_configured_venv(
+    dir: &Path,
+    is_pyright: impl FnOnce(&Path) -> bool,
+) -> Option<PathBuf> {
+    let (config, settings) = if let Ok(content) = std::fs::read_to_string(dir.join(PYRIGHTCONFIG)) {
+        (PYRIGHTCONFIG, pyrightconfig_venv_settings(&content)?)
+    } else {
+        let content = std::fs::read_to_string(dir.join(PYPROJECT)).ok()?;
+        (PYPROJECT, pyproject_venv_settings(&content)?)
+    };
+    let (venv_path, venv_name) = settings;
+    let venv = dir
+        .join(venv_path.as_deref().unwrap_or("."))
+        .join(venv_name);
+    if !venv.join(PYVENV_CFG).exists() {
+        tracing::warn!(
+            config = %dir.join(config).display(),
+            venv = %venv.display(),
+            "Configured pyright venv has no pyvenv.cfg, ignoring"
+        );
+        return None;
+    }
+    // venvPath is often "..": normalize so the pool key matches other lookups
+    let venv = venv.canonicalize().unwrap_or(venv);
+    if !is_pyright(&venv) {
+        tracing::debug!(
+            config = %dir.join(config).display(),
+            venv = %venv.display(),
+            "Configured pyright venv is not served by pyright, ignoring"
+        );
+        return None;
+    }
+    tracing::info!(
+        config = %dir.join(config).display(),
+        venv = %venv.display(),
+        "venv found via pyright config"
+    );
+    Some(venv)
+}
+
+/// `(venvPath, venv)` from a pyrightconfig.json (JSON with comments and
+/// trailing commas, as pyright accepts); `venv` is required
+fn pyrightconfig_venv_settings(content: &str) -> Option<(Option<String>, String)> {
+    let config: serde_json::Value = match serde_json::from_str(&strip_jsonc(content)) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::warn!(error = %e, "Could not parse pyrightconfig.json, ignoring");
+            return None;
+        }
+    };
+    let venv = config.get("venv")?.as_str()?.to_string();
+    let venv_path = config
+        .get("venvPath")
+        .and_then(|v| v.as_str())
+        .map(str::to_string);
+    Some((venv_path, venv))
+}
+
+/// `content` with `//` and `/* */` comments and trailing commas removed,
+/// leaving string literals untouched
+fn strip_jsonc(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (']' | '}', _) => {
+                // Drop a trailing comma (and the whitespace after it)
+                let kept = out.trim_end().len();
+                if out[..kept].ends_with(',') {
+                    out.truncate(kept - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `(venvPath, venv)` from the `[tool.pyright]` table of a pyproject.toml
+/// (line scan of `key = "value"` pairs, no TOML parse); `venv` is required
+fn pyproject_venv_settings(content: &str) -> Option<(Option<String>, String)> {
+    let mut in_table = false;
+    let mut venv_path = None;
+    let mut venv = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_table = line == "[tool.pyright]";
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let Some(value) = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        else {
+            continue;
+        };
+        match key.trim() {
+            "venvPath" => venv_path = Some(value.to_string()),
+            "venv" => venv = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Some((venv_path, venv?))
+}
+
+/// Walk up from `file_path` (within the git toplevel) to the first
+/// `pyproject.toml` with a `[tool.poetry]` table and ask Poetry for its env.
+/// Poetry keeps envs outside the tree, e.g. `~/.cache/pypoetry/virtualenvs/<hash>`,
+/// so the project is the `pyproject.toml` directory.
+pub async fn find_poetry_venv(
+    file_path: &Path,
+    git_toplevel: Option<&Path>,
+    poetry_envs: &mut PoetryEnvCache,
+) -> Option<ProjectVenv> {
+    let project = file_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| git_toplevel.map_or(true, |top| dir.starts_with(top)))
+        .find(|dir| is_poetry_project(dir))?;
+    let venv = poetry_envs
+        .env_for(project, "poetry", Instant::now())
+        .await?;
+    Some(ProjectVenv {
+        venv,
+        project: project.to_path_buf(),
+    })
+}
+
+/// Walk up from `file_path` (within the git toplevel) to the first PEP 582
+/// `__pypackages__` directory and use its `<X.Y>` env: the `prefer_python`
+/// version if it has one, else the newest. The project is the directory
+/// holding `__pypackages__`.
+fn find_pep582_env(
+    file_path: &Path,
+    git_toplevel: Option<&Path>,
+    prefer_python: Option<&str>,
+) -> Option<ProjectVenv> {
+    let project = file_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| git_toplevel.map_or(true, |top| dir.starts_with(top)))
+        .find(|dir| dir.join(PYPACKAGES).is_dir())?;
+    let mut envs: Vec<((u32, u32), PathBuf)> = std::fs::read_dir(project.join(PYPACKAGES))
+        .ok()?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let (major, minor) = path.file_name()?.to_str()?.split_once('.')?;
+            let version = (major.parse().ok()?, minor.parse().ok()?);
+            path.join("lib").is_dir().then_some((version, path))
+        })
+        .collect();
+    envs.sort();
+    let preferred = prefer_python.and_then(|preferred| {
+        envs.iter().position(|((major, minor), _)| {
+            python_version_matches(&format!("{major}.{minor}"), preferred)
+        })
+    });
+    let (_, venv) = match preferred {
+        Some(i) => envs.swap_remove(i),
+        None => envs.pop()?,
+    };
+    tracing::info!(
+        project = %project.display(),
+        venv = %venv.display(),
+        "venv found via PEP 582 __pypackages__"
+    );
+    Some(ProjectVenv {
+        venv,
+        project: project.to_path_buf(),
+    })
+}
+
+/// Whether `venv` is a PEP 582 `__pypackages__/<X.Y>` env
+fn is_pep582_env(venv: &Path) -> bool {
+    venv.parent()
+        .and_then(Path::file_name)
+        .is_some_and(|name| name == PYPACKAGES)
+}
+
+/// `poetry env info -p` answers by project directory, so Poetry runs once
+/// per project instead of on every lookup. Projects without an env yet are
+/// asked again after `POETRY_MISS_TTL`. A run that outlasts `POETRY_WAIT`
+/// continues in the background rather than holding up the lookup.
+#[derive(Debug, Default)]
+pub struct PoetryEnvCache {
+    envs: HashMap<PathBuf, PathBuf>,
+    misses: HashMap<PathBuf, Instant>,
+    running: HashMap<PathBuf, JoinHandle<Option<PathBuf>>>,
+}
+
+impl PoetryEnvCache {
+    /// Whether a `poetry env info` run is still going in the background
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// The env of `project`, running `poetry` only when nothing usable is cached
+    async fn env_for(&mut self, project: &Path, poetry: &str, now: Instant) -> Option<PathBuf> {
+        if let Some(venv) = self.envs.get(project) {
+            // The env was removed (e.g. `poetry env remove`): ask again
+            if venv.join(PYVENV_CFG).exists() {
+                return Some(venv.clone());
+            }
+            self.envs.remove(project);
+        }
+        if self
+            .misses
+            .get(project)
+            .is_some_and(|missed_at| now.saturating_duration_since(*missed_at) < POETRY_MISS_TTL)
+        {
+            return None;
+        }
+        let mut run = self.running.remove(project).unwrap_or_else(|| {
+            let (project, poetry) = (project.to_path_buf(), poetry.to_string());
+            tokio::spawn(async move { poetry_env_path(&project, &poetry).await })
+        });
+        let result = match tokio::time::timeout(POETRY_WAIT, &mut run).await {
+            Ok(result) => result.ok().flatten(),
+            Err(_) => {
+                tracing::info!(
+                    project = %project.display(),
+                    "poetry env info still running, continuing without it"
+                );
+                self.running.insert(project.to_path_buf(), run);
+                return None;
+            }
+        };
+        match result {
+            Some(venv) => {
+                self.misses.remove(project);
+                self.envs.insert(project.to_path_buf(), venv.clone());
+                Some(venv)
+            }
+            None => {
+                self.misses.insert(project.to_path_buf(), now);
+                None
+            }
+        }
+    }
+}
+
+/// Whether `dir/pyproject.toml` declares a `[tool.poetry]` table (line scan, no TOML parse)
+fn is_poetry_project(dir: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(dir.join(PYPROJECT)) else {
+        return false;
+    };
+    content.lines().any(|line| {
+        let line = line.trim();
+        line.starts_with("[tool.poetry]") || line.starts_with("[tool.poetry.")
+    })
 }
 
-/// Search for fallback env (.venv search from cwd at startup)
-pub async fn find_fallback_venv(cwd: &Path) -> Result<Option<PathBuf>, VenvError> {
+/// Run `<poetry> env info -p` in `project` and return the path if it is a venv
+async fn poetry_env_path(project: &Path, poetry: &str) -> Option<PathBuf> {
+    let mut cmd = Command::new(poetry);
+    cmd.args(["env", "info", "-p"])
+        .current_dir(project)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    let output = match tokio::time::timeout(POETRY_TIMEOUT, cmd.output()).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        Ok(Ok(output)) => {
+            tracing::warn!(
+                project = %project.display(),
+                status = %output.status,
+                "poetry env info failed (no env created yet?)"
+            );
+            return None;
+        }
+        Ok(Err(e)) => {
+            tracing::warn!(project = %project.display(), error = ?e, "Could not run poetry");
+            return None;
+        }
+        Err(_) => {
+            tracing::warn!(project = %project.display(), "poetry env info timed out");
+            return None;
+        }
+    };
+    let venv = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    if venv.as_os_str().is_empty() || !venv.join(PYVENV_CFG).exists() {
+        tracing::warn!(
+            project = %project.display(),
+            venv = %venv.display(),
+            "poetry env has no pyvenv.cfg, ignoring"
+        );
+        return None;
+    }
+    tracing::info!(
+        project = %project.display(),
+        venv = %venv.display(),
+        "venv found via poetry"
+    );
+    Some(venv)
+}
+
+/// The venv named by `VIRTUAL_ENV` in `dir/.envrc`, if it has a `pyvenv.cfg`
+fn envrc_virtual_env(dir: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(dir.join(ENVRC)).ok()?;
+    let venv = parse_envrc_virtual_env(&content, dir)?;
+    if !venv.join(PYVENV_CFG).exists() {
+        tracing::warn!(
+            envrc = %dir.join(ENVRC).display(),
+            venv = %venv.display(),
+            ".envrc VIRTUAL_ENV has no pyvenv.cfg, ignoring"
+        );
+        return None;
+    }
+    Some(venv)
+}
+
+/// Find the last `[export] VIRTUAL_ENV=...` line in an `.envrc`. This is a
+/// plain line parse, not shell evaluation: quotes are stripped, a leading
+/// `$PWD`/`${PWD}` and relative paths resolve against `dir`, anything else
+/// containing `$` or a command substitution is ignored.
+fn parse_envrc_virtual_env(content: &str, dir: &Path) -> Option<PathBuf> {
+    let mut found = None;
+    for line in content.lines() {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim_end() != "VIRTUAL_ENV" {
+            continue;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        let value = value
+            .strip_prefix("${PWD}")
+            .or_else(|| value.strip_prefix("$PWD"))
+            .map(|rest| rest.trim_start_matches('/'))
+            .unwrap_or(value);
+        if value.is_empty() || value.contains('$') || value.contains('`') {
+            continue;
+        }
+        found = Some(dir.join(value));
+    }
+    found
+}
+
+/// The venv named by `VIRTUAL_ENV` (uv, virtualenvwrapper, an activated
+/// shell), if it has a `pyvenv.cfg`. It may live outside the project tree,
+/// so no git toplevel boundary applies.
+fn activated_venv(virtual_env: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    let venv = PathBuf::from(virtual_env.filter(|v| !v.is_empty())?);
+    if venv.join(PYVENV_CFG).exists() {
+        Some(venv)
+    } else {
+        tracing::debug!(
+            venv = %venv.display(),
+            "VIRTUAL_ENV has no pyvenv.cfg, ignoring"
+        );
+        None
+    }
+}
+
+/// Project an activated venv belongs to: the nearest `pyproject.toml`
+/// directory from `cwd` (within the git toplevel), else `cwd`
+fn activated_venv_project(cwd: &Path, git_toplevel: Option<&Path>) -> PathBuf {
+    cwd.ancestors()
+        .take_while(|dir| git_toplevel.map_or(true, |top| dir.starts_with(top)))
+        .find(|dir| dir.join(PYPROJECT).is_file())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// Search for fallback env at startup: an activated `VIRTUAL_ENV`, then a
+/// venv directory (any of `venv_dirs`) at the git toplevel, then in cwd
+pub async fn find_fallback_venv(
+    cwd: &Path,
+    venv_dirs: &[String],
+    prefer_python: Option<&str>,
+) -> Result<Option<ProjectVenv>, VenvError> {
     tracing::info!(cwd = %cwd.display(), "Searching for fallback .venv");
 
     // 1. Get git toplevel
     let git_toplevel = get_git_toplevel(cwd).await?;
 
-    // 2. Search for .venv from toplevel
-    if let Some(toplevel) = &git_toplevel {
-        let venv_path = toplevel.join(VENV_DIR);
-        let pyvenv_cfg = venv_path.join(PYVENV_CFG);
+    // 2. Activated environment, usually kept outside the tree (uv's cache)
+    if let Some(venv_path) = activated_venv(std::env::var_os("VIRTUAL_ENV")) {
+        let project = activated_venv_project(cwd, git_toplevel.as_deref());
+        tracing::info!(
+            venv = %venv_path.display(),
+            project = %project.display(),
+            "Fallback venv taken from VIRTUAL_ENV"
+        );
+        return Ok(Some(ProjectVenv {
+            venv: venv_path,
+            project,
+        }));
+    }
 
+    // 3. Search for a venv directory at toplevel
+    if let Some(toplevel) = &git_toplevel {
         tracing::debug!(
             toplevel = %toplevel.display(),
-            checking_path = %venv_path.display(),
-            pyvenv_cfg = %pyvenv_cfg.display(),
-            exists = pyvenv_cfg.exists(),
-            "Checking git toplevel for .venv"
+            venv_dirs = ?venv_dirs,
+            "Checking git toplevel for venv"
         );
 
-        if pyvenv_cfg.exists() {
+        if let Some(venv_path) = preferred_venv_in(toplevel, venv_dirs, prefer_python) {
             tracing::info!(
                 venv = %venv_path.display(),
-                "Fallback .venv found at git toplevel"
+                marker = ?venv_path.file_name().unwrap_or_default(),
+                "Fallback venv found at git toplevel"
             );
-            return Ok(Some(venv_path));
+            return Ok(Some(ProjectVenv::in_tree(venv_path)));
         }
     } else {
         tracing::debug!("No git toplevel found, skipping toplevel check");
     }
 
-    // 3. Search for .venv from cwd
-    let venv_path = cwd.join(VENV_DIR);
-    let pyvenv_cfg = venv_path.join(PYVENV_CFG);
-
+    // 4. Search for a venv directory in cwd
     tracing::debug!(
         cwd = %cwd.display(),
-        checking_path = %venv_path.display(),
-        pyvenv_cfg = %pyvenv_cfg.display(),
-        exists = pyvenv_cfg.exists(),
-        "Checking cwd for .venv"
+        venv_dirs = ?venv_dirs,
+        "Checking cwd for venv"
     );
 
-    if pyvenv_cfg.exists() {
+    if let Some(venv_path) = preferred_venv_in(cwd, venv_dirs, prefer_python) {
         tracing::info!(
             venv = %venv_path.display(),
-            "Fallback .venv found at cwd"
+            marker = ?venv_path.file_name().unwrap_or_default(),
+            "Fallback venv found at cwd"
         );
-        return Ok(Some(venv_path));
+        return Ok(Some(ProjectVenv::in_tree(venv_path)));
     }
 
     tracing::warn!(
@@ -174,8 +938,21 @@ mod tests {
         let file = subdir.join("test.py");
         fs::write(&file, "# test").await.unwrap();
 
-        let result = find_venv(&file, None).await.unwrap();
-        assert_eq!(result, Some(venv));
+        let result = find_venv(
+            &file,
+            None,
+            &VenvSearch::default(),
+            &mut PoetryEnvCache::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result,
+            Some(ProjectVenv {
+                venv,
+                project: temp.path().to_path_buf()
+            })
+        );
     }
 
     #[tokio::test]
@@ -184,7 +961,592 @@ mod tests {
         let file = temp.path().join("test.py");
         fs::write(&file, "# test").await.unwrap();
 
-        let result = find_venv(&file, None).await.unwrap();
+        let result = find_venv(
+            &file,
+            None,
+            &VenvSearch::default(),
+            &mut PoetryEnvCache::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result, None);
     }
+
+    #[tokio::test]
+    async fn test_negative_cache_skips_rescan_until_ttl() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("test.py");
+        fs::write(&file, "# test").await.unwrap();
+        let search = VenvSearch::default();
+        let mut cache = NegativeVenvCache::new(Duration::from_secs(5));
+        let mut poetry_envs = PoetryEnvCache::default();
+        let start = Instant::now();
+
+        let first = cache
+            .find_venv(&file, None, &search, &mut poetry_envs, start)
+            .await
+            .unwrap();
+        assert_eq!(first, None);
+
+        // A venv created now is invisible while the miss is cached: no scan happens
+        let venv = temp.path().join(".venv");
+        fs::create_dir(&venv).await.unwrap();
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin")
+            .await
+            .unwrap();
+        let cached = cache
+            .find_venv(
+                &file,
+                None,
+                &search,
+                &mut poetry_envs,
+                start + Duration::from_secs(4),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cached, None);
+
+        // After the TTL the directory is scanned again
+        let rescanned = cache
+            .find_venv(
+                &file,
+                None,
+                &search,
+                &mut poetry_envs,
+                start + Duration::from_secs(6),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rescanned, Some(ProjectVenv::in_tree(venv)));
+    }
+
+    #[test]
+    fn test_resolve_venv_records_probed_dirs() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let pkg = root.join("pkg");
+        std::fs::create_dir_all(root.join(".venv")).unwrap();
+        std::fs::write(root.join(".venv/pyvenv.cfg"), "home = /usr/bin").unwrap();
+        std::fs::create_dir_all(&pkg).unwrap();
+
+        let resolution = resolve_venv(&pkg.join("mod.py"), Some(&root), &VenvSearch::default());
+        assert_eq!(
+            resolution.probed,
+            vec![(pkg.clone(), false), (root.clone(), true)]
+        );
+        assert_eq!(resolution.venv, Some(root.join(".venv")));
+        assert!(resolution.boundary.is_none());
+
+        let summary = resolution.probed_summary();
+        assert!(summary.contains(&format!("{} (missing)", pkg.display())));
+        assert!(summary.contains(&format!("{} (found)", root.display())));
+
+        // Stops at the toplevel boundary when nothing is found inside it
+        let resolution = resolve_venv(&pkg.join("mod.py"), Some(&pkg), &VenvSearch::default());
+        assert_eq!(resolution.probed, vec![(pkg.clone(), false)]);
+        assert_eq!(resolution.boundary, Some(root));
+        assert_eq!(resolution.venv, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_has_usable_interpreter() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let temp = tempdir().unwrap();
+        let venv = temp.path().join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        assert!(!has_usable_interpreter(&venv));
+
+        // Dangling symlink (base interpreter deleted)
+        symlink(temp.path().join("gone/python3"), venv_python(&venv)).unwrap();
+        assert!(!has_usable_interpreter(&venv));
+        std::fs::remove_file(venv_python(&venv)).unwrap();
+
+        std::fs::write(venv_python(&venv), "#!/bin/sh\n").unwrap();
+        assert!(!has_usable_interpreter(&venv));
+        std::fs::set_permissions(venv_python(&venv), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        assert!(has_usable_interpreter(&venv));
+    }
+
+    #[test]
+    fn test_parse_envrc_virtual_env() {
+        let dir = Path::new("/proj");
+        assert_eq!(
+            parse_envrc_virtual_env("export VIRTUAL_ENV=/opt/venvs/proj\n", dir),
+            Some(PathBuf::from("/opt/venvs/proj"))
+        );
+        assert_eq!(
+            parse_envrc_virtual_env("VIRTUAL_ENV=\"$PWD/.direnv/python\"", dir),
+            Some(PathBuf::from("/proj/.direnv/python"))
+        );
+        assert_eq!(
+            parse_envrc_virtual_env("export VIRTUAL_ENV='env'", dir),
+            Some(PathBuf::from("/proj/env"))
+        );
+        // Last assignment wins; shell expansions are not evaluated
+        assert_eq!(
+            parse_envrc_virtual_env("VIRTUAL_ENV=/a\nVIRTUAL_ENV=/b\n", dir),
+            Some(PathBuf::from("/b"))
+        );
+        assert_eq!(
+            parse_envrc_virtual_env("export VIRTUAL_ENV=$(poetry env info -p)", dir),
+            None
+        );
+        assert_eq!(parse_envrc_virtual_env("layout python3\n", dir), None);
+    }
+
+    #[tokio::test]
+    async fn test_direnv_layout_venv_belongs_to_envrc_dir() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let pkg = root.join("pkg");
+        let venv = root.join(".direnv/python-3.12");
+        std::fs::create_dir_all(&pkg).unwrap();
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        std::fs::write(
+            root.join(".envrc"),
+            "export VIRTUAL_ENV=\"$PWD/.direnv/python-3.12\"\n",
+        )
+        .unwrap();
+
+        let search = VenvSearch {
+            detect_direnv: true,
+            ..VenvSearch::default()
+        };
+        let found = find_venv(
+            &pkg.join("mod.py"),
+            Some(&root),
+            &search,
+            &mut PoetryEnvCache::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            found,
+            Some(ProjectVenv {
+                venv,
+                project: root.clone()
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_venv_from_envrc() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let pkg = root.join("pkg");
+        let external = temp.path().join("venvs/proj");
+        std::fs::create_dir_all(&pkg).unwrap();
+        std::fs::create_dir_all(&external).unwrap();
+        std::fs::write(external.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        std::fs::write(
+            root.join(".envrc"),
+            format!("export VIRTUAL_ENV={}\n", external.display()),
+        )
+        .unwrap();
+
+        let file = pkg.join("mod.py");
+        let direnv = VenvSearch {
+            detect_direnv: true,
+            ..VenvSearch::default()
+        };
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &VenvSearch::default()).venv,
+            None
+        );
+
+        let resolution = resolve_venv(&file, Some(&root), &direnv);
+        assert_eq!(resolution.venv, Some(external.clone()));
+        assert_eq!(resolution.envrc, Some(root.join(".envrc")));
+
+        // An in-tree .venv still takes precedence
+        std::fs::create_dir_all(root.join(".venv")).unwrap();
+        std::fs::write(root.join(".venv/pyvenv.cfg"), "home = /usr/bin").unwrap();
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &direnv).venv,
+            Some(root.join(".venv"))
+        );
+
+        // VIRTUAL_ENV without pyvenv.cfg is ignored
+        std::fs::remove_file(external.join("pyvenv.cfg")).unwrap();
+        std::fs::remove_dir_all(root.join(".venv")).unwrap();
+        assert_eq!(resolve_venv(&file, Some(&root), &direnv).venv, None);
+    }
+
+    #[test]
+    fn test_activated_venv() {
+        let temp = tempdir().unwrap();
+        let venv = temp.path().join("uv-cache/proj-abc");
+        std::fs::create_dir_all(&venv).unwrap();
+
+        assert_eq!(activated_venv(None), None);
+        assert_eq!(activated_venv(Some("".into())), None);
+        assert_eq!(activated_venv(Some(venv.clone().into_os_string())), None);
+
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        assert_eq!(
+            activated_venv(Some(venv.clone().into_os_string())),
+            Some(venv)
+        );
+    }
+
+    #[test]
+    fn test_activated_venv_project() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let service = root.join("service");
+        let cwd = service.join("src");
+        std::fs::create_dir_all(&cwd).unwrap();
+
+        // No pyproject.toml: the venv belongs to cwd
+        assert_eq!(activated_venv_project(&cwd, Some(&root)), cwd);
+
+        std::fs::write(service.join("pyproject.toml"), "[project]\n").unwrap();
+        assert_eq!(activated_venv_project(&cwd, Some(&root)), service);
+
+        // A pyproject.toml above the git toplevel is not this project's
+        std::fs::remove_file(service.join("pyproject.toml")).unwrap();
+        std::fs::write(temp.path().join("pyproject.toml"), "[project]\n").unwrap();
+        assert_eq!(activated_venv_project(&cwd, Some(&root)), cwd);
+    }
+
+    #[test]
+    fn test_resolve_venv_checks_configured_names_in_order() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let pkg = root.join("pkg");
+        std::fs::create_dir_all(&pkg).unwrap();
+        for name in ["venv", ".env"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+            std::fs::write(root.join(name).join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        }
+        let file = pkg.join("mod.py");
+
+        // Default only knows .venv
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &VenvSearch::default()).venv,
+            None
+        );
+
+        let search = VenvSearch {
+            venv_dirs: vec![".venv".to_string(), ".env".to_string(), "venv".to_string()],
+            ..VenvSearch::default()
+        };
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &search).venv,
+            Some(root.join(".env"))
+        );
+        assert!(has_venv(&root, &search.venv_dirs));
+        assert!(!has_venv(&pkg, &search.venv_dirs));
+    }
+
+    #[test]
+    fn test_resolve_venv_prefers_python_version() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        std::fs::create_dir_all(root.join(".venv311")).unwrap();
+        std::fs::write(
+            root.join(".venv311/pyvenv.cfg"),
+            "home = /usr/bin\nversion = 3.11.9\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join(".venv312")).unwrap();
+        std::fs::write(
+            root.join(".venv312/pyvenv.cfg"),
+            "home = /usr/bin\nversion_info = 3.12.4.final.0\n",
+        )
+        .unwrap();
+        let file = root.join("mod.py");
+
+        let mut search = VenvSearch {
+            venv_dirs: vec![".venv311".to_string(), ".venv312".to_string()],
+            ..VenvSearch::default()
+        };
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &search).venv,
+            Some(root.join(".venv311"))
+        );
+
+        search.prefer_python = Some("3.12".to_string());
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &search).venv,
+            Some(root.join(".venv312"))
+        );
+
+        // "3.1" is not a prefix of 3.11/3.12: fall back to --venv-dir order
+        search.prefer_python = Some("3.1".to_string());
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &search).venv,
+            Some(root.join(".venv311"))
+        );
+    }
+
+    #[test]
+    fn test_is_poetry_project() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path();
+        assert!(!is_poetry_project(dir));
+
+        std::fs::write(dir.join("pyproject.toml"), "[project]\nname = \"x\"\n").unwrap();
+        assert!(!is_poetry_project(dir));
+
+        std::fs::write(
+            dir.join("pyproject.toml"),
+            "[tool.poetry.dependencies]\npython = \"^3.12\"\n",
+        )
+        .unwrap();
+        assert!(is_poetry_project(dir));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_poetry_env_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir().unwrap();
+        let venv = temp.path().join("cache/virtualenvs/proj-AbC123-py3.12");
+        std::fs::create_dir_all(&venv).unwrap();
+        let project = temp.path().join("proj");
+        std::fs::create_dir_all(&project).unwrap();
+
+        // Stand-in for `poetry env info -p`
+        let poetry = temp.path().join("poetry");
+        std::fs::write(&poetry, format!("#!/bin/sh\necho {}\n", venv.display())).unwrap();
+        std::fs::set_permissions(&poetry, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let poetry = poetry.to_str().unwrap();
+
+        assert_eq!(poetry_env_path(&project, poetry).await, None);
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        assert_eq!(poetry_env_path(&project, poetry).await, Some(venv));
+        assert_eq!(poetry_env_path(&project, "/nonexistent/poetry").await, None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_poetry_env_cache_runs_poetry_once_per_project() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir().unwrap();
+        let venv = temp.path().join("cache/virtualenvs/proj-AbC123-py3.12");
+        std::fs::create_dir_all(&venv).unwrap();
+        let project = temp.path().join("proj");
+        std::fs::create_dir_all(&project).unwrap();
+
+        // Stand-in for `poetry env info -p` that counts its runs
+        let calls = temp.path().join("calls");
+        let poetry = temp.path().join("poetry");
+        std::fs::write(
+            &poetry,
+            format!(
+                "#!/bin/sh\necho run >> {}\necho {}\n",
+                calls.display(),
+                venv.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&poetry, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let poetry = poetry.to_str().unwrap();
+        let runs = || std::fs::read_to_string(&calls).unwrap().lines().count();
+
+        let mut cache = PoetryEnvCache::default();
+        let start = Instant::now();
+        // No env yet: the miss is remembered until the TTL passes
+        assert_eq!(cache.env_for(&project, poetry, start).await, None);
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        assert_eq!(cache.env_for(&project, poetry, start).await, None);
+        assert_eq!(runs(), 1);
+
+        let later = start + POETRY_MISS_TTL;
+        assert_eq!(
+            cache.env_for(&project, poetry, later).await,
+            Some(venv.clone())
+        );
+        assert_eq!(
+            cache.env_for(&project, poetry, later).await,
+            Some(venv.clone())
+        );
+        assert_eq!(runs(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_slow_poetry_continues_in_background() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir().unwrap();
+        let venv = temp.path().join("cache/virtualenvs/proj-AbC123-py3.12");
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        let project = temp.path().join("proj");
+        std::fs::create_dir_all(&project).unwrap();
+
+        let poetry = temp.path().join("poetry");
+        std::fs::write(
+            &poetry,
+            format!(
+                "#!/bin/sh
+sleep 1
+echo {}
+",
+                venv.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&poetry, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let poetry = poetry.to_str().unwrap();
+
+        let mut cache = PoetryEnvCache::default();
+        let start = std::time::Instant::now();
+        assert_eq!(cache.env_for(&project, poetry, Instant::now()).await, None);
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "lookup waited for poetry"
+        );
+        assert!(cache.is_running());
+
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert_eq!(
+            cache.env_for(&project, poetry, Instant::now()).await,
+            Some(venv)
+        );
+        assert!(!cache.is_running());
+    }
+
+    #[test]
+    fn test_find_pep582_env() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let file = root.join("src/app/main.py");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        assert_eq!(find_pep582_env(&file, Some(&root), None), None);
+
+        for version in ["3.9", "3.12", "3.11"] {
+            std::fs::create_dir_all(root.join(PYPACKAGES).join(version).join("lib")).unwrap();
+        }
+        // Not an env: no version name, or no lib
+        std::fs::create_dir_all(root.join(PYPACKAGES).join("cache/lib")).unwrap();
+        std::fs::create_dir_all(root.join(PYPACKAGES).join("3.13")).unwrap();
+
+        let newest = root.join(PYPACKAGES).join("3.12");
+        assert_eq!(
+            find_pep582_env(&file, Some(&root), None),
+            Some(ProjectVenv {
+                venv: newest.clone(),
+                project: root.clone()
+            })
+        );
+        assert_eq!(
+            find_pep582_env(&file, Some(&root), Some("3.11")).map(|env| env.venv),
+            Some(root.join(PYPACKAGES).join("3.11"))
+        );
+        assert!(has_usable_interpreter(&newest));
+        // Outside the git toplevel
+        assert_eq!(find_pep582_env(&file, Some(&root.join("src")), None), None);
+    }
+
+    #[test]
+    fn test_resolve_configured_venv() {
+        let temp = tempdir().unwrap();
+        let envs = temp.path().join("envs/proj");
+        let proj = temp.path().join("proj");
+        std::fs::create_dir_all(&envs).unwrap();
+        std::fs::create_dir_all(&proj).unwrap();
+        std::fs::write(envs.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+
+        assert_eq!(resolve_configured_venv(&proj, |_| true), None);
+
+        std::fs::write(
+            proj.join("pyproject.toml"),
+            "[tool.pyright]\nvenvPath = \"../envs\"\nvenv = \"proj\"\n\n[tool.other]\nvenv = \"x\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_configured_venv(&proj, |_| true),
+            Some(envs.canonicalize().unwrap())
+        );
+
+        // pyrightconfig.json takes precedence; a missing venv is ignored
+        std::fs::write(
+            proj.join("pyrightconfig.json"),
+            r#"{ "venvPath": "../envs", "venv": "missing" }"#,
+        )
+        .unwrap();
+        assert_eq!(resolve_configured_venv(&proj, |_| true), None);
+        std::fs::write(
+            proj.join("pyrightconfig.json"),
+            r#"{ "venvPath": "..", "venv": "envs/proj" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_configured_venv(&proj, |_| true),
+            Some(envs.canonicalize().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_pyrightconfig_venv_settings_accepts_jsonc() {
+        let content = r#"{
+            // Local env
+            "venvPath": "../envs", /* "venv": "wrong", */
+            "venv": "a // b",
+            "exclude": ["x/**",],
+        }"#;
+        assert_eq!(
+            pyrightconfig_venv_settings(content),
+            Some((Some("../envs".to_string()), "a // b".to_string()))
+        );
+        assert_eq!(
+            strip_jsonc(r#"{"a": "\"/*", "b": [1, 2 , ] }"#),
+            r#"{"a": "\"/*", "b": [1, 2 ] }"#
+        );
+    }
+
+    #[test]
+    fn test_resolve_venv_prefers_pyright_config() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("repo");
+        let external = temp.path().join("envs/repo");
+        std::fs::create_dir_all(root.join(".venv")).unwrap();
+        std::fs::write(root.join(".venv/pyvenv.cfg"), "home = /usr/bin").unwrap();
+        std::fs::create_dir_all(&external).unwrap();
+        std::fs::write(external.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+        std::fs::write(
+            root.join("pyrightconfig.json"),
+            format!(
+                r#"{{ "venvPath": "{}", "venv": "repo" }}"#,
+                temp.path().join("envs").display()
+            ),
+        )
+        .unwrap();
+        let file = root.join("main.py");
+
+        // Only a pyright backend reads pyright's config
+        let ty = VenvSearch {
+            backend: BackendKind::Ty,
+            ..VenvSearch::default()
+        };
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &ty).venv,
+            Some(root.join(".venv"))
+        );
+        let mapped = VenvSearch {
+            backend_map: vec!["/**=ty".parse().unwrap()],
+            ..VenvSearch::default()
+        };
+        assert_eq!(
+            resolve_venv(&file, Some(&root), &mapped).venv,
+            Some(root.join(".venv"))
+        );
+        let resolution = resolve_venv(&file, Some(&root), &VenvSearch::default());
+        assert_eq!(
+            resolution.project_venv(),
+            Some(ProjectVenv {
+                venv: external.canonicalize().unwrap(),
+                project: root.clone()
+            })
+        );
+    }
 }
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Venvs whose backend finished warming recently, persisted across restarts
+/// (`--warmup-state-persist`).
+///
+/// The file is a JSON object mapping venv path → unix seconds of the last
+/// completed warmup. A backend for a venv warmed within `window` starts
+/// `Ready`. The backend's index is rebuilt from scratch regardless, so the
+/// first requests after a quick restart may see a partially indexed project;
+/// keep the window short.
+#[derive(Debug)]
+pub struct WarmupStateStore {
+    path: PathBuf,
+    window: Duration,
+    warmed: HashMap<PathBuf, u64>,
+    /// Generation of the last snapshot handed to a writer task
+    generation: u64,
+    /// Generation on disk; writers hold it while writing so snapshots land
+    /// in order and a slow older write never replaces a newer one
+    written: Arc<Mutex<u64>>,
+}
+
+impl WarmupStateStore {
+    /// Read `path`; a missing or unreadable file starts empty. Called once
+    /// at startup, before the main loop runs.
+    pub fn load(path: PathBuf, window: Duration) -> Self {
+        let warmed = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!(path = %path.display(), error = %e, "Ignoring invalid warmup state file");
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to read warmup state file");
+                HashMap::new()
+            }
+        };
+        tracing::debug!(path = %path.display(), venvs = warmed.len(), "Loaded warmup state");
+        Self {
+            path,
+            window,
+            warmed,
+            generation: 0,
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Whether `venv` finished warming within the window before `now`
+    pub fn is_fresh(&self, venv: &Path, now: SystemTime) -> bool {
+        self.warmed
+            .get(venv)
+            .is_some_and(|&at| unix_secs(now).saturating_sub(at) < self.window.as_secs())
+    }
+
+    /// Remember that `venv` finished warming at `now` and rewrite the file on
+    /// the blocking pool, off the main loop
+    pub fn record(&mut self, venv: &Path, now: SystemTime) -> JoinHandle<()> {
+        let now_secs = unix_secs(now);
+        let window = self.window.as_secs();
+        self.warmed
+            .retain(|_, at| now_secs.saturating_sub(*at) < window);
+        self.warmed.insert(venv.to_path_buf(), now_secs);
+
+        self.generation += 1;
+        let generation = self.generation;
+        let snapshot = self.warmed.clone();
+        let path = self.path.clone();
+        let written = Arc::clone(&self.written);
+        tokio::task::spawn_blocking(move || {
+            let mut written = written.lock().unwrap_or_else(|e| e.into_inner());
+            if *written > generation {
+                return;
+            }
+            match save(&path, &snapshot) {
+                Ok(()) => *written = generation,
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Failed to write warmup state file")
+                }
+            }
+        })
+    }
+}
+
+/// Write via a temporary file so a concurrent reader never sees a partial file
+fn save(path: &Path, warmed: &HashMap<PathBuf, u64>) -> std::io::Result<()> {
+    let json = serde_json::to_vec(warmed)?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_recorded_venv_is_fresh_until_window_passes() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("warmup.json");
+        let venv = Path::new("/project/.venv");
+        let now = SystemTime::now();
+
+        let mut store = WarmupStateStore::load(path.clone(), Duration::from_secs(60));
+        assert!(!store.is_fresh(venv, now));
+        store.record(venv, now).await.unwrap();
+
+        // A new process reads what the previous one wrote
+        let reloaded = WarmupStateStore::load(path, Duration::from_secs(60));
+        assert!(reloaded.is_fresh(venv, now + Duration::from_secs(30)));
+        assert!(!reloaded.is_fresh(venv, now + Duration::from_secs(61)));
+        assert!(!reloaded.is_fresh(Path::new("/other/.venv"), now));
+    }
+
+    #[test]
+    fn test_invalid_file_starts_empty() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("warmup.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let store = WarmupStateStore::load(path, Duration::from_secs(60));
+        assert!(!store.is_fresh(Path::new("/project/.venv"), SystemTime::now()));
+    }
+}
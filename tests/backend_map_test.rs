@@ -0,0 +1,93 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn hover_scenario(text: &str, hovers: usize) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+    ];
+    for _ in 0..hovers {
+        steps.push(serde_json::json!({
+            "expect": { "method": "textDocument/hover" },
+            "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": text } } }]
+        }));
+    }
+    steps.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: `--backend-map` picks the backend per venv.
+///
+/// `tools/.venv` only provides a `ty` executable and `services/api/.venv` only
+/// `pyright-langserver`, so each hover is answered only if the proxy spawned
+/// the mapped backend. Both backends stay in the pool together.
+#[tokio::test]
+async fn backend_map_selects_backend_per_venv() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "services/api".to_string(),
+                scenario: hover_scenario("hover from pyright", 2),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "tools".to_string(),
+                scenario: hover_scenario("hover from ty", 1),
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let tools_bin = root.join("tools/.venv/bin");
+    std::fs::rename(tools_bin.join("pyright-langserver"), tools_bin.join("ty")).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &["--backend-map", "tools=ty"],
+    );
+
+    let root_uri = support::path_to_uri(&root);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let api_uri = support::path_to_uri(&root.join("services/api/main.py"));
+    let tools_uri = support::path_to_uri(&root.join("tools/main.py"));
+    proxy.did_open(&api_uri, "a = 1\n").await;
+    proxy.did_open(&tools_uri, "b = 2\n").await;
+
+    for (uri, expected) in [
+        (&api_uri, "hover from pyright"),
+        (&tools_uri, "hover from ty"),
+        (&api_uri, "hover from pyright"),
+    ] {
+        let resp = proxy
+            .request(
+                "textDocument/hover",
+                serde_json::json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 0, "character": 0 }
+                }),
+            )
+            .await;
+        assert!(
+            resp.error.is_none(),
+            "hover on {uri} failed: {:?}",
+            resp.error
+        );
+        assert_eq!(resp.result.unwrap()["contents"]["value"], expected);
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
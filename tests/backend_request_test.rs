@@ -0,0 +1,90 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: Backend-initiated refresh requests round-trip through the proxy.
+///
+/// - backend sends `workspace/semanticTokens/refresh` and `workspace/inlayHint/refresh`
+///   without params
+/// - proxy forwards them to the client with proxy-assigned ids
+/// - client responses (`result: null`) are routed back with the original backend ids
+#[tokio::test]
+async fn backend_refresh_request_round_trip() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [
+                    { "type": "request", "id": 7, "method": "workspace/semanticTokens/refresh" },
+                    { "type": "request", "id": 8, "method": "workspace/inlayHint/refresh" }
+                ]
+            },
+            { "expect": { "method": "<response>", "id": 7 }, "actions": [] },
+            { "expect": { "method": "<response>", "id": 8 }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "after refresh" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    for expected_method in [
+        "workspace/semanticTokens/refresh",
+        "workspace/inlayHint/refresh",
+    ] {
+        let refresh = proxy.read_next().await;
+        assert!(refresh.is_request(), "expected a server->client request");
+        assert_eq!(refresh.method_name(), Some(expected_method));
+        assert!(refresh.params.is_none(), "refresh requests carry no params");
+        proxy.respond(&refresh, serde_json::Value::Null).await;
+    }
+
+    // The backend only answers hover after both refresh responses arrived
+    // with their original ids (the mock exits on any mismatch).
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "after refresh"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,113 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: a JSON-RPC batch from the client is dispatched element by element,
+/// and the responses to its requests come back together as one batch array.
+/// A batch of notifications only gets no response; invalid elements get
+/// their own error responses, and an empty batch gets a single one.
+#[tokio::test]
+async fn batch_requests_are_answered_with_a_batch() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "hover" } }]
+            },
+            { "expect": { "method": "textDocument/didSave" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            { "expect": { "method": "$/setTrace" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let position = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 }
+    });
+    let batch = serde_json::json!([
+        { "jsonrpc": "2.0", "id": 100, "method": "textDocument/hover", "params": position },
+        {
+            "jsonrpc": "2.0",
+            "method": "textDocument/didSave",
+            "params": { "textDocument": { "uri": file_uri } }
+        },
+        { "jsonrpc": "2.0", "id": 101, "method": "textDocument/definition", "params": position }
+    ]);
+    proxy
+        .write_raw_frame(&serde_json::to_vec(&batch).unwrap())
+        .await;
+
+    let mut responses = proxy.read_next_batch().await;
+    responses.sort_by_key(|r| format!("{:?}", r.id));
+    assert_eq!(responses.len(), 2, "{responses:?}");
+    assert_eq!(responses[0].id, Some(RpcId::Number(100)));
+    assert_eq!(responses[0].result.as_ref().unwrap()["contents"], "hover");
+    assert_eq!(responses[1].id, Some(RpcId::Number(101)));
+    assert_eq!(responses[1].result, Some(serde_json::json!([])));
+
+    // Notifications only: nothing to answer, so the shutdown response is next
+    let notifications = serde_json::json!([
+        { "jsonrpc": "2.0", "method": "$/setTrace", "params": { "value": "off" } }
+    ]);
+    proxy
+        .write_raw_frame(&serde_json::to_vec(&notifications).unwrap())
+        .await;
+
+    // Empty batch: one invalid request response, not an array
+    proxy.write_raw_frame(b"[]").await;
+    let response = proxy.read_next().await;
+    assert_eq!(response.id, None);
+    assert_eq!(response.error.as_ref().unwrap().code, -32600);
+
+    // Invalid elements are answered in the batch response
+    let invalid = serde_json::json!([
+        { "jsonrpc": "2.0", "id": 102, "method": 17 },
+        5
+    ]);
+    proxy
+        .write_raw_frame(&serde_json::to_vec(&invalid).unwrap())
+        .await;
+    let responses = proxy.read_next_batch().await;
+    let ids: Vec<_> = responses.iter().map(|r| r.id.clone()).collect();
+    assert_eq!(ids, vec![Some(RpcId::Number(102)), None]);
+    assert!(responses
+        .iter()
+        .all(|r| r.error.as_ref().unwrap().code == -32600));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,104 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--max-backends 1`, opening a file in another venv while the
+/// only backend still owes a response evicts it anyway. The orphaned request
+/// is answered with RequestCancelled instead of hanging.
+#[tokio::test]
+async fn evicting_busy_backend_cancels_its_pending_requests() {
+    // proj-a never answers the hover
+    let scenario_a = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            { "expect": { "method": "textDocument/hover" }, "actions": [] }
+        ]
+    });
+    let scenario_b = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "hover from b" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario_a,
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario_b,
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy =
+        ProxyUnderTest::spawn_with_args(temp_dir, root.clone(), &root, &["--max-backends", "1"]);
+
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    let stuck_id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_a_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+
+    let cancelled = loop {
+        let msg = proxy.read_next().await;
+        if msg.is_response() {
+            break msg;
+        }
+    };
+    assert_eq!(
+        cancelled.id,
+        Some(typemux_cc::message::RpcId::Number(stuck_id))
+    );
+    assert_eq!(cancelled.error.as_ref().map(|e| e.code), Some(-32800));
+
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_b_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_resp.error.is_none(), "{:?}", hover_resp.error);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,110 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+fn initialized_steps() -> Vec<serde_json::Value> {
+    vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+    ]
+}
+
+/// E2E: `$/cancelRequest` for a pending request reaches only the backend that
+/// owns it. proj-b fails its scenario if it sees the cancel before its hover.
+#[tokio::test]
+async fn cancel_request_routed_to_owning_backend() {
+    let hover_id = 2;
+    let mut steps_a = initialized_steps();
+    steps_a.extend([
+        serde_json::json!({ "expect": { "method": "textDocument/hover" }, "actions": [] }),
+        serde_json::json!({
+            "expect": { "method": "$/cancelRequest", "params": { "id": hover_id } },
+            "actions": [{ "type": "respond", "id": hover_id, "body": null }]
+        }),
+        serde_json::json!({
+            "expect": { "method": "shutdown" },
+            "actions": [{ "type": "respond", "body": null }]
+        }),
+    ]);
+    let mut steps_b = initialized_steps();
+    steps_b.extend([
+        serde_json::json!({
+            "expect": { "method": "textDocument/hover" },
+            "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "hover from b" } } }]
+        }),
+        serde_json::json!({
+            "expect": { "method": "shutdown" },
+            "actions": [{ "type": "respond", "body": null }]
+        }),
+    ]);
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: serde_json::json!({ "on_startup": [], "steps": steps_a }),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: serde_json::json!({ "on_startup": [], "steps": steps_b }),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+
+    let id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_a_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(id, hover_id);
+    proxy
+        .notify("$/cancelRequest", serde_json::json!({ "id": id }))
+        .await;
+
+    let answered = loop {
+        let msg = proxy.read_next().await;
+        if msg.is_response() {
+            break msg;
+        }
+    };
+    assert_eq!(answered.id, Some(RpcId::Number(hover_id)));
+
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_b_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_resp.error.is_none(), "{:?}", hover_resp.error);
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "hover from b"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
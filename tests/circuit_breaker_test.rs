@@ -0,0 +1,125 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--circuit-breaker-threshold 1`, a venv whose backend crashed
+/// is not respawned; requests for it get an error response instead.
+#[tokio::test]
+async fn crashed_venv_not_respawned_while_breaker_open() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{ "type": "crash" }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--circuit-breaker-threshold", "1"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    proxy.wait_for_crash_cleanup(1, 5000).await;
+
+    let response = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    let error = response.error.expect("expected an error response");
+    assert!(
+        error.message.contains("after repeated crashes"),
+        "unexpected error: {}",
+        error.message
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: a backend that exits during initialize counts as a crash, so after
+/// `--circuit-breaker-threshold` failed launches the venv is refused.
+#[tokio::test]
+async fn initialize_failures_open_breaker() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "eof" }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    let hover = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 }
+    });
+
+    // cwd outside the package: no fallback backend
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &["--circuit-breaker-threshold", "2"],
+    );
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    for _ in 0..2 {
+        let response = proxy.request("textDocument/hover", hover.clone()).await;
+        let error = response.error.expect("expected an error response");
+        assert!(
+            error.message.contains("Initialize failed"),
+            "unexpected error: {}",
+            error.message
+        );
+    }
+
+    let response = proxy.request("textDocument/hover", hover).await;
+    let error = response.error.expect("expected an error response");
+    assert!(
+        error.message.contains("after repeated crashes"),
+        "unexpected error: {}",
+        error.message
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,77 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcMessage;
+
+fn scenario() -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            { "expect": { "method": "exit" }, "actions": [] }
+        ]
+    })
+}
+
+async fn start() -> (ProxyUnderTest, std::path::PathBuf) {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: scenario(),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--log-file", log_path.to_str().unwrap()],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    (proxy, log_path)
+}
+
+/// E2E: shutdown waits for every backend to exit before the proxy answers,
+/// so the following exit doesn't leave them orphaned.
+#[tokio::test]
+async fn shutdown_then_exit_waits_for_backends() {
+    let (mut proxy, log_path) = start().await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(status.success(), "expected a clean exit, got {status:?}");
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Backend exited gracefully"), "{log}");
+}
+
+/// E2E: an exit without a prior shutdown still shuts the backends down.
+#[tokio::test]
+async fn exit_without_shutdown_waits_for_backends() {
+    let (mut proxy, log_path) = start().await;
+
+    proxy
+        .write_raw_frame(&serde_json::to_vec(&RpcMessage::notification("exit", None)).unwrap())
+        .await;
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(status.success(), "expected a clean exit, got {status:?}");
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Backend exited gracefully"), "{log}");
+}
@@ -0,0 +1,97 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--clear-diagnostics-on-close`, closing a document publishes an
+/// empty diagnostics list for it after didClose is forwarded to the backend.
+#[tokio::test]
+async fn did_close_clears_diagnostics_when_enabled() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/didOpen" },
+                    "actions": [{
+                        "type": "notify",
+                        "method": "textDocument/publishDiagnostics",
+                        "params": {
+                            "uri": file_uri,
+                            "diagnostics": [{
+                                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                                "message": "undefined name"
+                            }]
+                        }
+                    }]
+                },
+                { "expect": { "method": "textDocument/didClose" }, "actions": [] },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--clear-diagnostics-on-close"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x\n").await;
+
+    let published = proxy.read_next().await;
+    assert_eq!(
+        published.method_name(),
+        Some("textDocument/publishDiagnostics")
+    );
+    assert_eq!(
+        published.params.as_ref().unwrap()["diagnostics"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    proxy
+        .notify(
+            "textDocument/didClose",
+            serde_json::json!({ "textDocument": { "uri": file_uri } }),
+        )
+        .await;
+
+    let cleared = proxy.read_next().await;
+    assert_eq!(
+        cleared.method_name(),
+        Some("textDocument/publishDiagnostics")
+    );
+    let params = cleared.params.as_ref().unwrap();
+    assert_eq!(params["uri"], file_uri);
+    assert_eq!(params["diagnostics"], serde_json::json!([]));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
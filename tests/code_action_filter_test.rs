@@ -0,0 +1,102 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--filter-code-action-kind` removes matching kinds (and their
+/// sub-kinds) from codeAction responses and keeps everything else.
+#[tokio::test]
+async fn filtered_code_action_kinds_removed() {
+    let action = |title: &str, kind: Option<&str>| {
+        let mut action = serde_json::json!({ "title": title });
+        if let Some(kind) = kind {
+            action["kind"] = serde_json::json!(kind);
+        }
+        action
+    };
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "codeActionProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/codeAction" },
+                "actions": [{ "type": "respond", "body": [
+                    action("Organize imports", Some("source.organizeImports")),
+                    action("Add import", Some("quickfix")),
+                    action("Extract method", Some("refactor.extract.function")),
+                    action("Inline variable", Some("refactor.inline")),
+                    action("Sourcery suggestion", Some("sourcery")),
+                    { "title": "Run command", "command": "x.run" }
+                ] }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--filter-code-action-kind",
+            "source",
+            "--filter-code-action-kind",
+            "refactor.extract",
+        ],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "import os\n").await;
+
+    let response = proxy
+        .request(
+            "textDocument/codeAction",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 9 } },
+                "context": { "diagnostics": [] }
+            }),
+        )
+        .await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+    let titles: Vec<String> = response
+        .result
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        titles,
+        vec![
+            "Add import",
+            "Inline variable",
+            "Sourcery suggestion",
+            "Run command"
+        ]
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,96 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--log-request-response-correlation` logs one event per answered
+/// request with its method, venv and elapsed time.
+#[tokio::test]
+async fn correlation_event_logged_for_completed_request() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [
+                    { "type": "sleep_ms", "ms": 50 },
+                    { "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "x: int" } } }
+                ]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--log-request-response-correlation",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_resp.error.is_none(), "{:?}", hover_resp.error);
+
+    // The log writer is asynchronous; give it a moment to flush
+    let mut event = None;
+    for _ in 0..20 {
+        let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        event = log
+            .lines()
+            .find(|l| l.contains("typemux_cc::correlation") && l.contains("textDocument/hover"))
+            .map(str::to_string);
+        if event.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let event = event.expect("no correlation event for the hover");
+    assert!(event.contains("Request completed"), "{event}");
+    assert!(event.contains(".venv"), "{event}");
+    let elapsed_ms: u64 = event
+        .split("elapsed_ms=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|ms| ms.parse().ok())
+        .unwrap_or_else(|| panic!("no elapsed_ms in {event}"));
+    assert!(elapsed_ms >= 50, "{event}");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,148 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// Scenario for a backend lifetime that crashes as soon as a document is opened.
+fn crash_on_open(fallback: bool) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+    ];
+    if fallback {
+        // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+        steps.push(serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }));
+    }
+    steps.push(serde_json::json!({
+        "expect": { "method": "textDocument/didOpen" },
+        "actions": [{ "type": "crash" }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: with `--shutdown-on-backend-crash-threshold 2`, the first crash is
+/// recovered from as usual and the second one within the window makes the
+/// proxy exit non-zero.
+#[tokio::test]
+async fn proxy_exits_when_crash_threshold_reached() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: crash_on_open(true),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--shutdown-on-backend-crash-threshold", "2"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    // First crash: below the threshold, the proxy cleans up and keeps running
+    proxy.wait_for_crash_cleanup(1, 5000).await;
+
+    // The recreated backend crashes again on the restored didOpen
+    support::write_scenario(&root.join("pkg"), &crash_on_open(false));
+    proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(!status.success(), "expected non-zero exit, got {status}");
+}
+
+/// E2E: reaching the threshold shuts the surviving backends down with
+/// shutdown/exit before the proxy exits, instead of orphaning them.
+#[tokio::test]
+async fn crash_threshold_shuts_down_remaining_backends() {
+    let healthy = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            { "expect": { "method": "exit" }, "actions": [] }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "pkg".to_string(),
+                scenario: crash_on_open(true),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "other".to_string(),
+                scenario: healthy,
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--shutdown-on-backend-crash-threshold",
+            "2",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy
+        .did_open(
+            &support::path_to_uri(&root.join("other/main.py")),
+            "y = 1\n",
+        )
+        .await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    proxy.wait_for_crash_cleanup(1, 5000).await;
+
+    support::write_scenario(&root.join("pkg"), &crash_on_open(false));
+    proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let status = proxy.wait_for_exit(10000).await;
+    assert!(!status.success(), "expected non-zero exit, got {status}");
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Backend exited gracefully"), "{log}");
+}
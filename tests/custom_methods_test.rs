@@ -0,0 +1,100 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn scenario(name: &str, expects_notification: bool) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+    ];
+    if expects_notification {
+        steps.push(serde_json::json!({
+            "expect": { "method": "pyright/fileTouched" },
+            "actions": []
+        }));
+    }
+    steps.push(serde_json::json!({
+        "expect": { "method": "pyright/projectStats" },
+        "actions": [{ "type": "respond", "body": [{ "project": name }] }]
+    }));
+    steps.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: `--forward-custom-methods pyright/` forwards extension methods.
+///
+/// - A `pyright/…` notification carrying a document URI reaches only that
+///   document's backend (backend-a would fail its scenario otherwise).
+/// - A URI-less `pyright/…` request goes to every backend and the result
+///   arrays are concatenated instead of being rejected as unroutable.
+#[tokio::test]
+async fn forward_custom_methods_routes_and_broadcasts() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario("a", false),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario("b", true),
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &["--forward-custom-methods", "pyright/"],
+    );
+
+    let root_uri = support::path_to_uri(&root);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+
+    proxy
+        .notify(
+            "pyright/fileTouched",
+            serde_json::json!({ "textDocument": { "uri": &file_b_uri } }),
+        )
+        .await;
+
+    let resp = proxy
+        .request("pyright/projectStats", serde_json::json!({}))
+        .await;
+    assert!(
+        resp.error.is_none(),
+        "custom request rejected: {:?}",
+        resp.error
+    );
+    let mut projects: Vec<&str> = resp
+        .result
+        .as_ref()
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["project"].as_str().unwrap())
+        .collect();
+    projects.sort();
+    assert_eq!(projects, ["a", "b"]);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,96 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--diagnostic-code-rule` downgrades one code and drops another before
+/// diagnostics reach the client.
+#[tokio::test]
+async fn diagnostic_code_rules_rewrite_and_drop() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let diagnostic = |code: serde_json::Value, message: &str| {
+        serde_json::json!({
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+            "severity": 1,
+            "code": code,
+            "message": message
+        })
+    };
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/didOpen" },
+                    "actions": [{
+                        "type": "notify",
+                        "method": "textDocument/publishDiagnostics",
+                        "params": {
+                            "uri": file_uri,
+                            "diagnostics": [
+                                diagnostic("reportMissingImports".into(), "import"),
+                                diagnostic(1001.into(), "numeric"),
+                                diagnostic("reportUndefinedVariable".into(), "undefined")
+                            ]
+                        }
+                    }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--diagnostic-code-rule",
+            "reportMissingImports:warning",
+            "--diagnostic-code-rule",
+            "1001:drop",
+        ],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "import foo\n").await;
+
+    let published = proxy.read_next().await;
+    assert_eq!(
+        published.method_name(),
+        Some("textDocument/publishDiagnostics")
+    );
+    let diagnostics = published.params.unwrap()["diagnostics"]
+        .as_array()
+        .unwrap()
+        .clone();
+    assert_eq!(diagnostics.len(), 2, "numeric code should be dropped");
+    assert_eq!(diagnostics[0]["code"], "reportMissingImports");
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[1]["code"], "reportUndefinedVariable");
+    assert_eq!(diagnostics[1]["severity"], 1);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,97 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn publish(uri: &str, message: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "notify",
+        "method": "textDocument/publishDiagnostics",
+        "params": {
+            "uri": uri,
+            "diagnostics": [{
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                "message": message
+            }]
+        }
+    })
+}
+
+fn scenario(did_open_actions: Vec<serde_json::Value>) -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": did_open_actions },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+/// E2E: two backends publish diagnostics for the same URI; only the backend
+/// owning the document (proj-b) reaches the client.
+#[tokio::test]
+async fn only_owner_diagnostics_reach_client() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario(vec![]),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario(vec![]),
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+
+    // proj-b publishes for its own file; proj-a wrongly publishes for proj-b's
+    // file too, then for its own file (which marks the end of its output)
+    support::write_scenario(
+        &root.join("proj-b"),
+        &scenario(vec![publish(&file_b_uri, "from owner")]),
+    );
+    support::write_scenario(
+        &root.join("proj-a"),
+        &scenario(vec![
+            publish(&file_b_uri, "from intruder"),
+            publish(&file_a_uri, "proj-a own"),
+        ]),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+    let owner = proxy.read_next().await;
+    assert_eq!(owner.params.as_ref().unwrap()["uri"], file_b_uri.as_str());
+    assert_eq!(
+        owner.params.as_ref().unwrap()["diagnostics"][0]["message"],
+        "from owner"
+    );
+
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    let next = proxy.read_next().await;
+    assert_eq!(next.method_name(), Some("textDocument/publishDiagnostics"));
+    assert_eq!(
+        next.params.as_ref().unwrap()["uri"],
+        file_a_uri.as_str(),
+        "intruder diagnostics for proj-b's file must be dropped"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,105 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--allow-did-change-before-did-open`, a full-text didChange for a
+/// document that was never opened reaches the backend as a didOpen.
+#[tokio::test]
+async fn did_change_before_did_open_opens_document() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": {
+                        "method": "textDocument/didOpen",
+                        "params": { "textDocument": { "uri": file_uri, "version": 3, "text": "a = 1\n" } }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": {
+                        "method": "textDocument/didChange",
+                        "params": { "textDocument": { "uri": file_uri, "version": 4 } }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": null }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--allow-did-change-before-did-open"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    // No didOpen for main.py: the first didChange carries the full text
+    proxy
+        .notify(
+            "textDocument/didChange",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri, "version": 3 },
+                "contentChanges": [{ "text": "a = 1\n" }]
+            }),
+        )
+        .await;
+    // Later incremental changes apply to the recovered document as usual
+    proxy
+        .notify(
+            "textDocument/didChange",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri, "version": 4 },
+                "contentChanges": [{
+                    "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 5 } },
+                    "text": "2"
+                }]
+            }),
+        )
+        .await;
+
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover.error.is_none(), "{:?}", hover.error);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,230 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn hover_step(value: &str) -> serde_json::Value {
+    serde_json::json!({
+        "expect": { "method": "textDocument/hover" },
+        "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": value } } }]
+    })
+}
+
+/// E2E: `workspace/didChangeConfiguration` reaches existing backends and is
+/// replayed to a backend spawned afterwards, right after its `initialized`.
+#[tokio::test]
+async fn configuration_is_replayed_to_new_backends() {
+    let settings = serde_json::json!({
+        "settings": { "python": { "analysis": { "typeCheckingMode": "strict" } } }
+    });
+
+    let scenario_a = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/didChangeConfiguration", "params": settings },
+                "actions": []
+            },
+            hover_step("hover from backend-a"),
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let scenario_b = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // Cached settings arrive before any document
+            {
+                "expect": { "method": "workspace/didChangeConfiguration", "params": settings },
+                "actions": []
+            },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            hover_step("hover from backend-b"),
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario_a,
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario_b,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+
+    let root_uri = support::path_to_uri(&root);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+
+    proxy
+        .notify("workspace/didChangeConfiguration", settings.clone())
+        .await;
+
+    // A backend that misses the settings fails its scenario, and the hover with it
+    let hover_a = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": &file_a_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_a.error.is_none(), "backend-a missed the settings");
+    assert_eq!(
+        hover_a.result.as_ref().unwrap()["contents"]["value"],
+        "hover from backend-a"
+    );
+
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+
+    let hover_b = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": &file_b_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(
+        hover_b.error.is_none(),
+        "backend-b missed the cached settings"
+    );
+    assert_eq!(
+        hover_b.result.as_ref().unwrap()["contents"]["value"],
+        "hover from backend-b"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: settings that change while a backend is still launching reach it
+/// once the launch finishes, even though its handshake was prepared with the
+/// old (here: no) configuration.
+#[cfg(unix)]
+#[tokio::test]
+async fn configuration_changed_during_launch_reaches_backend() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let settings = serde_json::json!({
+        "settings": { "python": { "analysis": { "typeCheckingMode": "strict" } } }
+    });
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/didChangeConfiguration", "params": settings },
+                "actions": []
+            },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            hover_step("hover from backend"),
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let (temp_dir, root) = support::setup_test_workspace(&WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    });
+
+    // The prewarm launch holds in its startup script until the test lets it go
+    let release = root.join("release");
+    let script = root.join("startup.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\nwhile [ ! -e '{}' ]; do sleep 0.01; done\n",
+            release.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &[
+            "--prewarm",
+            "--backend-startup-script",
+            script.to_str().unwrap(),
+        ],
+    );
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+    proxy.wait_for_log("Creating new backend instance").await;
+
+    proxy
+        .notify("workspace/didChangeConfiguration", settings.clone())
+        .await;
+    // A round trip makes sure the proxy has handled the settings
+    proxy
+        .request(
+            "workspace/executeCommand",
+            serde_json::json!({ "command": "pyright.organizeimports", "arguments": [] }),
+        )
+        .await;
+    std::fs::write(&release, "").unwrap();
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "a = 1\n").await;
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": &file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover.error.is_none(), "backend missed the changed settings");
+    assert_eq!(
+        hover.result.as_ref().unwrap()["contents"]["value"],
+        "hover from backend"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,79 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn scenario(extra: serde_json::Value) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+    ];
+    steps.extend(extra.as_array().unwrap().iter().cloned());
+    steps.push(serde_json::json!({
+        "expect": { "method": "textDocument/hover" },
+        "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "ok" } } }]
+    }));
+    steps.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: `textDocument/didSave` reaches only the backend owning the file.
+/// proj-b fails its scenario if it sees the didSave before its hover.
+#[tokio::test]
+async fn did_save_routed_to_owning_backend() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario(serde_json::json!([{
+                    "expect": { "method": "textDocument/didSave", "params": { "text": "a = 2\n" } },
+                    "actions": []
+                }])),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario(serde_json::json!([])),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+    proxy
+        .notify(
+            "textDocument/didSave",
+            serde_json::json!({ "textDocument": { "uri": file_a_uri }, "text": "a = 2\n" }),
+        )
+        .await;
+
+    for uri in [&file_a_uri, &file_b_uri] {
+        let hover_resp = proxy
+            .request(
+                "textDocument/hover",
+                serde_json::json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 0, "character": 0 }
+                }),
+            )
+            .await;
+        assert!(hover_resp.error.is_none(), "{uri}: {:?}", hover_resp.error);
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,140 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// Spawn a proxy with `--didchange-debounce <debounce_ms>` whose backend
+/// accepts exactly one full-document didChange (version 4) before a
+/// `request` (answered with null).
+async fn spawn_debounced_proxy(debounce_ms: &str, request: &str) -> (ProxyUnderTest, String) {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+                {
+                    "expect": {
+                        "method": "textDocument/didChange",
+                        "params": {
+                            "textDocument": { "uri": file_uri, "version": 4 },
+                            "contentChanges": [{ "text": "abc\n" }]
+                        }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": request },
+                    "actions": [{ "type": "respond", "body": null }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--didchange-debounce", debounce_ms],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "\n").await;
+    (proxy, file_uri)
+}
+
+/// Type "abc" one character per didChange (versions 2..=4).
+async fn type_abc(proxy: &mut ProxyUnderTest, file_uri: &str) {
+    for (i, ch) in ["a", "b", "c"].iter().enumerate() {
+        proxy
+            .notify(
+                "textDocument/didChange",
+                serde_json::json!({
+                    "textDocument": { "uri": file_uri, "version": i + 2 },
+                    "contentChanges": [{
+                        "range": { "start": { "line": 0, "character": i }, "end": { "line": 0, "character": i } },
+                        "text": ch
+                    }]
+                }),
+            )
+            .await;
+    }
+}
+
+async fn hover(proxy: &mut ProxyUnderTest, file_uri: &str) {
+    // Answered only if the backend saw exactly one coalesced didChange
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover.error.is_none(), "{:?}", hover.error);
+}
+
+/// E2E: rapid changes are forwarded once, after the quiet period.
+#[tokio::test]
+async fn rapid_changes_coalesce_after_quiet_period() {
+    let (mut proxy, file_uri) = spawn_debounced_proxy("200", "textDocument/hover").await;
+    type_abc(&mut proxy, &file_uri).await;
+
+    tokio::time::sleep(std::time::Duration::from_millis(600)).await;
+    hover(&mut proxy, &file_uri).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: a request for the document flushes its pending changes right away.
+#[tokio::test]
+async fn request_flushes_pending_changes() {
+    let (mut proxy, file_uri) = spawn_debounced_proxy("60000", "textDocument/hover").await;
+    type_abc(&mut proxy, &file_uri).await;
+
+    hover(&mut proxy, &file_uri).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: a request about no particular document (here `workspace/symbol`)
+/// flushes the pending changes of every document first.
+#[tokio::test]
+async fn uri_less_request_flushes_pending_changes() {
+    let (mut proxy, file_uri) = spawn_debounced_proxy("60000", "workspace/symbol").await;
+    type_abc(&mut proxy, &file_uri).await;
+
+    // Answered only if the backend saw the coalesced didChange first
+    let symbols = proxy
+        .request("workspace/symbol", serde_json::json!({ "query": "abc" }))
+        .await;
+    assert!(symbols.error.is_none(), "{:?}", symbols.error);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,73 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn scenario(after_open: serde_json::Value) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "completionProvider": {} } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+    ];
+    steps.extend(after_open.as_array().unwrap().iter().cloned());
+    steps.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: a document request outside the navigation methods (here completion)
+/// for a file whose backend was evicted respawns that backend instead of
+/// failing.
+#[tokio::test]
+async fn completion_respawns_evicted_backend() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                // Both lifetimes share the scenario; the first one is evicted
+                // before the completion arrives
+                scenario: scenario(serde_json::json!([{
+                    "expect": { "method": "textDocument/completion" },
+                    "actions": [{ "type": "respond", "body": { "isIncomplete": false, "items": [{ "label": "alpha" }] } }]
+                }])),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario(serde_json::json!([])),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy =
+        ProxyUnderTest::spawn_with_args(temp_dir, root.clone(), &root, &["--max-backends", "1"]);
+
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    // Pool cap 1: proj-a's backend is evicted
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+
+    let response = proxy
+        .request(
+            "textDocument/completion",
+            serde_json::json!({
+                "textDocument": { "uri": file_a_uri },
+                "position": { "line": 0, "character": 1 }
+            }),
+        )
+        .await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert_eq!(response.result.unwrap()["items"][0]["label"], "alpha");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
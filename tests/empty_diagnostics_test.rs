@@ -0,0 +1,92 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--dedup-empty-diagnostics`, consecutive empty publishes for a URI
+/// reach the client once (the non-empty → empty clear is kept).
+#[tokio::test]
+async fn consecutive_empty_diagnostics_are_deduplicated() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let publish = |messages: &[&str]| {
+        let diagnostics: Vec<_> = messages
+            .iter()
+            .map(|m| {
+                serde_json::json!({
+                    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                    "message": m
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "type": "notify",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": file_uri, "diagnostics": diagnostics }
+        })
+    };
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/didOpen" },
+                    "actions": [
+                        publish(&["first"]),
+                        publish(&[]),
+                        publish(&[]),
+                        publish(&[]),
+                        publish(&["second"])
+                    ]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--dedup-empty-diagnostics"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let mut counts = Vec::new();
+    for _ in 0..3 {
+        let msg = proxy.read_next().await;
+        assert_eq!(msg.method_name(), Some("textDocument/publishDiagnostics"));
+        counts.push(
+            msg.params.as_ref().unwrap()["diagnostics"]
+                .as_array()
+                .unwrap()
+                .len(),
+        );
+    }
+    assert_eq!(counts, vec![1, 0, 1]);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,94 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::error_codes;
+
+/// E2E: proxy-generated errors carry a code per failure class plus context in `data`.
+///
+/// - non-file URI → `UNROUTABLE`
+/// - venv whose backend executable is missing → `BACKEND_UNAVAILABLE`
+#[tokio::test]
+async fn proxy_errors_use_distinct_codes() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "pkg".to_string(),
+                scenario: scenario.clone(),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "broken".to_string(),
+                scenario,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    std::fs::remove_file(root.join("broken/.venv/bin/pyright-langserver")).unwrap();
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let hover = |uri: &str| {
+        serde_json::json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 0, "character": 0 }
+        })
+    };
+
+    let untitled = proxy
+        .request("textDocument/hover", hover("untitled:Untitled-1"))
+        .await;
+    let error = untitled.error.expect("non-file URI should fail");
+    assert_eq!(error.code, error_codes::UNROUTABLE);
+    assert_eq!(error.data.unwrap()["uri"], "untitled:Untitled-1");
+
+    let broken_uri = support::path_to_uri(&root.join("broken/main.py"));
+    let broken = proxy
+        .request("textDocument/hover", hover(&broken_uri))
+        .await;
+    let error = broken
+        .error
+        .expect("missing backend executable should fail");
+    assert_eq!(
+        error.code,
+        error_codes::BACKEND_UNAVAILABLE,
+        "{}",
+        error.message
+    );
+    assert_eq!(error.data.unwrap()["uri"], broken_uri.as_str());
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// Proxy codes stay clear of the codes LSP defines in the server error range
+/// (`-32001` UnknownErrorCode, `-32002` ServerNotInitialized), which clients act on.
+#[test]
+fn proxy_codes_avoid_lsp_codes() {
+    assert_eq!(error_codes::VENV_NOT_FOUND, -32010);
+    assert_eq!(error_codes::BACKEND_UNAVAILABLE, -32011);
+    assert_eq!(error_codes::RATE_LIMITED, -32012);
+    assert_eq!(error_codes::UNROUTABLE, -32013);
+    assert_eq!(error_codes::RESPONSE_TOO_LARGE, -32014);
+}
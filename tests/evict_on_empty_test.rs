@@ -0,0 +1,78 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--evict-on-empty`, closing the last open document evicts the
+/// backend once the grace period has passed.
+#[tokio::test]
+async fn closing_last_document_evicts_after_grace() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            { "expect": { "method": "textDocument/didClose" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--evict-on-empty",
+            "--evict-on-empty-grace",
+            "1",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    proxy
+        .notify(
+            "textDocument/didClose",
+            serde_json::json!({ "textDocument": { "uri": file_uri } }),
+        )
+        .await;
+
+    let mut log = String::new();
+    for _ in 0..50 {
+        log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        if log.contains("Evicting backend (no open documents)") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(
+        log.contains("Evicting backend (no open documents)"),
+        "backend was not evicted:\n{log}"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
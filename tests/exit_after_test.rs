@@ -0,0 +1,83 @@
+mod support;
+
+use std::time::{Duration, Instant};
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--exit-after` ends the proxy on a wall-clock deadline even while the
+/// client keeps it busy, shutting the backend down first.
+#[tokio::test]
+async fn exit_after_terminates_under_activity() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let started = Instant::now();
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--exit-after",
+            "2",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 0\n").await;
+
+    // Keep the proxy busy right up to the deadline (writing past it would hit
+    // a closed pipe). Hovers on a venv-less file are answered by the proxy itself.
+    let orphan_uri = support::path_to_uri(&root.join("orphan/main.py"));
+    while started.elapsed() < Duration::from_millis(1800) {
+        let resp = proxy
+            .request(
+                "textDocument/hover",
+                serde_json::json!({
+                    "textDocument": { "uri": orphan_uri },
+                    "position": { "line": 0, "character": 0 }
+                }),
+            )
+            .await;
+        assert!(resp.error.is_some());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(status.success(), "expected a clean exit, got {status:?}");
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed >= Duration::from_secs(2),
+        "exited early: {elapsed:?}"
+    );
+    assert!(elapsed < Duration::from_secs(6), "exited late: {elapsed:?}");
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Lifetime limit reached"), "{log}");
+    assert!(log.contains("Backend exited gracefully"), "{log}");
+}
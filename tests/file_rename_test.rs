@@ -0,0 +1,136 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn text_edit(text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "range": {
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 0 }
+        },
+        "newText": text
+    })
+}
+
+/// E2E: `workspace/willRenameFiles` / `didRenameFiles` are routed per venv.
+///
+/// - a rename inside proj-a only reaches backend-a (request and notification)
+/// - a rename from proj-a into proj-b fans out to both backends and the
+///   returned WorkspaceEdits are merged
+///
+/// Each mock backend exits on an unexpected method, so a misrouted rename
+/// to backend-b would make the cross-project fan-out lose its edit.
+#[tokio::test]
+async fn rename_files_routed_per_venv() {
+    let scenario_a = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/willRenameFiles" },
+                "actions": [{ "type": "respond", "body": { "changes": { "file:///a-importer.py": [text_edit("same")] } } }]
+            },
+            { "expect": { "method": "workspace/didRenameFiles" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/willRenameFiles" },
+                "actions": [{ "type": "respond", "body": { "changes": { "file:///a-importer.py": [text_edit("from-a")] } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let scenario_b = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/willRenameFiles" },
+                "actions": [{ "type": "respond", "body": { "changes": { "file:///b-importer.py": [text_edit("from-b")] } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario_a,
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario_b,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+
+    let root_uri = support::path_to_uri(&root);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    for pkg in ["proj-a", "proj-b"] {
+        let file = root.join(pkg).join("main.py");
+        std::fs::write(&file, "x = 1\n").unwrap();
+        proxy
+            .did_open(&support::path_to_uri(&file), "x = 1\n")
+            .await;
+    }
+
+    // Same-project rename: only backend-a is involved
+    let same_project = serde_json::json!({
+        "files": [{
+            "oldUri": support::path_to_uri(&root.join("proj-a/old.py")),
+            "newUri": support::path_to_uri(&root.join("proj-a/new.py"))
+        }]
+    });
+    let resp = proxy
+        .request("workspace/willRenameFiles", same_project.clone())
+        .await;
+    assert!(resp.error.is_none());
+    assert_eq!(
+        resp.result.as_ref().unwrap()["changes"]["file:///a-importer.py"][0]["newText"],
+        "same"
+    );
+    proxy.notify("workspace/didRenameFiles", same_project).await;
+
+    // Cross-project rename: both backends answer, edits are merged
+    let resp = proxy
+        .request(
+            "workspace/willRenameFiles",
+            serde_json::json!({
+                "files": [{
+                    "oldUri": support::path_to_uri(&root.join("proj-a/moved.py")),
+                    "newUri": support::path_to_uri(&root.join("proj-b/moved.py"))
+                }]
+            }),
+        )
+        .await;
+    assert!(resp.error.is_none());
+    let changes = &resp.result.as_ref().unwrap()["changes"];
+    assert_eq!(changes["file:///a-importer.py"][0]["newText"], "from-a");
+    assert_eq!(changes["file:///b-importer.py"][0]["newText"], "from-b");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
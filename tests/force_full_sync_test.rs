@@ -0,0 +1,99 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--force-full-sync`, an incremental didChange reaches the
+/// backend as a single range-less change holding the whole document.
+#[tokio::test]
+async fn incremental_change_forwarded_as_full_document() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    // contentChanges arrays compare exactly, so a leftover range fails the step
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+                {
+                    "expect": {
+                        "method": "textDocument/didChange",
+                        "params": {
+                            "textDocument": { "uri": file_uri, "version": 2 },
+                            "contentChanges": [{ "text": "a = 2\nb = 3\n" }]
+                        }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": null }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--force-full-sync"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "a = 1\n").await;
+    proxy
+        .notify(
+            "textDocument/didChange",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri, "version": 2 },
+                "contentChanges": [
+                    {
+                        "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 5 } },
+                        "text": "2"
+                    },
+                    {
+                        "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 0 } },
+                        "text": "b = 3\n"
+                    }
+                ]
+            }),
+        )
+        .await;
+
+    // Answered only if the backend accepted the full-sync didChange
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover.error.is_none(), "{:?}", hover.error);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,127 @@
+//! Frame layer micro-benchmarks. Ignored by default; run with
+//! `cargo test --release --test framing_bench -- --ignored --nocapture`.
+
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use typemux_cc::framing::{LspFrameReader, LspFrameWriter};
+use typemux_cc::message::RpcMessage;
+
+/// Frames per measured run
+const FRAMES: usize = 2000;
+
+/// A publishDiagnostics of `count` diagnostics (about 180 bytes each)
+fn diagnostics_frame(count: usize) -> Vec<u8> {
+    let diagnostics: Vec<_> = (0..count)
+        .map(|i| {
+            serde_json::json!({
+                "range": {
+                    "start": { "line": i, "character": 4 },
+                    "end": { "line": i, "character": 12 }
+                },
+                "severity": 1,
+                "source": "Pyright",
+                "code": "reportAttributeAccessIssue",
+                "message": format!("Cannot access attribute \"name_{i}\" for class \"Foo\"")
+            })
+        })
+        .collect();
+    let msg = RpcMessage::notification(
+        "textDocument/publishDiagnostics",
+        Some(serde_json::json!({
+            "uri": "file:///work/pkg/module.py",
+            "version": 7,
+            "diagnostics": diagnostics
+        })),
+    );
+    let body = serde_json::to_vec(&msg).unwrap();
+    let mut frame = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    frame.extend_from_slice(&body);
+    frame
+}
+
+fn per_frame(elapsed: Duration) -> String {
+    format!(
+        "{:.1} µs/frame",
+        elapsed.as_secs_f64() * 1e6 / FRAMES as f64
+    )
+}
+
+/// Backend → client forwarding of unchanged diagnostics: parse and
+/// re-serialize versus writing the raw body (`read_message_with_raw`)
+#[tokio::test]
+#[ignore]
+async fn bench_raw_passthrough() {
+    let frame = diagnostics_frame(200);
+    let input = frame.repeat(FRAMES);
+    println!("frame: {} bytes", frame.len());
+
+    let start = Instant::now();
+    let mut reader = LspFrameReader::new(&input[..]);
+    let mut writer = LspFrameWriter::new(tokio::io::sink());
+    for _ in 0..FRAMES {
+        let msg = reader.read_message().await.unwrap();
+        writer.write_message(&msg).await.unwrap();
+    }
+    println!("re-serialize: {}", per_frame(start.elapsed()));
+
+    let start = Instant::now();
+    let mut reader = LspFrameReader::new(&input[..]);
+    let mut writer = LspFrameWriter::new(tokio::io::sink());
+    for _ in 0..FRAMES {
+        let (msg, raw) = reader.read_message_with_raw().await.unwrap();
+        writer.write_raw(&msg, raw.unwrap()).await.unwrap();
+    }
+    println!("raw:          {}", per_frame(start.elapsed()));
+}
+
+/// Header parsing on small frames, where it dominates: a `$/progress`
+/// report with the usual Content-Length and Content-Type headers
+#[tokio::test]
+#[ignore]
+async fn bench_read_headers() {
+    let body = br#"{"jsonrpc":"2.0","method":"$/progress","params":{"token":"t","value":{"kind":"report","percentage":42}}}"#;
+    let mut frame = format!(
+        "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    frame.extend_from_slice(body);
+    let input = frame.repeat(FRAMES);
+    println!("frame: {} bytes", frame.len());
+
+    let start = Instant::now();
+    let mut reader = LspFrameReader::new(&input[..]);
+    for _ in 0..FRAMES {
+        reader.read_message().await.unwrap();
+    }
+    println!("read: {}", per_frame(start.elapsed()));
+}
+
+/// Read buffer size (`--read-buffer-size`) under a diagnostic burst. The
+/// frames arrive through a pipe in 64 KiB writes, like a backend's stdout,
+/// so a small buffer costs extra reads per frame
+#[tokio::test]
+#[ignore]
+async fn bench_read_buffer_size() {
+    let frame = diagnostics_frame(200);
+    let input = frame.repeat(FRAMES);
+    println!("frame: {} bytes", frame.len());
+
+    for capacity in [8 * 1024, 64 * 1024, 256 * 1024, 1024 * 1024] {
+        let (mut tx, rx) = tokio::io::duplex(64 * 1024);
+        let burst = input.clone();
+        let sender = tokio::spawn(async move {
+            for chunk in burst.chunks(64 * 1024) {
+                tx.write_all(chunk).await.unwrap();
+            }
+        });
+        let start = Instant::now();
+        let mut reader = LspFrameReader::with_capacity(capacity, rx);
+        for _ in 0..FRAMES {
+            reader.read_message().await.unwrap();
+        }
+        let elapsed = start.elapsed();
+        sender.await.unwrap();
+        println!("{:>5} KiB: {}", capacity / 1024, per_frame(elapsed));
+    }
+}
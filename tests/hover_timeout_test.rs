@@ -0,0 +1,75 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--hover-timeout` answers a hover from the cached document when the
+/// backend stays silent, and cancels the backend request.
+#[tokio::test]
+async fn hover_timeout_returns_fallback() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // Backend is busy: never answers the hover
+            { "expect": { "method": "textDocument/hover" }, "actions": [] },
+            { "expect": { "method": "$/cancelRequest" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--hover-timeout", "200"],
+    );
+
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy
+        .did_open(&file_uri, "import os\nvalue = os.getcwd()\n")
+        .await;
+
+    let resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 1, "character": 2 }
+            }),
+        )
+        .await;
+    assert!(resp.error.is_none());
+    let value = resp.result.as_ref().unwrap()["contents"]["value"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(value.contains("value = os.getcwd()"), "got: {value}");
+    assert!(value.contains("backend busy"), "got: {value}");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,117 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// Backend that answers `initialize` after 1.5s (the proxy waits 1s in these tests).
+fn slow_initialize_scenario() -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [
+                    { "type": "sleep_ms", "ms": 1500 },
+                    { "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }
+                ]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+fn spawn_slow_backend_proxy(action: &str) -> (ProxyUnderTest, std::path::PathBuf) {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: slow_initialize_scenario(),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--initialize-timeout",
+            "1",
+            "--initialize-response-timeout-action",
+            action,
+        ],
+    );
+    (proxy, root)
+}
+
+/// E2E: `minimal` gives up on the slow backend and answers with the default capabilities.
+#[tokio::test]
+async fn init_timeout_minimal_returns_default_capabilities() {
+    let (mut proxy, root) = spawn_slow_backend_proxy("minimal");
+
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"],
+        support::default_capabilities()
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `retry` keeps waiting and returns the backend's real capabilities.
+#[tokio::test]
+async fn init_timeout_retry_waits_longer() {
+    let (mut proxy, root) = spawn_slow_backend_proxy("retry");
+
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"]["hoverProvider"],
+        true
+    );
+    proxy.send_initialized().await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `disable` answers with the default capabilities and refuses to spawn
+/// another backend for the venv when a file in it is opened.
+#[tokio::test]
+async fn init_timeout_disable_stops_spawning() {
+    let (mut proxy, root) = spawn_slow_backend_proxy("disable");
+
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"],
+        support::default_capabilities()
+    );
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    // Reported immediately instead of waiting on another slow spawn
+    let started = std::time::Instant::now();
+    let notice = proxy.read_next().await;
+    assert_eq!(notice.method_name(), Some("window/showMessage"));
+    let message = notice.params.as_ref().unwrap()["message"].as_str().unwrap();
+    assert!(
+        message.contains("disabled"),
+        "unexpected message: {message}"
+    );
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
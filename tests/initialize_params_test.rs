@@ -0,0 +1,282 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--client-capabilities-augment` is deep-merged into the capabilities
+/// the backend receives in initialize.
+#[tokio::test]
+async fn capabilities_augment_reaches_backend() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": {
+                    "method": "initialize",
+                    "params": {
+                        "capabilities": {
+                            "textDocument": { "hover": { "contentFormat": ["markdown", "plaintext"] } }
+                        }
+                    }
+                },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--client-capabilities-augment",
+            r#"{"textDocument":{"hover":{"contentFormat":["markdown","plaintext"]}}}"#,
+        ],
+    );
+
+    // The backend only answers initialize when the augmented capabilities
+    // arrived; otherwise the proxy falls back to default capabilities.
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"]["hoverProvider"],
+        true
+    );
+    proxy.send_initialized().await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--pyright-diagnostic-mode` reaches a pyright backend both in its
+/// initialize `initializationOptions` and in a follow-up didChangeConfiguration,
+/// and stays in force when the client later changes its settings.
+#[tokio::test]
+async fn pyright_diagnostic_mode_reaches_backend() {
+    let settings =
+        serde_json::json!({ "python": { "analysis": { "diagnosticMode": "workspace" } } });
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": {
+                    "method": "initialize",
+                    "params": { "initializationOptions": settings }
+                },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": {
+                    "method": "workspace/didChangeConfiguration",
+                    "params": { "settings": settings }
+                },
+                "actions": []
+            },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "ok" } }]
+            },
+            {
+                "expect": {
+                    "method": "workspace/didChangeConfiguration",
+                    "params": { "settings": { "python": { "analysis": {
+                        "diagnosticMode": "workspace",
+                        "typeCheckingMode": "strict"
+                    } } } }
+                },
+                "actions": []
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "still ok" } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--pyright-diagnostic-mode", "workspace"],
+    );
+
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"]["hoverProvider"],
+        true
+    );
+    proxy.send_initialized().await;
+
+    // The backend only answers the hover if every earlier step matched
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(hover.result.as_ref().unwrap()["contents"], "ok");
+
+    // The client's own diagnosticMode doesn't reach the live backend
+    proxy
+        .notify(
+            "workspace/didChangeConfiguration",
+            serde_json::json!({ "settings": { "python": { "analysis": {
+                "diagnosticMode": "openFilesOnly",
+                "typeCheckingMode": "strict"
+            } } } }),
+        )
+        .await;
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(hover.result.as_ref().unwrap()["contents"], "still ok");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--pyright-diagnostic-mode` also wins over the client's answer to the
+/// backend's `workspace/configuration` pull, whichever section is asked for.
+#[tokio::test]
+async fn pyright_diagnostic_mode_overrides_configuration_response() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "workspace/didChangeConfiguration" }, "actions": [] },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{
+                    "type": "request",
+                    "id": 5,
+                    "method": "workspace/configuration",
+                    "params": { "items": [
+                        { "section": "python.analysis" },
+                        { "section": "python" },
+                        { "section": "pyright" }
+                    ] }
+                }]
+            },
+            {
+                "expect": {
+                    "method": "<response>",
+                    "id": 5,
+                    "result": [
+                        { "diagnosticMode": "workspace", "typeCheckingMode": "strict" },
+                        { "analysis": { "diagnosticMode": "workspace" }, "pythonPath": "/usr/bin/python3" },
+                        { "disableLanguageServices": false }
+                    ]
+                },
+                "actions": []
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "ok" } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let (temp_dir, root) = support::setup_test_workspace(&WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    });
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--pyright-diagnostic-mode", "workspace"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let pull = proxy.read_next().await;
+    assert_eq!(pull.method_name(), Some("workspace/configuration"));
+    proxy
+        .respond(
+            &pull,
+            serde_json::json!([
+                { "diagnosticMode": "openFilesOnly", "typeCheckingMode": "strict" },
+                { "pythonPath": "/usr/bin/python3" },
+                { "disableLanguageServices": false }
+            ]),
+        )
+        .await;
+
+    // The backend only answers the hover if the patched answer matched
+    let hover = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(hover.result.as_ref().unwrap()["contents"], "ok");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,186 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn scenario(did_open_params: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen", "params": did_open_params },
+                "actions": []
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "ok" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+async fn open(proxy: &mut ProxyUnderTest, uri: &str, language_id: Option<&str>) {
+    let mut text_document = serde_json::json!({ "uri": uri, "version": 1, "text": "x = 1\n" });
+    if let Some(language_id) = language_id {
+        text_document["languageId"] = serde_json::json!(language_id);
+    }
+    proxy
+        .notify(
+            "textDocument/didOpen",
+            serde_json::json!({ "textDocument": text_document }),
+        )
+        .await;
+}
+
+async fn assert_hover_ok(proxy: &mut ProxyUnderTest, uri: &str) {
+    let resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(resp.error.is_none(), "hover failed: {:?}", resp.error);
+    assert_eq!(resp.result.as_ref().unwrap()["contents"]["value"], "ok");
+}
+
+/// E2E: a didOpen without `languageId` reaches the backend with `--default-language-id`.
+#[tokio::test]
+async fn missing_language_id_uses_default() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: scenario(serde_json::json!({ "textDocument": { "languageId": "python" } })),
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    open(&mut proxy, &file_uri, None).await;
+    assert_hover_ok(&mut proxy, &file_uri).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--skip-non-python-documents` keeps a markdown didOpen away from the
+/// backend; the next Python didOpen is the first one it sees.
+#[tokio::test]
+async fn non_python_language_id_skipped() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: scenario(serde_json::json!({ "textDocument": { "languageId": "python" } })),
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--skip-non-python-documents"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let readme_uri = support::path_to_uri(&root.join("pkg/README.md"));
+    open(&mut proxy, &readme_uri, Some("markdown")).await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    open(&mut proxy, &file_uri, Some("python")).await;
+    assert_hover_ok(&mut proxy, &file_uri).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--reopen-on-language-id-change` closes the document on the backend
+/// before forwarding a didOpen that changes its languageId.
+#[tokio::test]
+async fn language_id_change_reopens_document() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/stubs.pyi"));
+
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/didOpen", "params": { "textDocument": { "languageId": "python" } } },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/didClose", "params": { "textDocument": { "uri": file_uri } } },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/didOpen", "params": { "textDocument": { "languageId": "python-stub" } } },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "ok" } } }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--reopen-on-language-id-change"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    open(&mut proxy, &file_uri, Some("python")).await;
+    open(&mut proxy, &file_uri, Some("python-stub")).await;
+    assert_hover_ok(&mut proxy, &file_uri).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
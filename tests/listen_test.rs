@@ -0,0 +1,148 @@
+mod support;
+
+use std::process::Stdio;
+use std::time::Duration;
+use support::{PackageConfig, WorkspaceConfig};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use typemux_cc::framing::{LspFrameReader, LspFrameWriter};
+use typemux_cc::message::{RpcId, RpcMessage};
+
+/// E2E: with `--listen`, the proxy serves a client over TCP instead of stdio.
+#[tokio::test]
+async fn client_served_over_tcp() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "x: int" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (_temp_dir, root) = support::setup_test_workspace(&config);
+
+    // Reserve a free port, then hand it to the proxy
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{port}");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_typemux-cc"))
+        .args(["--listen", &addr])
+        .current_dir(root.join("pkg"))
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env_remove("GIT_INDEX_FILE")
+        .env_remove("VIRTUAL_ENV")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to spawn proxy");
+
+    let mut stream = None;
+    for _ in 0..50 {
+        match TcpStream::connect(&addr).await {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    let (read_half, write_half) = stream.expect("proxy never listened").into_split();
+    let mut reader = LspFrameReader::new(read_half);
+    let mut writer = LspFrameWriter::new(write_half);
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    let request = |id: i64, method: &str, params: Option<serde_json::Value>| {
+        RpcMessage::request(RpcId::Number(id), method, params)
+    };
+    let init = request(
+        1,
+        "initialize",
+        Some(serde_json::json!({ "processId": null, "rootUri": root_uri, "capabilities": {} })),
+    );
+    writer.write_message(&init).await.unwrap();
+    assert_eq!(read_response(&mut reader).await.id, Some(RpcId::Number(1)));
+
+    let notifications = [
+        RpcMessage::notification("initialized", Some(serde_json::json!({}))),
+        RpcMessage::notification(
+            "textDocument/didOpen",
+            Some(serde_json::json!({
+                "textDocument": { "uri": file_uri, "languageId": "python", "version": 1, "text": "x = 1\n" }
+            })),
+        ),
+    ];
+    for msg in &notifications {
+        writer.write_message(msg).await.unwrap();
+    }
+    let hover = request(
+        2,
+        "textDocument/hover",
+        Some(serde_json::json!({
+            "textDocument": { "uri": file_uri },
+            "position": { "line": 0, "character": 0 }
+        })),
+    );
+    writer.write_message(&hover).await.unwrap();
+    let hover_resp = read_response(&mut reader).await;
+    assert_eq!(hover_resp.id, Some(RpcId::Number(2)));
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "x: int"
+    );
+
+    writer
+        .write_message(&request(3, "shutdown", None))
+        .await
+        .unwrap();
+    assert_eq!(read_response(&mut reader).await.id, Some(RpcId::Number(3)));
+
+    writer
+        .write_message(&RpcMessage::notification("exit", None))
+        .await
+        .unwrap();
+    let status = tokio::time::timeout(Duration::from_secs(5), child.wait())
+        .await
+        .expect("proxy did not exit")
+        .unwrap();
+    assert!(status.success(), "{status}");
+}
+
+/// Read messages from the TCP connection until a response arrives
+async fn read_response(reader: &mut LspFrameReader<tokio::net::tcp::OwnedReadHalf>) -> RpcMessage {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(5), reader.read_message())
+            .await
+            .expect("timed out waiting for a response over TCP")
+            .unwrap();
+        if msg.is_response() {
+            return msg;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: a malformed client frame is skipped instead of ending the session.
+///
+/// - unparseable body → dropped silently (no id to answer)
+/// - valid JSON that is not a JSON-RPC message but has an id → -32700 for that id
+/// - the following `initialize` is handled normally
+#[tokio::test]
+async fn malformed_frame_does_not_abort_proxy() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    proxy
+        .write_raw_frame(b"{\"jsonrpc\": \"2.0\", garbage")
+        .await;
+    proxy
+        .write_raw_frame(br#"{"jsonrpc":"2.0","id":99,"method":["initialize"]}"#)
+        .await;
+
+    let parse_error = proxy.read_next().await;
+    assert_eq!(parse_error.id, Some(RpcId::Number(99)));
+    assert_eq!(parse_error.error.as_ref().unwrap().code, -32700);
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    let init_resp = proxy.initialize(&root_uri).await;
+    assert!(init_resp.error.is_none(), "{:?}", init_resp.error);
+    assert!(init_resp.result.unwrap()["capabilities"].is_object());
+    proxy.send_initialized().await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
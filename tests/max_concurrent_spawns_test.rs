@@ -0,0 +1,103 @@
+#![cfg(unix)]
+
+mod support;
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+fn package(name: &str) -> PackageConfig {
+    PackageConfig {
+        name: name.to_string(),
+        scenario: serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+        has_venv: true,
+    }
+}
+
+/// Prewarm three venvs with a startup script that records how many launches
+/// were running at once; returns the highest count seen and how many
+/// launches ran.
+async fn peak_concurrent_launches(max_concurrent_spawns: &str) -> (usize, usize) {
+    let config = WorkspaceConfig {
+        packages: vec![package("pkg-a"), package("pkg-b"), package("pkg-c")],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let runs = root.join("runs");
+    std::fs::create_dir_all(&runs).unwrap();
+    let script = root.join("startup.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\ntouch {runs}/$$\nls {runs} | wc -l >> {counts}\nsleep 0.5\nrm {runs}/$$\n",
+            runs = runs.display(),
+            counts = root.join("counts").display(),
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &[
+            "--prewarm",
+            "--max-backends",
+            "3",
+            "--max-concurrent-spawns",
+            max_concurrent_spawns,
+            "--backend-startup-script",
+            script.to_str().unwrap(),
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    wait_for_log(&log_path, "Prewarmed workspace backends").await;
+    let counts: Vec<usize> = std::fs::read_to_string(root.join("counts"))
+        .unwrap()
+        .lines()
+        .map(|line| line.trim().parse().unwrap())
+        .collect();
+    let peak = counts.iter().copied().max().unwrap();
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+    (peak, counts.len())
+}
+
+async fn wait_for_log(log_path: &Path, needle: &str) {
+    for _ in 0..50 {
+        let log = std::fs::read_to_string(log_path).unwrap_or_default();
+        if log.contains(needle) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("{needle:?} never logged");
+}
+
+/// E2E: prewarm launches run in parallel up to `--max-concurrent-spawns`;
+/// the launch past the cap waits for a slot and still runs.
+#[tokio::test]
+async fn concurrent_launches_are_capped() {
+    assert_eq!(peak_concurrent_launches("1").await, (1, 3));
+    assert_eq!(peak_concurrent_launches("2").await, (2, 3));
+    assert_eq!(peak_concurrent_launches("3").await, (3, 3));
+}
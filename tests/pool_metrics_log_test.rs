@@ -0,0 +1,75 @@
+mod support;
+
+use std::time::{Duration, Instant};
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--pool-metrics-log-interval` writes a pool summary line to the log
+/// at the configured interval.
+#[tokio::test]
+async fn pool_metrics_logged_periodically() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--pool-metrics-log-interval",
+            "1",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let started = Instant::now();
+    let mut log = String::new();
+    while started.elapsed() < Duration::from_secs(5) {
+        log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        if log.matches("Pool metrics").count() >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let lines: Vec<_> = log.lines().filter(|l| l.contains("Pool metrics")).collect();
+    assert!(lines.len() >= 2, "expected 2 summaries in log:\n{log}");
+    // Two ticks one second apart: not fired back to back
+    assert!(started.elapsed() >= Duration::from_millis(900));
+    assert!(lines[1].contains("pool_size=1"), "{}", lines[1]);
+    assert!(lines[1].contains("open_documents=1"), "{}", lines[1]);
+    assert!(lines[1].contains("backend_spawns_total=1"), "{}", lines[1]);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,104 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: with `--preindex-open-files`, the new backend receives didOpen for
+/// every non-ignored project file; a later client didOpen replaces the
+/// proxy's copy with a didClose + didOpen.
+#[tokio::test]
+async fn project_files_opened_when_enabled() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let pkg = root.join("pkg");
+    std::fs::create_dir_all(pkg.join("lib")).unwrap();
+    std::fs::write(pkg.join("main.py"), "from lib.util import f\n").unwrap();
+    std::fs::write(pkg.join("lib/util.py"), "def f(): ...\n").unwrap();
+    std::fs::write(pkg.join("ignored.py"), "").unwrap();
+    std::fs::write(pkg.join(".gitignore"), "ignored.py\n").unwrap();
+    let main_uri = support::path_to_uri(&pkg.join("main.py"));
+    let util_uri = support::path_to_uri(&pkg.join("lib/util.py"));
+
+    // An unexpected didOpen (e.g. for ignored.py) would fail the scenario
+    support::write_scenario(
+        &pkg,
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": {
+                        "method": "textDocument/didOpen",
+                        "params": { "textDocument": { "uri": util_uri, "version": 0, "text": "def f(): ...\n" } }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": {
+                        "method": "textDocument/didOpen",
+                        "params": { "textDocument": { "uri": main_uri, "version": 0 } }
+                    },
+                    "actions": []
+                },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": {
+                        "method": "textDocument/didClose",
+                        "params": { "textDocument": { "uri": main_uri } }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": {
+                        "method": "textDocument/didOpen",
+                        "params": { "textDocument": { "uri": main_uri, "version": 1 } }
+                    },
+                    "actions": []
+                },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": { "contents": "f" } }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy =
+        ProxyUnderTest::spawn_with_args(temp_dir, root.clone(), &pkg, &["--preindex-open-files"]);
+    proxy.initialize(&support::path_to_uri(&pkg)).await;
+    proxy.send_initialized().await;
+    proxy.did_open(&main_uri, "from lib.util import f\n").await;
+
+    let response = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": main_uri },
+                "position": { "line": 0, "character": 21 }
+            }),
+        )
+        .await;
+    assert!(
+        response.error.is_none(),
+        "hover failed: {:?}",
+        response.error
+    );
+    assert_eq!(response.result.unwrap()["contents"], "f");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,126 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: with `--backend-respawn-preserve-diagnostics`, the last diagnostics of
+/// a file are re-published (marked stale) when its crashed backend is recreated.
+#[tokio::test]
+async fn cached_diagnostics_republished_on_recreation() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    // First lifetime: publish one diagnostic on didOpen, then crash
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/didOpen" },
+                    "actions": [
+                        {
+                            "type": "notify",
+                            "method": "textDocument/publishDiagnostics",
+                            "params": {
+                                "uri": file_uri,
+                                "diagnostics": [{
+                                    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                                    "message": "undefined name",
+                                    "source": "Pyright"
+                                }]
+                            }
+                        },
+                        { "type": "sleep_ms", "ms": 100 },
+                        { "type": "crash" }
+                    ]
+                }
+            ]
+        }),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--backend-respawn-preserve-diagnostics"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = y\n").await;
+
+    let published = proxy.read_next().await;
+    assert_eq!(
+        published.params.as_ref().unwrap()["diagnostics"][0]["source"],
+        "Pyright"
+    );
+    // Crash cleanup clears the file's diagnostics
+    proxy.wait_for_crash_cleanup(1, 5000).await;
+
+    // Second lifetime: restored didOpen, then answer the hover
+    support::write_scenario(
+        &root.join("pkg"),
+        &serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": null }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+    );
+
+    let hover = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    // The cached diagnostic is replayed before the recreated backend answers
+    let replayed = proxy.read_next().await;
+    assert_eq!(
+        replayed.method_name(),
+        Some("textDocument/publishDiagnostics")
+    );
+    let params = replayed.params.unwrap();
+    assert_eq!(params["uri"], file_uri);
+    assert_eq!(params["diagnostics"][0]["message"], "undefined name");
+    assert_eq!(params["diagnostics"][0]["source"], "(stale) Pyright");
+
+    let response = proxy.read_next().await;
+    assert_eq!(response.id, Some(RpcId::Number(hover)));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
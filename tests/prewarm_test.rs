@@ -0,0 +1,209 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::error_codes;
+
+fn package(name: &str) -> PackageConfig {
+    PackageConfig {
+        name: name.to_string(),
+        scenario: serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+        has_venv: true,
+    }
+}
+
+/// E2E: with `--prewarm`, every workspace venv gets a backend right after
+/// `initialized`, before any document is opened.
+#[tokio::test]
+async fn prewarm_starts_a_backend_per_venv() {
+    let config = WorkspaceConfig {
+        packages: vec![package("pkg-a"), package("pkg-b")],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(temp_dir, root.clone(), &root, &["--prewarm"]);
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    // Once both launches finish two backends are running, so a URI-less
+    // request can't be routed
+    let mut pool_size = serde_json::Value::Null;
+    for _ in 0..50 {
+        let response = proxy
+            .request(
+                "workspace/executeCommand",
+                serde_json::json!({ "command": "pyright.organizeimports", "arguments": [] }),
+            )
+            .await;
+        let error = response.error.expect("URI-less request should be rejected");
+        if error.code == error_codes::UNROUTABLE {
+            pool_size = error.data.unwrap()["poolSize"].clone();
+            if pool_size == 2 {
+                break;
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(pool_size, 2);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: prewarming stops at `--max-backends` and reports what it skipped.
+#[tokio::test]
+async fn prewarm_respects_pool_capacity() {
+    let config = WorkspaceConfig {
+        packages: vec![package("pkg-a"), package("pkg-b")],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &[
+            "--prewarm",
+            "--max-backends",
+            "1",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let mut log = String::new();
+    for _ in 0..50 {
+        log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        if log.contains("Prewarmed workspace backends") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let summary = log
+        .lines()
+        .find(|line| line.contains("Prewarmed workspace backends"))
+        .unwrap_or_else(|| panic!("no prewarm summary in log:\n{log}"));
+    assert!(summary.contains("prewarmed=1"), "{summary}");
+    assert!(summary.contains("skipped=1"), "{summary}");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: prewarming runs off the main loop. While one venv's slow startup
+/// script runs, the already-pooled fallback backend keeps answering; venvs in
+/// the pool are not launched again, and launches count toward `--max-backends`.
+#[cfg(unix)]
+#[tokio::test]
+async fn prewarm_launches_in_background_and_skips_pooled_venvs() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fallback = PackageConfig {
+        name: "pkg-a".to_string(),
+        scenario: serde_json::json!({
+            "on_startup": [],
+            "steps": [
+                {
+                    "expect": { "method": "initialize" },
+                    "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+                },
+                { "expect": { "method": "initialized" }, "actions": [] },
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                { "expect": { "method": "initialized" }, "actions": [] },
+                { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+                {
+                    "expect": { "method": "textDocument/hover" },
+                    "actions": [{ "type": "respond", "body": { "contents": "pkg-a" } }]
+                },
+                {
+                    "expect": { "method": "shutdown" },
+                    "actions": [{ "type": "respond", "body": null }]
+                }
+            ]
+        }),
+        has_venv: true,
+    };
+    let config = WorkspaceConfig {
+        packages: vec![fallback, package("pkg-b"), package("pkg-c")],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let script = root.join("startup.sh");
+    std::fs::write(
+        &script,
+        "#!/bin/sh\ncase \"$1\" in *pkg-b*) sleep 3;; esac\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg-a"),
+        &[
+            "--prewarm",
+            "--max-backends",
+            "2",
+            "--backend-startup-script",
+            script.to_str().unwrap(),
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg-a")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg-a/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    let started = std::time::Instant::now();
+    let response = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert_eq!(response.result.unwrap()["contents"], "pkg-a");
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(2),
+        "hover waited for the prewarm launch: {:?}",
+        started.elapsed()
+    );
+
+    let mut log = String::new();
+    for _ in 0..80 {
+        log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        if log.contains("Prewarmed workspace backends") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let summary = log
+        .lines()
+        .find(|line| line.contains("Prewarmed workspace backends"))
+        .unwrap_or_else(|| panic!("no prewarm summary in log:\n{log}"));
+    // pkg-a was already pooled, pkg-b was prewarmed, pkg-c didn't fit
+    assert!(summary.contains("found=3"), "{summary}");
+    assert!(summary.contains("prewarmed=1"), "{summary}");
+    assert!(summary.contains("skipped=1"), "{summary}");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,164 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: a backend progress token that never ends is reaped after
+/// `--progress-token-ttl` and the client receives a synthetic `end`.
+#[tokio::test]
+async fn stale_progress_token_gets_synthetic_end() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{
+                    "type": "notify",
+                    "method": "$/progress",
+                    "params": { "token": "analysis", "value": { "kind": "begin", "title": "Analyzing" } }
+                }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--progress-token-ttl", "1"],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let begin = proxy.read_next().await;
+    assert_eq!(begin.method_name(), Some("$/progress"));
+    assert_eq!(begin.params.as_ref().unwrap()["value"]["kind"], "begin");
+
+    let started = std::time::Instant::now();
+    let end = proxy.read_next().await;
+    assert_eq!(end.method_name(), Some("$/progress"));
+    let params = end.params.as_ref().unwrap();
+    assert_eq!(params["token"], "analysis");
+    assert_eq!(params["value"]["kind"], "end");
+    assert!(started.elapsed() >= std::time::Duration::from_millis(500));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// Backend that streams one partial result for `references` using the
+/// proxy-assigned token it expects to receive, then sends an empty final result.
+fn streaming_references_scenario(proxy_token: &str, uri_marker: &str) -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "referencesProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": {
+                    "method": "textDocument/references",
+                    "params": { "partialResultToken": proxy_token }
+                },
+                "actions": [
+                    {
+                        "type": "notify",
+                        "method": "$/progress",
+                        "params": { "token": proxy_token, "value": [{ "uri": uri_marker }] }
+                    },
+                    { "type": "respond", "body": [] }
+                ]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+/// E2E: `partialResultToken`s are remapped to proxy-unique tokens per request
+/// and streamed `$/progress` partial results are mapped back to the client's
+/// token, across two backends.
+#[tokio::test]
+async fn partial_result_tokens_remapped_across_backends() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: streaming_references_scenario("typemux-partial-1", "file:///from-a"),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: streaming_references_scenario("typemux-partial-2", "file:///from-b"),
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    for (project, client_token, marker) in [
+        ("proj-a", "refs", "file:///from-a"),
+        ("proj-b", "refs-b", "file:///from-b"),
+    ] {
+        let file_uri = support::path_to_uri(&root.join(project).join("main.py"));
+        proxy.did_open(&file_uri, "x = 1\n").await;
+
+        let id = proxy
+            .send_request(
+                "textDocument/references",
+                serde_json::json!({
+                    "textDocument": { "uri": file_uri },
+                    "position": { "line": 0, "character": 0 },
+                    "context": { "includeDeclaration": true },
+                    "partialResultToken": client_token
+                }),
+            )
+            .await;
+
+        let partial = proxy.read_next().await;
+        assert_eq!(partial.method_name(), Some("$/progress"));
+        let params = partial.params.as_ref().unwrap();
+        assert_eq!(params["token"], client_token);
+        assert_eq!(params["value"][0]["uri"], marker);
+
+        let response = proxy.read_next().await;
+        assert_eq!(response.id, Some(typemux_cc::message::RpcId::Number(id)));
+        assert_eq!(response.result, Some(serde_json::json!([])));
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
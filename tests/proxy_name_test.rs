@@ -0,0 +1,52 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--proxy-name` is reported as `serverInfo.name` and prefixes
+/// proxy-generated error responses.
+#[tokio::test]
+async fn proxy_name_used_in_server_info_and_errors() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: serde_json::json!({}),
+            has_venv: false,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--proxy-name", "acme-lsp"],
+    );
+
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    let server_info = &init_resp.result.as_ref().unwrap()["serverInfo"];
+    assert_eq!(server_info["name"], "acme-lsp");
+    assert_eq!(server_info["version"], env!("CARGO_PKG_VERSION"));
+    proxy.send_initialized().await;
+
+    // No .venv anywhere: the proxy answers the request itself
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    let resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    let message = &resp.error.as_ref().expect("expected an error").message;
+    assert!(
+        message.starts_with("acme-lsp: "),
+        "unexpected message: {message}"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,107 @@
+mod support;
+
+use std::collections::HashMap;
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::{error_codes, RpcId};
+
+/// E2E: `--max-message-rate` rejects a request flood beyond the burst size with
+/// a `RATE_LIMITED` (-32012) error while the requests within budget reach the backend.
+#[tokio::test]
+async fn request_flood_is_rate_limited() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // Only the burst (2 requests) is forwarded
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--max-message-rate", "2"],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let params = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 }
+    });
+    let mut ids = Vec::new();
+    for _ in 0..10 {
+        ids.push(
+            proxy
+                .send_request("textDocument/hover", params.clone())
+                .await,
+        );
+    }
+
+    let mut responses = HashMap::new();
+    while responses.len() < ids.len() {
+        let msg = proxy.read_next().await;
+        if let Some(RpcId::Number(id)) = msg.id {
+            responses.insert(id, msg);
+        }
+    }
+
+    let limited: Vec<_> = ids
+        .iter()
+        .filter(|id| responses[*id].error.is_some())
+        .collect();
+    assert_eq!(
+        limited.len(),
+        8,
+        "expected the 8 requests past the burst to be limited"
+    );
+    for id in &ids[..2] {
+        assert!(responses[id].error.is_none());
+    }
+    for id in limited {
+        let error = responses[id].error.as_ref().unwrap();
+        assert_eq!(error.code, error_codes::RATE_LIMITED);
+        assert!(error.message.contains("rate limited"));
+        assert!(
+            error.data.as_ref().unwrap()["retryAfterMs"]
+                .as_u64()
+                .unwrap()
+                > 0
+        );
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,110 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// Backend that registers the same file watcher as every other backend
+/// (under its own id) once a document is opened, then answers one hover.
+fn scenario(registration_id: &str, hover: &str) -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{
+                    "type": "request",
+                    "id": 5,
+                    "method": "client/registerCapability",
+                    "params": {
+                        "registrations": [{
+                            "id": registration_id,
+                            "method": "workspace/didChangeWatchedFiles",
+                            "registerOptions": { "watchers": [{ "globPattern": "**/*.py" }] }
+                        }]
+                    }
+                }]
+            },
+            { "expect": { "method": "<response>", "id": 5 }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": hover } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+/// E2E: the second backend's identical registerCapability is answered by the
+/// proxy; the client sees the watcher registered once.
+#[tokio::test]
+async fn duplicate_register_capability_not_forwarded() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario("watch-a", "hover from a"),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario("watch-b", "hover from b"),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let hover = |uri: &str| {
+        serde_json::json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 0, "character": 0 }
+        })
+    };
+
+    // proj-a registers first: the client gets the request
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    let register = proxy.read_next().await;
+    assert_eq!(register.method_name(), Some("client/registerCapability"));
+    proxy.respond(&register, serde_json::Value::Null).await;
+    let hover_a = proxy
+        .request("textDocument/hover", hover(&file_a_uri))
+        .await;
+    assert!(hover_a.error.is_none(), "{:?}", hover_a.error);
+
+    // proj-b's identical registration is answered by the proxy (the backend
+    // exits unless that response comes before the hover)
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let hover_id = proxy
+        .send_request("textDocument/hover", hover(&file_b_uri))
+        .await;
+    loop {
+        let msg = proxy.read_next().await;
+        assert_ne!(
+            msg.method_name(),
+            Some("client/registerCapability"),
+            "duplicate registration forwarded"
+        );
+        if msg.id == Some(RpcId::Number(hover_id)) {
+            assert!(msg.error.is_none(), "{:?}", msg.error);
+            assert_eq!(msg.result.unwrap()["contents"]["value"], "hover from b");
+            break;
+        }
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
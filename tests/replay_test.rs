@@ -0,0 +1,177 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: `--replay` drives the proxy from a recorded transcript.
+///
+/// The transcript contains initialize → initialized → didOpen → hover, followed
+/// by timed shutdown/exit so the hover response is delivered before exiting.
+#[tokio::test]
+async fn replay_transcript_initialize_did_open_hover() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "replayed hover" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let transcript = [
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "processId": null, "rootUri": root_uri, "capabilities": {}
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": { "uri": file_uri, "languageId": "python", "version": 1, "text": "x = 1\n" }
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+            "textDocument": { "uri": file_uri }, "position": { "line": 0, "character": 0 }
+        }}),
+        serde_json::json!({"elapsed_ms": 500, "message": {"jsonrpc": "2.0", "id": 3, "method": "shutdown"}}),
+        serde_json::json!({"elapsed_ms": 600, "message": {"jsonrpc": "2.0", "method": "exit"}}),
+    ]
+    .iter()
+    .map(|v| v.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+    let transcript_path = root.join("transcript.jsonl");
+    std::fs::write(&transcript_path, transcript).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--replay", transcript_path.to_str().unwrap()],
+    );
+
+    let init_resp = proxy.read_next().await;
+    assert_eq!(init_resp.id, Some(RpcId::Number(1)));
+    assert!(init_resp.result.is_some());
+
+    let hover_resp = proxy.read_next().await;
+    assert_eq!(hover_resp.id, Some(RpcId::Number(2)));
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "replayed hover"
+    );
+
+    let shutdown_resp = proxy.read_next().await;
+    assert_eq!(shutdown_resp.id, Some(RpcId::Number(3)));
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--synthetic-initialize` lets a transcript without `initialize` create
+/// a backend. The backend must receive the synthetic params verbatim.
+#[tokio::test]
+async fn replay_with_synthetic_initialize() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": {
+                    "method": "initialize",
+                    "params": { "initializationOptions": { "source": "synthetic" } }
+                },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "headless hover" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let init_params = serde_json::json!({
+        "processId": null,
+        "rootUri": root_uri,
+        "capabilities": {},
+        "initializationOptions": { "source": "synthetic" }
+    });
+    let init_path = root.join("initialize.json");
+    std::fs::write(&init_path, init_params.to_string()).unwrap();
+
+    let transcript = [
+        serde_json::json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": { "uri": file_uri, "languageId": "python", "version": 1, "text": "x = 1\n" }
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "textDocument/hover", "params": {
+            "textDocument": { "uri": file_uri }, "position": { "line": 0, "character": 0 }
+        }}),
+        serde_json::json!({"elapsed_ms": 500, "message": {"jsonrpc": "2.0", "id": 2, "method": "shutdown"}}),
+        serde_json::json!({"elapsed_ms": 600, "message": {"jsonrpc": "2.0", "method": "exit"}}),
+    ]
+    .iter()
+    .map(|v| v.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+    let transcript_path = root.join("transcript.jsonl");
+    std::fs::write(&transcript_path, transcript).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--replay",
+            transcript_path.to_str().unwrap(),
+            "--synthetic-initialize",
+            init_path.to_str().unwrap(),
+        ],
+    );
+
+    let hover_resp = proxy.read_next().await;
+    assert_eq!(hover_resp.id, Some(RpcId::Number(1)));
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "headless hover"
+    );
+
+    let shutdown_resp = proxy.read_next().await;
+    assert_eq!(shutdown_resp.id, Some(RpcId::Number(2)));
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,77 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: `--request-timeout` answers a request the backend never answers with
+/// RequestCancelled and forwards `$/cancelRequest` to the backend.
+#[tokio::test]
+async fn unanswered_request_is_cancelled_after_timeout() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // Hung backend: the hover is never answered
+            { "expect": { "method": "textDocument/hover" }, "actions": [] },
+            { "expect": { "method": "$/cancelRequest" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--request-timeout", "1"],
+    );
+
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let sent = std::time::Instant::now();
+    let id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let resp = proxy.read_next().await;
+    assert_eq!(resp.id, Some(RpcId::Number(id)));
+    assert_eq!(resp.error.as_ref().unwrap().code, -32800);
+    assert!(
+        sent.elapsed() >= std::time::Duration::from_secs(1),
+        "cancelled before the timeout: {:?}",
+        sent.elapsed()
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,84 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::error_codes;
+
+/// E2E: `--response-size-limit-bytes` replaces an oversized backend response
+/// with a `RESPONSE_TOO_LARGE` (-32014) error while smaller responses pass through untouched.
+#[tokio::test]
+async fn oversized_response_replaced_with_error() {
+    let huge = "x".repeat(8 * 1024);
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": huge } }]
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "int" } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--response-size-limit-bytes", "4096"],
+    );
+
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let params = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 }
+    });
+
+    let oversized = proxy.request("textDocument/hover", params.clone()).await;
+    let error = oversized
+        .error
+        .expect("oversized response should be an error");
+    assert_eq!(error.code, error_codes::RESPONSE_TOO_LARGE);
+    assert!(
+        error.message.contains("response too large"),
+        "unexpected message: {}",
+        error.message
+    );
+    assert!(oversized.result.is_none());
+
+    let small = proxy.request("textDocument/hover", params).await;
+    assert!(small.error.is_none());
+    assert_eq!(small.result.unwrap()["contents"], "int");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
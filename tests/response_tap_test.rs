@@ -0,0 +1,84 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--backend-response-tap log` observes each backend→client message,
+/// notifications and responses alike, without changing what the client gets.
+#[tokio::test]
+async fn log_tap_observes_backend_messages() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{
+                    "type": "notify",
+                    "method": "window/logMessage",
+                    "params": { "type": 3, "message": "indexing" }
+                }]
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "x: int" } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--backend-response-tap", "log"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let logged = proxy.read_next().await;
+    assert_eq!(logged.method_name(), Some("window/logMessage"));
+    assert_eq!(logged.params.unwrap()["message"], "indexing");
+
+    let hover_id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    let hover = proxy.read_next().await;
+    assert_eq!(hover.id, Some(typemux_cc::message::RpcId::Number(hover_id)));
+    assert_eq!(hover.result.unwrap()["contents"], "x: int");
+
+    let line = proxy.wait_for_log("typemux_cc::tap").await;
+    assert!(line.contains("window/logMessage"), "{line}");
+    let line = proxy.wait_for_log("typemux_cc::tap").await;
+    assert!(line.contains(&format!("Number({hover_id})")), "{line}");
+    assert!(line.contains("is_error=false"), "{line}");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,230 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+fn show_message_request_action() -> serde_json::Value {
+    serde_json::json!({
+        "type": "request",
+        "id": 7,
+        "method": "window/showMessageRequest",
+        "params": {
+            "type": 3,
+            "message": "Install missing stubs?",
+            "actions": [{ "title": "Yes" }, { "title": "No" }]
+        }
+    })
+}
+
+/// Read until the proxy forwards the backend's `window/showMessageRequest`
+async fn read_show_message_request(proxy: &mut ProxyUnderTest) -> typemux_cc::message::RpcMessage {
+    loop {
+        let msg = proxy.read_next().await;
+        if msg.method_name() == Some("window/showMessageRequest") {
+            return msg;
+        }
+    }
+}
+
+/// E2E: `window/showMessageRequest` reaches the client under a proxy-assigned
+/// id, and the user's choice goes back to the backend with the original id
+/// and the chosen action intact.
+#[tokio::test]
+async fn show_message_request_round_trip() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [show_message_request_action()]
+            },
+            {
+                "expect": { "method": "<response>", "id": 7, "result": { "title": "Yes" } },
+                "actions": []
+            },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "after choice" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let request = read_show_message_request(&mut proxy).await;
+    assert!(request.is_request());
+    assert!(
+        matches!(request.id, Some(RpcId::Number(n)) if n < 0),
+        "expected a proxy-assigned id, got {:?}",
+        request.id
+    );
+    let params = request.params.as_ref().unwrap();
+    assert_eq!(params["message"], "Install missing stubs?");
+    assert_eq!(params["actions"][1]["title"], "No");
+
+    proxy
+        .respond(&request, serde_json::json!({ "title": "Yes" }))
+        .await;
+
+    // The mock only answers hover after receiving id 7 with the chosen action
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_resp.error.is_none(), "{:?}", hover_resp.error);
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "after choice"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: the user answers a `window/showMessageRequest` after its backend was
+/// evicted. The answer is discarded instead of reaching another backend.
+#[tokio::test]
+async fn show_message_request_answer_after_eviction_is_discarded() {
+    let scenario_a = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [show_message_request_action()]
+            }
+        ]
+    });
+    // Any stray message (such as the late answer) before hover fails this backend
+    let scenario_b = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "hover from b" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario_a,
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario_b,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &[
+            "--max-backends",
+            "1",
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ],
+    );
+
+    let root_uri = support::path_to_uri(&root);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_a_uri = support::path_to_uri(&root.join("proj-a/main.py"));
+    proxy.did_open(&file_a_uri, "a = 1\n").await;
+    let request = read_show_message_request(&mut proxy).await;
+
+    // Opening proj-b evicts proj-a's backend (pool cap 1) before the user answers
+    let file_b_uri = support::path_to_uri(&root.join("proj-b/main.py"));
+    proxy.did_open(&file_b_uri, "b = 2\n").await;
+    proxy
+        .respond(&request, serde_json::json!({ "title": "Yes" }))
+        .await;
+
+    let hover_resp = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_b_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(hover_resp.error.is_none(), "{:?}", hover_resp.error);
+    assert_eq!(
+        hover_resp.result.as_ref().unwrap()["contents"]["value"],
+        "hover from b"
+    );
+
+    // The log writer is asynchronous; give it a moment to flush
+    let mut log = String::new();
+    for _ in 0..20 {
+        log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        if log.contains("originating backend is gone") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(
+        log.contains("Discarding client response: originating backend is gone"),
+        "{log}"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
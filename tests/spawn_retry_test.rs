@@ -0,0 +1,90 @@
+#![cfg(unix)]
+
+mod support;
+
+use std::os::unix::fs::PermissionsExt;
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: a backend that can't be spawned yet (its language server only
+/// becomes executable shortly after the first attempt) is retried within the
+/// same launch, and the didOpen that needed it still reaches it.
+#[tokio::test]
+async fn transient_spawn_failure_is_retried() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": "x: int" } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let (temp_dir, root) = support::setup_test_workspace(&WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    });
+
+    // The first spawn fails; the startup script makes the server executable
+    // again in the background, after the script itself has returned
+    let server = root.join("pkg/.venv/bin/pyright-langserver");
+    std::fs::set_permissions(&server, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let script = root.join("startup.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\n(sleep 0.05; chmod 755 '{}') >/dev/null 2>&1 &\n",
+            server.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &["--backend-startup-script", script.to_str().unwrap()],
+    );
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let file = root.join("pkg/main.py");
+    std::fs::write(&file, "x = 1\n").unwrap();
+    let uri = support::path_to_uri(&file);
+    proxy.did_open(&uri, "x = 1\n").await;
+
+    let response = proxy
+        .request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    assert!(
+        response.error.is_none(),
+        "hover failed: {:?}",
+        response.error
+    );
+    assert_eq!(response.result.unwrap()["contents"], "x: int");
+    proxy
+        .wait_for_log("Failed to spawn backend, retrying")
+        .await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
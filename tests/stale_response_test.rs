@@ -0,0 +1,208 @@
+mod support;
+
+use std::collections::HashMap;
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+// Request ids assigned by ProxyUnderTest: initialize = 1, then the two hovers.
+const HOVER_A_ID: i64 = 2;
+const HOVER_B_ID: i64 = 3;
+
+fn hover_body(text: &str) -> serde_json::Value {
+    serde_json::json!({ "contents": { "kind": "plaintext", "value": text } })
+}
+
+/// proj-a never answers the first hover; proj-b answers its own hover and
+/// also sends a late response carrying proj-a's request id (a stale response
+/// from a backend that does not own the request).
+fn setup(policy: &str) -> ProxyUnderTest {
+    let mut steps_a = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+        serde_json::json!({ "expect": { "method": "textDocument/hover" }, "actions": [] }),
+    ];
+    if policy != "drop" {
+        // The proxy answered the orphaned request and cancels it at its owner,
+        // which answers anyway; a second hover shows that answer was dropped.
+        // `retry` behaves the same: the owner is still alive, so re-issuing
+        // would put the id in flight twice on one backend.
+        steps_a.push(serde_json::json!({
+            "expect": { "method": "$/cancelRequest" },
+            "actions": [{ "type": "respond", "id": HOVER_A_ID, "body": hover_body("late from a") }]
+        }));
+        steps_a.push(serde_json::json!({
+            "expect": { "method": "textDocument/hover" },
+            "actions": [{ "type": "respond", "body": hover_body("second from a") }]
+        }));
+    }
+    steps_a.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+
+    let scenario_b = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [
+                    { "type": "respond", "id": HOVER_A_ID, "body": hover_body("stale from b") },
+                    { "type": "respond", "body": hover_body("hover from b") }
+                ]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: serde_json::json!({ "on_startup": [], "steps": steps_a }),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario_b,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root,
+        &["--on-stale-response", policy],
+    )
+}
+
+/// Open one file per project and send a hover to each backend. Returns the URIs.
+async fn send_hovers(proxy: &mut ProxyUnderTest) -> Vec<String> {
+    let root = proxy.root().to_path_buf();
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    let mut uris = Vec::new();
+    for pkg in ["proj-a", "proj-b"] {
+        let file = root.join(pkg).join("main.py");
+        std::fs::write(&file, "x = 1\n").unwrap();
+        let uri = support::path_to_uri(&file);
+        proxy.did_open(&uri, "x = 1\n").await;
+        uris.push(uri);
+    }
+
+    for (uri, expected_id) in uris.iter().zip([HOVER_A_ID, HOVER_B_ID]) {
+        let id = proxy
+            .send_request(
+                "textDocument/hover",
+                serde_json::json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 0, "character": 0 }
+                }),
+            )
+            .await;
+        assert_eq!(id, expected_id);
+    }
+    uris
+}
+
+/// Read the next `count` responses, keyed by request id.
+async fn read_responses(
+    proxy: &mut ProxyUnderTest,
+    count: usize,
+) -> HashMap<i64, typemux_cc::message::RpcMessage> {
+    let mut responses = HashMap::new();
+    while responses.len() < count {
+        let msg = proxy.read_next().await;
+        if let (true, Some(RpcId::Number(id))) = (msg.is_response(), &msg.id) {
+            responses.insert(*id, msg);
+        }
+    }
+    responses
+}
+
+/// The orphaned request is answered with RequestCancelled and the owner's
+/// late answer to it is dropped; the owner keeps serving later requests.
+async fn assert_cancelled_at_owner(proxy: &mut ProxyUnderTest) {
+    let uris = send_hovers(proxy).await;
+
+    let responses = read_responses(proxy, 2).await;
+    assert_eq!(responses[&HOVER_A_ID].error.as_ref().unwrap().code, -32800);
+    assert_eq!(
+        responses[&HOVER_B_ID].result.as_ref().unwrap()["contents"]["value"],
+        "hover from b"
+    );
+
+    let id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": uris[0] },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    let next = read_responses(proxy, 1).await;
+    assert_eq!(
+        next[&id].result.as_ref().unwrap()["contents"]["value"],
+        "second from a"
+    );
+}
+
+/// E2E: `--on-stale-response cancel` (default) answers the orphaned request
+/// with RequestCancelled and drops the owner's late answer to it.
+#[tokio::test]
+async fn stale_response_cancel() {
+    let mut proxy = setup("cancel");
+    assert_cancelled_at_owner(&mut proxy).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--on-stale-response retry` does not re-issue a request whose owner
+/// is still alive; it cancels it like `cancel`, so the client never gets two
+/// responses for one request.
+#[tokio::test]
+async fn stale_response_retry_with_live_owner_cancels() {
+    let mut proxy = setup("retry");
+    assert_cancelled_at_owner(&mut proxy).await;
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--on-stale-response drop` discards the stale response without
+/// answering the orphaned request.
+#[tokio::test]
+async fn stale_response_drop() {
+    let mut proxy = setup("drop");
+    send_hovers(&mut proxy).await;
+
+    // backend-b wrote the stale response first, so a cancel for the orphaned
+    // request would have reached the client before backend-b's own answer.
+    let first = proxy.read_next().await;
+    assert_eq!(first.id, Some(RpcId::Number(HOVER_B_ID)));
+    assert_eq!(
+        first.result.as_ref().unwrap()["contents"]["value"],
+        "hover from b"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,213 @@
+#![cfg(unix)]
+
+mod support;
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+fn write_script(root: &Path, body: &str) -> PathBuf {
+    let path = root.join("startup.sh");
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn workspace() -> (tempfile::TempDir, PathBuf) {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    support::setup_test_workspace(&WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    })
+}
+
+/// E2E: the startup script runs with the venv path and VIRTUAL_ENV before the
+/// backend is spawned.
+#[tokio::test]
+async fn startup_script_runs_before_spawn() {
+    let (temp_dir, root) = workspace();
+    let marker = root.join("startup.out");
+    let script = write_script(
+        &root,
+        &format!("echo \"$1 $VIRTUAL_ENV\" > {}", marker.display()),
+    );
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--backend-startup-script", script.to_str().unwrap()],
+    );
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"]["hoverProvider"],
+        true
+    );
+
+    let venv = root.join("pkg/.venv");
+    let recorded = std::fs::read_to_string(&marker).unwrap();
+    assert_eq!(
+        recorded.trim(),
+        format!("{} {}", venv.display(), venv.display())
+    );
+
+    proxy.send_initialized().await;
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: a failing startup script aborts backend creation with a clear error.
+#[tokio::test]
+async fn failing_startup_script_aborts_spawn() {
+    let (temp_dir, root) = workspace();
+    let script = write_script(&root, "exit 3");
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--backend-startup-script", script.to_str().unwrap()],
+    );
+    // No fallback backend could be started: minimal capabilities
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"],
+        support::default_capabilities()
+    );
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    let notice = proxy.read_next().await;
+    assert_eq!(notice.method_name(), Some("window/showMessage"));
+    let message = notice.params.as_ref().unwrap()["message"].as_str().unwrap();
+    assert!(
+        message.contains("startup script failed"),
+        "unexpected message: {message}"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: a slow startup script for one venv runs off the main loop. A response
+/// from another venv's backend is forwarded while it runs, and the request
+/// that triggered the launch is answered once the new backend is up.
+#[tokio::test]
+async fn slow_startup_script_does_not_stall_other_backends() {
+    let hover_steps = |delay_ms: u64, contents: &str| {
+        vec![
+            serde_json::json!({ "expect": { "method": "textDocument/didOpen" }, "actions": [] }),
+            serde_json::json!({
+                "expect": { "method": "textDocument/hover" },
+                "actions": [
+                    { "type": "sleep_ms", "ms": delay_ms },
+                    { "type": "respond", "body": { "contents": contents } }
+                ]
+            }),
+        ]
+    };
+    let scenario = |initialized_count: usize, delay_ms: u64, contents: &str| {
+        let mut steps = vec![serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+        })];
+        steps.extend(
+            std::iter::repeat(
+                serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+            )
+            .take(initialized_count),
+        );
+        steps.extend(hover_steps(delay_ms, contents));
+        steps.push(serde_json::json!({
+            "expect": { "method": "shutdown" },
+            "actions": [{ "type": "respond", "body": null }]
+        }));
+        serde_json::json!({ "on_startup": [], "steps": steps })
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "pkg-a".to_string(),
+                // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+                scenario: scenario(2, 300, "pkg-a"),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "pkg-b".to_string(),
+                scenario: scenario(1, 0, "pkg-b"),
+                has_venv: true,
+            },
+        ],
+    });
+    let script = write_script(&root, "case \"$1\" in *pkg-b*) sleep 2;; esac");
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg-a"),
+        &["--backend-startup-script", script.to_str().unwrap()],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg-a")))
+        .await;
+    proxy.send_initialized().await;
+
+    let a_uri = support::path_to_uri(&root.join("pkg-a/main.py"));
+    let b_uri = support::path_to_uri(&root.join("pkg-b/main.py"));
+    let hover = |uri: &str| {
+        serde_json::json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 0, "character": 0 }
+        })
+    };
+    proxy.did_open(&a_uri, "x = 1\n").await;
+    let started = std::time::Instant::now();
+    let a_id = proxy
+        .send_request("textDocument/hover", hover(&a_uri))
+        .await;
+    // Launches pkg-b's backend behind the 2s startup script
+    proxy.did_open(&b_uri, "y = 2\n").await;
+    let b_id = proxy
+        .send_request("textDocument/hover", hover(&b_uri))
+        .await;
+
+    let a_resp = proxy.read_next().await;
+    assert_eq!(a_resp.id, Some(RpcId::Number(a_id)));
+    assert_eq!(a_resp.result.unwrap()["contents"], "pkg-a");
+    assert!(
+        started.elapsed() < std::time::Duration::from_millis(1500),
+        "pkg-a's response waited for pkg-b's startup script: {:?}",
+        started.elapsed()
+    );
+
+    let b_resp = proxy.read_next().await;
+    assert_eq!(b_resp.id, Some(RpcId::Number(b_id)));
+    assert_eq!(b_resp.result.unwrap()["contents"], "pkg-b");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
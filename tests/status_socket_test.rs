@@ -0,0 +1,143 @@
+#![cfg(unix)]
+
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--status` reads a JSON snapshot from a running instance's
+/// `--status-socket`.
+#[tokio::test]
+async fn status_reports_pool_and_documents() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let socket = root.join("status.sock");
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--status-socket", socket.to_str().unwrap()],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    // The didOpen is handled asynchronously; poll until it is reflected
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let status = loop {
+        let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_typemux-cc"))
+            .args(["--status", "--status-socket", socket.to_str().unwrap()])
+            .output()
+            .await
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "--status failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        if status["open_documents"] == 1 || std::time::Instant::now() > deadline {
+            break status;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    };
+
+    assert_eq!(status["pool_size"], 1, "{status}");
+    assert_eq!(status["open_documents"], 1, "{status}");
+    assert_eq!(status["pending_requests"], 0, "{status}");
+    let backend = &status["backends"][0];
+    assert_eq!(
+        backend["venv"],
+        root.join("pkg/.venv").display().to_string(),
+        "{status}"
+    );
+    assert_eq!(backend["session"], 1, "{status}");
+    assert!(
+        backend["warmup_state"] == "warming" || backend["warmup_state"] == "ready",
+        "{status}"
+    );
+
+    // A second instance must not take over the live socket
+    let second = tokio::process::Command::new(env!("CARGO_BIN_EXE_typemux-cc"))
+        .args(["--status-socket", socket.to_str().unwrap()])
+        .current_dir(root.join("pkg"))
+        .env_remove("VIRTUAL_ENV")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .unwrap();
+    assert!(!second.status.success());
+    assert!(
+        String::from_utf8_lossy(&second.stderr).contains("in use by a running instance"),
+        "{}",
+        String::from_utf8_lossy(&second.stderr)
+    );
+    assert!(socket.exists());
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+    proxy.wait_for_exit(5000).await;
+    assert!(!socket.exists(), "socket file left behind");
+}
+
+/// A socket file left behind by an instance that is gone is replaced.
+#[tokio::test]
+async fn stale_status_socket_is_replaced() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let socket = dir.path().join("status.sock");
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    assert!(socket.exists());
+
+    // The instance serves the socket, then exits at the end of its (empty) input
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_typemux-cc"))
+        .args(["--status-socket", socket.to_str().unwrap()])
+        .current_dir(dir.path())
+        .env_remove("VIRTUAL_ENV")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Serving status"), "{stderr}");
+    assert!(!socket.exists(), "socket file left behind");
+}
+
+/// `--status` without a listening instance fails cleanly.
+#[tokio::test]
+async fn status_without_instance_fails() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_typemux-cc"))
+        .args(["--status", "--status-socket"])
+        .arg(dir.path().join("missing.sock"))
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not query"));
+}
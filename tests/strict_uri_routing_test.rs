@@ -0,0 +1,63 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::error_codes;
+
+/// E2E: with `--strict-uri-routing`, a URI-less request is rejected even
+/// though the single running backend could have taken it.
+#[tokio::test]
+async fn uri_less_request_rejected_with_one_backend() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--strict-uri-routing"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let response = proxy
+        .request(
+            "workspace/executeCommand",
+            serde_json::json!({ "command": "pyright.organizeimports", "arguments": [] }),
+        )
+        .await;
+    let error = response.error.expect("URI-less request should be rejected");
+    assert_eq!(error.code, error_codes::UNROUTABLE);
+    assert!(
+        error.message.contains("strict URI routing"),
+        "{}",
+        error.message
+    );
+    assert_eq!(error.data.unwrap()["poolSize"], 1);
+
+    // The backend never saw the request: its next expected message is shutdown
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -0,0 +1,63 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: `--strip-ansi` removes color codes from backend window/logMessage
+/// text before it reaches the client.
+#[tokio::test]
+async fn ansi_codes_stripped_from_log_message() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/didOpen" },
+                "actions": [{
+                    "type": "notify",
+                    "method": "window/logMessage",
+                    "params": { "type": 1, "message": "\u{1b}[1;31merror\u{1b}[0m: cannot import \u{1b}[33m'foo'\u{1b}[m" }
+                }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--strip-ansi"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "import foo\n").await;
+
+    let logged = proxy.read_next().await;
+    assert_eq!(logged.method_name(), Some("window/logMessage"));
+    let params = logged.params.unwrap();
+    assert_eq!(params["message"], "error: cannot import 'foo'");
+    assert_eq!(params["type"], 1);
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -3,12 +3,16 @@
 //! Provides `ProxyUnderTest`, a subprocess wrapper that speaks LSP framing
 //! to the proxy binary, plus workspace setup helpers.
 
+// Each integration test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
 use serde_json::Value;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tempfile::TempDir;
-use tokio::process::{Child, Command};
-use typemux_cc::framing::{LspFrameReader, LspFrameWriter};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use typemux_cc::framing::{Incoming, LspFrameReader, LspFrameWriter};
 use typemux_cc::message::{RpcId, RpcMessage};
 
 const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
@@ -60,10 +64,11 @@ pub fn setup_test_workspace(config: &WorkspaceConfig) -> (TempDir, PathBuf) {
             let venv_dir = pkg_dir.join(".venv");
             std::fs::create_dir_all(venv_dir.join("bin")).unwrap();
             std::fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+            // Stand-in interpreter: the proxy only checks it exists and is executable
+            let python_path = venv_dir.join("bin/python");
+            std::fs::write(&python_path, "#!/bin/sh\n").unwrap();
 
-            // Write scenario file into the venv.
-            let scenario_json = serde_json::to_string_pretty(&pkg.scenario).unwrap();
-            std::fs::write(venv_dir.join("scenario.json"), &scenario_json).unwrap();
+            write_scenario(&pkg_dir, &pkg.scenario);
 
             // Fake pyright-langserver that bridges to mock-lsp-backend.
             let script = format!(
@@ -78,6 +83,8 @@ pub fn setup_test_workspace(config: &WorkspaceConfig) -> (TempDir, PathBuf) {
                 use std::os::unix::fs::PermissionsExt;
                 std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))
                     .unwrap();
+                std::fs::set_permissions(&python_path, std::fs::Permissions::from_mode(0o755))
+                    .unwrap();
             }
         }
     }
@@ -85,6 +92,13 @@ pub fn setup_test_workspace(config: &WorkspaceConfig) -> (TempDir, PathBuf) {
     (temp, root)
 }
 
+/// Write (or replace) the scenario played back by a package's mock backend.
+/// Useful when expectations depend on paths only known after setup.
+pub fn write_scenario(pkg_dir: &Path, scenario: &Value) {
+    let scenario_json = serde_json::to_string_pretty(scenario).unwrap();
+    std::fs::write(pkg_dir.join(".venv/scenario.json"), scenario_json).unwrap();
+}
+
 // ── ProxyUnderTest ──────────────────────────────────────────────────
 
 /// A running proxy process with LSP framing readers/writers attached.
@@ -92,7 +106,7 @@ pub struct ProxyUnderTest {
     child: Child,
     reader: LspFrameReader<tokio::process::ChildStdout>,
     writer: LspFrameWriter<tokio::process::ChildStdin>,
-    #[allow(dead_code)]
+    stderr: BufReader<ChildStderr>,
     temp_dir: TempDir,
     root: PathBuf,
     next_id: i64,
@@ -101,8 +115,14 @@ pub struct ProxyUnderTest {
 impl ProxyUnderTest {
     /// Spawn the proxy binary with the given workspace as cwd.
     pub fn spawn(temp_dir: TempDir, root: PathBuf, cwd: &Path) -> Self {
+        Self::spawn_with_args(temp_dir, root, cwd, &[])
+    }
+
+    /// Spawn the proxy binary with extra CLI arguments.
+    pub fn spawn_with_args(temp_dir: TempDir, root: PathBuf, cwd: &Path, args: &[&str]) -> Self {
         let proxy_bin = env!("CARGO_BIN_EXE_typemux-cc");
         let mut child = Command::new(proxy_bin)
+            .args(args)
             .current_dir(cwd)
             // Clear git env vars so the proxy's `git rev-parse` uses the test
             // workspace's .git, not the outer repo's (important when running
@@ -110,6 +130,8 @@ impl ProxyUnderTest {
             .env_remove("GIT_DIR")
             .env_remove("GIT_WORK_TREE")
             .env_remove("GIT_INDEX_FILE")
+            // An activated venv in the test runner's shell would take over the fallback
+            .env_remove("VIRTUAL_ENV")
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -119,17 +141,43 @@ impl ProxyUnderTest {
 
         let stdin = child.stdin.take().unwrap();
         let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
 
         Self {
             child,
             reader: LspFrameReader::new(stdout),
             writer: LspFrameWriter::new(stdin),
+            stderr: BufReader::new(stderr),
             temp_dir,
             root,
             next_id: 1,
         }
     }
 
+    /// OS process id of the proxy (for sending signals).
+    pub fn pid(&self) -> u32 {
+        self.child.id().expect("proxy already exited")
+    }
+
+    /// Wait for the proxy process to exit on its own and return its status.
+    pub async fn wait_for_exit(&mut self, timeout_ms: u64) -> std::process::ExitStatus {
+        match tokio::time::timeout(
+            std::time::Duration::from_millis(timeout_ms),
+            self.child.wait(),
+        )
+        .await
+        {
+            Ok(Ok(status)) => status,
+            Ok(Err(e)) => panic!("wait_for_exit: wait failed: {e}"),
+            Err(_) => {
+                let stderr = self.dump_stderr().await;
+                panic!(
+                    "wait_for_exit: proxy still running after {timeout_ms}ms\n--- stderr ---\n{stderr}"
+                );
+            }
+        }
+    }
+
     /// Return the canonical workspace root path.
     pub fn root(&self) -> &Path {
         &self.root
@@ -157,7 +205,6 @@ impl ProxyUnderTest {
     }
 
     /// Send `textDocument/didOpen` notification.
-    #[allow(dead_code)] // Used by some but not all integration test binaries.
     pub async fn did_open(&mut self, uri: &str, text: &str) {
         let msg = RpcMessage::notification(
             "textDocument/didOpen",
@@ -173,12 +220,24 @@ impl ProxyUnderTest {
         self.write(&msg).await;
     }
 
-    /// Send a request and wait for the response (with timeout).
-    pub async fn request(&mut self, method: &str, params: Value) -> RpcMessage {
+    /// Send an arbitrary notification.
+    pub async fn notify(&mut self, method: &str, params: Value) {
+        let msg = RpcMessage::notification(method, Some(params));
+        self.write(&msg).await;
+    }
+
+    /// Send a request without waiting for the response. Returns the request id.
+    pub async fn send_request(&mut self, method: &str, params: Value) -> i64 {
         let id = self.next_id;
         self.next_id += 1;
         let msg = RpcMessage::request(RpcId::Number(id), method, Some(params));
         self.write(&msg).await;
+        id
+    }
+
+    /// Send a request and wait for the response (with timeout).
+    pub async fn request(&mut self, method: &str, params: Value) -> RpcMessage {
+        let id = self.send_request(method, params).await;
         // Read responses, skipping notifications, until we get one with our id.
         loop {
             let resp = self.read_next().await;
@@ -193,6 +252,12 @@ impl ProxyUnderTest {
         }
     }
 
+    /// Send a response to a server->client request received from the proxy.
+    pub async fn respond(&mut self, request: &RpcMessage, result: Value) {
+        let msg = RpcMessage::success_response(request, result);
+        self.write(&msg).await;
+    }
+
     /// Perform shutdown + exit sequence. Returns the shutdown response.
     pub async fn shutdown_and_exit(&mut self) -> RpcMessage {
         let resp = self.request("shutdown", Value::Null).await;
@@ -274,6 +339,30 @@ impl ProxyUnderTest {
         }
     }
 
+    /// Read the next frame, which must be a JSON-RPC batch (with timeout).
+    pub async fn read_next_batch(&mut self) -> Vec<RpcMessage> {
+        match tokio::time::timeout(READ_TIMEOUT, self.reader.read_message_or_batch()).await {
+            Ok(Ok(Incoming::Batch(messages))) => messages
+                .into_iter()
+                .map(|m| m.expect("read_next_batch: malformed batch element"))
+                .collect(),
+            Ok(Ok(Incoming::Single(msg))) => {
+                panic!("read_next_batch: got a single message {msg:?}")
+            }
+            Ok(Err(e)) => {
+                let stderr = self.dump_stderr().await;
+                panic!("read_next_batch: framing error: {e}\n--- proxy stderr ---\n{stderr}");
+            }
+            Err(_) => {
+                let stderr = self.dump_stderr().await;
+                panic!(
+                    "read_next_batch: timed out after {}s waiting for batch\n--- proxy stderr ---\n{stderr}",
+                    READ_TIMEOUT.as_secs()
+                );
+            }
+        }
+    }
+
     /// Write an LSP message to the proxy's stdin.
     async fn write(&mut self, msg: &RpcMessage) {
         self.writer.write_message(msg).await.unwrap_or_else(|e| {
@@ -281,19 +370,53 @@ impl ProxyUnderTest {
         });
     }
 
+    /// Write `body` to the proxy's stdin as one frame, without JSON encoding.
+    pub async fn write_raw_frame(&mut self, body: &[u8]) {
+        use tokio::io::AsyncWriteExt;
+        let stdin = self.writer.get_mut();
+        let header = format!("Content-Length: {}\r\n\r\n", body.len());
+        stdin.write_all(header.as_bytes()).await.unwrap();
+        stdin.write_all(body).await.unwrap();
+        stdin.flush().await.unwrap();
+    }
+
     /// Dump whatever is currently available on the proxy's stderr.
     async fn dump_stderr(&mut self) -> String {
         use tokio::io::AsyncReadExt;
-        if let Some(stderr) = self.child.stderr.as_mut() {
-            let mut buf = vec![0u8; 16384];
-            match tokio::time::timeout(std::time::Duration::from_millis(100), stderr.read(&mut buf))
-                .await
-            {
-                Ok(Ok(n)) => String::from_utf8_lossy(&buf[..n]).to_string(),
-                _ => "(could not read stderr)".to_string(),
+        let mut buf = vec![0u8; 16384];
+        match tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            self.stderr.read(&mut buf),
+        )
+        .await
+        {
+            Ok(Ok(n)) => String::from_utf8_lossy(&buf[..n]).to_string(),
+            _ => "(could not read stderr)".to_string(),
+        }
+    }
+
+    /// Read the proxy's log (stderr) until a line contains `needle` and
+    /// return that line (with timeout). Earlier lines are consumed.
+    pub async fn wait_for_log(&mut self, needle: &str) -> String {
+        let read = async {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if self.stderr.read_line(&mut line).await.unwrap_or(0) == 0 {
+                    return None;
+                }
+                if line.contains(needle) {
+                    return Some(line.clone());
+                }
             }
-        } else {
-            "(no stderr handle)".to_string()
+        };
+        match tokio::time::timeout(READ_TIMEOUT, read).await {
+            Ok(Some(line)) => line,
+            Ok(None) => panic!("wait_for_log: proxy log ended before {needle:?}"),
+            Err(_) => panic!(
+                "wait_for_log: timed out after {}s waiting for {needle:?}",
+                READ_TIMEOUT.as_secs()
+            ),
         }
     }
 }
@@ -313,3 +436,13 @@ pub fn path_to_uri(path: &Path) -> String {
         .expect("path_to_uri: invalid path")
         .to_string()
 }
+
+/// Capabilities the proxy advertises when no backend answered `initialize`.
+pub fn default_capabilities() -> Value {
+    serde_json::json!({
+        "textDocumentSync": { "openClose": true, "change": 2 },
+        "hoverProvider": true,
+        "definitionProvider": true,
+        "referencesProvider": true
+    })
+}
@@ -0,0 +1,163 @@
+#![cfg(unix)]
+
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: SIGTERM answers in-flight requests with RequestCancelled, shuts the
+/// backend down with shutdown/exit, and exits cleanly.
+#[tokio::test]
+async fn sigterm_cancels_requests_and_drains_backends() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // Never answered: still in flight when the signal arrives
+            { "expect": { "method": "textDocument/hover" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            { "expect": { "method": "exit" }, "actions": [] }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let log_path = root.join("proxy.log");
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--log-file", log_path.to_str().unwrap()],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let hover_id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    // Let the hover reach the backend
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+    // SAFETY: kill(2) on the proxy child we spawned
+    let ret = unsafe { libc::kill(proxy.pid() as libc::pid_t, libc::SIGTERM) };
+    assert_eq!(ret, 0);
+
+    let resp = proxy.read_next().await;
+    assert_eq!(resp.id, Some(typemux_cc::message::RpcId::Number(hover_id)));
+    assert_eq!(resp.error.as_ref().map(|e| e.code), Some(-32800));
+
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(status.success(), "expected a clean exit, got {status:?}");
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Termination signal received"), "{log}");
+    assert!(log.contains("Backend exited gracefully"), "{log}");
+}
+
+/// E2E: a request held while its backend is still launching is not pending on
+/// any backend yet, and is cancelled too.
+#[tokio::test]
+async fn sigterm_cancels_request_waiting_for_launch() {
+    let fallback = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            },
+            { "expect": { "method": "exit" }, "actions": [] }
+        ]
+    });
+    // Still initializing when the signal arrives
+    let slow = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [
+                    { "type": "sleep_ms", "ms": 5000 },
+                    { "type": "respond", "body": { "capabilities": {} } }
+                ]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "pkg".to_string(),
+                scenario: fallback,
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "slow".to_string(),
+                scenario: slow,
+                has_venv: true,
+            },
+        ],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("slow/main.py"));
+    let hover_id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    // Logged while the hover is dispatched, before it is parked
+    proxy.wait_for_log("slow/.venv").await;
+
+    // SAFETY: kill(2) on the proxy child we spawned
+    let ret = unsafe { libc::kill(proxy.pid() as libc::pid_t, libc::SIGTERM) };
+    assert_eq!(ret, 0);
+
+    let resp = proxy.read_next().await;
+    assert_eq!(resp.id, Some(typemux_cc::message::RpcId::Number(hover_id)));
+    assert_eq!(resp.error.as_ref().map(|e| e.code), Some(-32800));
+
+    let status = proxy.wait_for_exit(5000).await;
+    assert!(status.success(), "expected a clean exit, got {status:?}");
+}
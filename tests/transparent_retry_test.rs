@@ -0,0 +1,98 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: with `--transparent-retry`, a hover pending on a backend that crashes
+/// is re-issued to the respawned backend and answered under its original id.
+#[tokio::test]
+async fn hover_survives_backend_crash() {
+    let crash_on_hover = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // Left pending; the didSave sent after the scenario for the
+            // respawned backend is in place crashes the backend
+            { "expect": { "method": "textDocument/hover" }, "actions": [] },
+            { "expect": { "method": "textDocument/didSave" }, "actions": [{ "type": "crash" }] }
+        ]
+    });
+    let respawned = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "hoverProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/hover" },
+                "actions": [{ "type": "respond", "body": { "contents": { "kind": "plaintext", "value": "x: int" } } }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario: crash_on_hover,
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--transparent-retry"],
+    );
+    proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    proxy.send_initialized().await;
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let id = proxy
+        .send_request(
+            "textDocument/hover",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+    support::write_scenario(&root.join("pkg"), &respawned);
+    proxy
+        .notify(
+            "textDocument/didSave",
+            serde_json::json!({ "textDocument": { "uri": file_uri } }),
+        )
+        .await;
+
+    let response = loop {
+        let msg = proxy.read_next().await;
+        if msg.is_response() {
+            break msg;
+        }
+    };
+    assert_eq!(response.id, Some(RpcId::Number(id)));
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert_eq!(response.result.unwrap()["contents"]["value"], "x: int");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
@@ -1,6 +1,7 @@
 mod support;
 
 use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::error_codes;
 
 /// Priority 2: Venv detection — two packages, only one with `.venv`.
 ///
@@ -73,6 +74,10 @@ async fn venv_detection_routing() {
         init_resp.error.is_none(),
         "initialize should not return an error"
     );
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"],
+        support::default_capabilities()
+    );
 
     proxy.send_initialized().await;
 
@@ -119,10 +124,11 @@ async fn venv_detection_routing() {
             }),
         )
         .await;
-    assert!(
-        hover_resp_b.error.is_some(),
-        "hover on pkg-b should fail (no .venv)"
-    );
+    let error_b = hover_resp_b
+        .error
+        .expect("hover on pkg-b should fail (no .venv)");
+    assert_eq!(error_b.code, error_codes::VENV_NOT_FOUND);
+    assert_eq!(error_b.data.unwrap()["uri"], file_b_uri.as_str());
 
     // Shutdown
     let shutdown_resp = proxy.shutdown_and_exit().await;
@@ -0,0 +1,43 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// E2E: a venv whose `bin/python` is missing is not spawned; the client gets
+/// a clear showMessage instead of an opaque backend failure.
+#[tokio::test]
+async fn venv_without_interpreter_reports_clear_error() {
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            // The backend must never be started
+            scenario: serde_json::json!({ "on_startup": [], "steps": [] }),
+            has_venv: true,
+        }],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    std::fs::remove_file(root.join("pkg/.venv/bin/python")).unwrap();
+
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root.join("pkg"));
+    // No fallback backend could be started: minimal capabilities
+    let init_resp = proxy
+        .initialize(&support::path_to_uri(&root.join("pkg")))
+        .await;
+    assert_eq!(
+        init_resp.result.as_ref().unwrap()["capabilities"],
+        support::default_capabilities()
+    );
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+    let notice = proxy.read_next().await;
+    assert_eq!(notice.method_name(), Some("window/showMessage"));
+    let message = notice.params.as_ref().unwrap()["message"].as_str().unwrap();
+    assert!(
+        message.contains("has no usable Python interpreter"),
+        "unexpected message: {message}"
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
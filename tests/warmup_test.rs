@@ -0,0 +1,338 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+use typemux_cc::message::RpcId;
+
+/// E2E: `--warmup-queue-limit` bounds the per-backend warmup queue.
+///
+/// Three `textDocument/definition` requests are sent while the backend is
+/// warming with a limit of 2. The oldest is cancelled immediately; the other
+/// two are forwarded once warmup times out.
+#[tokio::test]
+async fn warmup_queue_limit_cancels_overflow() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "definitionProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--warmup-queue-limit", "2"],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let params = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 }
+    });
+    let first = proxy
+        .send_request("textDocument/definition", params.clone())
+        .await;
+    let second = proxy
+        .send_request("textDocument/definition", params.clone())
+        .await;
+    let third = proxy.send_request("textDocument/definition", params).await;
+
+    // Overflow: the oldest request is cancelled right away
+    let cancelled = proxy.read_next().await;
+    assert_eq!(cancelled.id, Some(RpcId::Number(first)));
+    assert_eq!(cancelled.error.as_ref().unwrap().code, -32800);
+
+    // Warmup times out (fail-open) and the remaining two are answered in order
+    for expected in [second, third] {
+        let resp = proxy.read_next().await;
+        assert_eq!(resp.id, Some(RpcId::Number(expected)));
+        assert!(resp.error.is_none());
+    }
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--no-warmup-method` forwards an excluded method immediately during warmup
+/// while other index-dependent methods stay queued.
+#[tokio::test]
+async fn no_warmup_method_bypasses_queue() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "referencesProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            // references is not queued, so it reaches the backend before definition
+            {
+                "expect": { "method": "textDocument/references" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--no-warmup-method", "textDocument/references"],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let params = serde_json::json!({
+        "textDocument": { "uri": file_uri },
+        "position": { "line": 0, "character": 0 },
+        "context": { "includeDeclaration": true }
+    });
+    let definition = proxy
+        .send_request("textDocument/definition", params.clone())
+        .await;
+    let references = proxy.send_request("textDocument/references", params).await;
+
+    let first = proxy.read_next().await;
+    assert_eq!(first.id, Some(RpcId::Number(references)));
+    let second = proxy.read_next().await;
+    assert_eq!(second.id, Some(RpcId::Number(definition)));
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--warmup-timeout` sets when a queued index-dependent request is released.
+///
+/// The mock backend never reports readiness, so a `textDocument/definition`
+/// sent during warmup is held until the configured 3s deadline passes.
+#[tokio::test]
+async fn warmup_timeout_releases_queue_at_deadline() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "definitionProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &["--warmup-timeout", "3"],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    // The backend instance (and its deadline) is created during initialize
+    let started = std::time::Instant::now();
+    proxy.initialize(&root_uri).await;
+    let initialized = std::time::Instant::now();
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let id = proxy
+        .send_request(
+            "textDocument/definition",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let resp = proxy.read_next().await;
+    assert_eq!(resp.id, Some(RpcId::Number(id)));
+    assert!(resp.error.is_none());
+    assert!(
+        started.elapsed() >= std::time::Duration::from_secs(3),
+        "released before the deadline: {:?}",
+        started.elapsed()
+    );
+    assert!(
+        initialized.elapsed() < std::time::Duration::from_millis(4500),
+        "released well after the deadline: {:?}",
+        initialized.elapsed()
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
+
+/// E2E: `--warmup-state-persist` starts a recently warmed venv's backend Ready.
+///
+/// The state file says the venv finished warming a few seconds ago, so a
+/// `textDocument/definition` is forwarded right away instead of waiting out
+/// the 30s warmup timeout.
+#[tokio::test]
+async fn warmup_state_persist_skips_warmup_for_fresh_venv() {
+    let scenario = serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "definitionProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            // dispatch_initialized forwards a 2nd "initialized" to fallback backends
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "textDocument/definition" },
+                "actions": [{ "type": "respond", "body": [] }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    });
+
+    let config = WorkspaceConfig {
+        packages: vec![PackageConfig {
+            name: "pkg".to_string(),
+            scenario,
+            has_venv: true,
+        }],
+    };
+
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let state_file = root.join("warmup-state.json");
+    let warmed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - 5;
+    let venv = root.join("pkg/.venv").to_string_lossy().into_owned();
+    std::fs::write(
+        &state_file,
+        serde_json::json!({ venv: warmed_at }).to_string(),
+    )
+    .unwrap();
+
+    let state_arg = state_file.to_string_lossy().into_owned();
+    let mut proxy = ProxyUnderTest::spawn_with_args(
+        temp_dir,
+        root.clone(),
+        &root.join("pkg"),
+        &[
+            "--warmup-timeout",
+            "30",
+            "--warmup-state-persist",
+            &state_arg,
+        ],
+    );
+
+    let root_uri = support::path_to_uri(&root.join("pkg"));
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+
+    let file_uri = support::path_to_uri(&root.join("pkg/main.py"));
+    proxy.did_open(&file_uri, "x = 1\n").await;
+
+    let sent = std::time::Instant::now();
+    let id = proxy
+        .send_request(
+            "textDocument/definition",
+            serde_json::json!({
+                "textDocument": { "uri": file_uri },
+                "position": { "line": 0, "character": 0 }
+            }),
+        )
+        .await;
+
+    let resp = proxy.read_next().await;
+    assert_eq!(resp.id, Some(RpcId::Number(id)));
+    assert!(resp.error.is_none());
+    assert!(
+        sent.elapsed() < std::time::Duration::from_secs(5),
+        "request was queued for warmup: {:?}",
+        sent.elapsed()
+    );
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
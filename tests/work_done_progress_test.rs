@@ -0,0 +1,120 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// Backend that creates a work-done progress token named "indexing" (the same
+/// name in every backend) once a document is opened and begins reporting on it.
+fn scenario(title: &str, expect_cancel: bool) -> serde_json::Value {
+    let mut steps = vec![
+        serde_json::json!({
+            "expect": { "method": "initialize" },
+            "actions": [{ "type": "respond", "body": { "capabilities": {} } }]
+        }),
+        serde_json::json!({ "expect": { "method": "initialized" }, "actions": [] }),
+        serde_json::json!({
+            "expect": { "method": "textDocument/didOpen" },
+            "actions": [{
+                "type": "request",
+                "id": 9,
+                "method": "window/workDoneProgress/create",
+                "params": { "token": "indexing" }
+            }]
+        }),
+        serde_json::json!({
+            "expect": { "method": "<response>", "id": 9 },
+            "actions": [{
+                "type": "notify",
+                "method": "$/progress",
+                "params": { "token": "indexing", "value": { "kind": "begin", "title": title } }
+            }]
+        }),
+    ];
+    if expect_cancel {
+        // The cancel arrives with the backend's own token
+        steps.push(serde_json::json!({
+            "expect": {
+                "method": "window/workDoneProgress/cancel",
+                "params": { "token": "indexing" }
+            },
+            "actions": [{
+                "type": "notify",
+                "method": "$/progress",
+                "params": { "token": "indexing", "value": { "kind": "end" } }
+            }]
+        }));
+    }
+    steps.push(serde_json::json!({
+        "expect": { "method": "shutdown" },
+        "actions": [{ "type": "respond", "body": null }]
+    }));
+    serde_json::json!({ "on_startup": [], "steps": steps })
+}
+
+/// E2E: two backends using the same progress token get distinct tokens at the
+/// client, and a client cancel reaches only the backend that owns the token.
+#[tokio::test]
+async fn work_done_progress_tokens_are_namespaced_per_backend() {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: scenario("a", true),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: scenario("b", false),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let mut proxy = ProxyUnderTest::spawn(temp_dir, root.clone(), &root);
+    proxy.initialize(&support::path_to_uri(&root)).await;
+    proxy.send_initialized().await;
+
+    // title → client-visible token
+    let mut tokens = std::collections::HashMap::new();
+    for name in ["proj-a", "proj-b"] {
+        let file_uri = support::path_to_uri(&root.join(format!("{name}/main.py")));
+        proxy.did_open(&file_uri, "x = 1\n").await;
+
+        let create = proxy.read_next().await;
+        assert_eq!(create.method_name(), Some("window/workDoneProgress/create"));
+        let created = create.params.as_ref().unwrap()["token"].clone();
+        proxy.respond(&create, serde_json::Value::Null).await;
+
+        let begin = loop {
+            let msg = proxy.read_next().await;
+            if msg.method_name() == Some("$/progress") {
+                break msg;
+            }
+        };
+        let params = begin.params.unwrap();
+        assert_eq!(params["token"], created, "progress uses the created token");
+        tokens.insert(
+            params["value"]["title"].as_str().unwrap().to_string(),
+            created,
+        );
+    }
+    assert_ne!(tokens["a"], tokens["b"]);
+
+    proxy
+        .notify(
+            "window/workDoneProgress/cancel",
+            serde_json::json!({ "token": tokens["a"] }),
+        )
+        .await;
+    let end = loop {
+        let msg = proxy.read_next().await;
+        if msg.method_name() == Some("$/progress") {
+            break msg;
+        }
+    };
+    let params = end.params.unwrap();
+    assert_eq!(params["token"], tokens["a"]);
+    assert_eq!(params["value"]["kind"], "end");
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+}
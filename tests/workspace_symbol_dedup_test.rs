@@ -0,0 +1,109 @@
+mod support;
+
+use support::{PackageConfig, ProxyUnderTest, WorkspaceConfig};
+
+/// A symbol in the shared module both projects index
+fn shared_symbol(root_uri: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": "helper",
+        "kind": 12,
+        "location": {
+            "uri": format!("{root_uri}/shared/util.py"),
+            "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 10 } }
+        }
+    })
+}
+
+fn scenario(symbols: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "on_startup": [],
+        "steps": [
+            {
+                "expect": { "method": "initialize" },
+                "actions": [{ "type": "respond", "body": { "capabilities": { "workspaceSymbolProvider": true } } }]
+            },
+            { "expect": { "method": "initialized" }, "actions": [] },
+            { "expect": { "method": "textDocument/didOpen" }, "actions": [] },
+            {
+                "expect": { "method": "workspace/symbol" },
+                "actions": [{ "type": "respond", "body": symbols }]
+            },
+            {
+                "expect": { "method": "shutdown" },
+                "actions": [{ "type": "respond", "body": null }]
+            }
+        ]
+    })
+}
+
+/// Start two backends that both report the shared symbol plus one of their own,
+/// run workspace/symbol and return the merged result.
+async fn merged_symbols(args: &[&str]) -> Vec<serde_json::Value> {
+    let config = WorkspaceConfig {
+        packages: vec![
+            PackageConfig {
+                name: "proj-a".to_string(),
+                scenario: serde_json::json!({}),
+                has_venv: true,
+            },
+            PackageConfig {
+                name: "proj-b".to_string(),
+                scenario: serde_json::json!({}),
+                has_venv: true,
+            },
+        ],
+    };
+    let (temp_dir, root) = support::setup_test_workspace(&config);
+    let root_uri = support::path_to_uri(&root);
+    for name in ["a", "b"] {
+        let own = serde_json::json!({
+            "name": format!("only_{name}"),
+            "kind": 12,
+            "location": {
+                "uri": format!("{root_uri}/proj-{name}/main.py"),
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 6 } }
+            }
+        });
+        support::write_scenario(
+            &root.join(format!("proj-{name}")),
+            &scenario(serde_json::json!([shared_symbol(&root_uri), own])),
+        );
+    }
+
+    // Start from the workspace root (no fallback venv)
+    let mut proxy = ProxyUnderTest::spawn_with_args(temp_dir, root.clone(), &root, args);
+    proxy.initialize(&root_uri).await;
+    proxy.send_initialized().await;
+    for name in ["a", "b"] {
+        let uri = support::path_to_uri(&root.join(format!("proj-{name}/main.py")));
+        proxy.did_open(&uri, "x = 1\n").await;
+    }
+
+    let response = proxy
+        .request("workspace/symbol", serde_json::json!({ "query": "" }))
+        .await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+    let symbols = response.result.unwrap().as_array().unwrap().clone();
+
+    let shutdown_resp = proxy.shutdown_and_exit().await;
+    assert!(shutdown_resp.error.is_none());
+    symbols
+}
+
+/// E2E: a symbol reported by both backends appears once in the merged list.
+#[tokio::test]
+async fn overlapping_symbols_merged_once() {
+    let symbols = merged_symbols(&[]).await;
+    let helpers = symbols.iter().filter(|s| s["name"] == "helper").count();
+    assert_eq!(helpers, 1, "{symbols:?}");
+    assert_eq!(symbols.len(), 3, "{symbols:?}");
+}
+
+/// E2E: `--no-dedup` keeps every backend's copy.
+#[tokio::test]
+async fn no_dedup_keeps_duplicates() {
+    let symbols = merged_symbols(&["--no-dedup"]).await;
+    let helpers = symbols.iter().filter(|s| s["name"] == "helper").count();
+    assert_eq!(helpers, 2, "{symbols:?}");
+    assert_eq!(symbols.len(), 4, "{symbols:?}");
+}